@@ -3,6 +3,7 @@ use parse_display::{Display, FromStr};
 
 use roguelike_utils::comp::*;
 use roguelike_utils::math::*;
+use roguelike_utils::rng::*;
 
 use roguelike_map::*;
 
@@ -31,6 +32,8 @@ pub enum Behavior {
     Attacking(EntityId),
     #[display("armed {0}")]
     Armed(usize), // countdown
+    #[display("fleeing {0}")]
+    Fleeing(EntityId), // running from this entity
 }
 
 impl Default for Behavior {
@@ -47,6 +50,7 @@ impl Behavior {
             Behavior::Investigating(_position) => "investigating",
             Behavior::Attacking(_entity_id) => "attacking",
             Behavior::Armed(_turns) => "armed",
+            Behavior::Fleeing(_entity_id) => "fleeing",
         }
     }
 
@@ -58,11 +62,13 @@ impl Behavior {
 pub fn ai_take_turn(monster_id: EntityId,
                     level: &mut Level,
                     config: &Config,
+                    aggression: f32,
+                    rng: &mut Rand32,
                     msg_log: &mut MsgLog) {
     if level.entities.status[&monster_id].alive {
         match level.entities.ai.get(&monster_id) {
             Some(Ai::Basic) => {
-                basic_ai_take_turn(monster_id, level, msg_log, config);
+                basic_ai_take_turn(monster_id, level, aggression, rng, msg_log, config);
             }
 
             None => {
@@ -74,15 +80,18 @@ pub fn ai_take_turn(monster_id: EntityId,
 
 pub fn basic_ai_take_turn(monster_id: EntityId,
                           level: &mut Level,
+                          aggression: f32,
+                          rng: &mut Rand32,
                           msg_log: &mut MsgLog,
                           config: &Config) {
     let monster_pos = level.entities.pos[&monster_id];
 
     if level.map.is_within_bounds(monster_pos) {
-        if level.entities.status[&monster_id].frozen == 0 {
+        // a disguised mimic waits until it is found
+        if level.entities.status[&monster_id].frozen == 0 && !level.entities.status[&monster_id].disguised {
             match level.entities.behavior[&monster_id] {
                 Behavior::Idle => {
-                    ai_idle(monster_id, level, msg_log, config);
+                    ai_idle(monster_id, level, aggression, rng, msg_log, config);
                 }
 
                 Behavior::Alert(pos) => {
@@ -94,24 +103,160 @@ pub fn basic_ai_take_turn(monster_id: EntityId,
                 }
 
                 Behavior::Attacking(entity_id) => {
-                    ai_attack(monster_id, entity_id, level, msg_log, config);
+                    if ai_summon(monster_id, level, rng, msg_log, config) {
+                        // summoning takes the summoner's turn
+                    } else if ai_steal(monster_id, entity_id, level, rng, msg_log) {
+                        // stealing, or running off with what was stolen, takes the thief's turn
+                    } else if ai_hesitates(monster_id, level, rng, config) {
+                        // a hesitating enemy loses its turn, and looks for the target again next turn
+                        let target_pos = level.entities.pos[&entity_id];
+                        level.entities.took_turn[&monster_id] |= Turn::Pass.turn();
+                        msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(target_pos)));
+                    } else {
+                        ai_attack(monster_id, entity_id, level, msg_log, config);
+                    }
                 }
 
                 Behavior::Armed(turns) => {
                     ai_armed(monster_id, turns, level, msg_log, config);
                 }
+
+                Behavior::Fleeing(entity_id) => {
+                    ai_flee(monster_id, entity_id, level, msg_log);
+                }
             }
         }
     }
 }
 
+/// Whether an enemy with no living allies nearby loses its nerve this turn.
+/// The RNG is only used for isolated enemies, so replays stay in step with the seed.
+pub fn ai_hesitates(monster_id: EntityId, level: &Level, rng: &mut Rand32, config: &Config) -> bool {
+    if config.morale_hesitate_chance <= 0.0 || level.entities.fearless.get(&monster_id) == Some(&true) {
+        return false;
+    }
+
+    let monster_pos = level.entities.pos[&monster_id];
+    let has_ally = level.entities.ids.iter().any(|other_id| {
+        *other_id != monster_id &&
+        level.entities.typ[other_id] == EntityType::Enemy &&
+        level.entities.status[other_id].alive &&
+        distance_maximum(level.entities.pos[other_id], monster_pos) <= config.morale_radius
+    });
+
+    return !has_ally && rng_trial(rng, config.morale_hesitate_chance);
+}
+
+/// Whether a summoner spawns a minion this turn. The summon is placed on a random
+/// free tile next to the summoner, and is skipped while on cooldown or at the minion cap.
+pub fn ai_summon(monster_id: EntityId, level: &mut Level, rng: &mut Rand32, msg_log: &mut MsgLog, config: &Config) -> bool {
+    if let Some(cooldown) = level.entities.summon_cooldown.get_mut(&monster_id) {
+        if *cooldown > 0 {
+            *cooldown -= 1;
+            return false;
+        }
+    } else {
+        return false;
+    }
+
+    let num_minions = level.entities.summoned_by.iter()
+                                                .filter(|(minion_id, summoner_id)| {
+                                                    *summoner_id == &monster_id && level.entities.status[minion_id].alive
+                                                })
+                                                .count();
+    if num_minions >= config.summon_max_minions {
+        return false;
+    }
+
+    let monster_pos = level.entities.pos[&monster_id];
+    let free_pos = Direction::directions().iter()
+                                          .map(|dir| dir.offset_pos(monster_pos, 1))
+                                          .filter(|pos| level.map.is_within_bounds(*pos) && !level.pos_blocked(*pos))
+                                          .collect::<Vec<Pos>>();
+    if free_pos.len() == 0 {
+        return false;
+    }
+
+    let index = rng_range_u32(rng, 0, free_pos.len() as u32) as usize;
+    level.entities.summon_cooldown[&monster_id] = config.summon_cooldown;
+    level.entities.took_turn[&monster_id] |= Turn::Pass.turn();
+    msg_log.log(Msg::Summon(monster_id, free_pos[index]));
+
+    return true;
+}
+
+/// Whether a thief steals from its target this turn. A thief next to a target with
+/// items takes a random one and starts fleeing, and a thief that is already carrying
+/// something keeps running. With nothing to steal, the thief attacks normally.
+pub fn ai_steal(monster_id: EntityId, target_id: EntityId, level: &mut Level, rng: &mut Rand32, msg_log: &mut MsgLog) -> bool {
+    if level.entities.name[&monster_id] != EntityName::Thief {
+        return false;
+    }
+
+    if level.entities.stolen.get(&monster_id).is_some() {
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Fleeing(target_id)));
+        ai_flee(monster_id, target_id, level, msg_log);
+        return true;
+    }
+
+    let monster_pos = level.entities.pos[&monster_id];
+    let target_pos = level.entities.pos[&target_id];
+    if distance_maximum(monster_pos, target_pos) != 1 || level.map.path_blocked_move(monster_pos, target_pos).is_some() {
+        return false;
+    }
+
+    let num_items = level.entities.inventory.get(&target_id).map_or(0, |inventory| inventory.len());
+    if num_items == 0 {
+        return false;
+    }
+
+    let index = rng_range_u32(rng, 0, num_items as u32) as usize;
+    let item_id = level.entities.inventory[&target_id][index];
+    level.entities.took_turn[&monster_id] |= Turn::Pass.turn();
+    msg_log.log(Msg::FaceTowards(monster_id, target_pos));
+    msg_log.log(Msg::Stole(monster_id, target_id, item_id));
+    msg_log.log(Msg::StateChange(monster_id, Behavior::Fleeing(target_id)));
+
+    return true;
+}
+
+/// Step to the free neighboring tile furthest from the entity being fled from,
+/// passing the turn when every step would bring the monster closer.
+pub fn ai_flee(monster_id: EntityId, target_id: EntityId, level: &mut Level, msg_log: &mut MsgLog) {
+    let monster_pos = level.entities.pos[&monster_id];
+    let target_pos = level.entities.pos[&target_id];
+
+    let mut best_dist = distance(monster_pos, target_pos);
+    let mut best_dir = None;
+    for dir in Direction::directions().iter() {
+        let pos = dir.offset_pos(monster_pos, 1);
+        if !level.map.is_within_bounds(pos) ||
+           level.pos_blocked(pos) ||
+           level.map.path_blocked_move(monster_pos, pos).is_some() {
+            continue;
+        }
+
+        let dist = distance(pos, target_pos);
+        if dist > best_dist {
+            best_dist = dist;
+            best_dir = Some(*dir);
+        }
+    }
+
+    if let Some(direction) = best_dir {
+        msg_log.log(Msg::TryMove(monster_id, direction, 1, MoveMode::Walk));
+    } else {
+        level.entities.took_turn[&monster_id] |= Turn::Pass.turn();
+    }
+}
+
 pub fn ai_alert(monster_id: EntityId,
                 _target_pos: Pos,
                 level: &mut Level,
                 msg_log: &mut MsgLog,
-                _config: &Config) {
+                config: &Config) {
     let player_id = level.find_by_name(EntityName::Player).unwrap();
-    let can_see_target = level.is_in_fov(monster_id, player_id) == FovResult::Inside;
+    let can_see_target = level.is_in_fov(monster_id, player_id, config) == FovResult::Inside;
 
     if can_see_target {
         // Can see target- attack
@@ -148,8 +293,33 @@ pub fn ai_armed(monster_id: EntityId,
     }
 }
 
+/// Occasionally make a sound while idle, so the player can hear where an enemy is before
+/// seeing it. This is separate from the enemy's turn, and its sound is only for the player.
+pub fn ai_idle_sound(monster_id: EntityId,
+                     level: &Level,
+                     rng: &mut Rand32,
+                     msg_log: &mut MsgLog,
+                     config: &Config) {
+    if !config.enemy_idle_sounds ||
+       level.entities.behavior.get(&monster_id) != Some(&Behavior::Idle) ||
+       !config.enemy_idle_sound_entities.contains(&level.entities.name[&monster_id]) {
+        return;
+    }
+
+    if rng_trial(rng, config.enemy_idle_sound_chance) {
+        let monster_pos = level.entities.pos[&monster_id];
+        msg_log.log(Msg::IdleSound(monster_id, monster_pos, config.enemy_idle_sound_radius));
+    }
+}
+
+/// An idle enemy that sees the player normally becomes alert, and attacks on its next turn.
+/// An aggression below 1 gives it a chance to stay idle for the turn, and above 1 a chance
+/// to attack right away. The RNG is only used when the aggression is not 1, so replays
+/// without dynamic difficulty stay in step with the seed.
 pub fn ai_idle(monster_id: EntityId,
                level: &mut Level,
+               aggression: f32,
+               rng: &mut Rand32,
                msg_log: &mut MsgLog,
                config: &Config) {
     let player_id = level.find_by_name(EntityName::Player).unwrap();
@@ -159,13 +329,21 @@ pub fn ai_idle(monster_id: EntityId,
         msg_log.log(Msg::Sound(monster_id, monster_pos, config.sound_golem_idle_radius));
     }
 
-    if level.is_in_fov(monster_id, player_id) == FovResult::Inside {
+    if level.is_in_fov(monster_id, player_id, config) == FovResult::Inside {
         let player_pos = level.entities.pos[&player_id];
         msg_log.log(Msg::FaceTowards(monster_id, player_pos));
 
         if level.entities.attack.get(&monster_id).is_some() {
-            msg_log.log(Msg::StateChange(monster_id, Behavior::Alert(player_pos)));
-            level.entities.took_turn[&monster_id] |= Turn::Pass.turn();
+            if aggression < 1.0 && rng_trial(rng, 1.0 - aggression) {
+                // the enemy only turns towards the player this turn, and stays idle
+                level.entities.took_turn[&monster_id] |= Turn::Pass.turn();
+            } else if aggression > 1.0 && rng_trial(rng, aggression - 1.0) {
+                // the enemy's turn is not over, so it goes on to attack
+                msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(player_id)));
+            } else {
+                msg_log.log(Msg::StateChange(monster_id, Behavior::Alert(player_pos)));
+                level.entities.took_turn[&monster_id] |= Turn::Pass.turn();
+            }
         } else {
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(player_pos)));
         }
@@ -199,12 +377,12 @@ pub fn ai_investigate(target_pos: Pos,
                       monster_id: EntityId,
                       level: &mut Level,
                       msg_log: &mut MsgLog,
-                      _config: &Config) {
+                      config: &Config) {
     let player_id = level.find_by_name(EntityName::Player).unwrap();
 
     let monster_pos = level.entities.pos[&monster_id];
 
-    let player_in_fov = level.is_in_fov(monster_id, player_id) == FovResult::Inside;
+    let player_in_fov = level.is_in_fov(monster_id, player_id, config) == FovResult::Inside;
 
     if player_in_fov {
         //let fov_path_clear = level.map.path_blocked_fov(monster_pos, player_pos).is_none();
@@ -257,6 +435,12 @@ pub fn ai_investigate(target_pos: Pos,
             if needs_investigation {
                 msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(sound_pos)));
             }
+        } else if let Some(scent_pos) = level.strongest_scent_near(monster_pos) {
+            // Follow the player's scent trail towards where they went.
+            if let Some(direction) = Direction::from_positions(monster_pos, scent_pos) {
+                msg_log.log(Msg::TryMove(monster_id, direction, 1, MoveMode::Walk));
+            }
+            msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(scent_pos)));
         } else {
             // If the golem reached the target, they become idle.
             // If they are next to the target, and it is occupied, they also become idle,
@@ -292,42 +476,6 @@ fn ai_move_towards_target(target_pos: Pos, monster_id: EntityId, level: &mut Lev
     }
 }
 
-pub fn ai_pos_that_hit_target(monster_id: EntityId,
-                              target_id: EntityId,
-                              level: &mut Level,
-                              config: &Config) -> Vec<Pos> {
-    let mut potential_move_targets = Vec::new();
-
-    let target_pos = level.entities.pos[&target_id];
-    let monster_pos = level.entities.pos[&monster_id];
-
-    // check all movement options in case one lets us hit the target
-    let attack = level.entities.attack[&monster_id];
-    let direction = level.entities.direction[&monster_id];
-    for move_action in &Direction::move_actions() {
-        for attack_offset in attack.attacks_with_reach(&move_action) {
-            let attackable_pos = add_pos(target_pos, attack_offset);
-
-            if attackable_pos == monster_pos ||
-               !level.map.is_within_bounds(attackable_pos) {
-                continue;
-            }
-
-            level.entities.set_pos(monster_id, attackable_pos);
-            level.entities.face(monster_id, target_pos);
-            let can_hit = ai_can_hit_target(level, monster_id, target_pos, &attack, config).is_some();
-
-            if can_hit {
-                potential_move_targets.push(attackable_pos);
-            }
-        }
-    }
-    level.entities.set_pos(monster_id, monster_pos);
-    level.entities.direction[&monster_id] = direction;
-
-    return potential_move_targets;
-}
-
 pub fn ai_fov_cost(monster_id: EntityId,
                    check_pos: Pos,
                    target_pos: Pos,
@@ -462,7 +610,7 @@ pub fn ai_move_to_attack_pos(monster_id: EntityId,
 
     let mut new_pos = monster_pos;
 
-    let mut potential_move_targets = ai_pos_that_hit_target(monster_id, target_id, level, config);
+    let mut potential_move_targets = level.attack_positions_for(monster_id, target_id, config);
 
     // sort by distance to monster to we consider closer positions first, allowing us to
     // skip far away paths we won't take anyway.
@@ -4,10 +4,27 @@ use std::io::Read;
 use roguelike_map::MapLoadConfig;
 use roguelike_utils::math::Color;
 
+use crate::types::{EntityName, Item, Skill};
+use crate::messaging::LogVerbosity;
+
 use serde_derive::*;
 use serde_yaml;
 
 
+/// How the main loop limits its frame rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameLimitMode {
+    Fixed, // sleep to hit frame_rate
+    Vsync, // wait for the display's refresh, falling back to Fixed if vsync is unavailable
+    Uncapped, // run as fast as possible, for benchmarking
+}
+
+impl Default for FrameLimitMode {
+    fn default() -> FrameLimitMode {
+        return FrameLimitMode::Fixed;
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Config {
     pub color_dark_brown: Color,
@@ -45,8 +62,14 @@ pub struct Config {
     pub idle_speed: f32,
     pub grass_idle_speed: f32,
     pub frame_rate: usize,
+    pub frame_limit_mode: FrameLimitMode,
+    pub slow_motion_scale: f32,
+    pub frame_budget_warn_frames: usize,
+    pub pause_when_unfocused: bool,
     pub item_throw_speed: f32,
     pub key_speed: f32,
+    pub interruptible_animations: bool,
+    pub input_buffer_size: usize,
     pub player_attack_speed: f32,
     pub player_attack_hammer_speed: f32,
     pub player_vault_sprite_speed: f32,
@@ -55,7 +78,16 @@ pub struct Config {
     pub yell_radius: usize,
     pub swap_radius: usize,
     pub ping_sound_radius: usize,
+    pub ping_marker_seconds: f32,
     pub fog_of_war: bool,
+    pub short_wall_markers: bool,
+    pub hud_bar: bool,
+    pub grass_blocks_sight_strict: bool,
+    pub reveal_adjacent: bool,
+    pub wall_occlusion: bool,
+    pub wall_occlusion_alpha: u8,
+    pub tile_variation: bool,
+    pub tile_variation_alpha: u8,
     pub player_health: i32,
     pub player_health_max: i32,
     pub player_stamina: u32,
@@ -64,8 +96,13 @@ pub struct Config {
     pub player_energy_max: u32,
     pub explored_alpha: u8,
     pub fov_edge_alpha: u8,
+    pub fov_edge_persistence: f32,
     pub sound_rubble_radius: usize,
     pub sound_golem_idle_radius: usize,
+    pub enemy_idle_sounds: bool,
+    pub enemy_idle_sound_chance: f32,
+    pub enemy_idle_sound_radius: usize,
+    pub enemy_idle_sound_entities: Vec<EntityName>,
     pub sound_grass_radius: usize,
     pub sound_radius_crushed: usize,
     pub sound_radius_attack: usize,
@@ -87,11 +124,27 @@ pub struct Config {
     pub stun_turns_throw_stone: usize,
     pub stun_turns_throw_spear: usize,
     pub stun_turns_throw_default: usize,
+    pub poison_vial_turns: usize,
+    pub spire_venom_turns: usize,
+    pub marking_stone_turns: usize,
+    pub hourglass_turns: usize,
+    pub self_use_items: Vec<Item>,
     pub overlay_directions: bool,
     pub overlay_player_fov: bool,
     pub overlay_floodfill: bool,
+    pub show_enemy_intent: bool,
+    pub show_target_stats: bool,
+    pub enemy_memory: bool,
+    pub enemy_memory_alpha: u8,
     pub fov_radius_monster: i32,
     pub fov_radius_player: i32,
+    pub morale_radius: i32,
+    pub morale_hesitate_chance: f32,
+    pub summon_cooldown: usize,
+    pub summon_max_minions: usize,
+    pub summon_minions_die_with_summoner: bool,
+    pub simultaneous_enemy_turns: bool,
+    pub scent_turns: usize,
     pub sound_radius_sneak: usize,
     pub sound_radius_walk: usize,
     pub sound_radius_run: usize,
@@ -100,6 +153,7 @@ pub struct Config {
     pub dampen_tall_wall: i32,
     pub cursor_fast_move_dist: i32,
     pub repeat_delay: f32,
+    pub invert_move_modifiers: bool,
     pub write_map_distribution: bool,
     pub print_key_log: bool,
     pub recording: bool,
@@ -110,15 +164,21 @@ pub struct Config {
     pub particle_duration: f32,
     pub particle_speed: f32,
     pub max_particles: usize,
+    pub ambient_effect: String,
     pub attack_animation_speed: f32,
     pub cursor_fade_seconds: f32,
     pub cursor_alpha: u8,
     pub save_load: bool,
+    pub save_slots: usize,
     pub minimal_output: bool,
     pub cursor_line: bool,
     pub blocking_positions: bool,
+    pub free_camera: bool,
     pub smoke_bomb_fov_block: usize,
     pub smoke_turns: usize,
+    pub fire_turns: usize,
+    pub fire_damage: i32,
+    pub fire_max_tiles: usize,
     pub looking_glass_magnify_amount: usize,
     pub hp_render_duration: usize,
     pub move_tiles_sneak: usize,
@@ -145,8 +205,50 @@ pub struct Config {
     pub ui_long_name_scale: f32,
 
     pub display_console_lines: usize,
+    pub log_verbosity: LogVerbosity,
 
     pub display_center_map_on_player: bool,
+    pub camera_margin: i32,
+    pub camera_lerp_speed: f32,
+
+    pub turn_limit: usize,
+
+    pub allow_diagonal_corner_cutting: bool,
+
+    pub difficulty: f32,
+    pub dynamic_difficulty: bool,
+    pub dynamic_difficulty_window: usize,
+    pub dynamic_difficulty_target_damage: i32,
+    pub dynamic_difficulty_step: f32,
+    pub dynamic_difficulty_min: f32,
+    pub dynamic_difficulty_max: f32,
+
+    pub bestiary_persists: bool,
+
+    pub unidentified_items: bool,
+    pub identified_items_persist: bool,
+
+    pub permadeath: bool,
+    pub practice_mode: bool,
+    pub practice_stun_turns: usize,
+
+    pub objective_exit: bool,
+    pub levels_to_win: usize,
+
+    pub disabled_entities: Vec<EntityName>,
+
+    pub starting_inventory: Vec<String>,
+    pub loot_table: Vec<(EntityName, String, u32)>, // entity, item name or 'nothing', weight
+    pub skill_cooldowns: Vec<(Skill, usize)>, // skill, turns
+    pub inventory_slots: usize,
+
+    pub game_over_screenshot: bool,
+    pub game_over_screenshot_dir: String,
+
+    pub death_hints: bool,
+    pub death_hint_threshold: usize,
+    pub death_hint_default: String,
+    pub death_hint_maps: Vec<(String, String)>, // map config, hint
 }
 
 impl Config {
@@ -157,9 +259,82 @@ impl Config {
         file.read_to_string(&mut config_string)
             .expect(&format!("Could not read contents of {}", file_name));
 
-        let config = serde_yaml::from_str(&config_string).expect(&format!("Could not parse {} file!", file_name));
+        let mut config: Config = serde_yaml::from_str(&config_string).expect(&format!("Could not parse {} file!", file_name));
+
+        if !valid_time_scale(config.slow_motion_scale) {
+            eprintln!("slow_motion_scale must be above 0, not {}, so 1.0 is used", config.slow_motion_scale);
+            config.slow_motion_scale = 1.0;
+        }
 
         return config
     }
+
+    /// Number of turns, counting the turn the skill is used, before it can be used again.
+    /// Skills without an entry in skill_cooldowns have no cooldown.
+    pub fn skill_cooldown(&self, skill: Skill) -> usize {
+        return self.skill_cooldowns.iter()
+                                   .find(|(cooldown_skill, _)| *cooldown_skill == skill)
+                                   .map(|(_, turns)| *turns)
+                                   .unwrap_or(0);
+    }
+
+    pub fn entity_disabled(&self, name: EntityName) -> bool {
+        return self.disabled_entities.contains(&name);
+    }
+
+    /// Set a field by name, parsing the value as YAML just like the config file.
+    /// The config is left unchanged if the field does not exist or the value has the wrong type.
+    pub fn set_field(&mut self, field_name: &str, value_str: &str) -> Result<(), String> {
+        let mut config_value = serde_yaml::to_value(&*self).map_err(|err| format!("{}", err))?;
+
+        let mapping = config_value.as_mapping_mut().ok_or("config is not a mapping".to_string())?;
+        let key = serde_yaml::Value::String(field_name.to_string());
+
+        let old_value = mapping.get(&key).ok_or(format!("no config field '{}'", field_name))?;
+
+        // strings do not need to be quoted, even if they look like numbers or booleans
+        let new_value;
+        if old_value.is_string() {
+            new_value = serde_yaml::Value::String(value_str.to_string());
+        } else {
+            new_value = serde_yaml::from_str(value_str).map_err(|err| format!("{}", err))?;
+        }
+        mapping.insert(key, new_value);
+
+        let new_config: Config = serde_yaml::from_value(config_value).map_err(|err| format!("{}", err))?;
+        if !valid_time_scale(new_config.slow_motion_scale) {
+            return Err(format!("slow_motion_scale must be above 0, not {}", new_config.slow_motion_scale));
+        }
+        *self = new_config;
+
+        return Ok(());
+    }
 }
 
+/// Whether an animation time scale can be used. A scale of 0 or less would stop
+/// or reverse animations.
+pub fn valid_time_scale(scale: f32) -> bool {
+    return scale > 0.0 && scale.is_finite();
+}
+
+#[test]
+pub fn test_config_set_field() {
+    let mut config = Config::from_file("../config.yaml");
+
+    assert!(config.set_field("fov_radius_player", "8").is_ok());
+    assert_eq!(8, config.fov_radius_player);
+
+    assert!(config.set_field("ambient_effect", "rain").is_ok());
+    assert_eq!("rain", config.ambient_effect);
+
+    // unknown fields and badly typed values are errors, and leave the config as it was
+    assert!(config.set_field("not_a_field", "1").is_err());
+    assert!(config.set_field("fov_radius_player", "true").is_err());
+    assert_eq!(8, config.fov_radius_player);
+
+    // animations can not be stopped or reversed
+    assert!(config.set_field("slow_motion_scale", "0").is_err());
+    assert!(config.set_field("slow_motion_scale", "-1").is_err());
+    assert!(config.set_field("slow_motion_scale", "0.5").is_ok());
+    assert_eq!(0.5, config.slow_motion_scale);
+}
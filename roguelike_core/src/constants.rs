@@ -3,6 +3,8 @@ pub const CONFIG_FILE_NAME: &str = &"config.json";
 /* Screen */
 pub const UI_CELLS_TOP: u32 = 3;
 pub const UI_CELLS_BOTTOM: u32 = 12;
+// width of the turn and enemy count text on the right of the top bar
+pub const HUD_CELLS: u32 = 14;
 
 pub const CELL_MULTIPLIER: u32 = 3;
 
@@ -17,12 +19,18 @@ pub const SCREEN_HEIGHT: u32 = (MAP_HEIGHT as u32 * MAP_CELLS_TO_PIXELS) + (UI_C
 pub const UI_PIXELS_TOP: u32 = UI_CELLS_TOP * UI_CELLS_TO_PIXELS;
 pub const UI_PIXELS_BOTTOM: u32 = UI_CELLS_BOTTOM * UI_CELLS_TO_PIXELS;
 
+pub const MAP_ZOOM_MIN: f32 = 1.0;
+pub const MAP_ZOOM_MAX: f32 = 4.0;
+pub const MAP_ZOOM_STEP: f32 = 1.25;
+
 /* General Settings */
 pub const BLINK_RADIUS: usize = 4;
 pub const TILE_FILL_METRIC_DIST: usize = 3;
 pub const ILLUMINATE_FOV_RADIUS: i32 = 1000;
 pub const STAB_STUN_TURNS: usize = 3;
 pub const ITEM_DURABILITY: usize = 5;
+pub const CURSOR_HISTORY_LENGTH: usize = 10; // cursor positions that can be stepped back to
+pub const LOOT_SCATTER_RADIUS: i32 = 2; // how far loot moves when an enemy dies on a blocked tile
 
 pub const GOLEM_ATTACK_DIAG: u8 = 180;
 pub const GOLEM_ATTACK_HORIZ: u8 = 181;
@@ -36,12 +44,21 @@ pub const GOL_MOVE_DISTANCE: usize = 1;
 pub const SPIRE_ATTACK_DISTANCE: usize = 5;
 pub const SPIRE_MOVE_DISTANCE: usize = 1;
 
+pub const SUMMONER_MOVE_DISTANCE: usize = 1;
+pub const SUMMONER_ATTACK_DISTANCE: usize = 1;
+
+pub const THIEF_MOVE_DISTANCE: usize = 1;
+pub const THIEF_ATTACK_DISTANCE: usize = 1;
+
 pub const PAWN_MOVE_DISTANCE: usize = 1;
 pub const PAWN_ATTACK_DISTANCE: usize = 1;
 
 pub const ROOK_MOVE_DISTANCE: usize = 1;
 pub const ROOK_ATTACK_DISTANCE: usize = 5;
 
+pub const MIMIC_MOVE_DISTANCE: usize = 1;
+pub const MIMIC_ATTACK_DISTANCE: usize = 1;
+
 pub const ARMIL_MOVE_DISTANCE: usize = 1;
 
 pub const KEY_ATTACK_DISTANCE: usize = 3;
@@ -68,6 +85,9 @@ pub const SKILL_SPRINT_AMOUNT: usize = 3;
 pub const SKILL_ROLL_AMOUNT: usize = 2;
 pub const SKILL_STONE_SKIN_TURNS: usize = 4;
 pub const SKILL_SWIFT_DISTANCE: usize = 4;
+pub const SKILL_DASH_DISTANCE: usize = 3;
+pub const SKILL_DASH_STUN_TURNS: usize = 2;
+pub const SKILL_WAR_CRY_TURNS: usize = 3;
 pub const SKILL_QUICK_REFLEXES_PERCENT: f32 = 0.5;
 
 /* Item Settings */
@@ -84,6 +104,8 @@ pub const FREEZE_TRAP_NUM_TURNS: usize = 5;
 /* Player */
 pub const PLAYER_THROW_DIST: usize = 4;
 pub const SLING_THROW_DIST: usize = 7;
+pub const STONE_VOLLEY_COUNT: usize = 3;
+pub const STONE_VOLLEY_SCATTER: i32 = 1;
 
 pub const PLAYER_MAX_ITEMS: usize = 3;
 
@@ -208,6 +230,11 @@ pub const SHADOW_INTERTILE_LEFT_DOWN: u8 = 7;
 pub const SHADOW_INTERTILE_DOWN: u8 = 5;
 pub const SHADOW_INTERTILE_DOWN_LEFT: u8 = 4;
 
+// size of the short wall marker, and its distance from the wall's edge of the tile,
+// as fractions of a cell. The inset keeps the marker clear of the wall sprite.
+pub const SHORT_WALL_MARKER_SIZE: f32 = 0.12;
+pub const SHORT_WALL_MARKER_INSET: f32 = 0.25;
+
 //pub const GRASS_INTERTILE_UP: u8 = 105;
 //pub const GRASS_INTERTILE_DOWN: u8 = 106;
 //pub const GRASS_INTERTILE_LEFT: u8 = 107;
@@ -233,6 +260,16 @@ pub const SHADOW_INTERTILE_DOWN_LEFT: u8 = 4;
 //pub const AT_SYMBOL: u8 = 64;
 //pub const ARMED_SYMBOL: u8 = 50; // NOTE armed is just a sprite that looks related to explosions
 
+/* Tutorial */
+pub const TUTORIAL_HINTS: [&str; 6] = [
+    "Use the arrow keys or numpad to move",
+    "Hold shift to run, or ctrl to sneak",
+    "Press g to pick up the item on your tile",
+    "Press z to use your primary item",
+    "Golems see in the direction they face- stay out of their sight",
+    "Walk off the right edge of the map to finish the level",
+];
+
 /* Golems */
 pub const AI_EXPLODE_RADIUS: usize = 2;
 pub const EXPLODE_SOUND_RADIUS: usize = 4;
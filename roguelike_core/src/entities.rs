@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::collections::HashMap;
 use std::default::Default;
 
 use serde::{Serialize, Deserialize};
@@ -38,16 +39,26 @@ pub struct Entities {
     pub selected_item: Comp<EntityId>,
     pub class: Comp<EntityClass>,
     pub skills: Comp<Vec<Skill>>,
+    pub cooldowns: Comp<HashMap<Skill, usize>>, // turns until each skill can be used again
     pub talents: Comp<Vec<Talent>>,
     pub status: Comp<StatusEffect>,
     pub passive: Comp<Passive>,
     pub illuminate: Comp<usize>,
     pub gate_pos: Comp<Pos>,
+    pub hint: Comp<(usize, bool)>, // hint index, consumed
+    pub size: Comp<(i32, i32)>, // footprint extending right and down from pos
+    pub venom: Comp<usize>, // turns of poison applied by attacks
+    pub fearless: Comp<bool>, // never hesitates when left without allies
+    pub summon_cooldown: Comp<usize>, // turns until a summoner can summon again
+    pub summoned_by: Comp<EntityId>, // the summoner that spawned this minion
+    pub stolen: Comp<EntityId>, // item carried off by a thief
+    pub recall_pos: Comp<Pos>, // tile marked by a recall stone
     pub stance: Comp<Stance>,
     pub took_turn: Comp<u8>,
     pub durability: Comp<usize>,
     pub modifier: Comp<ItemModifier>,
     pub fov_block: Comp<FovBlock>,
+    pub sprite_override: Comp<String>, // sprite name given in the map file
 
     // NOTE not sure about keeping these ones, or packaging into larger ones
     pub sound: Comp<Pos>,
@@ -111,6 +122,43 @@ impl Entities {
         return dropped_item;
     }
 
+    /// The inventory indices in each inventory slot, in inventory order.
+    /// Only misc items use slots, as other items replace the item of their class.
+    /// Stones share a single slot, placed where the first stone is.
+    pub fn inventory_slots(&self, entity_id: EntityId) -> Vec<Vec<usize>> {
+        let mut slots: Vec<Vec<usize>> = Vec::new();
+        let mut stone_slot = None;
+
+        for ix in 0..self.inventory[&entity_id].len() {
+            let item_id = self.inventory[&entity_id][ix];
+            if self.item[&item_id].class() != ItemClass::Misc {
+                continue;
+            }
+
+            if self.item[&item_id] == Item::Stone {
+                if let Some(stone_slot) = stone_slot {
+                    slots[stone_slot].push(ix);
+                    continue;
+                }
+                stone_slot = Some(slots.len());
+            }
+
+            slots.push(vec!(ix));
+        }
+
+        return slots;
+    }
+
+    /// Whether picking up the given item would use a new inventory slot. Only misc
+    /// items use slots, and a stone joins the stone slot if there is one.
+    pub fn needs_new_slot(&self, entity_id: EntityId, item: Item) -> bool {
+        if item.class() != ItemClass::Misc {
+            return false;
+        }
+
+        return item != Item::Stone || self.item_by_type(entity_id, Item::Stone).is_none();
+    }
+
     pub fn item_by_type(&self, entity_id: EntityId, item: Item) -> Option<usize> {
         for ix in 0..self.inventory[&entity_id].len() {
             let item_id = self.inventory[&entity_id][ix];
@@ -133,6 +181,29 @@ impl Entities {
         return None;
     }
 
+    /// Move the first item of the given class behind the other items of that class,
+    /// so the next one becomes the item used for that class's slot.
+    /// Returns false if there are fewer then two items to cycle between.
+    pub fn cycle_item_class(&mut self, entity_id: EntityId, item_class: ItemClass) -> bool {
+        let indices = (0..self.inventory[&entity_id].len())
+                          .filter(|ix| self.item[&self.inventory[&entity_id][*ix]].class() == item_class)
+                          .collect::<Vec<usize>>();
+
+        if indices.len() < 2 {
+            return false;
+        }
+
+        let first_index = indices[0];
+        let last_index = indices[indices.len() - 1];
+
+        // removing the first item shifts the last item down one, so inserting at
+        // the last index places the first item directly after it.
+        let item_id = self.inventory[&entity_id].remove(first_index).unwrap();
+        self.inventory[&entity_id].insert(last_index, item_id);
+
+        return true;
+    }
+
     pub fn summarize_entity(&mut self, id: EntityId) -> String {
         return format!("Entity {:?}: {} at {}", self.name[&id], self.typ[&id], self.pos[&id]);
     }
@@ -164,6 +235,32 @@ impl Entities {
         self.pos[&entity_id] = pos;
     }
 
+    /// The tiles covered by an entity if it were at the given position.
+    /// Entities without a size cover only their own tile.
+    pub fn footprint_at(&self, entity_id: EntityId, pos: Pos) -> Vec<Pos> {
+        let (width, height) = self.size.get(&entity_id).map(|size| *size).unwrap_or((1, 1));
+
+        let mut positions = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                positions.push(Pos::new(pos.x + x, pos.y + y));
+            }
+        }
+
+        return positions;
+    }
+
+    pub fn occupies(&self, entity_id: EntityId, check_pos: Pos) -> bool {
+        let pos = self.pos[&entity_id];
+
+        if let Some((width, height)) = self.size.get(&entity_id) {
+            return check_pos.x >= pos.x && check_pos.x < pos.x + width &&
+                   check_pos.y >= pos.y && check_pos.y < pos.y + height;
+        }
+
+        return check_pos == pos;
+    }
+
     pub fn move_next_to(&mut self, entity_id: EntityId, pos: Pos) {
         let self_pos = self.pos[&entity_id];
         let mut diff_x = pos.x - self_pos.x;
@@ -200,17 +297,42 @@ impl Entities {
         return self.stamina[&entity_id] >= amount;
     }
 
+    pub fn skill_cooldown(&self, entity_id: EntityId, skill: Skill) -> usize {
+        if self.status[&entity_id].test_mode {
+            return 0;
+        }
+
+        return self.cooldowns.get(&entity_id)
+                             .and_then(|cooldowns| cooldowns.get(&skill))
+                             .map(|turns| *turns)
+                             .unwrap_or(0);
+    }
+
+    pub fn count_down_cooldowns(&mut self, entity_id: EntityId) {
+        if let Some(cooldowns) = self.cooldowns.get_mut(&entity_id) {
+            for turns in cooldowns.values_mut() {
+                *turns = turns.saturating_sub(1);
+            }
+            cooldowns.retain(|_, turns| *turns > 0);
+        }
+    }
+
     pub fn use_energy(&mut self, entity_id: EntityId) {
         if !self.status[&entity_id].test_mode {
             self.energy[&entity_id] -= 1;
         }
     }
 
-    pub fn take_damage(&mut self, entity_id: EntityId, damage: i32) -> bool {
+    pub fn take_damage(&mut self, entity_id: EntityId, source_id: EntityId, damage: i32) -> bool {
         let mut was_hit = false;
 
         if damage > 0 && self.status[&entity_id].alive && self.status[&entity_id].stone == 0 {
             if let Some(hp) = self.hp.get_mut(&entity_id) {
+                self.status[&entity_id].last_damager = Some(source_id);
+
+                // getting hurt breaks concentration on a charged attack
+                self.status[&entity_id].charged_attack = None;
+
                 if !self.status[&entity_id].test_mode {
                     hp.hp -= damage;
 
@@ -394,6 +516,7 @@ impl Entities {
         self.behavior.remove(&id);
         self.fov_radius.remove(&id);
         self.fov_block.remove(&id);
+        self.sprite_override.remove(&id);
         self.attack_type.remove(&id);
         self.item.remove(&id);
         self.movement.remove(&id);
@@ -408,11 +531,20 @@ impl Entities {
         self.selected_item.remove(&id);
         self.class.remove(&id);
         self.skills.remove(&id);
+        self.cooldowns.remove(&id);
         self.sound.remove(&id);
         self.typ.remove(&id);
         self.status.remove(&id);
         self.illuminate.remove(&id);
         self.gate_pos.remove(&id);
+        self.hint.remove(&id);
+        self.size.remove(&id);
+        self.venom.remove(&id);
+        self.fearless.remove(&id);
+        self.summon_cooldown.remove(&id);
+        self.summoned_by.remove(&id);
+        self.stolen.remove(&id);
+        self.recall_pos.remove(&id);
         self.took_turn.remove(&id);
         self.durability.remove(&id);
         self.blocks.remove(&id);
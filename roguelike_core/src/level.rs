@@ -1,3 +1,6 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use serde::{Serialize, Deserialize};
 
 use pathfinding::directed::astar::astar;
@@ -13,12 +16,42 @@ use crate::config::Config;
 use crate::constants::*;
 use crate::entities::*;
 use crate::types::*;
+use crate::ai::ai_can_hit_target;
+use crate::utils::throw_range;
 
 
+/// Everything besides the map tiles that the player's FOV depends on.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FovCacheKey {
+    pub pos: Pos,
+    pub map_version: u64,
+    pub crouching: bool,
+    pub radius: i32,
+    pub wall_sight: bool,
+    pub modifiers: Vec<(EntityId, Pos, Option<FovBlock>, usize)>, // entity, position, fov block, illumination radius
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Level {
     pub map: Map,
     pub entities: Entities,
+
+    // the last full-map player FOV, reused until anything it depends on changes.
+    #[serde(skip)]
+    pub fov_cache: RefCell<Option<(FovCacheKey, Vec<(Pos, FovResult)>)>>,
+
+    // scent left by the player, as the number of turns until it fades from each tile.
+    #[serde(default)]
+    pub scent: HashMap<Pos, usize>,
+}
+
+// the FOV cache is only a record of earlier results, so it is not compared.
+impl PartialEq for Level {
+    fn eq(&self, other: &Level) -> bool {
+        return self.map == other.map &&
+               self.entities == other.entities &&
+               self.scent == other.scent;
+    }
 }
 
 impl Level {
@@ -26,6 +59,8 @@ impl Level {
         Level {
             map,
             entities,
+            fov_cache: RefCell::new(None),
+            scent: HashMap::new(),
         }
     }
 
@@ -153,7 +188,7 @@ impl Level {
         return radius;
     }
 
-    pub fn is_in_fov(&self, entity_id: EntityId, other_id: EntityId) -> FovResult {
+    pub fn is_in_fov(&self, entity_id: EntityId, other_id: EntityId, config: &Config) -> FovResult {
         let stance = self.entities.stance[&entity_id];
         let other_stance = self.entities.stance.get(&other_id).unwrap_or(&Stance::Standing);
         let crouching = stance == Stance::Crouching || other_stance == &Stance::Crouching;
@@ -162,6 +197,10 @@ impl Level {
 
         if self.entities.needs_removal[&other_id] {
             return FovResult::Outside;
+        } else if config.grass_blocks_sight_strict && other_pos != self.entities.pos[&entity_id] && self.map.is_within_bounds(other_pos) &&
+                  self.map[other_pos].block_sight && self.map[other_pos].surface == Surface::Grass {
+            // the grass itself can still be seen, but not what is hiding in it
+            return FovResult::Outside;
         } else {
             return self.fov_check(entity_id, other_pos, crouching);
         }
@@ -179,10 +218,79 @@ impl Level {
         return fov_result == FovResult::Inside;
     }
 
+    fn player_fov_key(&self, player_id: EntityId) -> FovCacheKey {
+        let crouching = self.entities.stance[&player_id] == Stance::Crouching;
+        let wall_sight = self.has_wall_sight(player_id);
+
+        // entities that block, magnify, or illuminate change what the player can see.
+        let mut modifiers = Vec::new();
+        for (entity_id, fov_block) in self.entities.fov_block.iter() {
+            modifiers.push((entity_id, self.entities.pos[&entity_id], Some(*fov_block), 0));
+        }
+        for (entity_id, illuminate) in self.entities.illuminate.iter() {
+            if !self.entities.needs_removal[&entity_id] {
+                modifiers.push((entity_id, self.entities.pos[&entity_id], None, *illuminate));
+            }
+        }
+
+        return FovCacheKey { pos: self.entities.pos[&player_id],
+                             map_version: self.map.version,
+                             crouching,
+                             radius: self.fov_radius(player_id),
+                             wall_sight,
+                             modifiers,
+        };
+    }
+
+    /// The FOV result for every position on the map that is not outside of the player's FOV.
+    /// The result is cached, and only recomputed when the player, the map, or an entity
+    /// affecting FOV has changed.
+    pub fn player_fov(&self, player_id: EntityId) -> Vec<(Pos, FovResult)> {
+        let key = self.player_fov_key(player_id);
+
+        if let Some((cached_key, cached_fov)) = &*self.fov_cache.borrow() {
+            if *cached_key == key {
+                return cached_fov.clone();
+            }
+        }
+
+        let mut fov = Vec::new();
+        let (map_width, map_height) = self.map.size();
+        for y in 0..map_height {
+            for x in 0..map_width {
+                let pos = Pos::new(x, y);
+                let fov_result = self.pos_in_fov_edge(player_id, pos);
+                if fov_result != FovResult::Outside {
+                    fov.push((pos, fov_result));
+                }
+            }
+        }
+
+        *self.fov_cache.borrow_mut() = Some((key, fov.clone()));
+
+        return fov;
+    }
+
     fn fov_check_player(&self, entity_id: EntityId, check_pos: Pos, crouching: bool, view_distance: i32) -> FovResult {
         let entity_pos = self.entities.pos[&entity_id];
 
-        let fov_result = self.map.is_in_fov_edge(entity_pos, check_pos, view_distance, crouching);
+        let mut fov_result = self.map.is_in_fov_edge(entity_pos, check_pos, view_distance, crouching);
+
+        // With wall sight, a tile directly behind a visible full tile wall is also visible.
+        // Only the tile immediately behind the wall is revealed, so sight does not
+        // continue past a second wall.
+        if fov_result == FovResult::Outside && self.has_wall_sight(entity_id) {
+            if self.map.is_within_bounds(check_pos) && distance_maximum(entity_pos, check_pos) <= view_distance {
+                let path = line(entity_pos, check_pos);
+                if path.len() >= 2 {
+                    let wall_pos = path[path.len() - 2];
+                    if self.map[wall_pos].tile_type == TileType::Wall &&
+                       self.map.is_in_fov_edge(entity_pos, wall_pos, view_distance, crouching) == FovResult::Inside {
+                        fov_result = FovResult::Inside;
+                    }
+                }
+            }
+        }
 
         // If we can't see the tile, check for a latern that illuminates it, allowing
         // us to see it anyway. Ignore tiles that are blocked for sight anyway.
@@ -422,7 +530,7 @@ impl Level {
             let pos = self.entities.pos[key];
             let is_mouse = self.entities.name[key] == EntityName::Mouse;
 
-            if !is_mouse && check_pos == pos && !self.entities.needs_removal[key] {
+            if !is_mouse && self.entities.occupies(*key, check_pos) && !self.entities.needs_removal[key] {
                 entity_ids.push(*key);
             }
         }
@@ -497,8 +605,8 @@ impl Level {
     }
 
     pub fn has_blocking_entity(&self, pos: Pos) -> Option<EntityId> {
-        for (key, other_pos) in self.entities.pos.iter() {
-            if *other_pos == pos {
+        for (key, _other_pos) in self.entities.pos.iter() {
+            if self.entities.occupies(key, pos) {
                 if self.entities.blocks[&key] {
                     return Some(key);
                 }
@@ -508,6 +616,46 @@ impl Level {
         return None;
     }
 
+    /// Would any tile of the entity's footprint be out of bounds, within a wall,
+    /// or on another blocking entity if it were placed at the given position?
+    pub fn footprint_blocked(&self, entity_id: EntityId, pos: Pos) -> bool {
+        for footprint_pos in self.entities.footprint_at(entity_id, pos) {
+            if !self.map.is_within_bounds(footprint_pos) || self.map[footprint_pos].block_move {
+                return true;
+            }
+
+            for other_id in self.get_entities_at_pos(footprint_pos) {
+                if other_id != entity_id && self.entities.blocks[&other_id] {
+                    return true;
+                }
+            }
+        }
+
+        return false;
+    }
+
+    /// Set an entity's footprint, returning false and leaving the entity
+    /// unchanged if the new footprint would be blocked.
+    pub fn set_entity_size(&mut self, entity_id: EntityId, size: (i32, i32)) -> bool {
+        if size.0 < 1 || size.1 < 1 {
+            return false;
+        }
+
+        let prev_size = self.entities.size.get(&entity_id).map(|size| *size);
+        self.entities.size.insert(entity_id, size);
+
+        let pos = self.entities.pos[&entity_id];
+        if self.footprint_blocked(entity_id, pos) {
+            self.entities.size.remove(&entity_id);
+            if let Some(prev_size) = prev_size {
+                self.entities.size.insert(entity_id, prev_size);
+            }
+            return false;
+        }
+
+        return true;
+    }
+
     pub fn has_trap(&self, pos: Pos) -> Option<EntityId> {
         for (key, other_pos) in self.entities.pos.iter() {
             if *other_pos == pos {
@@ -530,6 +678,17 @@ impl Level {
         return None;
     }
 
+    /// Whether an entity can see the tile behind a wall, either from the wall_sight passive
+    /// or from carrying a glass eye.
+    pub fn has_wall_sight(&self, entity_id: EntityId) -> bool {
+        if self.entities.passive.get(&entity_id).map_or(false, |p| p.wall_sight) {
+            return true;
+        }
+
+        return self.entities.inventory.get(&entity_id).is_some() &&
+               self.is_in_inventory(entity_id, Item::GlassEye).is_some();
+    }
+
     pub fn using(&self, entity_id: EntityId, item: Item) -> Option<EntityId> {
         if let Some(inventory) = self.entities.inventory.get(&entity_id) {
             if let Some(item_id) = inventory.get(0) {
@@ -567,6 +726,91 @@ impl Level {
         return within;
     }
 
+    /// Positions from which the entity could attack the target, accounting for the
+    /// entity's attack reach and anything blocking the attack. The entity's current
+    /// position is not included.
+    pub fn attack_positions_for(&mut self, entity_id: EntityId, target_id: EntityId, config: &Config) -> Vec<Pos> {
+        let mut attack_positions = Vec::new();
+
+        let target_pos = self.entities.pos[&target_id];
+        let entity_pos = self.entities.pos[&entity_id];
+
+        // check all movement options in case one lets us hit the target
+        let attack = self.entities.attack[&entity_id];
+        let direction = self.entities.direction[&entity_id];
+        for move_action in &Direction::move_actions() {
+            for attack_offset in attack.attacks_with_reach(&move_action) {
+                let attackable_pos = add_pos(target_pos, attack_offset);
+
+                if attackable_pos == entity_pos ||
+                   !self.map.is_within_bounds(attackable_pos) ||
+                   self.map[attackable_pos].block_move {
+                    continue;
+                }
+
+                // place the entity at the position, facing the target, to check the attack from there.
+                self.entities.set_pos(entity_id, attackable_pos);
+                self.entities.face(entity_id, target_pos);
+                let can_hit = ai_can_hit_target(self, entity_id, target_pos, &attack, config).is_some();
+
+                if can_hit && !attack_positions.contains(&attackable_pos) {
+                    attack_positions.push(attackable_pos);
+                }
+            }
+        }
+        self.entities.set_pos(entity_id, entity_pos);
+        self.entities.direction[&entity_id] = direction;
+
+        return attack_positions;
+    }
+
+    /// Leave scent on a tile. Water carries no scent.
+    pub fn leave_scent(&mut self, pos: Pos, turns: usize) {
+        if !self.map.is_within_bounds(pos) || self.map[pos].tile_type == TileType::Water {
+            return;
+        }
+
+        self.scent.insert(pos, turns);
+    }
+
+    /// Fade all scent by one turn, removing tiles with no scent left.
+    pub fn decay_scent(&mut self) {
+        for turns in self.scent.values_mut() {
+            *turns = turns.saturating_sub(1);
+        }
+        self.scent.retain(|_pos, turns| *turns > 0);
+    }
+
+    pub fn scent_at(&self, pos: Pos) -> usize {
+        return self.scent.get(&pos).map_or(0, |turns| *turns);
+    }
+
+    /// The open tile next to the given position with the freshest scent, if it is
+    /// fresher than the scent on the position itself.
+    pub fn strongest_scent_near(&self, pos: Pos) -> Option<Pos> {
+        let mut best_pos = None;
+        let mut best_scent = self.scent_at(pos);
+
+        for dir in &Direction::directions() {
+            let next_pos = dir.offset_pos(pos, 1);
+            if !self.map.is_within_bounds(next_pos) || self.pos_blocked(next_pos) {
+                continue;
+            }
+
+            if self.map.path_blocked_move(pos, next_pos).is_some() {
+                continue;
+            }
+
+            let next_scent = self.scent_at(next_pos);
+            if next_scent > best_scent {
+                best_scent = next_scent;
+                best_pos = Some(next_pos);
+            }
+        }
+
+        return best_pos;
+    }
+
     // check whether the entity could see a location if it were facing towards that position.
     pub fn could_see(&mut self, entity_id: EntityId, target_pos: Pos, _config: &Config) -> bool {
         let current_facing = self.entities.direction[&entity_id];
@@ -615,6 +859,72 @@ impl Level {
         return hit_pos;
     }
 
+    /// Whether an item thrown by the player from start_pos at end_pos would reach it. This follows
+    /// the throw itself, including its range and the walls and entities that stop it. A throw
+    /// past its range still goes as far as it can, so it is out of range only if nothing stops it first.
+    pub fn line_of_fire(&self, start_pos: Pos, end_pos: Pos, hard: bool) -> LineOfFire {
+        if start_pos == end_pos {
+            return LineOfFire::Clear;
+        }
+
+        let throw_line = line(start_pos, end_pos);
+        let range_end = *throw_line.iter().take(throw_range(hard)).last().unwrap();
+
+        let hit_pos = self.throw_towards(start_pos, range_end);
+        if hit_pos == end_pos {
+            return LineOfFire::Clear;
+        }
+
+        // a throw lands on the tile of an entity it hits
+        if hit_pos != start_pos {
+            if let Some(hit_entity) = self.has_blocking_entity(hit_pos) {
+                if self.entities.typ[&hit_entity] == EntityType::Enemy {
+                    return LineOfFire::BlockedByEnemy;
+                } else {
+                    return LineOfFire::BlockedByObject;
+                }
+            }
+        }
+
+        if hit_pos == range_end {
+            return LineOfFire::OutOfRange;
+        }
+
+        // otherwise it stopped short of the next tile, which is behind a wall or holds a column
+        let next_index = throw_line.iter().position(|pos| *pos == hit_pos).map_or(0, |index| index + 1);
+        if self.has_blocking_entity(throw_line[next_index]).is_some() {
+            return LineOfFire::BlockedByObject;
+        }
+
+        return LineOfFire::BlockedByWall;
+    }
+
+    /// The tiles a boomerang flies over from start_pos towards end_pos. It passes over
+    /// enemies and the player, and stops before walls and other blocking entities.
+    pub fn boomerang_path(&self, start_pos: Pos, end_pos: Pos) -> Vec<Pos> {
+        let mut path = Vec::new();
+
+        let mut prev_pos = start_pos;
+        for pos in line(start_pos, end_pos) {
+            if !self.map.is_within_bounds(pos) ||
+               self.map.move_blocked(prev_pos, pos, BlockedType::Move).is_some() {
+                break;
+            }
+
+            if let Some(hit_entity) = self.has_blocking_entity(pos) {
+                let typ = self.entities.typ[&hit_entity];
+                if typ != EntityType::Enemy && typ != EntityType::Player {
+                    break;
+                }
+            }
+
+            path.push(pos);
+            prev_pos = pos;
+        }
+
+        return path;
+    }
+
     pub fn calculate_use_skill(&self, entity_id: EntityId, _skill: Skill, dir: Direction, _move_mode: MoveMode) -> UseResult {
         let entity_pos = self.entities.pos[&entity_id];
 
@@ -639,7 +949,8 @@ impl Level {
             Item::Lantern | Item::Teleporter | Item::SpikeTrap | 
             Item::SoundTrap | Item::BlinkTrap | Item::FreezeTrap |
             Item::Sling | Item::SeedCache | Item::SmokeBomb |
-            Item::LookingGlass | Item::Thumper => {
+            Item::LookingGlass | Item::Thumper | Item::PoisonVial |
+            Item::SwapStone | Item::MarkingStone => {
                 let dist = if item == Item::Sling {
                     PLAYER_THROW_DIST
                 } else {
@@ -654,6 +965,24 @@ impl Level {
                 }
             }
 
+            Item::SpikeTrapKit | Item::FreezeTrapKit => {
+                // a kit can only be set up on an open, empty tile next to the player
+                let place_pos = dir.offset_pos(pos, 1);
+                if self.map.is_within_bounds(place_pos) &&
+                   self.map.path_blocked_move(pos, place_pos).is_none() &&
+                   self.has_blocking_entity(place_pos).is_none() &&
+                   self.item_at_pos(place_pos).is_none() {
+                    result.pos = Some(place_pos);
+                    result.hit_positions.push(place_pos);
+                }
+            }
+
+            Item::Boomerang => {
+                result.pos = Some(pos);
+                let end_pos = dir.offset_pos(pos, PLAYER_THROW_DIST as i32);
+                result.hit_positions = self.boomerang_path(pos, end_pos);
+            }
+
             Item::Dagger => {
                 let target_pos = dir.offset_pos(pos, 1);
                 let hit_pos = dir.offset_pos(target_pos, 1);
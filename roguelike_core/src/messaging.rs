@@ -13,6 +13,7 @@ use crate::movement::{MoveType, MoveMode};
 use crate::ai::Behavior;
 use crate::movement::Attack;
 use crate::level::*;
+use crate::constants::TUTORIAL_HINTS;
 
 
 #[derive(Copy, Clone, PartialEq, Debug, Deserialize, Serialize)]
@@ -22,18 +23,28 @@ pub enum Msg {
     Crushed(EntityId, Pos), // entity that did the crushing, position
     Sound(EntityId, Pos, usize), // entity causing sound, location, radius
     ReactToSound(EntityId, Pos), // entity causing sound, location
+    IdleSound(EntityId, Pos, usize), // entity causing sound, location, radius. Does not alert anyone.
     SoundHitTile(EntityId, Pos, usize, Pos), // entity causing sound, source pos, radius, hit pos
     SoundTrapTriggered(EntityId, EntityId), // trap, entity
     SpikeTrapTriggered(EntityId, EntityId), // trap, entity
     BlinkTrapTriggered(EntityId, EntityId), // trap, entity
     Blink(EntityId),
+    BlinkBehind(EntityId, Pos), // entity, position of the targeted enemy
     FreezeTrapTriggered(EntityId, EntityId), // trap, entity
     GateTriggered(EntityId, EntityId), // trap, entity
     Froze(EntityId, usize), // entity, num turns
+    MimicRevealed(EntityId),
+    Poisoned(EntityId, EntityId, usize), // entity, poisoner, num turns
+    PoisonDamage(EntityId, EntityId, i32), // entity, poisoner, damage
+    Ignite(Pos),
+    Burned(EntityId, EntityId, i32), // entity, fire, damage
     PlayerDeath,
     PickedUp(EntityId, EntityId), // entity, item
     PickUp(EntityId), // entity trying to pick up an item
     ItemThrow(EntityId, EntityId, Pos, Pos, bool), // thrower, stone id, start, end, hard
+    BoomerangThrow(EntityId, EntityId, Pos, Pos), // thrower, boomerang id, start, end
+    BoomerangReturn(EntityId, EntityId), // thrower, boomerang id
+    StoneVolley(EntityId, Pos, Pos), // thrower, start, end
     ItemLanded(EntityId, Pos, Pos), // stone id, start, end
     TryAttack(EntityId, Attack, Pos), // attacker, attack description, attack pos
     Attack(EntityId, EntityId, i32), // attacker, attacked, hp lost
@@ -61,18 +72,26 @@ pub enum Msg {
     Hit(EntityId, Pos, WeaponType, AttackStyle),
     HammerRaise(EntityId, usize, Direction), // entity, item index, direction moved
     HammerSwing(EntityId, EntityId, Pos), // entity, item, position swung at
+    AttackCharged(EntityId, EntityId), // entity, item
     HammerHitEntity(EntityId, EntityId), // entity, hit entity
     HammerHitWall(EntityId, Blocked),
     Stabbed(EntityId, EntityId), // entity, hit entity
     FailedBlink(EntityId),
     NotEnoughEnergy(EntityId),
+    SkillOnCooldown(EntityId, Skill, usize), // entity, skill, turns remaining
+    Respawned(EntityId, Pos, usize), // entity, start position, deaths
+    Spared(EntityId, Pos), // entity kept alive by practice mode, position it was left at
     DropFailed(EntityId),
     DroppedItem(EntityId, EntityId),
     PlayerTurn,
     Triggered(EntityId, EntityId), // trap, entity
+    Hint(usize), // hint index
     Untriggered(EntityId, EntityId), // trap, entity
     AddClass(EntityClass),
     DropItem(EntityId, u64), // entity, item index
+    InventoryFull(EntityId, EntityId), // entity, item
+    DropToPickUp(EntityId, usize, EntityId), // entity, inventory slot, item
+    CycleSlot(EntityId, ItemClass),
     GrassWall(EntityId, Direction),
     GrassThrow(EntityId, Direction),
     GrassShoes(EntityId, ActionMode),
@@ -81,11 +100,20 @@ pub enum Msg {
     Illuminate(EntityId, Pos, usize), // entity, position, amount
     HealSkill(EntityId, usize), // entity, amount
     EatHerb(EntityId, EntityId), // entity, item
+    TimeStop(EntityId, EntityId), // entity, item
+    RaiseMirror(EntityId, EntityId), // entity, item
+    Recall(EntityId, EntityId), // entity, item
+    RecallMarked(EntityId, Pos), // entity, marked position
+    FailedRecall(EntityId),
+    Reflected(EntityId, EntityId), // reflecting entity, attacker
     TryFarSight(EntityId, usize), // entity, amount
     FarSight(EntityId, usize), // entity, amount
+    TryWarCry(EntityId, usize), // entity, turns
+    WarCry(EntityId, usize), // entity, turns
     Ping(EntityId, Pos),
     Sprint(EntityId, Direction, usize), // entity, direction, amount
     Roll(EntityId, Direction, usize), // entity, direction, amount
+    RollLanded(EntityId),
     Rubble(EntityId, Pos),
     Reform(EntityId, Pos),
     StoneSkin(EntityId),
@@ -97,9 +125,12 @@ pub enum Msg {
     WhirlWind(EntityId, Pos),
     TrySwift(EntityId, Direction),
     Swift(EntityId, Pos),
+    TryDash(EntityId, Direction),
     ArmDisarmTrap(EntityId, EntityId), // acting entity, trap id
     PlaceTrap(EntityId, Pos, EntityId), // placing entity, position, trap id
     SpawnedObject(EntityId, EntityType, Pos, EntityName, Direction),
+    Summon(EntityId, Pos), // summoner, position of the new minion
+    Stole(EntityId, EntityId, EntityId), // thief, victim, item
     FaceTowards(EntityId, Pos), // set facing towards a position
     SetFacing(EntityId, Direction), // set the facing to a direction
     Facing(EntityId, Direction), // facing was modified for an entity
@@ -114,11 +145,14 @@ pub enum Msg {
     NewLevel,
     CursorState(bool, Pos),
     Restart,
+    ContinueGame,
+    ReturnToMenu(bool), // save the game first
     Forget(EntityId),
     Dodged(EntityId),
     Stance(EntityId, Stance),
     GainEnergy(EntityId, u32),
     UsedEnergy(EntityId),
+    UsedSkill(EntityId, Skill),
     GainStamina(EntityId, u32),
     RefillStamina(EntityId),
     NotEnoughStamina(EntityId),
@@ -127,13 +161,18 @@ pub enum Msg {
     SetPos(EntityId, Pos),
     GameState(GameState),
     CursorMove(Pos),
+    FreeCamera(bool, Pos), // whether the free camera is active, camera position
     AddSkill(Skill),
     AddTalent(Talent),
     GatePos(EntityId, Pos),
+    EntitySize(EntityId, i32, i32),
     Frozen(EntityId, bool),
     Thaw(EntityId, usize),
     Overlay(bool),
+    ToggleItemHighlight,
+    SoundDebug(bool),
     DebugEnabled(bool),
+    TimeScale(f32), // speed of animations and effects, below 1.0 for slow motion
     NextMoveMode(MoveMode),
     CursorAction(UseAction),
     TestMode(bool),
@@ -147,18 +186,28 @@ impl fmt::Display for Msg {
             Msg::Crushed(entity_id, pos) => write!(f, "crushed {} {} {}", entity_id, pos.x, pos.y),
             Msg::Sound(entity_id, pos, radius) => write!(f, "sound {} {} {} {}", entity_id, pos.x, pos.y, radius),
             Msg::ReactToSound(entity_id, pos) => write!(f, "react_to_sound {} {} {}", entity_id, pos.x, pos.y),
+            Msg::IdleSound(entity_id, pos, radius) => write!(f, "idle_sound {} {} {} {}", entity_id, pos.x, pos.y, radius),
             Msg::SoundHitTile(entity_id, source_pos, radius, hit_pos) => write!(f, "sound_hit_tile {} {} {} {} {} {}", entity_id, source_pos.x, source_pos.y, radius, hit_pos.x, hit_pos.y),
             Msg::SoundTrapTriggered(trap_id, entity_id) => write!(f, "sound_trap_triggered {} {}", trap_id, entity_id),
             Msg::SpikeTrapTriggered(trap_id, entity_id) => write!(f, "spike_trap_triggered {} {}", trap_id, entity_id),
             Msg::BlinkTrapTriggered(trap_id, entity_id) => write!(f, "blink_trap_triggered {} {}", trap_id, entity_id),
             Msg::Blink(entity_id) => write!(f, "blink {}", entity_id),
+            Msg::BlinkBehind(entity_id, target_pos) => write!(f, "blink_behind {} {} {}", entity_id, target_pos.x, target_pos.y),
             Msg::FreezeTrapTriggered(trap_id, entity_id) => write!(f, "freeze_trap_triggered {} {}", trap_id, entity_id),
             Msg::GateTriggered(trap_id, entity_id) => write!(f, "gate_triggered {} {}", trap_id, entity_id),
             Msg::Froze(entity_id, turns) => write!(f, "froze {} {}", entity_id, turns),
+            Msg::MimicRevealed(entity_id) => write!(f, "mimic_revealed {}", entity_id),
+            Msg::Poisoned(entity_id, poisoner, turns) => write!(f, "poisoned {} {} {}", entity_id, poisoner, turns),
+            Msg::PoisonDamage(entity_id, poisoner, damage) => write!(f, "poison_damage {} {} {}", entity_id, poisoner, damage),
+            Msg::Ignite(pos) => write!(f, "ignite {} {}", pos.x, pos.y),
+            Msg::Burned(entity_id, fire_id, damage) => write!(f, "burned {} {} {}", entity_id, fire_id, damage),
             Msg::PlayerDeath => write!(f, "player_death"),
             Msg::PickedUp(entity_id, item_id) => write!(f, "picked_up {} {}", entity_id, item_id),
             Msg::PickUp(entity_id) => write!(f, "pickup {}", entity_id),
             Msg::ItemThrow(entity_id, item_id, start, end, hard) => write!(f, "item_throw {} {} {} {} {} {} {}", entity_id, item_id, start.x, start.y, end.x, end.y, hard),
+            Msg::BoomerangThrow(entity_id, item_id, start, end) => write!(f, "boomerang_throw {} {} {} {} {} {}", entity_id, item_id, start.x, start.y, end.x, end.y),
+            Msg::BoomerangReturn(entity_id, item_id) => write!(f, "boomerang_return {} {}", entity_id, item_id),
+            Msg::StoneVolley(entity_id, start, end) => write!(f, "stone_volley {} {} {} {} {}", entity_id, start.x, start.y, end.x, end.y),
             Msg::ItemLanded(item_id, start, end) => write!(f, "item_landed {} {} {} {} {}", item_id, start.x, start.y, end.x, end.y),
             Msg::TryAttack(entity_id, attack, pos) => {
                 match attack {
@@ -189,6 +238,7 @@ impl fmt::Display for Msg {
                     Behavior::Investigating(pos) => write!(f, "state_change_investigating {} {} {}", entity_id, pos.x, pos.y),
                     Behavior::Attacking(target_id) => write!(f, "state_change_attacking {} {}", entity_id, target_id),
                     Behavior::Armed(turns) => write!(f, "state_change_armed {}", turns),
+                    Behavior::Fleeing(target_id) => write!(f, "state_change_fleeing {} {}", entity_id, target_id),
                 }
             }
             Msg::BehaviorChanged(entity_id, behavior) => {
@@ -198,6 +248,7 @@ impl fmt::Display for Msg {
                     Behavior::Investigating(pos) => write!(f, "behavior_changed_investigating {} {} {}", entity_id, pos.x, pos.y),
                     Behavior::Attacking(target_id) => write!(f, "behavior_changed_attacking {} {}", entity_id, target_id),
                     Behavior::Armed(turns) => write!(f, "behavior_changed_armed {}", turns),
+                    Behavior::Fleeing(target_id) => write!(f, "behavior_changed_fleeing {} {}", entity_id, target_id),
                 }
             }
             Msg::Collided(entity_id, pos) => write!(f, "collided {} {} {}", entity_id, pos.x, pos.y),
@@ -208,18 +259,26 @@ impl fmt::Display for Msg {
             Msg::Hit(entity_id, pos, weapon_type, attack_style) => write!(f, "hit {} {} {} {} {}", entity_id, pos.x, pos.y, weapon_type, attack_style),
             Msg::HammerRaise(entity_id, item_index, dir) => write!(f, "hammer_raise {} {} {}", entity_id, item_index, dir),
             Msg::HammerSwing(entity_id, item_id, pos) => write!(f, "hammer_swing {} {} {} {}", entity_id, item_id, pos.x, pos.y),
+            Msg::AttackCharged(entity_id, item_id) => write!(f, "attack_charged {} {}", entity_id, item_id),
             Msg::HammerHitEntity(entity_id, target_id) => write!(f, "hammer_hit_entity {} {}", entity_id, target_id),
             Msg::HammerHitWall(entity_id, blocked) => write!(f, "hammer_hit_wall {} {} {} {} {} {}", entity_id, blocked.start_pos, blocked.end_pos, blocked.direction, blocked.blocked_tile, blocked.wall_type),
             Msg::Stabbed(entity_id, target_id) => write!(f, "stabbed {} {}", entity_id, target_id),
             Msg::FailedBlink(entity_id) => write!(f, "failed_blink {}", entity_id),
             Msg::NotEnoughEnergy(entity_id) => write!(f, "not_enough_energy {}", entity_id),
+            Msg::SkillOnCooldown(entity_id, skill, turns) => write!(f, "skill_on_cooldown {} {} {}", entity_id, skill, turns),
+            Msg::Respawned(entity_id, pos, deaths) => write!(f, "respawned {} {} {} {}", entity_id, pos.x, pos.y, deaths),
+            Msg::Spared(entity_id, pos) => write!(f, "spared {} {} {}", entity_id, pos.x, pos.y),
             Msg::DropFailed(entity_id) => write!(f, "drop_failed {}", entity_id),
             Msg::DroppedItem(entity_id, item_id) => write!(f, "dropped_item {} {}", entity_id, item_id),
             Msg::PlayerTurn => write!(f, "player_turn"),
             Msg::Triggered(trap_id, entity_id) => write!(f, "triggered {} {}", trap_id, entity_id),
+            Msg::Hint(index) => write!(f, "hint {}", index),
             Msg::Untriggered(trap_id, entity_id) => write!(f, "untriggered {} {}", trap_id, entity_id),
             Msg::AddClass(entity_class) => write!(f, "add_class {}", entity_class),
             Msg::DropItem(entity_id, item_index) => write!(f, "drop_item {} {}", entity_id, item_index),
+            Msg::InventoryFull(entity_id, item_id) => write!(f, "inventory_full {} {}", entity_id, item_id),
+            Msg::DropToPickUp(entity_id, slot, item_id) => write!(f, "drop_to_pick_up {} {} {}", entity_id, slot, item_id),
+            Msg::CycleSlot(entity_id, item_class) => write!(f, "cycle_slot {} {}", entity_id, item_class),
             Msg::GrassThrow(entity_id, direction) => write!(f, "grass_throw {} {}", entity_id, direction),
             Msg::GrassWall(entity_id, direction) => write!(f, "grass_wall {} {}", entity_id, direction),
             Msg::GrassShoes(entity_id, action_mode) => write!(f, "grass_shoes {} {}", entity_id, action_mode),
@@ -228,11 +287,20 @@ impl fmt::Display for Msg {
             Msg::Illuminate(entity_id, pos, amount) => write!(f, "illuminate {} {} {} {}", entity_id, pos.x, pos.y, amount),
             Msg::HealSkill(entity_id, amount) => write!(f, "heal_skill {} {}", entity_id, amount),
             Msg::EatHerb(entity_id, item_id) => write!(f, "eat_herb {} {}", entity_id, item_id),
+            Msg::TimeStop(entity_id, item_id) => write!(f, "time_stop {} {}", entity_id, item_id),
+            Msg::RaiseMirror(entity_id, item_id) => write!(f, "raise_mirror {} {}", entity_id, item_id),
+            Msg::Recall(entity_id, item_id) => write!(f, "recall {} {}", entity_id, item_id),
+            Msg::RecallMarked(entity_id, pos) => write!(f, "recall_marked {} {} {}", entity_id, pos.x, pos.y),
+            Msg::FailedRecall(entity_id) => write!(f, "failed_recall {}", entity_id),
+            Msg::Reflected(entity_id, attacker_id) => write!(f, "reflected {} {}", entity_id, attacker_id),
             Msg::TryFarSight(entity_id, amount) => write!(f, "try_farsight {} {}", entity_id, amount),
             Msg::FarSight(entity_id, amount) => write!(f, "farsight {} {}", entity_id, amount),
+            Msg::TryWarCry(entity_id, turns) => write!(f, "try_war_cry {} {}", entity_id, turns),
+            Msg::WarCry(entity_id, turns) => write!(f, "war_cry {} {}", entity_id, turns),
             Msg::Ping(entity_id, pos) => write!(f, "ping {} {} {}", entity_id, pos.x, pos.y),
             Msg::Sprint(entity_id, direction, amount) => write!(f, "sprint {} {} {}", entity_id, direction, amount),
             Msg::Roll(entity_id, direction, amount) => write!(f, "roll {} {} {}", entity_id, direction, amount),
+            Msg::RollLanded(entity_id) => write!(f, "roll_landed {}", entity_id),
             Msg::Rubble(entity_id, pos) => write!(f, "rubble {} {} {}", entity_id, pos.x, pos.y),
             Msg::Reform(entity_id, pos) => write!(f, "reform {} {} {}", entity_id, pos.x, pos.y),
             Msg::StoneSkin(entity_id) => write!(f, "stone_skin {}", entity_id),
@@ -244,9 +312,12 @@ impl fmt::Display for Msg {
             Msg::WhirlWind(entity_id, pos) => write!(f, "whirlwind {} {} {}", entity_id, pos.x, pos.y),
             Msg::TrySwift(entity_id, direction) => write!(f, "try_swift {} {}", entity_id, direction),
             Msg::Swift(entity_id, pos) => write!(f, "swift {} {} {}", entity_id, pos.x, pos.y),
+            Msg::TryDash(entity_id, direction) => write!(f, "try_dash {} {}", entity_id, direction),
             Msg::ArmDisarmTrap(entity_id, trap_id) => write!(f, "arm_disarm_trap {} {}", entity_id, trap_id),
             Msg::PlaceTrap(entity_id, pos, trap_id) => write!(f, "place_trap {} {} {} {}", entity_id, pos.x, pos.y, trap_id),
             Msg::SpawnedObject(entity_id, entity_type, pos, entity_name, facing) => write!(f, "spawned {} {} {} {} {} {}", entity_id, entity_type, pos.x, pos.y, entity_name, facing),
+            Msg::Summon(entity_id, pos) => write!(f, "summon {} {} {}", entity_id, pos.x, pos.y),
+            Msg::Stole(entity_id, target_id, item_id) => write!(f, "stole {} {} {}", entity_id, target_id, item_id),
             Msg::FaceTowards(entity_id, pos) => write!(f, "face_towards {} {} {}", entity_id, pos.x, pos.y),
             Msg::SetFacing(entity_id, direction) => write!(f, "set_facing {} {}", entity_id, direction),
             Msg::Facing(entity_id, direction) => write!(f, "facing {} {}", entity_id, direction),
@@ -261,11 +332,14 @@ impl fmt::Display for Msg {
             Msg::NewLevel => write!(f, "newlevel"),
             Msg::CursorState(state, pos) => write!(f, "cursorstate {} {} {}", state, pos.x, pos.y),
             Msg::Restart => write!(f, "restart"),
+            Msg::ContinueGame => write!(f, "continue_game"),
+            Msg::ReturnToMenu(save) => write!(f, "return_to_menu {}", save),
             Msg::Forget(entity_id) => write!(f, "forget {}", entity_id),
             Msg::Dodged(entity_id) => write!(f, "dodged {}", entity_id),
             Msg::Stance(entity_id, stance) => write!(f, "stance {} {}", entity_id, stance),
             Msg::GainEnergy(entity_id, amount) => write!(f, "gain_energy {} {}", entity_id, amount),
             Msg::UsedEnergy(entity_id) => write!(f, "used_energy {}", entity_id),
+            Msg::UsedSkill(entity_id, skill) => write!(f, "used_skill {} {}", entity_id, skill),
             Msg::GainStamina(entity_id, amount) => write!(f, "gain_stamina {} {}", entity_id, amount),
             Msg::RefillStamina(entity_id) => write!(f, "refill_stamina {}", entity_id),
             Msg::NotEnoughStamina(entity_id) => write!(f, "not_enough_stamina {}", entity_id),
@@ -274,13 +348,18 @@ impl fmt::Display for Msg {
             Msg::SetPos(entity_id, pos) => write!(f, "set_pos {} {} {}", entity_id, pos.x, pos.y),
             Msg::GameState(state) => write!(f, "game_state {}", state),
             Msg::CursorMove(pos) => write!(f, "cursor_move {} {}", pos.x, pos.y),
+            Msg::FreeCamera(state, pos) => write!(f, "free_camera {} {} {}", state, pos.x, pos.y),
             Msg::AddSkill(skill) => write!(f, "add_skill {}", skill),
             Msg::AddTalent(talent) => write!(f, "add_talent {}", talent),
             Msg::GatePos(entity_id, pos) => write!(f, "gate_pos {} {} {}", entity_id, pos.x, pos.y),
+            Msg::EntitySize(entity_id, width, height) => write!(f, "entity_size {} {} {}", entity_id, width, height),
             Msg::Frozen(entity_id, state) => write!(f, "frozen {} {}", entity_id, state),
             Msg::Thaw(entity_id, amount) => write!(f, "thaw {} {}", entity_id, amount),
             Msg::Overlay(state) => write!(f, "overlay {}", state),
+            Msg::ToggleItemHighlight => write!(f, "toggle_item_highlight"),
+            Msg::SoundDebug(state) => write!(f, "sound_debug {}", state),
             Msg::DebugEnabled(state) => write!(f, "debug_enabled {}", state),
+            Msg::TimeScale(scale) => write!(f, "time_scale {}", scale),
             Msg::NextMoveMode(move_mode) => write!(f, "next_move_mode {}", move_mode),
             Msg::CursorAction(use_action) => write!(f, "cursor_action {}", use_action),
             Msg::TestMode(state) => write!(f, "test_mode {}", state),
@@ -289,7 +368,45 @@ impl fmt::Display for Msg {
 }
 
 
+/// How many messages are written to the console, from fewest to most.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum LogVerbosity {
+    Minimal,
+    Normal,
+    Verbose,
+}
+
+impl Default for LogVerbosity {
+    fn default() -> LogVerbosity {
+        return LogVerbosity::Verbose;
+    }
+}
+
 impl Msg {
+    /// The lowest verbosity at which this message is written to the console.
+    /// Deaths and level changes are always shown, and combat is shown unless only
+    /// critical messages are wanted. Routine movement is only shown when verbose.
+    pub fn verbosity(&self) -> LogVerbosity {
+        match self {
+            Msg::PlayerDeath | Msg::NewLevel | Msg::Respawned(_, _, _) | Msg::Spared(_, _) |
+            Msg::Killed(_, _, _) | Msg::Attack(_, _, _) | Msg::Stabbed(_, _) |
+            Msg::HammerHitEntity(_, _) | Msg::ExplosionHit(_, _) |
+            Msg::PoisonDamage(_, _, _) | Msg::Burned(_, _, _) | Msg::Stole(_, _, _) => {
+                return LogVerbosity::Minimal;
+            }
+
+            Msg::Moved(_, _, _, _) | Msg::TryMove(_, _, _, _) | Msg::Collided(_, _) |
+            Msg::ChangeMoveMode(_, _) | Msg::MoveMode(_, _) | Msg::BehaviorChanged(_, _) |
+            Msg::Pass | Msg::JumpWall(_, _, _) | Msg::WallKick(_, _) => {
+                return LogVerbosity::Verbose;
+            }
+
+            _ => {
+                return LogVerbosity::Normal;
+            }
+        }
+    }
+
     pub fn msg_line(&self, data: &Level) -> String {
         match self {
             // No need to show this to the player
@@ -309,7 +426,7 @@ impl Msg {
                 return "Blink trap triggered".to_string();
             }
 
-            Msg::Blink(entity_id) => {
+            Msg::Blink(entity_id) | Msg::BlinkBehind(entity_id, _) => {
                 return format!("{:?} blinked",
                                data.entities.name[entity_id].clone());
             }
@@ -330,20 +447,46 @@ impl Msg {
                 return "Gate activated".to_string();
             }
 
+            Msg::Hint(index) => {
+                return TUTORIAL_HINTS.get(*index).unwrap_or(&"").to_string();
+            }
+
             Msg::PlayerDeath => {
                 return "Player died!".to_string();
             }
 
+            Msg::NewLevel => {
+                return "Entered a new level".to_string();
+            }
+
             Msg::PickedUp(entity_id, item_id) => {
                 return format!("{:?} picked up a {:?}",
                                data.entities.name[entity_id].clone(),
                                data.entities.name[item_id].clone());
             }
 
+            Msg::Stole(entity_id, _target_id, item_id) => {
+                return format!("{:?} stole a {:?}!",
+                               data.entities.name[entity_id].clone(),
+                               data.entities.name[item_id].clone());
+            }
+
+            Msg::InventoryFull(_entity_id, item_id) => {
+                return format!("No room for the {:?}", data.entities.name[item_id].clone());
+            }
+
             Msg::ItemThrow(_thrower, _item, _start, _end, _hard) => {
                 return "Item throw".to_string();
             }
 
+            Msg::BoomerangThrow(entity_id, _item, _start, _end) => {
+                return format!("{:?} throws a boomerang", data.entities.name[entity_id]);
+            }
+
+            Msg::StoneVolley(entity_id, _start, _end) => {
+                return format!("{:?} throws a volley of stones", data.entities.name[entity_id]);
+            }
+
             Msg::TryAttack(_entity_id, _attack_info, _attack_pos) => {
                 return "".to_string();
             }
@@ -439,6 +582,10 @@ impl Msg {
                 return format!("{:?} swung their hammer", data.entities.name[entity_id]);
             }
 
+            Msg::AttackCharged(entity_id, item_id) => {
+                return format!("{:?} readies their {:?}", data.entities.name[entity_id], data.entities.name[item_id]);
+            }
+
             Msg::HammerHitEntity(entity_id, hit_entity) => {
                 let entity_name = &data.entities.name[entity_id];
                 let hit_entity_name = &data.entities.name[hit_entity];
@@ -463,14 +610,42 @@ impl Msg {
                 return format!("{:?} does not have enough energy for that", data.entities.name[entity_id]);
             }
 
+            Msg::SkillOnCooldown(entity_id, skill, turns) => {
+                return format!("{:?} can't use {:?} for {} more turns", data.entities.name[entity_id], skill, turns);
+            }
+
+            Msg::Respawned(entity_id, _pos, deaths) => {
+                return format!("{:?} died and returned to the start of the level ({} deaths)", data.entities.name[entity_id], deaths);
+            }
+
+            Msg::Spared(entity_id, _pos) => {
+                return format!("{:?} would have died, but practice mode kept them alive", data.entities.name[entity_id]);
+            }
+
             Msg::DropFailed(entity_id) => {
                 return format!("{:?} tried to drop an item, but its too crowded!", data.entities.name[entity_id]);
             }
 
+            Msg::MimicRevealed(_entity_id) => {
+                return "The herb was a mimic!".to_string();
+            }
+
             Msg::Froze(entity_id, _num_turns) => {
                 return format!("{:?} was frozen!", data.entities.name[entity_id]);
             }
 
+            Msg::Poisoned(entity_id, _poisoner, _num_turns) => {
+                return format!("{:?} was poisoned!", data.entities.name[entity_id]);
+            }
+
+            Msg::PoisonDamage(entity_id, _poisoner, damage) => {
+                return format!("{:?} took {} poison damage", data.entities.name[entity_id], damage);
+            }
+
+            Msg::Burned(entity_id, _fire_id, damage) => {
+                return format!("{:?} took {} fire damage", data.entities.name[entity_id], damage);
+            }
+
             // These probably doesn't need to be printed
             //Msg::Triggered(trap, entity_id) => {
             //    return format!("{:?} triggered a {:?} trap!", data.entities.name[entity_id], data.entities.name[trap]);
@@ -520,10 +695,34 @@ impl Msg {
                 return format!("{:?} ate an herb", data.entities.name[entity_id]);
             }
 
+            Msg::TimeStop(entity_id, _item_id) => {
+                return format!("{:?} turns the hourglass, and time stops", data.entities.name[entity_id]);
+            }
+
+            Msg::RaiseMirror(entity_id, _item_id) => {
+                return format!("{:?} raises a mirror", data.entities.name[entity_id]);
+            }
+
+            Msg::RecallMarked(entity_id, _pos) => {
+                return format!("{:?} marks this place with the recall stone", data.entities.name[entity_id]);
+            }
+
+            Msg::FailedRecall(entity_id) => {
+                return format!("{:?} failed to recall, the marked place is blocked", data.entities.name[entity_id]);
+            }
+
+            Msg::Reflected(entity_id, attacker_id) => {
+                return format!("{:?} reflects the attack back at {:?}", data.entities.name[entity_id], data.entities.name[attacker_id]);
+            }
+
             Msg::FarSight(entity_id, amount) => {
                 return format!("{:?} has farsight ({})", data.entities.name[entity_id], amount);
             }
 
+            Msg::WarCry(entity_id, turns) => {
+                return format!("{:?} lets out a war cry, hasted for {} turns", data.entities.name[entity_id], turns);
+            }
+
             Msg::Ping(entity_id, pos) => {
                 return format!("{:?} has pinged ({})", data.entities.name[entity_id], pos);
             }
@@ -620,6 +819,7 @@ pub enum InfoMsg {
     EntityInFov(EntityId, FovResult),
     EntityAtCursor(EntityId),
     EntityMovement(EntityId, Pos),
+    EntityIntent(EntityId, Pos),
     EntityAttack(EntityId, Pos),
     EntityFov(EntityId, Pos),
     UsePos(Pos),
@@ -630,10 +830,23 @@ pub enum InfoMsg {
     UseOption(Pos, Direction),
     TileFov(Pos, FovResult),
     Impression(Pos),
+    ImpressionCleared(Pos),
+    PingMarker(Pos),
     InventoryItem(Item, ItemClass),
+    AttackCharged,
+    Hasted(usize), // turns
+    TimeStop(usize), // turns
+    SkillCooldown(Skill, usize),
+    UnidentifiedItem(Item),
     PlayerGhost(Pos),
     PlayerAction,
     UseAction(UseAction),
+    GrassWallPreview(Pos, Direction),
+    PassWallPreview(Pos),
+    BlinkPreview(Pos),
+    LineOfFire(LineOfFire),
+    BestiaryEntry(EntityName, i32, Behavior), // enemy, max hp, behavior when first seen
+    RunStats(RunStats),
 }
 
 impl fmt::Display for InfoMsg {
@@ -642,6 +855,7 @@ impl fmt::Display for InfoMsg {
             InfoMsg::EntityInFov(entity_id, in_fov) => write!(f, "entity_in_fov {} {}", entity_id, in_fov),
             InfoMsg::EntityAtCursor(entity_id) => write!(f, "entity_at_cursor {}", entity_id),
             InfoMsg::EntityMovement(entity_id, pos) => write!(f, "entity_movement {} {} {}", entity_id, pos.x, pos.y),
+            InfoMsg::EntityIntent(entity_id, pos) => write!(f, "entity_intent {} {} {}", entity_id, pos.x, pos.y),
             InfoMsg::EntityAttack(entity_id, pos) => write!(f, "entity_attack {} {} {}", entity_id, pos.x, pos.y),
             InfoMsg::EntityFov(entity_id, pos) => write!(f, "entity_fov {} {} {}", entity_id, pos.x, pos.y),
             InfoMsg::UsePos(pos) => write!(f, "use_pos {} {}", pos.x, pos.y),
@@ -652,10 +866,25 @@ impl fmt::Display for InfoMsg {
             InfoMsg::UseOption(pos, dir) => write!(f, "use_option {} {} {}", pos.x, pos.y, dir),
             InfoMsg::TileFov(pos, fov_result) => write!(f, "fov_result {} {} {}", pos.x, pos.y, fov_result),
             InfoMsg::Impression(pos) => write!(f, "impression {} {}", pos.x, pos.y),
+            InfoMsg::ImpressionCleared(pos) => write!(f, "impression_cleared {} {}", pos.x, pos.y),
+            InfoMsg::PingMarker(pos) => write!(f, "ping_marker {} {}", pos.x, pos.y),
             InfoMsg::InventoryItem(item, item_class) => write!(f, "inventory_item {} {}", item, item_class),
+            InfoMsg::AttackCharged => write!(f, "attack_charged"),
+            InfoMsg::Hasted(turns) => write!(f, "hasted {}", turns),
+            InfoMsg::TimeStop(turns) => write!(f, "time_stop {}", turns),
+            InfoMsg::SkillCooldown(skill, turns) => write!(f, "skill_cooldown {} {}", skill, turns),
+            InfoMsg::UnidentifiedItem(item) => write!(f, "unidentified_item {}", item),
             InfoMsg::PlayerGhost(pos) => write!(f, "player_ghost {} {}", pos.x, pos.y),
             InfoMsg::PlayerAction => write!(f, "player_action"),
             InfoMsg::UseAction(use_action) => write!(f, "use_action {}", use_action),
+            InfoMsg::GrassWallPreview(pos, dir) => write!(f, "grass_wall_preview {} {} {}", pos.x, pos.y, dir),
+            InfoMsg::PassWallPreview(pos) => write!(f, "pass_wall_preview {} {}", pos.x, pos.y),
+            InfoMsg::BlinkPreview(pos) => write!(f, "blink_preview {} {}", pos.x, pos.y),
+            InfoMsg::LineOfFire(line_of_fire) => write!(f, "line_of_fire {}", line_of_fire),
+            InfoMsg::BestiaryEntry(name, hp, behavior) => write!(f, "bestiary_entry {} {} {}", name, hp, behavior),
+            InfoMsg::RunStats(stats) => write!(f, "run_stats {} {} {} {} {} {} {}",
+                                               stats.turns, stats.tiles_explored, stats.enemies_killed,
+                                               stats.damage_dealt, stats.damage_taken, stats.skills_used, stats.items_thrown),
         }
     }
 }
@@ -722,3 +951,14 @@ impl MsgLog {
     }
 }
 
+
+#[test]
+pub fn test_log_verbosity_keeps_critical_messages() {
+    // critical messages are shown at every verbosity
+    assert_eq!(LogVerbosity::Minimal, Msg::PlayerDeath.verbosity());
+    assert_eq!(LogVerbosity::Minimal, Msg::NewLevel.verbosity());
+
+    let moved = Msg::Moved(0, MoveType::Move, MoveMode::Walk, Pos::new(1, 1));
+    assert!(moved.verbosity() > LogVerbosity::Normal);
+    assert!(moved.verbosity() <= LogVerbosity::Verbose);
+}
@@ -80,6 +80,7 @@ pub enum MoveType {
     Collide,
     Blink,
     Misc,
+    Pushed, // forced on an entity, so it makes no sound of its own and springs no traps
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
@@ -596,8 +597,12 @@ pub fn entity_move_blocked_by_entity(entity_id: EntityId,
         let next_tile_water = level.map[next].tile_type == TileType::Water;
         let push_is_blocked = level.map.path_blocked_move(other_pos, next).is_some();
         let is_column = level.entities.typ[&other_id] == EntityType::Column;
+        // boulders can be pushed into water, where they sink
+        let is_boulder = level.entities.name[&other_id] == EntityName::Boulder;
 
-        if level.can_push(entity_id, other_id) && !next_tile_water && !(push_is_blocked && is_column) {
+        if level.can_push(entity_id, other_id) &&
+           !(next_tile_water && !is_boulder) &&
+           !(push_is_blocked && is_column) {
             let direction = Direction::from_dxy(delta_pos.x, delta_pos.y).unwrap();
             let push_amount = 1;
             // TODO issue 150 this is where pushing comes from. 
@@ -628,7 +633,8 @@ pub fn entity_move_blocked_by_entity_and_wall(entity_id: EntityId, other_id: Ent
         let attack: Option<Attack>;
         if can_stab(level, entity_id, other_id) {
             attack = Some(Attack::Stab(other_id, true));
-        } else if level.map[next_pos(pos, dxy)].tile_type != TileType::Water {
+        } else if level.map[next_pos(pos, dxy)].tile_type != TileType::Water ||
+                  level.entities.name[&other_id] == EntityName::Boulder {
             let direction = Direction::from_dxy(delta_pos.x, delta_pos.y).unwrap();
             let push_amount = 1;
             attack = Some(Attack::Push(other_id, direction, push_amount));
@@ -683,6 +689,39 @@ pub fn entity_move_blocked_by_entity_and_wall(entity_id: EntityId, other_id: Ent
     return movement;
 }
 
+/// Movement for entities covering more then one tile. These entities only move
+/// when their entire footprint is clear, and do not push or jump walls.
+fn calculate_large_move(dir: Direction,
+                        reach: Reach,
+                        entity_id: EntityId,
+                        level: &Level) -> Option<Movement> {
+    let pos = level.entities.pos[&entity_id];
+
+    if let Some(delta_pos) = reach.move_with_reach(&dir) {
+        let mut move_pos = pos;
+        for line_pos in line(pos, add_pos(pos, delta_pos)) {
+            if level.footprint_blocked(entity_id, line_pos) {
+                break;
+            }
+
+            // each tile of the footprint must be able to make the move
+            let footprint = level.entities.footprint_at(entity_id, move_pos);
+            let offset = sub_pos(line_pos, move_pos);
+            if footprint.iter().any(|tile_pos| level.map.move_blocked(*tile_pos, add_pos(*tile_pos, offset), BlockedType::Move).is_some()) {
+                break;
+            }
+
+            move_pos = line_pos;
+        }
+
+        if move_pos != pos {
+            return Some(Movement::move_to(move_pos, MoveType::Move));
+        }
+    }
+
+    return None;
+}
+
 pub fn calculate_move(dir: Direction,
                       reach: Reach,
                       entity_id: EntityId,
@@ -691,6 +730,10 @@ pub fn calculate_move(dir: Direction,
 
     let pos = level.entities.pos[&entity_id];
 
+    if level.entities.size.get(&entity_id).is_some() {
+        return calculate_large_move(dir, reach, entity_id, level);
+    }
+
     // get the location we would move to given the input direction
     if let Some(delta_pos) = reach.move_with_reach(&dir) {
         let (dx, dy) = delta_pos.to_tuple();
@@ -28,6 +28,29 @@ impl UseResult {
     }
 }
 
+/// Whether an item thrown at a position would reach it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, FromStr, Serialize, Deserialize)]
+#[display(style="snake_case")]
+pub enum LineOfFire {
+    Clear,
+    BlockedByWall,
+    BlockedByEnemy,
+    BlockedByObject,
+    OutOfRange,
+}
+
+impl LineOfFire {
+    pub fn description(&self) -> &str {
+        match self {
+            LineOfFire::Clear => "clear",
+            LineOfFire::BlockedByWall => "blocked by wall",
+            LineOfFire::BlockedByEnemy => "blocked by enemy",
+            LineOfFire::BlockedByObject => "blocked by object",
+            LineOfFire::OutOfRange => "out of range, lands short",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Turn {
     Pass = 0x01,
@@ -115,6 +138,17 @@ pub enum Trap {
     Freeze,
 }
 
+impl Trap {
+    pub fn name(&self) -> EntityName {
+        match self {
+            Trap::Spikes => EntityName::SpikeTrap,
+            Trap::Sound => EntityName::SoundTrap,
+            Trap::Blink => EntityName::BlinkTrap,
+            Trap::Freeze => EntityName::FreezeTrap,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Display, FromStr, Serialize, Deserialize)]
 #[display(style = "snake_case")]
 pub enum Talent {
@@ -125,7 +159,7 @@ pub enum Talent {
     EnergyShield,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Display, FromStr, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Display, FromStr, Serialize, Deserialize)]
 #[display(style = "snake_case")]
 pub enum Skill {
     GrassWall,
@@ -151,6 +185,9 @@ pub enum Skill {
     PassThrough,
     WhirlWind,
     Swift,
+    Dash,
+    WarCry,
+    StoneVolley,
 }
 
 impl Skill {
@@ -179,6 +216,9 @@ impl Skill {
             Skill::PassThrough => EntityClass::Wind,
             Skill::WhirlWind => EntityClass::Wind,
             Skill::Swift => EntityClass::Wind,
+            Skill::Dash => EntityClass::Body,
+            Skill::WarCry => EntityClass::Body,
+            Skill::StoneVolley => EntityClass::Monolith,
         }
     }
 
@@ -207,6 +247,9 @@ impl Skill {
             Skill::PassThrough => SkillMode::Direction,
             Skill::WhirlWind => SkillMode::Cursor,
             Skill::Swift => SkillMode::Direction,
+            Skill::Dash => SkillMode::Direction,
+            Skill::WarCry => SkillMode::Immediate,
+            Skill::StoneVolley => SkillMode::Direction,
         }
     }
 }
@@ -228,8 +271,12 @@ pub enum GameState {
     SkillMenu,
     ClassMenu,
     HelpMenu,
+    BestiaryMenu,
     ConfirmQuit,
+    MainMenu,
+    TimeUp,
     Use,
+    FreeCamera,
     Exit,
 }
 
@@ -244,7 +291,12 @@ impl GameState {
         return self == GameState::Inventory   ||
                self == GameState::SkillMenu   ||
                self == GameState::ConfirmQuit ||
+               self == GameState::MainMenu    ||
+               self == GameState::TimeUp      ||
+               self == GameState::Lose        ||
+               self == GameState::Win         ||
                self == GameState::HelpMenu    ||
+               self == GameState::BestiaryMenu ||
                self == GameState::ClassMenu;
     }
 }
@@ -269,7 +321,7 @@ impl ItemModifier {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Display, FromStr, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Display, FromStr, Serialize, Deserialize)]
 #[display(style = "lowercase")]
 pub enum Item {
     Stone,
@@ -285,6 +337,7 @@ pub enum Item {
     Lantern,
     Thumper,
     Sling,
+    Boomerang,
     Teleporter,
     Herb,
     SeedOfStone,
@@ -292,10 +345,18 @@ pub enum Item {
     SmokeBomb,
     LookingGlass,
     GlassEye,
+    PoisonVial,
+    SwapStone,
+    MarkingStone,
+    RecallStone,
+    Hourglass,
+    Mirror,
     SpikeTrap,
     SoundTrap,
     BlinkTrap,
     FreezeTrap,
+    SpikeTrapKit,
+    FreezeTrapKit,
 }
 
 impl Item {
@@ -312,11 +373,18 @@ impl Item {
             Item::Axe => ItemClass::Primary,
             Item::Khopesh => ItemClass::Primary,
             Item::Sling => ItemClass::Primary,
+            Item::Boomerang => ItemClass::Primary,
             Item::Teleporter => ItemClass::Consumable,
             Item::Herb => ItemClass::Consumable,
             Item::SeedOfStone => ItemClass::Consumable,
             Item::SeedCache => ItemClass::Consumable,
             Item::SmokeBomb => ItemClass::Consumable,
+            Item::PoisonVial => ItemClass::Consumable,
+            Item::SwapStone => ItemClass::Consumable,
+            Item::MarkingStone => ItemClass::Consumable,
+            Item::RecallStone => ItemClass::Consumable,
+            Item::Hourglass => ItemClass::Consumable,
+            Item::Mirror => ItemClass::Consumable,
             Item::LookingGlass => ItemClass::Consumable,
             Item::GlassEye => ItemClass::Consumable,
             Item::Lantern => ItemClass::Consumable,
@@ -325,6 +393,8 @@ impl Item {
             Item::SoundTrap => ItemClass::Consumable,
             Item::BlinkTrap => ItemClass::Consumable,
             Item::FreezeTrap => ItemClass::Consumable,
+            Item::SpikeTrapKit => ItemClass::Consumable,
+            Item::FreezeTrapKit => ItemClass::Consumable,
         }
     }
 
@@ -347,13 +417,22 @@ impl Item {
             Item::SmokeBomb => EntityName::SmokeBomb,
             Item::LookingGlass => EntityName::LookingGlass,
             Item::GlassEye => EntityName::GlassEye,
+            Item::PoisonVial => EntityName::PoisonVial,
+            Item::SwapStone => EntityName::SwapStone,
+            Item::MarkingStone => EntityName::MarkingStone,
+            Item::RecallStone => EntityName::RecallStone,
+            Item::Hourglass => EntityName::Hourglass,
+            Item::Mirror => EntityName::Mirror,
             Item::Lantern => EntityName::Lantern,
             Item::Thumper => EntityName::Thumper,
             Item::Sling => EntityName::Sling,
+            Item::Boomerang => EntityName::Boomerang,
             Item::SpikeTrap => EntityName::SpikeTrap,
             Item::SoundTrap => EntityName::SoundTrap,
             Item::BlinkTrap => EntityName::BlinkTrap,
             Item::FreezeTrap => EntityName::FreezeTrap,
+            Item::SpikeTrapKit => EntityName::SpikeTrapKit,
+            Item::FreezeTrapKit => EntityName::FreezeTrapKit,
         }
     }
 
@@ -367,6 +446,7 @@ impl Item {
             Item::Sword => Some(WeaponType::Slash),
             
             Item::Sling => Some(WeaponType::Blunt),
+            Item::Boomerang => Some(WeaponType::Blunt),
             Item::Axe => Some(WeaponType::Slash),
             Item::Khopesh => Some(WeaponType::Slash),
 
@@ -376,6 +456,12 @@ impl Item {
             Item::SmokeBomb => None,
             Item::LookingGlass => None,
             Item::GlassEye => None,
+            Item::PoisonVial => None,
+            Item::SwapStone => None,
+            Item::MarkingStone => None,
+            Item::RecallStone => None,
+            Item::Hourglass => None,
+            Item::Mirror => None,
             Item::Herb => None,
             Item::Stone => None,
             Item::Key => None,
@@ -385,6 +471,23 @@ impl Item {
             Item::SoundTrap => None,
             Item::BlinkTrap => None,
             Item::FreezeTrap => None,
+            Item::SpikeTrapKit => None,
+            Item::FreezeTrapKit => None,
+        }
+    }
+
+    /// The name shown for a consumable that has not been identified yet.
+    pub fn unknown_name(&self) -> &'static str {
+        match self {
+            Item::Herb => "Unknown Herb",
+            Item::SeedOfStone | Item::SeedCache => "Unknown Seed",
+            Item::SmokeBomb | Item::PoisonVial => "Unknown Vial",
+            Item::LookingGlass | Item::GlassEye | Item::Mirror => "Unknown Glass",
+            Item::Teleporter | Item::SwapStone | Item::MarkingStone | Item::RecallStone => "Unknown Stone",
+            Item::Lantern | Item::Thumper | Item::Hourglass => "Unknown Device",
+            Item::SpikeTrap | Item::SoundTrap | Item::BlinkTrap | Item::FreezeTrap |
+            Item::SpikeTrapKit | Item::FreezeTrapKit => "Unknown Trap",
+            _ => "Unknown Item",
         }
     }
 
@@ -395,10 +498,21 @@ impl Item {
         }
     }
 
+    /// The trap a trap kit sets up when it is placed.
+    pub fn kit_trap(&self) -> Option<Trap> {
+        match self {
+            Item::SpikeTrapKit => Some(Trap::Spikes),
+            Item::FreezeTrapKit => Some(Trap::Freeze),
+            _ => None,
+        }
+    }
+
     pub fn throw_stun_turns(&self, config: &Config) -> usize {
         match self {
             Item::Stone => config.stun_turns_throw_stone,
             Item::Spear => config.stun_turns_throw_spear,
+            Item::SwapStone => 0,
+            Item::MarkingStone => 0,
             _ => config.stun_turns_throw_default,
         }
     }
@@ -469,7 +583,9 @@ pub enum EntityName {
     Gol,
     Pawn,
     Rook,
+    Mimic,
     Column,
+    Boulder,
     Key,
     Exit,
     Dagger,
@@ -483,19 +599,31 @@ pub enum EntityName {
     Axe,
     Khopesh,
     Sling,
+    Boomerang,
     SeedOfStone,
     SeedCache,
     SmokeBomb,
     LookingGlass,
     GlassEye,
+    PoisonVial,
+    SwapStone,
+    MarkingStone,
+    RecallStone,
+    Hourglass,
+    Mirror,
     Teleporter,
     Spire,
+    Summoner,
+    Thief,
     Armil,
     SpikeTrap,
     BlinkTrap,
     FreezeTrap,
     SoundTrap,
+    SpikeTrapKit,
+    FreezeTrapKit,
     GateTrigger,
+    HintTrigger,
     Stone,
     Mouse,
     Cursor,
@@ -504,6 +632,7 @@ pub enum EntityName {
     Grass,
     Statue,
     Smoke,
+    Fire,
     Magnifier,
     Other,
 }
@@ -563,6 +692,7 @@ pub struct Passive {
     pub light_touch: bool,
     pub sure_footed: bool,
     pub quick_reflexes: bool,
+    pub wall_sight: bool,
 }
 
 impl Passive {
@@ -571,17 +701,41 @@ impl Passive {
     }
 }
 
+/// Totals for the current run, shown on the end screen. These are kept across levels
+/// and in saves, and only reset when a new run is started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub turns: usize,
+    pub tiles_explored: usize,
+    pub enemies_killed: usize,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub skills_used: usize,
+    pub items_thrown: usize,
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct StatusEffect {
     pub frozen: usize, // turns
+    pub poisoned: usize, // turns
+    pub poisoner: Option<EntityId>,
+    pub last_damager: Option<EntityId>, // the entity, trap, or column that last hurt this entity
     pub soft_steps: usize, // turns
+    pub hasted: usize, // turns
+    pub time_stop: usize, // turns during which enemies do not act
+    pub reflecting: bool, // the next enemy attack this turn is turned back on the attacker
+    pub boomerang: Option<(EntityId, usize)>, // on a boomerang in flight, its thrower and turns until it returns
+    pub disguised: bool, // a mimic that still looks like an item
     pub extra_fov: usize, // amount
     pub blinked: bool,
+    pub rolling: bool, // passing over traps during a dodge roll
+    pub fresh_trap: bool, // a trap placed from a kit, which the player does not set off on the following turn
     pub active: bool,
     pub alive: bool,
     pub stone: usize,
     pub land_roll: bool,
-    pub hammer_raised: Option<(EntityId, Direction, usize)>, // item id, direction to hit, turns to wait
+    pub charged_attack: Option<(EntityId, Direction, usize)>, // item id, direction to hit, turns to wait
+    pub marked: Option<(usize, Pos)>, // turns left, and where its impression was last shown
     pub test_mode: bool,
 }
 
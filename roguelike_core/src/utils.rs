@@ -16,6 +16,15 @@ use crate::entities::*;
 use crate::level::*;
 
 
+/// How far a thrown item can travel. A stone shot from a sling, a hard throw, goes further.
+pub fn throw_range(hard: bool) -> usize {
+    if hard {
+        return SLING_THROW_DIST;
+    } else {
+        return PLAYER_THROW_DIST;
+    }
+}
+
 pub fn reduce_item_durability(data: &mut Level, entity_id: EntityId, item_id: EntityId) {
     if let Some(durability) = data.entities.durability.get_mut(&item_id) {
         if *durability > 1 {
@@ -109,7 +118,7 @@ pub fn push_attack(entity_id: EntityId,
 pub fn crush(handle: EntityId, target: EntityId, entities: &mut Entities, msg_log: &mut MsgLog) {
     let damage = entities.hp.get(&target).map_or(0, |f| f.hp);
     if damage > 0 {
-        entities.take_damage(target, damage);
+        entities.take_damage(target, handle, damage);
 
         entities.status[&target].alive = false;
         entities.blocks[&target] = false;
@@ -119,15 +128,22 @@ pub fn crush(handle: EntityId, target: EntityId, entities: &mut Entities, msg_lo
 }
 
 pub fn attack(entity: EntityId, target: EntityId, data: &mut Level, msg_log: &mut MsgLog) {
+    attack_as(entity, entity, target, data, msg_log);
+}
+
+/// Resolve `entity`'s attack against `target`, using `entity`'s weapon and venom, with the hit
+/// credited to `source`. These only differ for a reflected attack, where an attacker's own attack
+/// is turned back on it by the reflecting entity.
+pub fn attack_as(entity: EntityId, source: EntityId, target: EntityId, data: &mut Level, msg_log: &mut MsgLog) {
     if data.using(entity, Item::Hammer).is_some() {
         data.entities.status[&target].alive = false;
         data.entities.blocks[&target] = false;
 
-        data.entities.take_damage(target, HAMMER_DAMAGE);
-        data.entities.messages[&target].push(Message::Attack(entity));
+        data.entities.take_damage(target, source, HAMMER_DAMAGE);
+        data.entities.messages[&target].push(Message::Attack(source));
 
         // NOTE assumes that this kills the enemy
-        msg_log.log(Msg::Killed(entity, target, HAMMER_DAMAGE));
+        msg_log.log(Msg::Killed(source, target, HAMMER_DAMAGE));
 
         let hit_pos = data.entities.pos[&target];
         // NOTE this creates rubble even if the player somehow is hit by a hammer...
@@ -135,7 +151,7 @@ pub fn attack(entity: EntityId, target: EntityId, data: &mut Level, msg_log: &mu
             data.map[hit_pos].surface = Surface::Rubble;
         }
     } else if data.using(target, Item::Shield).is_some() {
-        let pos = data.entities.pos[&entity];
+        let pos = data.entities.pos[&source];
         let other_pos = data.entities.pos[&target];
         let diff = sub_pos(other_pos, pos);
 
@@ -147,24 +163,26 @@ pub fn attack(entity: EntityId, target: EntityId, data: &mut Level, msg_log: &mu
         if !data.map.path_blocked_move(other_pos, Pos::new(x_diff, y_diff)).is_some() &&
            !data.has_blocking_entity(past_pos).is_some() {
             data.entities.set_pos(target, past_pos);
-            data.entities.set_pos(entity, other_pos);
+            data.entities.set_pos(source, other_pos);
 
-            data.entities.messages[&target].push(Message::Attack(entity));
+            data.entities.messages[&target].push(Message::Attack(source));
         }
     } else if data.using(entity, Item::Sword).is_some() {
-        msg_log.log(Msg::Attack(entity, target, SWORD_DAMAGE));
-        msg_log.log(Msg::Killed(entity, target, SWORD_DAMAGE));
+        msg_log.log(Msg::Attack(source, target, SWORD_DAMAGE));
+        msg_log.log(Msg::Killed(source, target, SWORD_DAMAGE));
     } else {
         // NOTE could add another section for the sword- currently the same as normal attacks
         let damage = 1;
-        if data.entities.take_damage(target, damage) {
-            msg_log.log(Msg::Attack(entity, target, damage));
+        if data.entities.take_damage(target, source, damage) {
+            msg_log.log(Msg::Attack(source, target, damage));
             // TODO consider moving this to the Attack msg
             if data.entities.hp[&target].hp <= 0 {
-                msg_log.log(Msg::Killed(entity, target, damage));
+                msg_log.log(Msg::Killed(source, target, damage));
+            } else if let Some(venom) = data.entities.venom.get(&entity) {
+                msg_log.log(Msg::Poisoned(target, source, *venom));
             }
 
-            data.entities.messages[&target].push(Message::Attack(entity));
+            data.entities.messages[&target].push(Message::Attack(source));
         }
     }
 }
@@ -568,6 +586,59 @@ fn test_floodfill() {
     assert_eq!(6, flood.len());
 }
 
+/// All positions that can be reached from the start position by walking.
+/// When through_walls is set, walls do not block movement, which covers positions
+/// only reachable with skills such as PassWall. Water blocks movement either way.
+pub fn reachable_positions(map: &Map, start: Pos, through_walls: bool) -> HashSet<Pos> {
+    let mut reached: HashSet<Pos> = HashSet::new();
+    let mut current: Vec<Pos> = vec!(start);
+    reached.insert(start);
+
+    while let Some(pos) = current.pop() {
+        for next_pos in map.neighbors(pos) {
+            if reached.contains(&next_pos) {
+                continue;
+            }
+
+            let passable;
+            if through_walls {
+                passable = map[next_pos].tile_type != TileType::Water;
+            } else {
+                passable = map.path_blocked_move(pos, next_pos).is_none();
+            }
+
+            if passable {
+                reached.insert(next_pos);
+                current.push(next_pos);
+            }
+        }
+    }
+
+    return reached;
+}
+
+#[test]
+fn test_reachable_positions() {
+    let mut map = Map::from_dims(5, 3);
+
+    // wall off the rightmost column
+    for y in 0..3 {
+        map[(3, y)] = Tile::wall();
+    }
+    map[(4, 0)] = Tile::water();
+
+    let start = Pos::new(0, 0);
+
+    let walking = reachable_positions(&map, start, false);
+    assert_eq!(9, walking.len());
+    assert!(!walking.contains(&Pos::new(4, 1)));
+
+    let through_walls = reachable_positions(&map, start, true);
+    assert!(through_walls.contains(&Pos::new(4, 1)));
+    assert!(through_walls.contains(&Pos::new(3, 1)));
+    assert!(!through_walls.contains(&Pos::new(4, 0)));
+}
+
 pub struct Cone {
     start: Pos,
     dir: Direction,
@@ -830,14 +901,53 @@ pub fn change_move_mode(entity_id: EntityId,
     }
 }
 
+fn blink_pos_valid(pos: Pos, blink_pos: Pos, level: &Level) -> bool {
+    return level.has_blocking_entity(blink_pos).is_none() &&
+           level.map.path_blocked_move(pos, blink_pos).is_none();
+}
+
+/// All positions that a blink from the given position could land on.
+pub fn blink_candidates(pos: Pos, level: &Level) -> Vec<Pos> {
+    return floodfill(&level.map, pos, BLINK_RADIUS).into_iter()
+                                                   .filter(|blink_pos| blink_pos_valid(pos, *blink_pos, level))
+                                                   .collect::<Vec<Pos>>();
+}
+
+/// The tile directly behind the enemy at the target position, as seen from the given
+/// entity, if a blink can land there. There is no such tile if there is no enemy at
+/// the target, if the target is out of blink range or out of the entity's sight, or if
+/// the tile behind it is off the map or blocked.
+pub fn find_blink_behind_pos(entity_id: EntityId, target_pos: Pos, level: &Level) -> Option<Pos> {
+    let pos = level.entities.pos[&entity_id];
+    if distance_maximum(pos, target_pos) > BLINK_RADIUS as i32 || !level.pos_in_fov(entity_id, target_pos) {
+        return None;
+    }
+
+    let target_id = level.has_blocking_entity(target_pos)?;
+    if level.entities.typ[&target_id] != EntityType::Enemy {
+        return None;
+    }
+
+    let dir = Direction::from_positions(pos, target_pos)?;
+    let behind_pos = dir.offset_pos(target_pos, 1);
+    if !level.map.is_within_bounds(behind_pos) || level.map[behind_pos].block_move {
+        return None;
+    }
+
+    if blink_pos_valid(target_pos, behind_pos, level) {
+        return Some(behind_pos);
+    }
+
+    return None;
+}
+
 pub fn find_blink_pos(pos: Pos, rng: &mut Rand32, level: &mut Level) -> Option<Pos> {
     let mut potential_positions = floodfill(&level.map, pos, BLINK_RADIUS);
     while potential_positions.len() > 0 {
         let ix = rng_range_u32(rng, 0, potential_positions.len() as u32) as usize;
         let rand_pos = potential_positions[ix];
 
-        if level.has_blocking_entity(rand_pos).is_none() &&
-           level.map.path_blocked_move(pos, rand_pos).is_none() {
+        if blink_pos_valid(pos, rand_pos, level) {
                return Some(rand_pos);
         }
 
@@ -847,6 +957,51 @@ pub fn find_blink_pos(pos: Pos, rng: &mut Rand32, level: &mut Level) -> Option<P
     return None;
 }
 
+/// The intertile walls created by a grass wall used in the given direction,
+/// as the tile position and the side of that tile the wall is placed on.
+pub fn grass_wall_positions(pos: Pos, direction: Direction) -> Vec<(Pos, Direction)> {
+    let mut walls = Vec::new();
+
+    match direction {
+        Direction::Left | Direction::Right | Direction::Up | Direction::Down => {
+            walls.push((pos, direction));
+            let next_to = direction.clockwise().clockwise().offset_pos(pos, 1);
+            walls.push((next_to, direction));
+            let next_to = direction.counterclockwise().counterclockwise().offset_pos(pos, 1);
+            walls.push((next_to, direction));
+        }
+
+        Direction::DownLeft | Direction::DownRight | Direction::UpLeft | Direction::UpRight => {
+            let next_to = direction.clockwise().offset_pos(pos, 1);
+            walls.push((next_to, direction.counterclockwise()));
+            let next_to = direction.counterclockwise().offset_pos(pos, 1);
+            walls.push((next_to, direction.clockwise()));
+        }
+    }
+
+    return walls;
+}
+
+/// The position a pass wall skill would move to when used from 'pos' towards 'skill_pos', if any.
+pub fn pass_wall_pos(level: &Level, pos: Pos, skill_pos: Pos) -> Option<Pos> {
+    if let Some(dir) = Direction::from_positions(pos, skill_pos) {
+        let target_pos = dir.offset_pos(pos, 1);
+
+        if let Some(blocked) = level.map.path_blocked_move(pos, target_pos) {
+            if level.map.tile_is_blocking(blocked.end_pos) {
+                let next = next_from_to(pos, blocked.end_pos);
+                if !level.map.tile_is_blocking(next) {
+                    return Some(next);
+                }
+            } else {
+                return Some(skill_pos);
+            }
+        }
+    }
+
+    return None;
+}
+
 pub fn hammer_swing(entity_id: EntityId, item_id: EntityId, pos: Pos, level: &mut Level, msg_log: &mut MsgLog) {
     let entity_pos = level.entities.pos[&entity_id];
 
@@ -905,9 +1060,16 @@ pub fn place_rubble(pos: Pos, map: &mut Map) {
     map[pos].tile_type = TileType::Empty;
 }
 
-pub fn try_use_energy(entity_id: EntityId, skill: Skill, level: &mut Level, msg_log: &mut MsgLog) -> bool {
+pub fn try_use_energy(entity_id: EntityId, skill: Skill, level: &mut Level, msg_log: &mut MsgLog, config: &Config) -> bool {
     let pos = level.entities.pos[&entity_id];
 
+    // a skill on cooldown fails without using any energy
+    let cooldown = level.entities.skill_cooldown(entity_id, skill);
+    if cooldown > 0 {
+        msg_log.log(Msg::SkillOnCooldown(entity_id, skill, cooldown));
+        return false;
+    }
+
     // Use the Skill's own class instead of the entities.
     //let class = level.entities.class[&entity_id];
     let class = skill.class();
@@ -965,6 +1127,17 @@ pub fn try_use_energy(entity_id: EntityId, skill: Skill, level: &mut Level, msg_
         msg_log.log(Msg::UsedEnergy(entity_id));
     }
 
+    if enough_energy {
+        msg_log.log(Msg::UsedSkill(entity_id, skill));
+    }
+
+    let cooldown = config.skill_cooldown(skill);
+    if enough_energy && cooldown > 0 {
+        if let Some(cooldowns) = level.entities.cooldowns.get_mut(&entity_id) {
+            cooldowns.insert(skill, cooldown);
+        }
+    }
+
     return enough_energy;
 }
 
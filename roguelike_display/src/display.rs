@@ -71,9 +71,6 @@ pub struct Display {
     pub font: SpriteSheet,
     pub font_texture: Texture,
 
-    pub intern: HashMap<String, Str>,
-    pub next_str: usize,
-
     pub canvas_panel: Panel,
 
     pub rng: Rand32,
@@ -141,8 +138,6 @@ impl Display {
                          panels,
                          screen_areas,
                          screen_texture,
-                         intern: HashMap::new(),
-                         next_str: 0,
                          canvas_panel,
                          rng: Rand32::new(0),
         };
@@ -154,12 +149,12 @@ impl Display {
     }
 
     pub fn add_string(&mut self, string: &str) -> usize {
-        if let Some(key) = self.intern.get(string) {
+        if let Some(key) = self.state.intern.get(string) {
             return *key;
         } else {
-            let index = self.next_str;
-            self.intern.insert(string.to_string(), index);
-            self.next_str += 1;
+            let index = self.state.next_str;
+            self.state.intern.insert(string.to_string(), index);
+            self.state.next_str += 1;
             return index;
         }
     }
@@ -167,8 +162,12 @@ impl Display {
     pub fn process_draw_commands(&mut self) {
         let canvas = &mut self.canvas;
 
+        // The map panel is normally covered by the background tiles, but a scrolled
+        // camera may show space past the map edges which must be cleared.
+        let clear_map = self.state.camera_pos != Pos::new(0, 0);
+
         for panel_name in PanelName::names().iter() {
-            let clear = *panel_name != PanelName::Map;
+            let clear = *panel_name != PanelName::Map || clear_map;
             let panel = self.panels.get_mut(panel_name).unwrap();
 
             panel.process_cmds_if_new(clear,
@@ -184,11 +183,150 @@ impl Display {
         self.canvas.present();
     }
 
-    pub fn save_screenshot(&mut self, name: &str) {
+    /// Position within the screen texture of the given window pixel, as the window may be scaled.
+    fn screen_pixel_from_window(&self, pixel: Pos) -> Pos {
+        let (window_width, window_height) = self.canvas.output_size().unwrap();
+        return Pos::new((pixel.x as i64 * SCREEN_WIDTH as i64 / window_width as i64) as i32,
+                        (pixel.y as i64 * SCREEN_HEIGHT as i64 / window_height as i64) as i32);
+    }
+
+    /// Source and destination rectangles used to copy the map panel onto the screen.
+    /// Maps smaller than the panel are centered, and when zoomed in only part of the
+    /// source, starting at the zoom offset, is scaled up to fill the destination.
+    fn map_rects(&self) -> (Rect, Rect) {
+        let mut map_rect = self.canvas_panel.get_rect_from_area(&self.screen_areas[&PanelName::Map]);
+        let mut map_src = Rect::new(0, 0, map_rect.width(), map_rect.height());
+
+        // Handle maps that are smaller then the maximum size by trying to center them.
+        let map_width = self.state.map.width();
+        let map_height = self.state.map.height();
+        if map_width < MAP_WIDTH || map_height < MAP_HEIGHT {
+            // Larger maps scroll with the camera, so they fill the panel.
+            let map_width = std::cmp::min(map_width, MAP_WIDTH);
+            let map_height = std::cmp::min(map_height, MAP_HEIGHT);
+            let map_width_pixels = map_width as u32 * MAP_CELLS_TO_PIXELS;
+            let map_height_pixels = map_height as u32 * MAP_CELLS_TO_PIXELS;
+            // Source map is from 0, 0 to the extents currently used.
+            map_src = Rect::new(0, 0, map_width_pixels, map_height_pixels);
+
+            // Destination is centered and the same size as the smaller map area above.
+            let x_offset = ((MAP_WIDTH / 2) - (map_width / 2)) * MAP_CELLS_TO_PIXELS as i32;
+            let y_offset = ((MAP_HEIGHT / 2) - (map_height / 2)) * MAP_CELLS_TO_PIXELS as i32;
+            map_rect.x += x_offset;
+            map_rect.y += y_offset;
+            map_rect.w = map_width_pixels as i32;
+            map_rect.h = map_height_pixels as i32;
+        }
+
+        let zoom = self.state.map_zoom;
+        let (offset_x, offset_y) = self.clamped_zoom_offset(self.state.map_zoom_offset, zoom, &map_src);
+        let zoomed_src = Rect::new(map_src.x + offset_x as i32,
+                                   map_src.y + offset_y as i32,
+                                   (map_src.width() as f32 / zoom) as u32,
+                                   (map_src.height() as f32 / zoom) as u32);
+
+        return (zoomed_src, map_rect);
+    }
+
+    /// Keep the zoomed source region within the map's source rectangle.
+    fn clamped_zoom_offset(&self, offset: (f32, f32), zoom: f32, map_src: &Rect) -> (f32, f32) {
+        let max_x = map_src.width() as f32 - map_src.width() as f32 / zoom;
+        let max_y = map_src.height() as f32 - map_src.height() as f32 / zoom;
+        return (clampf(offset.0, 0.0, max_x), clampf(offset.1, 0.0, max_y));
+    }
+
+    /// Zoom the map panel in (positive amounts) or out (negative amounts) around the
+    /// given window pixel, keeping the map under that pixel in place where possible.
+    pub fn zoom_map(&mut self, amount: i32, pixel: Pos) {
+        let screen_pixel = self.screen_pixel_from_window(pixel);
+        let (_map_src, map_rect) = self.map_rects();
+        if !map_rect.contains_point((screen_pixel.x, screen_pixel.y)) {
+            return;
+        }
+
+        let zoom = self.state.map_zoom;
+        let new_zoom = clampf(zoom * MAP_ZOOM_STEP.powi(amount), MAP_ZOOM_MIN, MAP_ZOOM_MAX);
+
+        // The source point under the cursor is the offset plus the cursor's distance into
+        // the map, scaled down by the zoom. Pick the new offset to keep that point in place.
+        let (offset_x, offset_y) = self.state.map_zoom_offset;
+        let local_x = (screen_pixel.x - map_rect.x) as f32;
+        let local_y = (screen_pixel.y - map_rect.y) as f32;
+        let src_x = offset_x + local_x / zoom;
+        let src_y = offset_y + local_y / zoom;
+        let new_offset = (src_x - local_x / new_zoom, src_y - local_y / new_zoom);
+
+        let map_src = Rect::new(0, 0, map_rect.width(), map_rect.height());
+        self.state.map_zoom = new_zoom;
+        self.state.map_zoom_offset = self.clamped_zoom_offset(new_offset, new_zoom, &map_src);
+    }
+
+    /// Map tile under the given window pixel, if the pixel is within the map panel.
+    pub fn map_tile_from_pixel(&self, pixel: Pos) -> Option<Pos> {
+        let mut screen_pixel = self.screen_pixel_from_window(pixel);
+
+        // When zoomed, find the pixel that would be under the cursor without zoom.
+        let (map_src, map_rect) = self.map_rects();
+        if map_rect.contains_point((screen_pixel.x, screen_pixel.y)) {
+            let zoom = self.state.map_zoom;
+            let offset_x = map_src.x as f32;
+            let offset_y = map_src.y as f32;
+            screen_pixel.x = map_rect.x + (offset_x + (screen_pixel.x - map_rect.x) as f32 / zoom) as i32;
+            screen_pixel.y = map_rect.y + (offset_y + (screen_pixel.y - map_rect.y) as f32 / zoom) as i32;
+        }
+
+        let screen_cell = self.canvas_panel.cell_from_pixel(screen_pixel);
+        let (cell_x, cell_y) = self.screen_areas[&PanelName::Map].cell_at(screen_cell)?;
+
+        let mut tile = Pos::new(cell_x as i32 / CELL_MULTIPLIER as i32, cell_y as i32 / CELL_MULTIPLIER as i32);
+
+        // Smaller maps are centered within the panel.
+        let (map_width, map_height) = self.state.map.size();
+        if map_width < MAP_WIDTH {
+            tile.x -= (MAP_WIDTH / 2) - (map_width / 2);
+        }
+        if map_height < MAP_HEIGHT {
+            tile.y -= (MAP_HEIGHT / 2) - (map_height / 2);
+        }
+
+        let tile = add_pos(tile, self.state.camera_pos);
+        if self.state.map.is_within_bounds(tile) {
+            return Some(tile);
+        }
+
+        return None;
+    }
+
+    /// Find the key of the inventory button under a mouse position, if any.
+    pub fn button_from_pixel(&self, pixel: Pos) -> Option<char> {
+        // The window may be scaled, so first find the position within the screen.
+        let (window_width, window_height) = self.canvas.output_size().unwrap();
+        let screen_x = pixel.x as f32 * SCREEN_WIDTH as f32 / window_width as f32;
+        let screen_y = pixel.y as f32 * SCREEN_HEIGHT as f32 / window_height as f32;
+
+        let (cell_width, cell_height) = self.canvas_panel.cell_dims();
+        let area = &self.screen_areas[&PanelName::Inventory];
+        let area_x = screen_x / cell_width as f32 - area.x_offset as f32;
+        let area_y = screen_y / cell_height as f32 - area.y_offset as f32;
+        if area_x < 0.0 || area_y < 0.0 || area_x >= area.width as f32 || area_y >= area.height as f32 {
+            return None;
+        }
+
+        // The inventory panel has fewer cells than its screen area, so scale into its cells.
+        let panel = &self.panels[&PanelName::Inventory];
+        let cell_x = area_x * panel.cells.0 as f32 / area.width as f32;
+        let cell_y = area_y * panel.cells.1 as f32 / area.height as f32;
+
+        return self.state.buttons.iter()
+                                 .find(|button| button.contains(cell_x, cell_y))
+                                 .map(|button| button.chr);
+    }
+
+    pub fn save_screenshot(&mut self, name: &str) -> Result<(), String> {
         let format = PixelFormatEnum::RGB24;
-        let (width, height) = self.canvas.output_size().unwrap();
+        let (width, height) = self.canvas.output_size()?;
 
-        let pixels = self.canvas.read_pixels(None, format).unwrap();
+        let pixels = self.canvas.read_pixels(None, format)?;
 
         let mut shot = Image::new(width, height);
 
@@ -202,7 +340,7 @@ impl Display {
             }
         }
 
-        shot.save(format!("{}.bmp", name)).unwrap();
+        return shot.save(format!("{}.bmp", name)).map_err(|err| err.to_string());
     }
 
     pub fn add_sprite(&mut self, name: String, texture: Texture) {
@@ -264,14 +402,81 @@ impl Display {
         }
     }
 
+    /// Whether any entity is still playing out an animation, such as a move, rather than idling.
+    pub fn animations_in_progress(&self) -> bool {
+        return self.state.animations.iter().any(|(_entity_id, anims)| anims.iter().any(|anim| anim.in_progress()));
+    }
+
+    /// Cut short any in-progress animations so new input is shown immediately.
+    /// Entities playing a non-interruptible animation, such as a death, are left alone.
+    pub fn interrupt_animations(&mut self, config: &Config) {
+        let mut interrupted = Vec::new();
+        for (entity_id, anims) in self.state.animations.iter() {
+            let in_progress = anims.iter().any(|anim| anim.in_progress());
+            let interruptible = anims.iter().all(|anim| anim.interruptible());
+            if in_progress && interruptible {
+                interrupted.push(entity_id);
+            }
+        }
+
+        for entity_id in interrupted {
+            self.state.clear_animations(entity_id);
+            self.play_idle_animation(entity_id, config);
+        }
+    }
+
+    /// Draw an entity with the given sprite rather than the one for its name.
+    /// A sprite that does not exist is reported, and the entity keeps its default sprite.
+    pub fn set_sprite_override(&mut self, entity_id: EntityId, sprite_name: &str, config: &Config) {
+        self.state.sprite_override.insert(entity_id, sprite_name.to_string());
+
+        if let Some(index) = self.state.tileset_index(sprite_name) {
+            self.state.tile_index.insert(entity_id, index);
+        } else if !self.sprite_exists(sprite_name) {
+            eprintln!("Sprite override '{}' for entity {} does not exist, using the default sprite", sprite_name, entity_id);
+            return;
+        }
+
+        if self.state.name.get(&entity_id).is_some() {
+            self.play_idle_animation(entity_id, config);
+        }
+    }
+
     pub fn get_idle_animation(&mut self, entity_id: EntityId, config: &Config) -> Option<Animation> {
-        let name = self.state.name[&entity_id];
+        if let Some(sprite_name) = self.state.sprite_override.get(&entity_id).cloned() {
+            if self.sprite_exists(&sprite_name) {
+                return Some(self.loop_sprite(&sprite_name, config.idle_speed));
+            } else if let Some(index) = self.state.tileset_index(&sprite_name) {
+                let sprite = self.static_sprite("rustrogueliketiles", index);
+                return Some(Animation::Loop(sprite));
+            }
+        }
+
+        let mut name = self.state.name[&entity_id];
+
+        // a revealed mimic uses the pawn's sprites, and a disguised one looks like an herb
+        if name == EntityName::Mimic {
+            if self.state.disguised.contains(&entity_id) {
+                name = EntityName::Herb;
+            } else {
+                name = EntityName::Pawn;
+            }
+        }
+
+        // a summoner uses the rook's sprites
+        if name == EntityName::Summoner {
+            name = EntityName::Rook;
+        }
+
+        // a thief uses the gol's sprites, and is tinted when drawn
+        if name == EntityName::Thief {
+            name = EntityName::Gol;
+        }
 
         if name == EntityName::Player || name == EntityName::Gol || name == EntityName::Pawn || name == EntityName::Rook {
             // we check for stance first in case the entity has been created but no stance is yet
             // available.
             if self.state.stance.get(&entity_id).is_some() {
-                let name = self.state.name[&entity_id];
                 let stance = self.state.stance[&entity_id];
                 let direction = self.state.direction[&entity_id];
 
@@ -294,6 +499,14 @@ impl Display {
                 let index = self.state.tileset_index(&"spike_trap").unwrap();
                 let sprite = self.static_sprite("rustrogueliketiles", index);
                 return Some(Animation::Loop(sprite));
+            } else if name == EntityName::SpikeTrapKit {
+                let index = self.state.tileset_index(&"unused_trap_1").unwrap();
+                let sprite = self.static_sprite("rustrogueliketiles", index);
+                return Some(Animation::Loop(sprite));
+            } else if name == EntityName::FreezeTrapKit {
+                let index = self.state.tileset_index(&"unused_trap_2").unwrap();
+                let sprite = self.static_sprite("rustrogueliketiles", index);
+                return Some(Animation::Loop(sprite));
             } else if name == EntityName::Armil {
                 if matches!(self.state.behavior.get(&entity_id), Some(Behavior::Armed(_))) {
                     return Some(self.loop_sprite("armil_readytoexplode", config.idle_speed));
@@ -302,6 +515,8 @@ impl Display {
                 }
             } else if name == EntityName::Lantern {
                 return Some(self.loop_sprite("Lantern_Idle", config.fire_speed));
+            } else if name == EntityName::Fire {
+                return Some(self.loop_sprite("Lantern_Idle", config.fire_speed));
             } else if name == EntityName::Smoke {
                 let index = self.state.tileset_index(&"smoke").unwrap();
                 let sprite = self.static_sprite("rustrogueliketiles", index);
@@ -318,10 +533,22 @@ impl Display {
                 let index = self.state.tileset_index(&"sling").unwrap();
                 let sprite = self.static_sprite("rustrogueliketiles", index);
                 return Some(Animation::Loop(sprite));
+            } else if name == EntityName::Boomerang {
+                let index = self.state.tileset_index(&"unnamed_artifact_3").unwrap();
+                let sprite = self.static_sprite("rustrogueliketiles", index);
+                return Some(Animation::Loop(sprite));
             } else if name == EntityName::GlassEye {
                 let index = self.state.tileset_index(&"crystal_ball").unwrap();
                 let sprite = self.static_sprite("rustrogueliketiles", index);
                 return Some(Animation::Loop(sprite));
+            } else if name == EntityName::Hourglass {
+                let index = self.state.tileset_index(&"unnamed_artifact_1").unwrap();
+                let sprite = self.static_sprite("rustrogueliketiles", index);
+                return Some(Animation::Loop(sprite));
+            } else if name == EntityName::Mirror {
+                let index = self.state.tileset_index(&"unnamed_artifact_2").unwrap();
+                let sprite = self.static_sprite("rustrogueliketiles", index);
+                return Some(Animation::Loop(sprite));
             } else if name == EntityName::Herb {
                 let index = self.state.tileset_index(&"herb").unwrap();
                 let sprite = self.static_sprite("rustrogueliketiles", index);
@@ -346,6 +573,10 @@ impl Display {
                 } else {
                     return Some(self.random_sprite("GrassAnim", config.grass_idle_speed));
                 }
+            } else if name == EntityName::Boulder || name == EntityName::MarkingStone || name == EntityName::RecallStone {
+                let index = self.state.tileset_index(&"stone").unwrap();
+                let sprite = self.static_sprite("rustrogueliketiles", index);
+                return Some(Animation::Loop(sprite));
             } else if name == EntityName::Statue {
                 let statue_1 = self.state.tileset_index(&"statue_1").unwrap();
                 let statue_2 = self.state.tileset_index(&"statue_2").unwrap();
@@ -398,12 +629,24 @@ impl Display {
         self.state.impressions.clear();
         self.state.prev_turn_fov.clear();
         self.state.sound_tiles.clear();
+        self.state.sound_events.clear();
         self.state.effects.clear();
         self.state.gate_pos.clear();
         self.state.frozen.clear();
+        self.state.disguised.clear();
+        self.state.dead_enemies.clear();
+        self.state.sprite_override.clear();
+        self.state.seen_items.clear();
+        self.state.enemy_memory.clear();
+        self.state.objective_memory.clear();
+        self.state.camera_smooth = None;
+        self.state.free_camera_pos = None;
         self.state.cursor_pos = None;
 
         self.clear_turn_state();
+
+        // tiles from the previous level should not fade out on the new one
+        self.state.fov_fade.clear();
     }
 
     pub fn clear_turn_state(&mut self) {
@@ -413,13 +656,29 @@ impl Display {
         self.state.hit_positions.clear();
         self.state.entities_at_cursor.clear();
         self.state.entity_movements.clear();
+        self.state.entity_intents.clear();
         self.state.entity_attacks.clear();
         self.state.entity_fov.clear();
-        self.state.sound_tiles.clear();
+
+        // remember when tiles were last in the FoV, so tiles leaving it can fade out
+        let time = self.state.time;
+        for (pos, fov_result) in self.state.fov.iter() {
+            self.state.fov_fade.insert(*pos, (time, *fov_result));
+        }
         self.state.fov.clear();
         self.state.entities_in_fov.clear();
         self.state.inventory.clear();
+        self.state.unidentified_items.clear();
+        self.state.attack_charged = false;
+        self.state.hasted = 0;
+        self.state.time_stop = 0;
+        self.state.skill_cooldowns.clear();
+        self.state.bestiary.clear();
         self.state.player_ghost = None;
+        self.state.grass_wall_preview.clear();
+        self.state.pass_wall_preview = None;
+        self.state.blink_preview.clear();
+        self.state.line_of_fire = None;
     }
 
     pub fn map_message(&mut self, map_str: &str) {
@@ -484,6 +743,10 @@ impl Display {
                 self.state.entity_movements.get_mut(&entity_id).unwrap().push(pos);
             }
 
+            InfoMsg::EntityIntent(entity_id, pos) => {
+                self.state.entity_intents.insert(entity_id, pos);
+            }
+
             InfoMsg::EntityAttack(entity_id, pos) => {
                 if self.state.entity_attacks.get(&entity_id).is_none() {
                     self.state.entity_attacks.insert(entity_id, Vec::new());
@@ -502,6 +765,34 @@ impl Display {
                 self.state.inventory.push((item, item_class));
             }
 
+            InfoMsg::UnidentifiedItem(item) => {
+                self.state.unidentified_items.insert(item);
+            }
+
+            InfoMsg::AttackCharged => {
+                self.state.attack_charged = true;
+            }
+
+            InfoMsg::Hasted(turns) => {
+                self.state.hasted = turns;
+            }
+
+            InfoMsg::TimeStop(turns) => {
+                self.state.time_stop = turns;
+            }
+
+            InfoMsg::RunStats(stats) => {
+                self.state.run_stats = stats;
+            }
+
+            InfoMsg::SkillCooldown(skill, turns) => {
+                self.state.skill_cooldowns.insert(skill, turns);
+            }
+
+            InfoMsg::BestiaryEntry(name, max_hp, behavior) => {
+                self.state.bestiary.push((name, max_hp, behavior));
+            }
+
             InfoMsg::PlayerGhost(player_ghost) => {
                 self.state.player_ghost = Some(player_ghost);
             }
@@ -513,34 +804,96 @@ impl Display {
                 self.state.impressions.push(Impression::new(impression_sprite, pos));
             }
 
+            InfoMsg::ImpressionCleared(pos) => {
+                // other impressions may share the position, so only one is removed
+                if let Some(index) = self.state.impressions.iter().position(|impression| impression.pos == pos) {
+                    self.state.impressions.remove(index);
+                }
+            }
+
+            InfoMsg::PingMarker(pos) => {
+                // the marker fades on its own timer, separate from the ping's sound effect
+                let tiles = lookup_spritekey(&self.sprites, "rustrogueliketiles");
+                let index = self.state.tileset_index(&"golem_impression").unwrap();
+                let marker_sprite = Sprite::new(index as u32, tiles);
+                let fade_effect = Effect::fade(marker_sprite, config.color_light_red, 255, 0, pos, config.ping_marker_seconds);
+                self.state.play_effect(fade_effect);
+            }
+
             InfoMsg::PlayerAction => {
                 // inventory is re-emitted after every action, so clear it first
                 self.state.inventory.clear();
+                self.state.unidentified_items.clear();
+                self.state.attack_charged = false;
+                self.state.hasted = 0;
+                self.state.time_stop = 0;
+                self.state.skill_cooldowns.clear();
+
+                // skill previews are also re-emitted after every action
+                self.state.grass_wall_preview.clear();
+                self.state.pass_wall_preview = None;
+                self.state.blink_preview.clear();
             }
 
             InfoMsg::UseAction(use_action) => {
                 self.state.use_action = use_action;
             }
+
+            InfoMsg::GrassWallPreview(pos, dir) => {
+                self.state.grass_wall_preview.push((pos, dir));
+            }
+
+            InfoMsg::PassWallPreview(pos) => {
+                self.state.pass_wall_preview = Some(pos);
+            }
+
+            InfoMsg::BlinkPreview(pos) => {
+                self.state.blink_preview.push(pos);
+            }
+
+            InfoMsg::LineOfFire(line_of_fire) => {
+                self.state.line_of_fire = Some(line_of_fire);
+            }
         }
     }
 
     pub fn process_message(&mut self, msg: Msg, map: &Map, config: &Config) {
+        self.state.process_turn_message(msg, map, config);
+
         match msg {
             Msg::StartTurn => {
                 self.clear_turn_state();
-                self.state.turn_count += 1;
             }
 
             Msg::GameState(new_state) => {
                 self.state.state = new_state;
+
+                if new_state != GameState::Inventory {
+                    self.state.pending_pickup = None;
+                }
+            }
+
+            Msg::InventoryFull(_entity_id, item_id) => {
+                self.state.pending_pickup = self.state.name.get(&item_id).copied();
             }
 
             Msg::CursorMove(pos) => {
                 // clear entities at cursor at move- positions will come in separate messages
                 self.state.entities_at_cursor.clear();
+                self.state.line_of_fire = None;
                 self.state.cursor_pos = Some(pos);
             }
 
+            Msg::FreeCamera(state, pos) => {
+                if state {
+                    self.state.free_camera_pos = Some(pos);
+                } else {
+                    self.state.free_camera_pos = None;
+                    // snap back to the player rather than panning across the map
+                    self.state.camera_smooth = None;
+                }
+            }
+
             Msg::CursorState(state, pos) => {
                 if state {
                     self.state.cursor_pos = Some(pos);
@@ -548,6 +901,7 @@ impl Display {
                     self.state.cursor_pos = None;
                     self.state.cursor_action = None;
                     self.state.entities_at_cursor.clear();
+                    self.state.line_of_fire = None;
 
                     let tiles = lookup_spritekey(&self.sprites, "rustrogueliketiles");
 
@@ -571,9 +925,6 @@ impl Display {
             }
 
             Msg::SoundHitTile(cause_id, source_pos, radius, hit_pos) => {
-                // Add to this turn's sound tiles list
-                self.state.sound_tiles.push(hit_pos);
-
                 let player_id = self.state.player_id();
                 let player_pos = self.state.pos[&player_id];
 
@@ -606,8 +957,14 @@ impl Display {
 
             Msg::Restart => {
                 self.state.skills.clear();
+                self.state.skill_cooldowns.clear();
                 self.state.talents.clear();
                 self.state.turn_count = 0;
+                self.state.enemies_killed = 0;
+                self.state.killed_by = None;
+                self.state.run_stats = RunStats::default();
+                self.state.death_hint = None;
+                self.state.deaths = 0;
                 self.clear_level_state();
                 self.clear_console_messages();
             }
@@ -710,13 +1067,25 @@ impl Display {
                 self.play_idle_animation(entity_id, config);
             }
 
-            Msg::Killed(_attacker, attacked, _damage) => {
+            Msg::Killed(attacker, attacked, _damage) => {
+                if self.state.typ[&attacked] == EntityType::Enemy {
+                    self.state.enemies_killed += 1;
+                    self.state.dead_enemies.insert(attacked);
+                }
+
+                if self.state.typ[&attacked] == EntityType::Player {
+                    if let Some(name) = self.state.name.get(&attacker) {
+                        self.state.killed_by = Some((*name, self.state.turn_count));
+                    }
+                }
+
                 if self.state.typ[&attacked] != EntityType::Player {
                     self.state.clear_animations(attacked);
 
                     let sprite_name = format!("{:?}_death", self.state.name[&attacked]);
                     if self.sprite_exists(&sprite_name) {
-                        let sprite = self.new_sprite(&sprite_name, 1.0);
+                        let mut sprite = self.new_sprite(&sprite_name, 1.0);
+                        sprite.interruptible = false;
                         self.state.play_animation(attacked, Animation::Once(sprite));
                     }
                 }
@@ -762,6 +1131,30 @@ impl Display {
                 }
             }
 
+            Msg::Burned(entity_id, _fire_id, damage) => {
+                let entity_pos = self.state.pos[&entity_id];
+                let burn_nums = Effect::number_change(-damage, entity_pos, config.color_orange);
+                self.state.play_effect(burn_nums);
+
+                if !self.state.test_mode {
+                    if let Some(hp) = self.state.hp.get_mut(&entity_id) {
+                        *hp -= damage;
+                    }
+                }
+            }
+
+            Msg::PoisonDamage(entity_id, _poisoner, damage) => {
+                let entity_pos = self.state.pos[&entity_id];
+                let poison_nums = Effect::number_change(-damage, entity_pos, config.color_soft_green);
+                self.state.play_effect(poison_nums);
+
+                if !self.state.test_mode {
+                    if let Some(hp) = self.state.hp.get_mut(&entity_id) {
+                        *hp -= damage;
+                    }
+                }
+            }
+
             Msg::Attack(attacker, attacked, damage) => {
                 let attacked_pos = self.state.pos[&attacked];
                 let hit_nums = Effect::number_change(-damage, attacked_pos, config.color_light_red);
@@ -787,6 +1180,16 @@ impl Display {
                     self.state.hp[&attacked] -= damage;
                 }
             }
+
+            Msg::Reflected(entity_id, attacker_id) => {
+                // a reflected ranged attack travels back along its line to the attacker
+                let entity_pos = self.state.pos[&entity_id];
+                let attacker_pos = self.state.pos[&attacker_id];
+                if distance(entity_pos, attacker_pos) > 1 {
+                    let beam_effect = Effect::beam(config.beam_duration, entity_pos, attacker_pos);
+                    self.state.play_effect(beam_effect);
+                }
+            }
             
             Msg::ExplosionHitTile(pos) => {
                 let mut color = config.color_light_red;
@@ -825,6 +1228,11 @@ impl Display {
                 self.state.name.insert(entity_id, name);
                 self.state.direction.insert(entity_id, facing);
 
+                // mimics start out looking like an herb
+                if name == EntityName::Mimic {
+                    self.state.disguised.insert(entity_id);
+                }
+
                 if let Some(ix_pos) = self.state.ids.iter().position(|val| *val == entity_id) {
                     eprintln!("entity id {} already at position {}", entity_id, ix_pos);
                     panic!();
@@ -846,7 +1254,8 @@ impl Display {
                 // Update impressions based on previous FoV and current FoV.
                 for entity_id in self.state.prev_turn_fov.iter() {
                     // Impressions are only of golems.
-                    if self.state.typ.get(entity_id) != Some(&EntityType::Enemy) {
+                    if self.state.typ.get(entity_id) != Some(&EntityType::Enemy) ||
+                       self.state.disguised.contains(entity_id) {
                         continue;
                     }
 
@@ -858,6 +1267,50 @@ impl Display {
                     }
                 }
 
+                // Remember where each visible enemy is, and forget enemies whose last
+                // seen position is visible without them in it.
+                for entity_id in self.state.ids.iter() {
+                    if self.state.typ.get(entity_id) != Some(&EntityType::Enemy) ||
+                       self.state.disguised.contains(entity_id) {
+                        continue;
+                    }
+
+                    if self.state.entity_is_in_fov(*entity_id) == FovResult::Inside {
+                        if let Some(sprite) = self.state.drawn_sprites.get(entity_id) {
+                            let pos = self.state.pos[entity_id];
+                            self.state.enemy_memory.insert(*entity_id, (pos, *sprite));
+                        }
+                    } else if let Some((seen_pos, _sprite)) = self.state.enemy_memory.get(entity_id) {
+                        if self.state.pos_is_in_fov(*seen_pos) == FovResult::Inside {
+                            self.state.enemy_memory.remove(entity_id);
+                        }
+                    }
+                }
+
+                // Remember where each visible item is, whether or not item highlighting is
+                // on, so turning it on shows items seen earlier.
+                for entity_id in self.state.ids.iter() {
+                    if self.state.typ.get(entity_id) != Some(&EntityType::Item) {
+                        continue;
+                    }
+
+                    // items in an inventory are not on the map
+                    let pos = self.state.pos[entity_id];
+                    if !self.state.map.is_within_bounds(pos) {
+                        self.state.seen_items.remove(entity_id);
+                        continue;
+                    }
+
+                    if self.state.entity_is_in_fov(*entity_id) == FovResult::Inside {
+                        self.state.seen_items.insert(*entity_id, pos);
+                    } else if let Some(seen_pos) = self.state.seen_items.get(entity_id) {
+                        // an item that is no longer where it was seen is forgotten once that spot is seen again
+                        if self.state.pos_is_in_fov(*seen_pos) == FovResult::Inside {
+                            self.state.seen_items.remove(entity_id);
+                        }
+                    }
+                }
+
                 /* Remove impressions that are currently visible */
                 let mut impressions_visible = Vec::new();
                 for (index, impression) in self.state.impressions.iter().enumerate() {
@@ -889,7 +1342,13 @@ impl Display {
                 self.state.hp.remove(&entity_id);
                 self.state.max_hp.remove(&entity_id);
                 self.state.gate_pos.remove(&entity_id);
+                self.state.size.remove(&entity_id);
                 self.state.frozen.remove(&entity_id);
+                self.state.disguised.remove(&entity_id);
+                self.state.dead_enemies.remove(&entity_id);
+                self.state.sprite_override.remove(&entity_id);
+                self.state.seen_items.remove(&entity_id);
+                self.state.enemy_memory.remove(&entity_id);
 
                 if let Some(ix_pos) = self.state.ids.iter().position(|val| *val == entity_id) {
                     self.state.ids.remove(ix_pos);
@@ -898,7 +1357,9 @@ impl Display {
 
             Msg::NewLevel => {
                 self.clear_level_state();
-                self.state.play_effect(Effect::particles(1.0));
+                if let Some(effect) = ambient_effect(&config.ambient_effect, config) {
+                    self.state.play_effect(effect);
+                }
                 self.clear_console_messages();
             }
 
@@ -910,6 +1371,17 @@ impl Display {
                 self.state.pos[&entity_id] = pos;
             }
 
+            Msg::Respawned(entity_id, pos, deaths) => {
+                self.state.pos[&entity_id] = pos;
+                self.state.deaths = deaths;
+                self.state.killed_by = None;
+            }
+
+            Msg::Spared(entity_id, pos) => {
+                self.state.pos[&entity_id] = pos;
+                self.state.killed_by = None;
+            }
+
             Msg::AddClass(_class) => {
                 self.state.skills.clear();
             }
@@ -926,6 +1398,10 @@ impl Display {
                 self.state.gate_pos.insert(entity_id, pos);
             }
 
+            Msg::EntitySize(entity_id, width, height) => {
+                self.state.size.insert(entity_id, (width, height));
+            }
+
             Msg::Froze(entity_id, num_turns) => {
                 self.state.frozen.insert(entity_id, num_turns);
             }
@@ -934,6 +1410,11 @@ impl Display {
                 self.state.frozen[&entity_id] -= num_turns;
             }
 
+            Msg::MimicRevealed(entity_id) => {
+                self.state.disguised.remove(&entity_id);
+                self.play_idle_animation(entity_id, config);
+            }
+
             Msg::NextMoveMode(move_mode) => {
                 self.state.move_mode = move_mode;
             }
@@ -942,10 +1423,22 @@ impl Display {
                 self.state.overlay = state;
             }
 
+            Msg::ToggleItemHighlight => {
+                self.state.item_highlight = !self.state.item_highlight;
+            }
+
+            Msg::SoundDebug(state) => {
+                self.state.sound_debug = state;
+            }
+
             Msg::DebugEnabled(state) => {
                 self.state.debug_enabled = state;
             }
 
+            Msg::TimeScale(scale) => {
+                self.state.time_scale = scale;
+            }
+
             Msg::CursorAction(use_action) => {
                 self.state.cursor_action = Some(use_action);
             }
@@ -966,13 +1459,15 @@ impl Display {
         }
     }
 
-    pub fn draw_all(&mut self, rng: &mut Rand32, config: &Config) {
+    pub fn draw_all(&mut self, rng: &mut Rand32) {
         self.process_draw_commands();
         self.copy_panels();
-        self.state.update_animations(rng, config);
+        self.state.update_animations(rng);
     }
 
     pub fn copy_panels(&mut self) {
+        let (map_src, map_rect) = self.map_rects();
+
         let canvas_panel = &mut self.canvas_panel;
         let textures = &mut self.textures;
         let state = self.state.state;
@@ -980,8 +1475,6 @@ impl Display {
 
         let section_name_scale = 1.35;
         let in_cursor_mode = self.state.cursor_pos.is_some();
-        let map_width = self.state.map.width();
-        let map_height = self.state.map.height();
 
         self.canvas.with_texture_canvas(&mut self.screen_texture, |canvas| {
             canvas.set_blend_mode(BlendMode::None);
@@ -992,26 +1485,6 @@ impl Display {
             let pip_rect = canvas_panel.get_rect_from_area(&screen_areas[&PanelName::Pip]);
             canvas.copy(&textures[&PanelName::Pip], None, pip_rect).unwrap();
 
-            //let map_cell_dims = panels[&PanelName::Map].cell_dims();
-            let mut map_rect = canvas_panel.get_rect_from_area(&screen_areas[&PanelName::Map]);
-            let mut map_src = None;
-
-            // Handle maps that are smaller then the maximum size by trying to center them.
-            if map_width < MAP_WIDTH || map_height < MAP_HEIGHT {
-                let map_width_pixels = map_width as u32 * MAP_CELLS_TO_PIXELS;
-                let map_height_pixels = map_height as u32 * MAP_CELLS_TO_PIXELS;
-                // Source map is from 0, 0 to the extents currently used.
-                map_src = Some(Rect::new(0, 0, map_width_pixels, map_height_pixels));
-                
-                // Destination is centered and the same size as the smaller map area above.
-                let x_offset = ((MAP_WIDTH / 2) - (map_width / 2)) * MAP_CELLS_TO_PIXELS as i32;
-                let y_offset = ((MAP_HEIGHT / 2) - (map_height / 2)) * MAP_CELLS_TO_PIXELS as i32;
-                map_rect.x += x_offset;
-                map_rect.y += y_offset;
-                map_rect.w = map_width_pixels as i32;
-                map_rect.h = map_height_pixels as i32;
-            }
-
             canvas.copy(&textures[&PanelName::Map], map_src, map_rect).unwrap();
 
             let player_area = screen_areas[&PanelName::Player];
@@ -1113,8 +1586,15 @@ pub struct DisplayState {
     pub tileset_names: TileMap,
 
     // animation information
+    // Each entity's animations are saved with their current frame, so a game loaded from a save
+    // shows the same frame it was saved on. One-shot animations, such as a death, resume from
+    // where they were rather than restarting or being skipped.
     pub animations: Comp<VecDeque<Animation>>,
     pub next_anim_key: i64,
+    // animation names, which are kept with the animations so a loaded save's names
+    // refer to the same strings as when it was saved.
+    pub intern: HashMap<String, Str>,
+    pub next_str: usize,
 
     // sprites drawn this frame
     pub drawn_sprites: Comp<Sprite>,
@@ -1132,22 +1612,59 @@ pub struct DisplayState {
     pub max_hp: Comp<i32>,
     pub behavior: Comp<Behavior>,
     pub inventory: Vec<(Item, ItemClass)>,
+    pub unidentified_items: HashSet<Item>,
+    pub attack_charged: bool,
+    pub hasted: usize,
+    pub time_stop: usize,
+    pub skill_cooldowns: HashMap<Skill, usize>,
+    pub deaths: usize,
+    pub save_exists: bool,
+    pub seed: u64,
+    pub pending_pickup: Option<EntityName>, // item waiting for an inventory slot to be dropped
+    pub map_zoom: f32,
+    pub map_zoom_offset: (f32, f32), // source pixels hidden to the left and above when zoomed
+    pub bestiary: Vec<(EntityName, i32, Behavior)>,
     pub skills: Vec<Skill>,
     pub talents: Vec<Talent>,
     pub gate_pos: Comp<Pos>,
+    pub size: Comp<(i32, i32)>,
     pub frozen: Comp<usize>,
+    pub disguised: HashSet<EntityId>, // mimics still drawn as items
+    pub dead_enemies: HashSet<EntityId>, // enemies killed but not removed yet, left out of the HUD counts
+    pub sprite_override: HashMap<EntityId, String>, // sprite names given in the map file
+    pub seen_items: HashMap<EntityId, Pos>, // where each item was last seen by the player
+    pub enemy_memory: HashMap<EntityId, (Pos, Sprite)>, // where each enemy was last seen, and its sprite then
+    pub objective_memory: HashMap<EntityId, Pos>, // where the key and exit were last seen
     pub player_ghost: Option<Pos>,
+    pub line_of_fire: Option<LineOfFire>, // whether a throw at the cursor would reach it
+
+    // predicted results of the skill being used
+    pub grass_wall_preview: Vec<(Pos, Direction)>,
+    pub pass_wall_preview: Option<Pos>,
+    pub blink_preview: Vec<Pos>,
 
     pub map: Map,
 
+    // map position shown in the top left cell of the map panel
+    pub camera_pos: Pos,
+    pub camera_smooth: Option<(f32, f32)>, // unrounded camera position while it catches up to the player
+    pub free_camera_pos: Option<Pos>, // position the camera is centered on instead of the player
+
     // settings
     pub debug_enabled: bool,
     pub overlay: bool,
+    pub item_highlight: bool,
+    pub sound_debug: bool,
+    pub time_scale: f32, // multiplies the frame time used for animations and effects, for slow motion
     pub move_mode: MoveMode,
     pub use_action: UseAction,
     pub cursor_action: Option<UseAction>,
 
     pub turn_count: usize,
+    pub enemies_killed: usize,
+    pub killed_by: Option<(EntityName, usize)>, // what killed the player, and on which turn
+    pub run_stats: RunStats,
+    pub death_hint: Option<String>, // shown on the game over screen after repeated deaths on a map
 
     // game state
     pub state: GameState,
@@ -1161,12 +1678,19 @@ pub struct DisplayState {
     // tiles that heard a sound
     pub sound_tiles: Vec<Pos>,
 
+    // tiles reached by each source's sounds during the last turn, for debugging
+    pub sound_events: HashMap<EntityId, Vec<Pos>>,
+
+    // the turn the current sounds were heard on
+    pub sound_turn: usize,
+
     // Action log with turn count.
     pub msg_lines: VecDeque<(usize, String)>,
 
     // turn data from messages
     // Player FoV information. Missing tiles are Fov::Outside.
     pub fov: HashMap<Pos, FovResult>,
+    pub fov_fade: HashMap<Pos, (f32, FovResult)>, // time each tile was last in the FoV, and how it was seen
     pub entities_in_fov: HashMap<EntityId, FovResult>,
     pub use_pos: Option<Pos>,
     pub use_dirs: HashSet<(Pos, Direction)>,
@@ -1174,6 +1698,8 @@ pub struct DisplayState {
     pub hit_positions: HashSet<Pos>,
     pub entities_at_cursor: Vec<EntityId>,
     pub entity_movements: HashMap<EntityId, Vec<Pos>>,
+    // next planned position of alert enemies, if they intend to move
+    pub entity_intents: HashMap<EntityId, Pos>,
     pub entity_attacks: HashMap<EntityId, Vec<Pos>>,
     pub entity_fov: HashMap<EntityId, Vec<Pos>>,
 
@@ -1186,6 +1712,12 @@ pub struct DisplayState {
     pub test_mode: bool,
 
     pub debug_entries: HashMap<String, String>,
+    // consecutive frames each debug timing has been over the frame budget
+    pub debug_over_budget: HashMap<String, usize>,
+
+    // clickable inventory buttons, in cells of the inventory panel
+    pub buttons: Vec<UiButton>,
+
     pub rng: Rand32,
 }
 
@@ -1197,6 +1729,8 @@ impl DisplayState {
             animations: Comp::<VecDeque<Animation>>::new(),
             tileset_names: HashMap::new(),
             next_anim_key: 0,
+            intern: HashMap::new(),
+            next_str: 0,
             drawn_sprites: Comp::new(),
             tile_index: Comp::new(),
             pos: Comp::new(),
@@ -1210,24 +1744,60 @@ impl DisplayState {
             max_hp: Comp::new(),
             behavior: Comp::new(),
             inventory: Vec::new(),
+            unidentified_items: HashSet::new(),
+            attack_charged: false,
+            hasted: 0,
+            time_stop: 0,
+            skill_cooldowns: HashMap::new(),
+            deaths: 0,
+            save_exists: false,
+            seed: 0,
+            pending_pickup: None,
+            map_zoom: 1.0,
+            map_zoom_offset: (0.0, 0.0),
+            bestiary: Vec::new(),
             skills: Vec::new(),
             talents: Vec::new(),
             gate_pos: Comp::new(),
+            size: Comp::new(),
             frozen: Comp::new(),
+            disguised: HashSet::new(),
+            dead_enemies: HashSet::new(),
+            sprite_override: HashMap::new(),
+            seen_items: HashMap::new(),
+            enemy_memory: HashMap::new(),
+            objective_memory: HashMap::new(),
             player_ghost: None,
+            line_of_fire: None,
+            grass_wall_preview: Vec::new(),
+            pass_wall_preview: None,
+            blink_preview: Vec::new(),
             map: Map::from_dims(1, 1),
+            camera_pos: Pos::new(0, 0),
+            camera_smooth: None,
+            free_camera_pos: None,
             debug_enabled: false,
             overlay: false,
+            item_highlight: false,
+            sound_debug: false,
+            time_scale: 1.0,
             move_mode: MoveMode::Walk,
             use_action: UseAction::Interact,
             cursor_action: None,
             turn_count: 0,
+            enemies_killed: 0,
+            killed_by: None,
+            run_stats: RunStats::default(),
+            death_hint: None,
             state: GameState::Playing,
             impressions: Vec::new(),
             prev_turn_fov: Vec::new(),
             sound_tiles: Vec::new(),
+            sound_events: HashMap::new(),
+            sound_turn: 0,
             msg_lines: VecDeque::new(),
             fov: HashMap::new(),
+            fov_fade: HashMap::new(),
             entities_in_fov: HashMap::new(),
             use_pos: None,
             use_dirs: HashSet::new(),
@@ -1235,6 +1805,7 @@ impl DisplayState {
             hit_positions: HashSet::new(),
             entities_at_cursor: Vec::new(),
             entity_movements: HashMap::new(),
+            entity_intents: HashMap::new(),
             entity_attacks: HashMap::new(),
             entity_fov: HashMap::new(),
             dt: 0.0,
@@ -1243,6 +1814,8 @@ impl DisplayState {
             cursor_pos: None,
             test_mode: false,
             debug_entries: HashMap::<String, String>::new(),
+            debug_over_budget: HashMap::<String, usize>::new(),
+            buttons: Vec::new(),
             rng: Rand32::new(0),
         };
     }
@@ -1293,10 +1866,12 @@ impl DisplayState {
         return self.tileset_names.get(name).map(|index| *index - 1);
     }
 
-    pub fn update_animations(&mut self, rng: &mut Rand32, config: &Config) {
+    pub fn update_animations(&mut self, rng: &mut Rand32) {
         for anims in self.animations.store.iter_mut() {
             if let Some(anim) = anims.get_mut(0) {
-                anim.step(self.dt, rng, config.frame_rate as f32);
+                // the frame time is already scaled for slow motion, and is measured rather than
+                // fixed, so animations keep their speed whatever the frame limit mode.
+                anim.step(self.dt, rng);
             }
         }
 
@@ -1307,6 +1882,80 @@ impl DisplayState {
         }
     }
 
+    /// Center the camera on the player for maps larger than the map panel,
+    /// stopping at the map edges plus the configured margin.
+    pub fn update_camera(&mut self, config: &Config) {
+        let player_pos = self.name.iter()
+                                  .find(|(_id, name)| **name == EntityName::Player)
+                                  .and_then(|(id, _name)| self.pos.get(&id));
+
+        let mut camera_pos = Pos::new(0, 0);
+        if let Some(free_camera_pos) = self.free_camera_pos {
+            camera_pos.x = camera_offset(free_camera_pos.x, self.map.width(), MAP_WIDTH, config.camera_margin);
+            camera_pos.y = camera_offset(free_camera_pos.y, self.map.height(), MAP_HEIGHT, config.camera_margin);
+        } else if config.display_center_map_on_player {
+            if let Some(player_pos) = player_pos {
+                camera_pos.x = camera_offset(player_pos.x, self.map.width(), MAP_WIDTH, config.camera_margin);
+                camera_pos.y = camera_offset(player_pos.y, self.map.height(), MAP_HEIGHT, config.camera_margin);
+            }
+        }
+
+        if config.camera_lerp_speed > 0.0 {
+            let (target_x, target_y) = (camera_pos.x as f32, camera_pos.y as f32);
+            let (x, y) = self.camera_smooth.unwrap_or((target_x, target_y));
+
+            let amount = (config.camera_lerp_speed * self.dt).min(1.0);
+            let x = camera_lerp(x, target_x, amount, MAP_WIDTH);
+            let y = camera_lerp(y, target_y, amount, MAP_HEIGHT);
+            self.camera_smooth = Some((x, y));
+
+            camera_pos = Pos::new(x.round() as i32, y.round() as i32);
+        }
+
+        self.camera_pos = camera_pos;
+    }
+
+    /// Track the turn count and the sounds heard during a turn. A turn's messages end with
+    /// StartTurn, so its sounds are kept through it to be drawn, and are cleared when the
+    /// next turn's first sound is heard, or when the next turn ends without any.
+    pub fn process_turn_message(&mut self, msg: Msg, map: &Map, config: &Config) {
+        match msg {
+            Msg::StartTurn => {
+                if self.sound_turn != self.turn_count {
+                    self.sound_tiles.clear();
+                    self.sound_events.clear();
+                }
+                self.turn_count += 1;
+            }
+
+            Msg::IdleSound(cause_id, source_pos, radius) => {
+                // idle sounds are not resolved into tiles by the game, as nothing reacts to them
+                let sound_aoe = aoe_fill(map, AoeEffect::Sound, source_pos, radius, config);
+                for hit_pos in sound_aoe.positions() {
+                    self.record_sound(cause_id, hit_pos);
+                }
+            }
+
+            Msg::SoundHitTile(cause_id, _source_pos, _radius, hit_pos) => {
+                self.record_sound(cause_id, hit_pos);
+            }
+
+            _ => {}
+        }
+    }
+
+    fn record_sound(&mut self, cause_id: EntityId, hit_pos: Pos) {
+        // the first sound of a turn replaces the previous turn's sounds
+        if self.sound_turn != self.turn_count {
+            self.sound_tiles.clear();
+            self.sound_events.clear();
+            self.sound_turn = self.turn_count;
+        }
+
+        self.sound_tiles.push(hit_pos);
+        self.sound_events.entry(cause_id).or_insert(Vec::new()).push(hit_pos);
+    }
+
     pub fn player_id(&self) -> EntityId {
         let mut player_id = None;
         for (key, nam) in self.name.iter() {
@@ -1344,6 +1993,27 @@ impl DisplayState {
         self.animations[&entity_id].pop_front();
     }
 
+    /// The number of living enemies on the level, and how many of them are in the player's FoV.
+    /// Disguised mimics are not counted, so the counts do not give them away.
+    pub fn enemy_counts(&self) -> (usize, usize) {
+        let mut alive = 0;
+        let mut in_fov = 0;
+        for (entity_id, typ) in self.typ.iter() {
+            if *typ != EntityType::Enemy ||
+               self.dead_enemies.contains(&entity_id) ||
+               self.disguised.contains(&entity_id) {
+                continue;
+            }
+
+            alive += 1;
+            if self.entity_is_in_fov(entity_id) == FovResult::Inside {
+                in_fov += 1;
+            }
+        }
+
+        return (alive, in_fov);
+    }
+
     pub fn entity_is_in_fov(&self, entity_id: EntityId) -> FovResult {
         if let Some(fov_result) = self.entities_in_fov.get(&entity_id) {
             return *fov_result;
@@ -1366,6 +2036,27 @@ impl DisplayState {
 }
 
 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UiButton {
+    pub chr: char,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl UiButton {
+    pub fn new(chr: char, x: f32, y: f32, width: f32, height: f32) -> UiButton {
+        return UiButton { chr, x, y, width, height };
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        return x >= self.x && x < self.x + self.width &&
+               y >= self.y && y < self.y + self.height;
+    }
+}
+
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Impression {
     pub sprite: Sprite,
@@ -1379,6 +2070,38 @@ impl Impression {
 }
 
 
+/// The particle effect for a named ambient preset, if there is one.
+fn ambient_effect(name: &str, config: &Config) -> Option<Effect> {
+    match name {
+        "dust" => Some(Effect::particles(1.0, config.particle_speed, 0.0, Color::white())),
+        "rain" => Some(Effect::particles(4.0, 0.5 * config.particle_speed, 6.0 * config.particle_speed, config.color_ice_blue)),
+        "ash" => Some(Effect::particles(0.5, 0.3 * config.particle_speed, 0.5 * config.particle_speed, config.color_medium_grey)),
+        "none" => None,
+        _ => {
+            eprintln!("Unknown ambient effect '{}'", name);
+            None
+        }
+    }
+}
+
+/// Move a camera offset along one axis towards its target. The camera may lag behind by
+/// less than half the view, so the player it is centered on never leaves the view.
+fn camera_lerp(current: f32, target: f32, amount: f32, view_len: i32) -> f32 {
+    let max_lag = (view_len / 2 - 1) as f32;
+    let next = current + (target - current) * amount;
+    return next.max(target - max_lag).min(target + max_lag);
+}
+
+/// Offset along one axis that centers the view on 'center', clamped so that no more than
+/// 'margin' tiles past either end of the map are shown.
+fn camera_offset(center: i32, map_len: i32, view_len: i32, margin: i32) -> i32 {
+    if map_len <= view_len {
+        return 0;
+    }
+
+    return clamp(center - view_len / 2, -margin, map_len - view_len + margin);
+}
+
 fn sheet_direction(direction: Direction) -> Direction {
     match direction {
         Direction::Up => return Direction::Up,
@@ -22,6 +22,8 @@ use crate::display::*;
 
 
 pub fn render_all(panels: &mut Panels, display_state: &mut DisplayState, sprites: &Vec<SpriteSheet>, config: &Config, dt: f32) -> Result<(), String> {
+    // only the visuals are slowed down. The frame rate and the game itself are unaffected.
+    let dt = dt * display_state.time_scale;
     display_state.dt = dt;
     display_state.time += dt;
 
@@ -31,21 +33,39 @@ pub fn render_all(panels: &mut Panels, display_state: &mut DisplayState, sprites
     /* Draw Map */
     render_panels(panels, display_state, config, sprites);
 
+    /* Scroll Map to Camera */
+    display_state.update_camera(config);
+    let camera_pos = display_state.camera_pos;
+    panels.get_mut(&PanelName::Map).unwrap().shift_cmds(Pos::new(-camera_pos.x, -camera_pos.y));
+
     /* Draw Debug Overlay */
     if display_state.debug_enabled {
-        render_debug(panels.get_mut(&PanelName::Map).unwrap(), display_state);
+        let frame_budget = 1.0 / config.frame_rate as f32;
+        render_debug(panels.get_mut(&PanelName::Map).unwrap(), display_state, frame_budget, config.frame_budget_warn_frames);
     }
 
     let menu_panel = panels.get_mut(&PanelName::Menu).unwrap();
 
-    if display_state.state == GameState::Inventory {
+    if display_state.state == GameState::Inventory && display_state.pending_pickup.is_some() {
+        render_inventory_full(menu_panel, display_state);
+    } else if display_state.state == GameState::Inventory {
         render_inventory(menu_panel, display_state, sprites, config);
     } else if display_state.state == GameState::SkillMenu {
         render_skill_menu(menu_panel, display_state);
     } else if display_state.state == GameState::ClassMenu {
         render_class_menu(menu_panel);
     } else if display_state.state == GameState::ConfirmQuit {
-        render_confirm_quit(menu_panel);
+        render_confirm_quit(menu_panel, display_state);
+    } else if display_state.state == GameState::MainMenu {
+        render_main_menu(menu_panel, display_state);
+    } else if display_state.state == GameState::TimeUp {
+        render_time_up(menu_panel, display_state);
+    } else if display_state.state == GameState::Lose {
+        render_lose(menu_panel, display_state);
+    } else if display_state.state == GameState::Win {
+        render_win(menu_panel, display_state);
+    } else if display_state.state == GameState::BestiaryMenu {
+        render_bestiary(menu_panel, display_state);
     } else if display_state.state == GameState::HelpMenu {
         let help_panel = panels.get_mut(&PanelName::Help).unwrap();
         render_help(help_panel);
@@ -69,7 +89,7 @@ fn render_panels(panels: &mut Panels,
 
     {
         let _map = timer!("MAP");
-        render_map(display_state, panel, sprites);
+        render_map(display_state, panel, config, sprites);
     }
 
     {
@@ -102,6 +122,9 @@ fn render_panels(panels: &mut Panels,
 
     {
         let _extra = timer!("EXTRA");
+        if config.enemy_memory {
+            render_enemy_memory(panel, display_state, config);
+        }
         render_impressions(panel, display_state, config);
         render_effects(panel, display_state, config, sprites);
         render_overlays(panel, display_state, config, sprites);
@@ -116,26 +139,44 @@ fn render_panels(panels: &mut Panels,
     /* Draw Inventory */
     {
         let inventory_panel = &mut panels.get_mut(&PanelName::Inventory).unwrap();
-        render_inventory(inventory_panel, display_state, sprites, config);
+        display_state.buttons = render_inventory(inventory_panel, display_state, sprites, config);
     }
 
     /* Draw Game Info */
     {
         let info_panel = &mut panels.get_mut(&PanelName::Info).unwrap();
-        render_info(info_panel, display_state);
+        render_info(info_panel, display_state, config);
     }
 }
 
 
-fn render_debug(panel: &mut Panel, display_state: &mut DisplayState) {
+/// Render debug entries, showing timings in red when they have been over the
+/// frame budget (in seconds) for 'warn_frames' frames in a row.
+fn render_debug(panel: &mut Panel, display_state: &mut DisplayState, frame_budget: f32, warn_frames: usize) {
+    let text_color = Color::new(0xcd, 0xb4, 0x96, 255);
+    let warn_color = Color::new(0xff, 0x30, 0x30, 255);
+
     let mut text_list = Vec::new();
     for (key, value) in display_state.debug_entries.iter() {
-        text_list.push(format!("{}: {}", key, value));
+        let over_budget = value.parse::<f32>().map_or(false, |time| time > frame_budget);
+
+        let count = display_state.debug_over_budget.entry(key.clone()).or_insert(0);
+        if over_budget {
+            *count += 1;
+        } else {
+            *count = 0;
+        }
+
+        let color = if *count >= warn_frames && warn_frames > 0 {
+            warn_color
+        } else {
+            text_color
+        };
+        text_list.push((color, format!("{}: {}", key, value)));
     }
 
     let text_pos = Pos::new(1, 1);
-    let text_color = Color::new(0xcd, 0xb4, 0x96, 255);
-    panel.text_list_cmd(&text_list, text_color, text_pos, 1.0);
+    panel.colored_text_list_cmd(&text_list, text_pos, 1.0);
 }
 
 /// Draw an outline and title around an area of the screen
@@ -189,13 +230,29 @@ fn render_player_info(panel: &mut Panel, display_state: &DisplayState) {
 
     list.push(format!("turn {}", display_state.turn_count));
 
+    if display_state.attack_charged {
+        list.push("attack charged".to_string());
+    }
+
+    if display_state.hasted > 0 {
+        list.push(format!("hasted {}", display_state.hasted));
+    }
+
+    if display_state.time_stop > 0 {
+        list.push(format!("time stopped {}", display_state.time_stop));
+    }
+
+    if display_state.deaths > 0 {
+        list.push(format!("deaths {}", display_state.deaths));
+    }
+
     let text_pos = Pos::new(x_offset, 1);
 
     let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
     panel.text_list_cmd(&list, ui_color, text_pos, 1.0);
 }
 
-fn render_info(panel: &mut Panel, display_state: &mut DisplayState) {
+fn render_info(panel: &mut Panel, display_state: &mut DisplayState, config: &Config) {
     let text_color = Color::new(0xcd, 0xb4, 0x96, 255);
 
     if let Some(info_pos) = display_state.cursor_pos {
@@ -228,9 +285,24 @@ fn render_info(panel: &mut Panel, display_state: &mut DisplayState) {
             if entity_in_fov {
                 drawn_info = true;
 
+                // a disguised mimic shows up as the item it pretends to be
+                if display_state.disguised.contains(&obj_id) {
+                    text_list.push(format!("* {:?}", EntityName::Herb));
+                    text_list.push("".to_string());
+                    continue;
+                }
+
                 text_list.push(format!("* {:?}", display_state.name[&obj_id]));
                 if let Some(hp) = display_state.hp.get(&obj_id) {
-                    text_list.push(format!(" hp {:?}", hp));
+                    if config.show_target_stats && display_state.typ[&obj_id] == EntityType::Enemy {
+                        if let Some(max_hp) = display_state.max_hp.get(&obj_id) {
+                            text_list.push(format!(" hp {} / {}", hp, max_hp));
+                        } else {
+                            text_list.push(format!(" hp {:?}", hp));
+                        }
+                    } else {
+                        text_list.push(format!(" hp {:?}", hp));
+                    }
                 } else {
                     text_list.push("".to_string());
                 }
@@ -247,7 +319,27 @@ fn render_info(panel: &mut Panel, display_state: &mut DisplayState) {
                     text_list.push(format!("  {}", "dead"));
                 } else if let Some(behave) = display_state.behavior.get(&obj_id) {
                     text_list.push(format!(" currently {}", behave.description()));
+
+                    if config.show_target_stats && display_state.typ[&obj_id] == EntityType::Enemy {
+                        let frozen = display_state.frozen.get(&obj_id).map_or(0, |turns| *turns);
+                        if frozen > 0 {
+                            text_list.push(format!(" stunned {} turns", frozen));
+                            text_list.push(format!(" acts in {} turns", frozen + 1));
+                        } else if let Behavior::Armed(turns) = behave {
+                            text_list.push(format!(" explodes in {} turns", turns));
+                        } else {
+                            text_list.push(" acts next turn".to_string());
+                        }
+                    }
                 }
+            } else if config.show_target_stats &&
+                      display_state.entity_is_in_fov(obj_id) == FovResult::Edge &&
+                      display_state.typ[&obj_id] == EntityType::Enemy &&
+                      !display_state.disguised.contains(&obj_id) {
+                // enemies at the edge of the player's view are only partly seen
+                drawn_info = true;
+                text_list.push(format!("* {:?}", display_state.name[&obj_id]));
+                text_list.push(" hp ?".to_string());
             }
         }
 
@@ -256,6 +348,9 @@ fn render_info(panel: &mut Panel, display_state: &mut DisplayState) {
             for impr in display_state.impressions.iter() {
                 if impr.pos == info_pos {
                     text_list.push("* Golem".to_string());
+                    if config.show_target_stats {
+                        text_list.push(" hp ?".to_string());
+                    }
                     break;
                 }
             }
@@ -292,6 +387,10 @@ fn render_info(panel: &mut Panel, display_state: &mut DisplayState) {
             if display_state.map.tile_is_blocking(info_pos) {
                 text_list.push(format!("blocked"));
             }
+
+            if let Some(line_of_fire) = display_state.line_of_fire {
+                text_list.push(format!("line of fire: {}", line_of_fire.description()));
+            }
         }
 
         panel.text_list_cmd(&text_list, text_color, text_pos, 1.0);
@@ -323,7 +422,33 @@ fn render_skill_menu(panel: &mut Panel, display_state: &DisplayState) {
     let mut list = Vec::new();
 
     for (index, skill) in display_state.skills.iter().enumerate() {
-        list.push(format!("{} {:?}", index, skill));
+        if let Some(turns) = display_state.skill_cooldowns.get(skill) {
+            list.push(format!("{} {:?} ({} turns)", index, skill, turns));
+        } else {
+            list.push(format!("{} {:?}", index, skill));
+        }
+    }
+
+    let y_pos = 2;
+    let text_pos = Pos::new(1, y_pos);
+
+    let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
+
+    panel.text_list_cmd(&list, ui_color, text_pos, 1.0);
+}
+
+fn render_bestiary(panel: &mut Panel, display_state: &DisplayState) {
+    // Render header
+    render_placard(panel, "Bestiary");
+
+    let mut list = Vec::new();
+
+    if display_state.bestiary.len() == 0 {
+        list.push("no enemies encountered".to_string());
+    }
+
+    for (name, max_hp, behavior) in display_state.bestiary.iter() {
+        list.push(format!("{:?}: {} hp, first seen {}", name, max_hp, behavior.description()));
     }
 
     let y_pos = 2;
@@ -352,7 +477,61 @@ fn render_class_menu(panel: &mut Panel) {
     panel.text_list_cmd(&list, ui_color, text_pos, 1.0);
 }
 
-fn render_confirm_quit(panel: &mut Panel) {
+/// The names of the items in each inventory slot, with all stones in one slot.
+/// Only misc items use slots.
+fn inventory_slot_names(display_state: &DisplayState) -> Vec<String> {
+    let mut slots = Vec::new();
+
+    let num_stones = display_state.inventory.iter().filter(|(item, _)| *item == Item::Stone).count();
+    let mut stones_listed = false;
+
+    for (item, item_class) in display_state.inventory.iter() {
+        if *item_class != ItemClass::Misc {
+            continue;
+        }
+
+        if *item == Item::Stone {
+            if !stones_listed {
+                slots.push(format!("Stone x{}", num_stones));
+                stones_listed = true;
+            }
+        } else if display_state.unidentified_items.contains(item) {
+            slots.push(item.unknown_name().to_string());
+        } else {
+            slots.push(format!("{:?}", item));
+        }
+    }
+
+    return slots;
+}
+
+fn render_inventory_full(panel: &mut Panel, display_state: &DisplayState) {
+    // Render header
+    render_placard(panel, "Inventory Full");
+
+    let mut list = Vec::new();
+
+    if let Some(item_name) = display_state.pending_pickup {
+        list.push(format!("drop an item to pick up the {:?}", item_name));
+        list.push("".to_string());
+    }
+
+    for (index, slot_name) in inventory_slot_names(display_state).iter().enumerate() {
+        list.push(format!("{} {}", index, slot_name));
+    }
+
+    list.push("".to_string());
+    list.push("esc: leave it".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(1, y_pos);
+
+    let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
+
+    panel.text_list_cmd(&list, ui_color, text_pos, 1.0);
+}
+
+fn render_confirm_quit(panel: &mut Panel, display_state: &DisplayState) {
     // Render header
     render_placard(panel, "Quit?");
 
@@ -364,7 +543,119 @@ fn render_confirm_quit(panel: &mut Panel) {
     list.push("".to_string());
     list.push("r: restart".to_string());
     list.push("".to_string());
+    list.push("m: save and return to menu".to_string());
+    list.push("".to_string());
+    list.push("n: return to menu, discarding this run's saves".to_string());
+    list.push("".to_string());
     list.push("?: help".to_string());
+    list.push("".to_string());
+    list.push(format!("seed: {}", display_state.seed));
+
+    let y_pos = 2;
+    let text_pos = Pos::new(1, y_pos);
+
+    let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
+
+    panel.text_list_cmd(&list, ui_color, text_pos, 1.0);
+}
+
+fn render_main_menu(panel: &mut Panel, display_state: &DisplayState) {
+    // Render header
+    render_placard(panel, "Rust Roguelike");
+
+    let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
+    let disabled_color = Color::new(0x6a, 0x5e, 0x50, 255);
+
+    // continue is greyed out when there is no save to load
+    let continue_color = if display_state.save_exists { ui_color } else { disabled_color };
+
+    let y_pos = 2;
+    panel.text_cmd("0: new game", ui_color, Pos::new(1, y_pos), 1.0);
+    panel.text_cmd("1: continue", continue_color, Pos::new(1, y_pos + 2), 1.0);
+    panel.text_cmd("2: quit", ui_color, Pos::new(1, y_pos + 4), 1.0);
+}
+
+fn render_time_up(panel: &mut Panel, display_state: &DisplayState) {
+    // Render header
+    render_placard(panel, "Time Up");
+
+    let mut list = Vec::new();
+
+    run_stats_list(&mut list, display_state);
+    list.push("r: restart".to_string());
+    list.push("".to_string());
+    list.push("e: export replay".to_string());
+    list.push("".to_string());
+    list.push("q: quit game".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(1, y_pos);
+
+    let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
+
+    panel.text_list_cmd(&list, ui_color, text_pos, 1.0);
+}
+
+fn render_win(panel: &mut Panel, display_state: &DisplayState) {
+    // Render header
+    render_placard(panel, "You Won");
+
+    let mut list = Vec::new();
+
+    run_stats_list(&mut list, display_state);
+    list.push("r: restart".to_string());
+    list.push("".to_string());
+    list.push("m: return to menu".to_string());
+    list.push("".to_string());
+    list.push("e: export replay".to_string());
+    list.push("".to_string());
+    list.push("q: quit game".to_string());
+
+    let y_pos = 2;
+    let text_pos = Pos::new(1, y_pos);
+
+    let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
+
+    panel.text_list_cmd(&list, ui_color, text_pos, 1.0);
+}
+
+/// The run's totals, as shown on each of the end screens.
+fn run_stats_list(list: &mut Vec<String>, display_state: &DisplayState) {
+    let stats = &display_state.run_stats;
+    list.push(format!("turns taken: {}", stats.turns));
+    list.push(format!("tiles explored: {}", stats.tiles_explored));
+    list.push(format!("enemies defeated: {}", stats.enemies_killed));
+    list.push(format!("damage dealt: {}", stats.damage_dealt));
+    list.push(format!("damage taken: {}", stats.damage_taken));
+    list.push(format!("skills used: {}", stats.skills_used));
+    list.push(format!("items thrown: {}", stats.items_thrown));
+    list.push("".to_string());
+}
+
+fn render_lose(panel: &mut Panel, display_state: &DisplayState) {
+    // Render header
+    render_placard(panel, "You Died");
+
+    let mut list = Vec::new();
+
+    if let Some((name, turn)) = display_state.killed_by {
+        if name == EntityName::Player {
+            list.push(format!("Killed by your own hand on turn {}", turn));
+        } else {
+            list.push(format!("Killed by a {:?} on turn {}", name, turn));
+        }
+        list.push("".to_string());
+    }
+    run_stats_list(&mut list, display_state);
+    if let Some(hint) = &display_state.death_hint {
+        list.push(format!("Hint: {}", hint));
+        list.push("".to_string());
+    }
+    list.push("r: restart".to_string());
+    list.push("".to_string());
+    list.push("e: export replay".to_string());
+    list.push("".to_string());
+    list.push("q: quit game".to_string());
 
     let y_pos = 2;
     let text_pos = Pos::new(1, y_pos);
@@ -387,6 +678,11 @@ fn render_help(panel: &mut Panel) {
     list.push("Arrow keys or number keys to move.".to_string());
     list.push("\tThere is 8 directional movement.".to_string());
     list.push("".to_string());
+    list.push("v outlines the items you have seen.".to_string());
+    list.push("".to_string());
+    list.push("u undoes the last turn.".to_string());
+    list.push("\tOnly one turn can be undone, and not after dying or leaving a level.".to_string());
+    list.push("".to_string());
     list.push("Items:".to_string());
     list.push("To use an item:".to_string());
     list.push("Z is always the primary item.".to_string());
@@ -415,11 +711,13 @@ fn render_help(panel: &mut Panel) {
     list.push("\tExtra information is given for tiles under the cursor.".to_string());
     list.push("\tHold shift for fast movement.".to_string());
     list.push("\tHold ctrl for tiles around the player.".to_string());
+    list.push("\tu moves the cursor back to where it was before its last move.".to_string());
     list.push("".to_string());
     list.push("Skills:".to_string());
     list.push("h brings up the class menu.".to_string());
     list.push("\tCurrently, a class may be selected at any time.".to_string());
     list.push("j brings up the skill list.".to_string());
+    list.push("b brings up the bestiary of enemies encountered.".to_string());
     list.push("a, s, d uses the corresponding skill.".to_string());
     list.push("\tSkills may apply immediately or show direction.".to_string());
 
@@ -439,6 +737,16 @@ fn render_button(name: &str, x_offset: f32, y_offset: f32, panel: &mut Panel, sp
     panel.sprite_float_scaled_cmd(button, ui_color, x_offset, y_offset, config.x_scale_buttons, config.y_scale_buttons);
 }
 
+/// The clickable area of a button drawn with render_button, if its slot is in use.
+fn button_area(chr: char, filled: bool, x_offset: f32, y_offset: f32, config: &Config) -> Option<UiButton> {
+    if !filled {
+        return None;
+    }
+
+    let key = chr.to_ascii_lowercase();
+    return Some(UiButton::new(key, x_offset, y_offset, config.x_scale_buttons, config.y_scale_buttons));
+}
+
 fn render_name(first_word: &str, second_word: &str, x_offset: f32, y_offset: f32, color: Color, panel: &mut Panel, config: &Config) {
     if second_word.len() > 0 {
         let first_x_offset = x_offset + config.ui_inv_name_0_x_offset;
@@ -551,6 +859,11 @@ fn render_skill(skill: Skill, x_offset: f32, y_offset: f32, color: Color, panel:
             second_word = "wind";
         },
 
+        Skill::StoneVolley => {
+            first_word = "stone".to_string();
+            second_word = "volley";
+        },
+
         _ => {
             first_word = format!("{:?}", skill);
         }
@@ -559,7 +872,7 @@ fn render_skill(skill: Skill, x_offset: f32, y_offset: f32, color: Color, panel:
     render_name(&first_word, second_word, x_offset, y_offset, color, panel, config);
 }
 
-fn render_inventory_talent(chr: char, index: usize, x_offset: f32, y_offset: f32, panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) {
+fn render_inventory_talent(chr: char, index: usize, x_offset: f32, y_offset: f32, panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) -> Option<UiButton> {
     let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
     let highlight_ui_color = Color::new(0, 0, 0, 255);
 
@@ -584,9 +897,11 @@ fn render_inventory_talent(chr: char, index: usize, x_offset: f32, y_offset: f32
     if let Some(talent) = display_state.talents.get(index) {
         render_talent(*talent, x_offset, y_offset, text_color, panel, config);
     }
+
+    return button_area(chr, index < display_state.talents.len(), x_offset, y_offset, config);
 }
 
-fn render_inventory_skill(chr: char, index: usize, x_offset: f32, y_offset: f32, panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) {
+fn render_inventory_skill(chr: char, index: usize, x_offset: f32, y_offset: f32, panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) -> Option<UiButton> {
     let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
     let highlight_ui_color = Color::new(0, 0, 0, 255);
 
@@ -611,6 +926,8 @@ fn render_inventory_skill(chr: char, index: usize, x_offset: f32, y_offset: f32,
     if let Some(skill) = display_state.skills.get(index) {
         render_skill(*skill, x_offset, y_offset, text_color, panel, config);
     }
+
+    return button_area(chr, index < display_state.skills.len(), x_offset, y_offset, config);
 }
 
 fn should_highlight_item(display_state: &DisplayState, use_action: UseAction) -> bool {
@@ -619,7 +936,7 @@ fn should_highlight_item(display_state: &DisplayState, use_action: UseAction) ->
     return use_mode_action || cursor_mode_action;
 }
 
-fn render_inventory_item(chr: char, item_class: ItemClass, x_offset: f32, y_offset: f32, panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) {
+fn render_inventory_item(chr: char, item_class: ItemClass, x_offset: f32, y_offset: f32, panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) -> Option<UiButton> {
     let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
     let highlight_ui_color = Color::new(0, 0, 0, 255);
 
@@ -636,57 +953,70 @@ fn render_inventory_item(chr: char, item_class: ItemClass, x_offset: f32, y_offs
 
     let text_x_offset = x_offset + config.ui_inv_name_x_offset;
     let text_y_offset = y_offset + config.ui_inv_name_y_offset;
+    // the inventory is emitted in the player's chosen order, so the first item of
+    // this class is the one that will be used for this slot.
+    let mut filled = false;
     for (item, cur_item_class) in display_state.inventory.iter() {
         if *cur_item_class == item_class {
-            let item_text = format!("{:?}", item);
+            let item_text;
+            if display_state.unidentified_items.contains(item) {
+                item_text = item.unknown_name().to_string();
+            } else {
+                item_text = format!("{:?}", item);
+            }
             panel.text_float_cmd(&item_text, text_color, text_x_offset, text_y_offset, config.ui_inv_name_scale);
+            filled = true;
             break;
         }
     }
+
+    return button_area(chr, filled, x_offset, y_offset, config);
 }
 
-/// Render an inventory section within the given area
-fn render_inventory(panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) {
+/// Render an inventory section within the given area, returning the buttons that can be clicked
+fn render_inventory(panel: &mut Panel, display_state: &DisplayState, sprites: &Vec<SpriteSheet>, config: &Config) -> Vec<UiButton> {
     let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
     let highlight_ui_color = Color::new(0, 0, 0, 255);
 
+    let mut buttons = Vec::new();
+
     let mut x_offset = config.x_offset_buttons;
     let mut y_offset = config.y_offset_buttons;
 
     /* Talents */
     // TODO replace with qwe when available.
-    render_inventory_talent('Q', 0, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_talent('Q', 0, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
-    render_inventory_talent('W', 1, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_talent('W', 1, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
-    render_inventory_talent('E', 2, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_talent('E', 2, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
-    render_inventory_talent('R', 3, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_talent('R', 3, x_offset, y_offset, panel, display_state, sprites, config));
 
     /* Skills */
     y_offset += config.y_spacing_buttons;
     x_offset = config.x_offset_buttons;
-    render_inventory_skill('A', 0, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_skill('A', 0, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
-    render_inventory_skill('S', 1, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_skill('S', 1, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
-    render_inventory_skill('D', 2, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_skill('D', 2, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
-    render_inventory_skill('F', 3, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_skill('F', 3, x_offset, y_offset, panel, display_state, sprites, config));
 
     /* Items */
     y_offset += config.y_spacing_buttons;
     x_offset = config.x_offset_buttons;
-    render_inventory_item('Z', ItemClass::Primary, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_item('Z', ItemClass::Primary, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
-    render_inventory_item('X', ItemClass::Consumable, x_offset, y_offset, panel, display_state, sprites, config);
+    buttons.extend(render_inventory_item('X', ItemClass::Consumable, x_offset, y_offset, panel, display_state, sprites, config));
 
     x_offset += config.x_spacing_buttons;
     let text_color;
@@ -712,10 +1042,18 @@ fn render_inventory(panel: &mut Panel, display_state: &DisplayState, sprites: &V
         let item_text = format!("Stone x{}", num_stones);
         panel.text_float_cmd(&item_text, text_color, text_x_offset, text_y_offset, config.ui_inv_name_scale);
     }
+    buttons.extend(button_area('C', num_stones > 0, x_offset, y_offset, config));
 
     // TODO need another item class to use for this location.
     //x_offset += config.x_spacing_buttons;
     //render_inventory_item('V', ItemClass::Consumable, x_offset, y_offset, panel, display_state, sprites, config);
+
+    // show how many inventory slots are used in the otherwise empty last location
+    x_offset += config.x_spacing_buttons;
+    let slots_text = format!("{}/{}", inventory_slot_names(display_state).len(), config.inventory_slots);
+    panel.text_float_cmd(&slots_text, ui_color, x_offset + config.ui_inv_name_x_offset, y_offset + config.ui_inv_name_y_offset, config.ui_inv_name_scale);
+
+    return buttons;
 }
 
 /// render the background files, including water tiles
@@ -724,26 +1062,20 @@ fn render_background(panel: &mut Panel, display_state: &mut DisplayState, sprite
 
     let sprite_key = lookup_spritekey(sprites, "rustrogueliketiles");
 
-    for y in 0..map_height {
-        for x in 0..map_width {
-            let map_pos = Pos::new(x, y);
-
-            let tile = &display_state.map[(x, y)];
-            // TODO why are these branches identical?
-            let index = display_state.tileset_index(&"open_tile").unwrap();
-            if tile.tile_type != TileType::Water {
-                let sprite = Sprite::new(index as u32, sprite_key);
-                panel.sprite_cmd(sprite, Color::white(), map_pos);
-            } else {
-                let sprite = Sprite::new(index as u32, sprite_key);
-                panel.sprite_cmd(sprite, Color::white(), map_pos);
-            }
-        }
-    }
+    // every tile, including water, starts with the same open tile sprite, so the
+    // whole background is drawn as one batch.
+    let index = display_state.tileset_index(&"open_tile").unwrap();
+    let sprite = Sprite::new(index as u32, sprite_key);
+    panel.sprite_batch_cmd(sprite, Color::white(), Pos::new(0, 0), (map_width as u32, map_height as u32));
 }
 
 /// Render Wall Shadows (full tile and intertile walls, left and down)
-fn render_wall_shadow(panel: &mut Panel, pos: Pos, display_state: &mut DisplayState, sprites: &Vec<SpriteSheet>, shadow_color: Color) {
+fn render_wall_shadow(panel: &mut Panel, pos: Pos, display_state: &mut DisplayState, sprites: &Vec<SpriteSheet>, shadow_color: Color, config: &Config) {
+    if config.wall_occlusion {
+        render_wall_occlusion(panel, pos, display_state, config);
+        return;
+    }
+
     let shadow_sprite_key = lookup_spritekey(sprites, "shadowtiles");
 
     let tile = display_state.map[pos];
@@ -821,9 +1153,78 @@ fn render_wall_shadow(panel: &mut Panel, pos: Pos, display_state: &mut DisplaySt
     }
 }
 
+/// Darken a floor tile by how much of it is surrounded by walls, so concave corners are
+/// shaded more than tiles along a single straight wall.
+fn render_wall_occlusion(panel: &mut Panel, pos: Pos, display_state: &DisplayState, config: &Config) {
+    if display_state.map[pos].tile_type == TileType::Wall {
+        return;
+    }
+
+    let occlusion = wall_occlusion(&display_state.map, pos);
+    if occlusion > 0.0 {
+        let mut shadow_color = Color::black();
+        shadow_color.a = (config.wall_occlusion_alpha as f32 * occlusion).round() as u8;
+        panel.highlight_cmd(shadow_color, pos);
+    }
+}
+
+/// The fraction of a tile's surroundings that are walls, from 0.0 to 1.0.
+/// Orthogonal walls count fully and diagonal walls count half. Inter-tile walls on the
+/// tile's edges count partially, with tall walls counting more than short walls.
+/// This only depends on the map, so a given map is always shaded the same way.
+fn wall_occlusion(map: &Map, pos: Pos) -> f32 {
+    let is_wall = |x: i32, y: i32| {
+        let neighbor = Pos::new(x, y);
+        return map.is_within_bounds(neighbor) && map[neighbor].tile_type == TileType::Wall;
+    };
+
+    let intertile_weight = |wall: Wall| {
+        match wall {
+            Wall::ShortWall => 0.25,
+            Wall::TallWall => 0.5,
+            _ => 0.0,
+        }
+    };
+
+    let (x, y) = pos.to_tuple();
+    let mut occlusion: f32 = 0.0;
+
+    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+        if is_wall(x + dx, y + dy) {
+            occlusion += 1.0;
+        }
+    }
+
+    for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter() {
+        if is_wall(x + dx, y + dy) {
+            occlusion += 0.5;
+        }
+    }
+
+    // a tile's own walls are on its left and bottom, and its neighbors hold the walls
+    // on its right and top.
+    occlusion += intertile_weight(map[pos].left_wall);
+    occlusion += intertile_weight(map[pos].bottom_wall);
+    if map.is_within_bounds(Pos::new(x + 1, y)) {
+        occlusion += intertile_weight(map[(x + 1, y)].left_wall);
+    }
+    if map.is_within_bounds(Pos::new(x, y - 1)) {
+        occlusion += intertile_weight(map[(x, y - 1)].bottom_wall);
+    }
+
+    // four orthogonal and four diagonal walls fully enclose a tile
+    let max_occlusion = 4.0 * 1.0 + 4.0 * 0.5;
+    return (occlusion / max_occlusion).min(1.0);
+}
+
 fn render_map_above(panel: &mut Panel, display_state: &DisplayState, config: &Config, sprites: &Vec<SpriteSheet>) {
     let (map_width, map_height) = display_state.map.size();
 
+    // markers are drawn before the fog of war so they are darkened with their tiles
+    if config.short_wall_markers {
+        render_short_wall_markers(panel, display_state, config);
+    }
+
     let sprite_key = lookup_spritekey(sprites, "rustrogueliketiles");
     for y in 0..map_height {
         for x in 0..map_width {
@@ -852,8 +1253,8 @@ fn render_map_above(panel: &mut Panel, display_state: &DisplayState, config: &Co
 
             let fov_result = display_state.pos_is_in_fov(pos);
 
-            // apply a FoW darkening to cells
-            if config.fog_of_war && fov_result != FovResult::Inside {
+            // apply a FoW darkening to cells, except those revealed next to the player
+            if config.fog_of_war && fov_result != FovResult::Inside && !revealed_adjacent(display_state, config, pos) {
                 let is_in_fov_ext = fov_result == FovResult::Edge;
 
                 let mut blackout_color = Color::black();
@@ -863,7 +1264,7 @@ fn render_map_above(panel: &mut Panel, display_state: &DisplayState, config: &Co
                     //panel.sprite_cmd(sprite, blackout_color, pos);
                     panel.highlight_cmd(blackout_color, pos);
                 } else if display_state.map[pos].explored {
-                    blackout_color.a = config.explored_alpha;
+                    blackout_color.a = explored_fade_alpha(display_state, config, pos);
                     //panel.sprite_cmd(sprite, blackout_color, pos);
                     panel.highlight_cmd(blackout_color, pos);
                 } else {
@@ -874,6 +1275,34 @@ fn render_map_above(panel: &mut Panel, display_state: &DisplayState, config: &Co
     }
 }
 
+/// Whether a tile next to the player is shown even outside of the FoV, so the player
+/// can navigate by touch in darkness.
+fn revealed_adjacent(display_state: &DisplayState, config: &Config, pos: Pos) -> bool {
+    if !config.reveal_adjacent {
+        return false;
+    }
+
+    let player_pos = display_state.pos[&display_state.player_id()];
+    return distance_maximum(pos, player_pos) <= 1;
+}
+
+/// Darkening of an explored tile outside of the FoV. Tiles that just left the FoV fade from
+/// how they were last seen to the explored alpha, so tiles at the edge do not flicker.
+fn explored_fade_alpha(display_state: &DisplayState, config: &Config, pos: Pos) -> u8 {
+    if config.fov_edge_persistence > 0.0 {
+        if let Some((left_time, fov_result)) = display_state.fov_fade.get(&pos) {
+            let elapsed = display_state.time - left_time;
+            if elapsed < config.fov_edge_persistence {
+                let start_alpha = if *fov_result == FovResult::Edge { config.fov_edge_alpha } else { 0 };
+                let scale = elapsed / config.fov_edge_persistence;
+                return lerp(start_alpha as f32, config.explored_alpha as f32, scale) as u8;
+            }
+        }
+    }
+
+    return config.explored_alpha;
+}
+
 fn render_map_middle(panel: &mut Panel, display_state: &mut DisplayState, config: &Config, sprites: &Vec<SpriteSheet>) {
     let (map_width, map_height) = display_state.map.size();
 
@@ -883,7 +1312,7 @@ fn render_map_middle(panel: &mut Panel, display_state: &mut DisplayState, config
             let pos = Pos::new(x, y);
 
             let shadow_color = config.color_shadow;
-            render_wall_shadow(panel, pos, display_state, sprites, shadow_color);
+            render_wall_shadow(panel, pos, display_state, sprites, shadow_color, config);
 
             let tile = display_state.map[pos];
 
@@ -902,10 +1331,18 @@ fn render_map_middle(panel: &mut Panel, display_state: &mut DisplayState, config
 fn render_pip(panel: &mut Panel, display_state: &DisplayState, config: &Config) {
     let player_id = display_state.player_id();
 
+    // the HUD takes the right side of the bar, and the player's bars share the rest
+    let mut bars_width = panel.cells.0 as f32;
+    // practice mode always shows the HUD, so it is not mistaken for normal play
+    if config.hud_bar || config.practice_mode {
+        bars_width -= HUD_CELLS as f32;
+        render_hud(panel, bars_width as i32, display_state, config);
+    }
+
     if let Some(hp) = display_state.hp.get(&player_id) {
         let health_color = Color::new(0x96, 0x54, 0x56, 255);
 
-        let bar_width = panel.cells.0 as f32 / config.player_health_max as f32;
+        let bar_width = bars_width / config.player_health_max as f32;
 
         let current_hp = if *hp > 0 {
             *hp
@@ -924,7 +1361,7 @@ fn render_pip(panel: &mut Panel, display_state: &DisplayState, config: &Config)
     if let Some(energy) = display_state.energy.get(&player_id) {
         let energy_color = Color::new(176, 132, 87, 255);
             
-        let bar_width = panel.cells.0 as f32 / config.player_energy_max as f32;
+        let bar_width = bars_width / config.player_energy_max as f32;
 
         for energy_index in 0..*energy {
             let x_offset = 0.3;
@@ -939,7 +1376,7 @@ fn render_pip(panel: &mut Panel, display_state: &DisplayState, config: &Config)
     if let Some(stamina) = display_state.stamina.get(&player_id) {
         let stamina_color = Color::new(130, 140, 102, 255);
             
-        let bar_width = panel.cells.0 as f32 / config.player_stamina_max as f32;
+        let bar_width = bars_width / config.player_stamina_max as f32;
 
         for stamina_index in 0..*stamina {
             let x_offset = 0.3;
@@ -952,8 +1389,32 @@ fn render_pip(panel: &mut Panel, display_state: &DisplayState, config: &Config)
     }
 }
 
+/// Show the turn count and the number of enemies left, and how many are in view.
+/// In practice mode the first line marks the run as practice instead, and the
+/// enemy counts share a line.
+fn render_hud(panel: &mut Panel, x_offset: i32, display_state: &DisplayState, config: &Config) {
+    let (alive, in_fov) = display_state.enemy_counts();
+
+    let mut list = Vec::new();
+    let mut first_line = 0;
+    if config.practice_mode {
+        panel.text_cmd("practice", config.color_light_orange, Pos::new(x_offset + 1, 0), 1.0);
+        first_line = 1;
+
+        list.push(format!("turn    {}", display_state.turn_count));
+        list.push(format!("enemies {}/{}", in_fov, alive));
+    } else {
+        list.push(format!("turn    {}", display_state.turn_count));
+        list.push(format!("enemies {}", alive));
+        list.push(format!("in view {}/{}", in_fov, alive));
+    }
+
+    let ui_color = Color::new(0xcd, 0xb4, 0x96, 255);
+    panel.text_list_cmd(&list, ui_color, Pos::new(x_offset + 1, first_line), 1.0);
+}
+
 /// Render the map, with environment and walls
-fn render_map(display_state: &mut DisplayState, panel: &mut Panel, sprites: &Vec<SpriteSheet>) {
+fn render_map(display_state: &mut DisplayState, panel: &mut Panel, config: &Config, sprites: &Vec<SpriteSheet>) {
     let (map_width, map_height) = display_state.map.size();
 
     let sprite_key = lookup_spritekey(sprites, "rustrogueliketiles");
@@ -982,10 +1443,61 @@ fn render_map(display_state: &mut DisplayState, panel: &mut Panel, sprites: &Vec
                 let sprite = Sprite::new(index as u32, sprite_key);
                 panel.sprite_cmd(sprite, Color::white(), pos);
             }
+
+            // walls and water have their own sprites, so only bare floor is varied
+            if config.tile_variation &&
+               tile.surface == Surface::Floor &&
+               (tile.tile_type == TileType::Empty || tile.tile_type == TileType::Exit) {
+                render_tile_variation(panel, pos, display_state.seed, config);
+            }
         }
     }
 }
 
+/// Lighten or darken a floor tile by a smooth noise value, so neighboring tiles
+/// have similar shades.
+fn render_tile_variation(panel: &mut Panel, pos: Pos, seed: u64, config: &Config) {
+    let noise = tile_noise(seed, pos, config.tile_noise_scaler as f32);
+
+    let mut color = if noise > 0.0 { Color::white() } else { Color::black() };
+    color.a = (config.tile_variation_alpha as f32 * noise.abs()).round() as u8;
+    if color.a > 0 {
+        panel.highlight_cmd(color, pos);
+    }
+}
+
+/// Value noise from -1.0 to 1.0, interpolated between random values on a grid
+/// with the given spacing in tiles. This only depends on the seed and position, so
+/// a tile keeps its value from frame to frame.
+fn tile_noise(seed: u64, pos: Pos, scale: f32) -> f32 {
+    let scale = scale.max(1.0);
+    let x = pos.x as f32 / scale;
+    let y = pos.y as f32 / scale;
+
+    let x0 = x.floor() as i32;
+    let y0 = y.floor() as i32;
+
+    // smoothstep the fractional part so the grid lines do not show
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let tx = smooth(x - x0 as f32);
+    let ty = smooth(y - y0 as f32);
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let top = lerp(lattice_value(seed, x0, y0), lattice_value(seed, x0 + 1, y0), tx);
+    let bottom = lerp(lattice_value(seed, x0, y0 + 1), lattice_value(seed, x0 + 1, y0 + 1), tx);
+    return lerp(top, bottom, ty);
+}
+
+/// A random value from -1.0 to 1.0 for a grid point, from a hash of the seed and point.
+fn lattice_value(seed: u64, x: i32, y: i32) -> f32 {
+    let mut hash = seed ^ ((x as u32 as u64) << 32 | (y as u32 as u64));
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash = hash ^ (hash >> 31);
+
+    return (hash >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0;
+}
+
 fn surface_index(display_state: &DisplayState, surface: Surface, block_sight: bool) -> Option<u8> {
     match surface {
         Surface::Rubble => {
@@ -1075,6 +1587,54 @@ fn render_intertile_walls(panel: &mut Panel,
     }
 }
 
+/// Mark each side of every tile that has a short wall, in a single pass over the map.
+fn render_short_wall_markers(panel: &mut Panel, display_state: &DisplayState, config: &Config) {
+    let (map_width, map_height) = display_state.map.size();
+
+    for y in 0..map_height {
+        for x in 0..map_width {
+            let pos = Pos::new(x, y);
+            let tile = display_state.map[pos];
+
+            if tile.left_wall == Wall::ShortWall {
+                render_short_wall_marker(panel, pos, Direction::Left, config);
+            }
+
+            if x + 1 < map_width && display_state.map[move_x(pos, 1)].left_wall == Wall::ShortWall {
+                render_short_wall_marker(panel, pos, Direction::Right, config);
+            }
+
+            if y > 0 && display_state.map[move_y(pos, -1)].bottom_wall == Wall::ShortWall {
+                render_short_wall_marker(panel, pos, Direction::Up, config);
+            }
+
+            if tile.bottom_wall == Wall::ShortWall {
+                render_short_wall_marker(panel, pos, Direction::Down, config);
+            }
+        }
+    }
+}
+
+/// Mark a short wall on the given side of a tile with a small square, centered along the
+/// wall and inset into the tile so it does not cover the wall sprite.
+fn render_short_wall_marker(panel: &mut Panel, pos: Pos, side: Direction, config: &Config) {
+    let size = SHORT_WALL_MARKER_SIZE;
+    let center = 0.5 - size / 2.0;
+    let far = 1.0 - SHORT_WALL_MARKER_INSET - size;
+
+    let (x_offset, y_offset) = match side {
+        Direction::Left => (SHORT_WALL_MARKER_INSET, center),
+        Direction::Right => (far, center),
+        Direction::Up => (center, SHORT_WALL_MARKER_INSET),
+        Direction::Down => (center, far),
+        _ => return,
+    };
+
+    let x = pos.x as f32 + x_offset;
+    let y = pos.y as f32 + y_offset;
+    panel.rect_float_cmd(x, y, (size, size), true, config.color_light_orange);
+}
+
 /// Render each effect currently playing in the game
 /// The strategy here is to copy the effects vector, update all items,
 /// and then remove finished effects from back to front. The
@@ -1090,36 +1650,40 @@ fn render_effects(panel: &mut Panel,
         // NOTE(perf) cloning the effect vector each iteration!
         let mut effect = display_state.effects[index].clone();
         match &mut effect {
-            Effect::Particles(rate, particles) => {
-                if particles.len() < config.max_particles && rng_trial(&mut display_state.rng, *rate) {
-                    let x = rng_range(&mut display_state.rng, 0.0, panel.cells.0 as f32 - 1.0);
-                    let y = rng_range(&mut display_state.rng, 0.0, panel.cells.1 as f32 - 1.0);
-                    particles.push(Particle::new(config.particle_duration, x, y));
+            Effect::Particles(rate, x_speed, y_speed, particle_color, particles) => {
+                // rates above 1 spawn several particles a frame, but never more than max_particles.
+                let mut spawn_rate = *rate;
+                while spawn_rate > 0.0 && particles.len() < config.max_particles {
+                    if rng_trial(&mut display_state.rng, spawn_rate.min(1.0)) {
+                        let x = rng_range(&mut display_state.rng, 0.0, panel.cells.0 as f32 - 1.0);
+                        let y = rng_range(&mut display_state.rng, 0.0, panel.cells.1 as f32 - 1.0);
+                        particles.push(Particle::new(config.particle_duration, x, y));
+                    }
+                    spawn_rate -= 1.0;
                 }
 
                 let sprite_key = lookup_spritekey(sprites, "Particle_Speck");
 
                 let mut index = 0;
                 while index < particles.len() {
-                    let cell = Pos::new(particles[index].x as i32, particles[index].y as i32);
                     particles[index].duration -= display_state.dt;
 
+                    // offset the particle according to how long it has been running.
+                    let time_running = config.particle_duration - particles[index].duration;
+                    let new_x = particles[index].x + *x_speed * time_running;
+                    let new_y = particles[index].y + *y_speed * time_running;
+                    let new_cell = Pos::new(new_x.ceil() as i32, new_y.ceil() as i32);
+
                     // if the particle is finished, or has left the map, remove it.
-                    if particles[index].duration < 0.0 || !display_state.map.is_within_bounds(cell) {
+                    if particles[index].duration < 0.0 || !display_state.map.is_within_bounds(new_cell) {
                         particles.swap_remove(index);
                     } else {
-                        // offset the particle according to how long it has been running.
-                        let x_offset = config.particle_speed * (config.particle_duration - particles[index].duration);
-                        let new_x = particles[index].x + x_offset;
-                        let new_cell = Pos::new(new_x.ceil() as i32, particles[index].y.ceil() as i32);
-
-                        if display_state.map.is_within_bounds(new_cell) && 
-                           display_state.pos_is_in_fov(new_cell) == FovResult::Inside {
-                            let mut color = Color::white();
+                        if display_state.pos_is_in_fov(new_cell) == FovResult::Inside {
+                            let mut color = *particle_color;
                             // fade the particle out according to how long it has been running.
-                            color.a = (255.0 * (particles[index].duration / config.particle_duration)) as u8;
+                            color.a = (color.a as f32 * (particles[index].duration / config.particle_duration)) as u8;
                             let sprite = Sprite::new(0, sprite_key);
-                            panel.sprite_float_cmd(sprite, color, new_x, particles[index].y);
+                            panel.sprite_float_cmd(sprite, color, new_x, new_y);
                         }
                         index += 1;
                     }
@@ -1148,7 +1712,7 @@ fn render_effects(panel: &mut Panel,
                 if *sound_dt >= config.sound_timeout {
                     effect_complete = true;
                 } else {
-                    *sound_dt += 1.0 / config.frame_rate as f32;
+                    *sound_dt += display_state.dt;
                     if *sound_dt > config.sound_timeout {
                         *sound_dt = config.sound_timeout;
                     }
@@ -1258,10 +1822,25 @@ fn render_effects(panel: &mut Panel,
     }
 }
 
+/// Draw an entity's sprite, scaling it across the entity's footprint if it
+/// covers more then one tile.
+fn render_entity_sprite(panel: &mut Panel,
+                        size: Option<(i32, i32)>,
+                        sprite: Sprite,
+                        color: Color,
+                        pos: Pos) {
+    if let Some((width, height)) = size {
+        panel.sprite_float_scaled_cmd(sprite, color, pos.x as f32, pos.y as f32, width as f32, height as f32);
+    } else {
+        panel.sprite_cmd(sprite, color, pos);
+    }
+}
+
 fn render_entity(panel: &mut Panel,
                  entity_id: EntityId,
                  display_state: &mut DisplayState,
                  color: Option<Color>,
+                 revealed: bool,
                  sprites: &Vec<SpriteSheet>) -> Option<Sprite> {
     let mut animation_result = AnimationResult::new();
 
@@ -1277,7 +1856,9 @@ fn render_entity(panel: &mut Panel,
     let color = color.unwrap_or(Color::new(255, 255, 255, 255));
 
     let is_in_fov =
-       display_state.entity_is_in_fov(entity_id) == FovResult::Inside;
+       revealed || display_state.entity_is_in_fov(entity_id) == FovResult::Inside;
+
+    let size = display_state.size.get(&entity_id).map(|size| *size);
 
     if is_in_fov {
         if let Some(anims) = display_state.animations.get_mut(&entity_id) {
@@ -1288,7 +1869,7 @@ fn render_entity(panel: &mut Panel,
                     display_state.play_effect(effect);
                 } else {
                     if let Some(sprite) = animation_result.sprite {
-                        panel.sprite_cmd(sprite, color, animation_result.pos);
+                        render_entity_sprite(panel, size, sprite, color, animation_result.pos);
                     }
 
                     // for animations other then effects, keep playing by pushing to front of
@@ -1303,12 +1884,13 @@ fn render_entity(panel: &mut Panel,
             let index = display_state.tile_index[&entity_id];
             let sprite = Sprite::new(index as u32, tiles);
 
-            panel.sprite_cmd(sprite, color, pos);
+            render_entity_sprite(panel, size, sprite, color, pos);
             animation_result.sprite = Some(sprite);
         }
     } else {
         // if not in FoV, see if we need to add an impression for a golem
-        if display_state.typ[&entity_id] == EntityType::Enemy {
+        if display_state.typ[&entity_id] == EntityType::Enemy &&
+           !display_state.disguised.contains(&entity_id) {
             if display_state.entity_is_in_fov(entity_id) == FovResult::Edge {
                 if display_state.impressions.iter().all(|impresssion| impresssion.pos != pos) {
                     let tiles = lookup_spritekey(sprites, "rustrogueliketiles");
@@ -1335,6 +1917,25 @@ fn render_impressions(panel: &mut Panel, display_state: &mut DisplayState, confi
     }
 }
 
+/// Draw a faded sprite at the position each enemy was last seen, if it is not visible now.
+/// These are only drawn, and are not entities that can be targeted.
+fn render_enemy_memory(panel: &mut Panel, display_state: &DisplayState, config: &Config) {
+    let memory_color = Color::new(255, 255, 255, config.enemy_memory_alpha);
+
+    for (entity_id, (pos, sprite)) in display_state.enemy_memory.iter() {
+        if display_state.entity_is_in_fov(*entity_id) == FovResult::Inside {
+            continue;
+        }
+
+        // impressions are drawn in place of a memory at the same position
+        if display_state.impressions.iter().any(|impression| impression.pos == *pos) {
+            continue;
+        }
+
+        panel.sprite_cmd(*sprite, memory_color, *pos);
+    }
+}
+
 fn render_entity_type(panel: &mut Panel, typ: EntityType, display_state: &mut DisplayState, config: &Config, sprites: &Vec<SpriteSheet>) {
     if typ == EntityType::Player && display_state.state == GameState::Use && display_state.use_dir.is_some() {
         // For the player in use-mode, while holding down a direction, we
@@ -1354,7 +1955,7 @@ fn render_entity_type(panel: &mut Panel, typ: EntityType, display_state: &mut Di
         if let Some(pos) = use_pos {
             render_entity_ghost(panel, player_id, player_pos, config, display_state, sprites);
             display_state.pos[&player_id] = pos;
-            render_entity(panel, player_id, display_state, None, sprites);
+            render_entity(panel, player_id, display_state, None, false, sprites);
             display_state.pos[&player_id] = player_pos;
         }
     } else {
@@ -1364,7 +1965,17 @@ fn render_entity_type(panel: &mut Panel, typ: EntityType, display_state: &mut Di
             index += 1;
 
             if display_state.typ[&entity_id] == typ {
-                let maybe_sprite = render_entity(panel, entity_id, display_state, None, sprites);
+                // tiles next to the player may be revealed, but enemies on them are not
+                let revealed = typ != EntityType::Enemy &&
+                               revealed_adjacent(display_state, config, display_state.pos[&entity_id]);
+
+                // a thief shares the gol's sprites, so it is tinted to tell the two apart
+                let color = if display_state.name.get(&entity_id) == Some(&EntityName::Thief) {
+                    Some(config.color_mint_green)
+                } else {
+                    None
+                };
+                let maybe_sprite = render_entity(panel, entity_id, display_state, color, revealed, sprites);
 
                 if let Some(sprite) = maybe_sprite {
                     display_state.drawn_sprites.insert(entity_id, sprite);
@@ -1391,6 +2002,10 @@ fn render_overlay_use(panel: &mut Panel,
 
     let sprite_key = lookup_spritekey(sprites, "rustrogueliketiles");
 
+    if let Some(free_camera_pos) = display_state.free_camera_pos {
+        panel.outline_cmd(config.color_mint_green, free_camera_pos);
+    }
+
     let arrow_horiz = display_state.tileset_index(&"arrow_horiz").unwrap();
     let arrow_diag = display_state.tileset_index(&"arrow_diag").unwrap();
     if let Some(use_dir) = display_state.use_dir {
@@ -1436,6 +2051,67 @@ fn render_sound_overlay(panel: &mut Panel,
     }
 }
 
+fn render_sound_debug_overlay(panel: &mut Panel,
+                              display_state: &mut DisplayState,
+                              config: &Config) {
+    let source_colors = [config.color_light_red,
+                         config.color_light_green,
+                         config.color_ice_blue,
+                         config.color_light_orange,
+                         config.color_pink,
+                         config.color_mint_green];
+
+    // sort sources so each keeps the same color between frames
+    let mut sources = display_state.sound_events.keys().map(|id| *id).collect::<Vec<EntityId>>();
+    sources.sort();
+
+    for source_id in sources {
+        let mut color = source_colors[source_id as usize % source_colors.len()];
+        color.a = config.sound_alpha;
+
+        for pos in display_state.sound_events[&source_id].clone().iter() {
+            panel.highlight_cmd(color, *pos);
+        }
+
+        // outline the source's own position, if it is still known
+        if let Some(source_pos) = display_state.pos.get(&source_id) {
+            color.a = 255;
+            panel.outline_cmd(color, *source_pos);
+        }
+    }
+}
+
+fn render_skill_preview(panel: &mut Panel,
+                        display_state: &mut DisplayState,
+                        config: &Config,
+                        tiles_key: SpriteKey) {
+    // grass walls are drawn as transparent versions of the walls they would create
+    let mut wall_color = Color::white();
+    wall_color.a = config.grid_alpha_overlay;
+    for (pos, dir) in display_state.grass_wall_preview.clone().iter() {
+        let sprite_name = match dir {
+            Direction::Left => "left_intertile_grass_wall",
+            Direction::Right => "right_intertile_grass_wall",
+            Direction::Up => "up_intertile_grass_wall",
+            _ => "down_intertile_grass_wall",
+        };
+        let index = display_state.tileset_index(&sprite_name).unwrap();
+        panel.sprite_cmd(Sprite::new(index as u32, tiles_key), wall_color, *pos);
+    }
+
+    if let Some(pass_pos) = display_state.pass_wall_preview {
+        let mut pass_color = config.color_light_green;
+        pass_color.a = 255;
+        panel.outline_cmd(pass_color, pass_pos);
+    }
+
+    let mut blink_color = config.color_ice_blue;
+    blink_color.a = config.highlight_player_move;
+    for pos in display_state.blink_preview.clone().iter() {
+        panel.highlight_cmd(blink_color, *pos);
+    }
+}
+
 fn render_game_overlays(panel: &mut Panel,
                         display_state: &mut DisplayState,
                         config: &Config,
@@ -1445,6 +2121,8 @@ fn render_game_overlays(panel: &mut Panel,
 
     let tiles_key = lookup_spritekey(sprites, "rustrogueliketiles");
 
+    render_skill_preview(panel, display_state, config, tiles_key);
+
     if let Some(cursor_pos) = display_state.cursor_pos {
         // render trigger plate wall highlight if selected
         for entity in display_state.entities_at_cursor.iter() {
@@ -1664,7 +2342,8 @@ fn render_overlay_attack(panel: &mut Panel,
 
         if display_state.pos_is_in_fov(pos) == FovResult::Inside &&
            entity_id != player_id &&
-           display_state.typ[&entity_id] == EntityType::Enemy {
+           display_state.typ[&entity_id] == EntityType::Enemy &&
+           !display_state.disguised.contains(&entity_id) {
            render_attack_overlay(panel, config, display_state, entity_id);
            render_fov_overlay(panel, display_state, config, entity_id);
            render_movement_overlay(panel, config, display_state, entity_id, sprites);
@@ -1716,6 +2395,26 @@ fn render_overlays(panel: &mut Panel,
         render_overlay_fov(panel, display_state, config, tiles_key);
     }
 
+    // outline items the player has seen
+    if display_state.item_highlight {
+        render_overlay_items(panel, display_state, config);
+    }
+
+    // point towards the key, or the exit once the key is carried
+    if config.objective_exit {
+        render_overlay_objective(panel, display_state, config, tiles_key);
+    }
+
+    // outline the player while hasted
+    if display_state.hasted > 0 {
+        render_overlay_hasted(panel, display_state, config);
+    }
+
+    // render ghosts where alert enemies plan to move
+    if config.show_enemy_intent {
+        render_overlay_intent(panel, display_state, config, sprites);
+    }
+
     // draw attack and fov position highlights
     if let Some(_cursor_pos) = display_state.cursor_pos {
         render_overlay_attack(panel, display_state, config, sprites);
@@ -1746,6 +2445,10 @@ fn render_overlays(panel: &mut Panel,
     if config.overlay_floodfill {
         render_overlay_floodfill(panel, display_state, config, tiles_key);
     }
+
+    if display_state.sound_debug {
+        render_sound_debug_overlay(panel, display_state, config);
+    }
 }
 
 fn render_overlay_movement(panel: &mut Panel,
@@ -1766,6 +2469,72 @@ fn render_overlay_movement(panel: &mut Panel,
     }
 }
 
+fn render_overlay_intent(panel: &mut Panel,
+                         display_state: &mut DisplayState,
+                         config: &Config,
+                         sprites: &Vec<SpriteSheet>) {
+    let intents: Vec<(EntityId, Pos)> = display_state.entity_intents.iter().map(|(id, pos)| (*id, *pos)).collect();
+    for (entity_id, intent_pos) in intents {
+        if display_state.pos.get(&entity_id).is_none() {
+            continue;
+        }
+
+        render_entity_ghost(panel, entity_id, intent_pos, config, display_state, sprites);
+    }
+}
+
+/// Draw an arrow next to the player pointing towards the current objective while it is out
+/// of sight. This points at the last place the objective was seen, or at its actual position
+/// if it has not been seen yet, such as at the start of a level.
+fn render_overlay_objective(panel: &mut Panel, display_state: &mut DisplayState, config: &Config, sprite_key: SpriteKey) {
+    let player_id = display_state.player_id();
+    let player_pos = display_state.pos[&player_id];
+
+    let has_key = display_state.inventory.iter().any(|(item, _class)| *item == Item::Key);
+    let objective_name = if has_key { EntityName::Exit } else { EntityName::Key };
+
+    // a key in an inventory is not on the map
+    let objective_id = display_state.ids.iter().find(|entity_id| {
+        display_state.name.get(entity_id) == Some(&objective_name) &&
+        display_state.map.is_within_bounds(display_state.pos[entity_id])
+    });
+
+    let objective_id = match objective_id {
+        Some(objective_id) => *objective_id,
+        None => return,
+    };
+
+    let objective_pos = display_state.pos[&objective_id];
+    if display_state.entity_is_in_fov(objective_id) == FovResult::Inside {
+        display_state.objective_memory.insert(objective_id, objective_pos);
+        return;
+    }
+
+    let target_pos = *display_state.objective_memory.get(&objective_id).unwrap_or(&objective_pos);
+    let dxy = sub_pos(target_pos, player_pos);
+    if let Some(dir) = Direction::from_dxy(dxy.x.signum(), dxy.y.signum()) {
+        let arrow_horiz = display_state.tileset_index(&"arrow_horiz").unwrap();
+        let arrow_diag = display_state.tileset_index(&"arrow_diag").unwrap();
+        let arrow_pos = dir.offset_pos(player_pos, 1);
+        render_arrow(panel, sprite_key, dir, arrow_pos, config.color_light_orange, arrow_horiz, arrow_diag);
+    }
+}
+
+fn render_overlay_hasted(panel: &mut Panel, display_state: &mut DisplayState, config: &Config) {
+    let player_id = display_state.player_id();
+    let player_pos = display_state.pos[&player_id];
+
+    panel.outline_cmd(config.color_light_orange, player_pos);
+}
+
+/// Outline every item the player can see, as well as items seen earlier in explored areas
+/// at the position they were last seen. Items that were never seen are not revealed.
+fn render_overlay_items(panel: &mut Panel, display_state: &DisplayState, config: &Config) {
+    for seen_pos in display_state.seen_items.values() {
+        panel.outline_cmd(config.color_mint_green, *seen_pos);
+    }
+}
+
 fn render_overlay_alertness(panel: &mut Panel,
                             display_state: &mut DisplayState,
                             sprite_key: SpriteKey,
@@ -1834,6 +2603,10 @@ fn render_overlay_alertness(panel: &mut Panel,
                                                 pos);
                     }
 
+                    Behavior::Fleeing(_) => {
+                        // a fleeing thief is not looking for anyone
+                    }
+
                     Behavior::Armed(_) => {
                         // TODO maybe need another symbol here.
                         let index = display_state.tileset_index(&"stunned_mark").unwrap();
@@ -1926,7 +2699,7 @@ fn render_entity_ghost(panel: &mut Panel,
     let dt = display_state.dt;
     display_state.dt = 0.0;
     let ghost_color = Color::new(255, 255, 255, config.ghost_alpha);
-    render_entity(panel, entity_id, display_state, Some(ghost_color), sprites);
+    render_entity(panel, entity_id, display_state, Some(ghost_color), false, sprites);
     display_state.dt = dt;
 
     display_state.pos[&entity_id] = entity_pos;
@@ -32,7 +32,7 @@ impl Particle {
 pub enum Effect {
     Sound(Aoe, f32), // area of effect, time since start
     Beam(usize, Pos, Pos), // start, end
-    Particles(f32, Vec<Particle>), // spawn rate, current particles
+    Particles(f32, f32, f32, Color, Vec<Particle>), // spawn rate, x speed, y speed, color, current particles
     Attack(Pos, Pos, SpriteAnim),
     Fade(Sprite, Color, u8, u8, Pos, f32, f32), // sprite, color, start alpha, end alpha, position, seconds, time taken
     NumberChange(i32, Pos, Color, usize), // Change amount, tile position, color, frame count
@@ -40,8 +40,8 @@ pub enum Effect {
 }
 
 impl Effect {
-    pub fn particles(rate: f32) -> Effect {
-        return Effect::Particles(rate, Vec::new());
+    pub fn particles(rate: f32, x_speed: f32, y_speed: f32, color: Color) -> Effect {
+        return Effect::Particles(rate, x_speed, y_speed, color, Vec::new());
     }
 
     pub fn beam(duration: usize, start: Pos, end: Pos) -> Effect {
@@ -111,6 +111,7 @@ pub struct SpriteAnim {
     pub flip_horiz: bool,
     pub flip_vert: bool,
     pub rotation: f64,
+    pub interruptible: bool,
 }
 
 impl SpriteAnim {
@@ -133,6 +134,7 @@ impl SpriteAnim {
                         flip_horiz,
                         flip_vert,
                         rotation,
+                        interruptible: true,
         };
     }
 
@@ -194,10 +196,30 @@ impl Animation {
         }
     }
 
-    pub fn step(&mut self, dt: f32, rng: &mut Rand32, frame_rate: f32) {
+    /// Whether this animation may be cut short by new input. Looping animations
+    /// never end on their own, so they are not considered in progress.
+    pub fn interruptible(&self) -> bool {
+        match self {
+            Animation::Loop(_) => return true,
+            Animation::RandomLoop(_) => return true,
+            Animation::Between(sprite_anim, _, _, _, _) => return sprite_anim.interruptible,
+            Animation::Once(sprite_anim) => return sprite_anim.interruptible,
+            Animation::PlayEffect(_) => return false,
+        }
+    }
+
+    pub fn in_progress(&self) -> bool {
+        match self {
+            Animation::Loop(_) => return false,
+            Animation::RandomLoop(_) => return false,
+            _ => return true,
+        }
+    }
+
+    pub fn step(&mut self, dt: f32, rng: &mut Rand32) {
         match self {
             Animation::Between(_sprite_anim, _start, _end, ref mut dist, blocks_per_sec) => {
-               *dist = *dist + (*blocks_per_sec / frame_rate); 
+               *dist = *dist + (*blocks_per_sec * dt); 
             }
 
             Animation::Loop(ref mut sprite_anim) => {
@@ -1,5 +1,6 @@
 use std::str::FromStr;
 use std::io::BufRead;
+use std::collections::HashMap;
 
 use sdl2::render::{Texture, TextureCreator, RenderTarget, WindowCanvas, BlendMode, Canvas};
 use sdl2::rect::Rect;
@@ -11,7 +12,7 @@ use parse_display::{Display, FromStr};
 
 use roguelike_utils::math::*;
 
-use roguelike_map::{PlayerDirection};
+use roguelike_map::{PlayerDirection, add_pos};
 
 use crate::animation::{Sprite, SpriteKey};
 use crate::panel::{Panel};
@@ -36,6 +37,8 @@ pub enum Justify {
 pub enum DrawCmd {
     #[display("sprite {0} {1} {2}")]
     Sprite(Sprite, Color, Pos),
+    #[display("sprite_batch {0} {1} {2} {3} {4}")]
+    SpriteBatch(Sprite, Color, Pos, u32, u32), // sprite, color, start cell, num cells width/height
     #[display("sprite_scaled {0} {1} {2} {3} {4}")]
     SpriteScaled(Sprite, f32, PlayerDirection, Color, Pos),
     #[display("sprite_float {0} {1} {2} {3} {4} {5}")]
@@ -68,6 +71,7 @@ impl DrawCmd {
     pub fn pos(&self) -> Pos {
         match self {
             DrawCmd::Sprite(_, _, pos) => *pos,
+            DrawCmd::SpriteBatch(_, _, pos, _, _) => *pos,
             DrawCmd::SpriteScaled(_, _, _, _, pos) => *pos,
             DrawCmd::SpriteFloat(_, _, x, y, _, _) => Pos::new(*x as i32, *y as i32),
             DrawCmd::HighlightTile(_, pos) => *pos,
@@ -80,6 +84,25 @@ impl DrawCmd {
             DrawCmd::Fill(pos, _) => *pos,
         }
     }
+
+    /// Move a command by the given number of cells.
+    pub fn shift(&mut self, offset: Pos) {
+        let (x_offset, y_offset) = (offset.x as f32, offset.y as f32);
+        match self {
+            DrawCmd::Sprite(_, _, pos) => *pos = add_pos(*pos, offset),
+            DrawCmd::SpriteBatch(_, _, pos, _, _) => *pos = add_pos(*pos, offset),
+            DrawCmd::SpriteScaled(_, _, _, _, pos) => *pos = add_pos(*pos, offset),
+            DrawCmd::SpriteFloat(_, _, x, y, _, _) => { *x += x_offset; *y += y_offset; }
+            DrawCmd::HighlightTile(_, pos) => *pos = add_pos(*pos, offset),
+            DrawCmd::OutlineTile(_, pos) => *pos = add_pos(*pos, offset),
+            DrawCmd::Text(_, _, pos, _) => *pos = add_pos(*pos, offset),
+            DrawCmd::TextFloat(_, _, x, y, _) => { *x += x_offset; *y += y_offset; }
+            DrawCmd::TextJustify(_, _, _, _, pos, _, _) => *pos = add_pos(*pos, offset),
+            DrawCmd::Rect(pos, _, _, _, _, _) => *pos = add_pos(*pos, offset),
+            DrawCmd::RectFloat(x, y, _, _, _, _) => { *x += x_offset; *y += y_offset; }
+            DrawCmd::Fill(pos, _) => *pos = add_pos(*pos, offset),
+        }
+    }
 }
 
 pub fn process_draw_cmd(panel: &Panel,
@@ -116,6 +139,10 @@ pub fn process_draw_cmd(panel: &Panel,
                            sprite.flip_vert).unwrap();
         }
 
+        DrawCmd::SpriteBatch(sprite, color, pos, width, height) => {
+            draw_sprite_batch(panel, canvas, sprite_texture, sprites, *sprite, *color, *pos, (*width, *height), &HashMap::new());
+        }
+
         DrawCmd::SpriteScaled(sprite, scale, direction, color, pos) => {
             let cell_dims = panel.cell_dims();
             let sprite_sheet = &mut sprites[sprite.key];
@@ -457,6 +484,51 @@ pub fn process_draw_cmd(panel: &Panel,
     }
 }
 
+/// Draw the same sprite into every cell of a rectangle of cells, setting up the texture
+/// once for the whole batch. Each cell is copied exactly as a single Sprite command would be,
+/// and cells that are going to be filled are skipped, so the result is pixel-identical to
+/// drawing each cell separately.
+pub fn draw_sprite_batch(panel: &Panel,
+                         canvas: &mut WindowCanvas,
+                         sprite_texture: &mut Texture,
+                         sprites: &mut Vec<SpriteSheet>,
+                         sprite: Sprite,
+                         color: Color,
+                         start: Pos,
+                         dims: (u32, u32),
+                         fill_map: &HashMap<Pos, u32>) {
+    let sprite_sheet = &mut sprites[sprite.key];
+    let (cell_width, cell_height) = panel.cell_dims();
+
+    canvas.set_blend_mode(BlendMode::Blend);
+
+    let src_rect = sprite_sheet.sprite_src(sprite.index);
+    sprite_texture.set_color_mod(color.r, color.g, color.b);
+    sprite_texture.set_alpha_mod(color.a);
+
+    for y in 0..dims.1 as i32 {
+        for x in 0..dims.0 as i32 {
+            let cell = Pos::new(start.x + x, start.y + y);
+            if fill_map.get(&cell).map_or(false, |count| *count > 0) {
+                continue;
+            }
+
+            let dst_rect = Rect::new(cell.x * cell_width as i32,
+                                     cell.y * cell_height as i32,
+                                     cell_width as u32,
+                                     cell_height as u32);
+
+            canvas.copy_ex(sprite_texture,
+                           src_rect,
+                           Some(dst_rect),
+                           sprite.rotation,
+                           None,
+                           sprite.flip_horiz,
+                           sprite.flip_vert).unwrap();
+        }
+    }
+}
+
 fn draw_outline_tile<T>(panel: &Panel,
                         canvas: &mut Canvas<T>,
                         cell: Pos,
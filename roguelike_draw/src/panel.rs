@@ -10,7 +10,7 @@ use roguelike_map::{PlayerDirection};
 
 use crate::animation::{Sprite};
 use crate::area::{Area};
-use crate::drawcmd::{process_draw_cmd, DrawCmd, Justify};
+use crate::drawcmd::{process_draw_cmd, draw_sprite_batch, DrawCmd, Justify};
 use crate::spritesheet::{SpriteSheet};
 
 
@@ -124,6 +124,12 @@ impl Panel {
         self.draw_cmd(cmd);
     }
 
+    /// Draw the same sprite in every cell of a rectangle with a single command.
+    pub fn sprite_batch_cmd(&mut self, sprite: Sprite, color: Color, pos: Pos, dims: (u32, u32)) {
+        let cmd = DrawCmd::SpriteBatch(sprite, color, pos, dims.0, dims.1);
+        self.draw_cmd(cmd);
+    }
+
     pub fn sprite_scaled_cmd(&mut self, sprite: Sprite, scale: f32, direction: PlayerDirection, color: Color, pos: Pos) {
         let cmd = DrawCmd::SpriteScaled(sprite, scale, direction, color, pos);
         self.draw_cmd(cmd);
@@ -192,6 +198,13 @@ impl Panel {
         self.draw_cmds.push(cmd);
     }
 
+    /// Move all commands drawn so far by the given number of cells.
+    pub fn shift_cmds(&mut self, offset: Pos) {
+        for cmd in self.draw_cmds.iter_mut() {
+            cmd.shift(offset);
+        }
+    }
+
     pub fn process_cmds_if_new(&mut self,
                                clear: bool,
                                texture: &mut Texture,
@@ -246,6 +259,12 @@ impl Panel {
             }
 
             for cmd in self.draw_cmds.iter() {
+                // Batches check each of their cells against the fills themselves.
+                if let DrawCmd::SpriteBatch(sprite, color, pos, width, height) = cmd {
+                    draw_sprite_batch(self, canvas, sprite_texture, sprites, *sprite, *color, *pos, (*width, *height), &fill_map);
+                    continue;
+                }
+
                 // Check if there is going to be a fill in this position,
                 // in which case there is no need to draw an aligned command.
                 if cmd.aligned() {
@@ -12,6 +12,7 @@ use roguelike_core::messaging::{Msg, InfoMsg, MsgLog};
 use roguelike_core::constants::*;
 use roguelike_core::config::Config;
 use roguelike_core::level::*;
+use roguelike_core::utils::pass_wall_pos;
 
 use crate::game::*;
 use crate::input::*;
@@ -42,12 +43,16 @@ pub enum InputAction {
     SkillFacing(ActionMode, usize),
     #[display("startuseitem {0}")]
     StartUseItem(ItemClass),
+    #[display("cycleslot {0}")]
+    CycleSlot(ItemClass),
     #[display("startuseskill {0} {1}")]
     StartUseSkill(usize, ActionMode),
     #[display("startusetalent {0}")]
     StartUseTalent(usize),
     #[display("usedir {0}")]
     UseDir(Direction),
+    NextTarget,
+    PrevTarget,
     FinalizeUse,
     AbortUse,
     Pass,
@@ -59,7 +64,9 @@ pub enum InputAction {
     #[display("cursormove {0} {1} {2}")]
     CursorMove(Direction, bool, bool), // move direction, is relative, is long
     CursorReturn,
+    CursorUndo,
     CursorToggle,
+    FreeCamera,
     #[display("mousepos {0}")]
     MousePos(Pos),
     #[display("mousebutton {0} {1}")]
@@ -68,6 +75,7 @@ pub enum InputAction {
     SkillMenu,
     ClassMenu,
     HelpMenu,
+    BestiaryMenu,
     Exit,
     Esc,
     ForceExit,
@@ -75,10 +83,16 @@ pub enum InputAction {
     RegenerateMap,
     TestMode,
     OverlayToggle,
+    ToggleItemHighlight,
     #[display("selectentry {0}")]
     SelectEntry(usize),
     DebugToggle,
+    SlowMotionToggle,
     Restart,
+    #[display("returntomenu {0}")]
+    ReturnToMenu(bool), // save the game first
+    UndoTurn,
+    ExportReplay,
     None,
 }
 
@@ -126,7 +140,11 @@ pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> boo
         }
 
         InputAction::Exit => {
-            if game.settings.state != GameState::ConfirmQuit {
+            // the main menu and end screens quit directly, as there is no game in progress to lose
+            let game_over = game.settings.state == GameState::TimeUp ||
+                            game.settings.state == GameState::Lose ||
+                            game.settings.state == GameState::Win;
+            if game.settings.state != GameState::ConfirmQuit && game.settings.state != GameState::MainMenu && !game_over {
                 change_state(&mut game.settings, GameState::ConfirmQuit, &mut game.msg_log);
                 return true;
             } else {
@@ -140,13 +158,49 @@ pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> boo
             return true;
         }
 
+        // slow motion only changes how fast the display plays animations, not the game itself
+        InputAction::SlowMotionToggle => {
+            game.settings.slow_motion = !game.settings.slow_motion;
+            let time_scale = if game.settings.slow_motion { game.config.slow_motion_scale } else { 1.0 };
+            game.msg_log.log(Msg::TimeScale(time_scale));
+            return true;
+        }
+
         _ => {
             return false;
         }
     }
 }
 
-pub fn handle_input_inventory(input: InputAction, settings: &mut Settings, msg_log: &mut MsgLog) {
+pub fn handle_input_inventory(input: InputAction,
+                              level: &Level,
+                              settings: &mut Settings,
+                              msg_log: &mut MsgLog) {
+    // when the inventory was opened by picking up an item that did not fit, a slot
+    // is chosen to drop, or the item is left where it is.
+    if let Some(item_id) = settings.pending_pickup {
+        match input {
+            InputAction::SelectEntry(slot_index) => {
+                let player_id = level.find_by_name(EntityName::Player).unwrap();
+                let slot_count = level.entities.inventory_slots(player_id).len();
+                if slot_index < slot_count {
+                    msg_log.log(Msg::DropToPickUp(player_id, slot_index, item_id));
+                    settings.pending_pickup = None;
+                    change_state(settings, GameState::Playing, msg_log);
+                }
+            }
+
+            InputAction::Inventory | InputAction::Esc => {
+                settings.pending_pickup = None;
+                change_state(settings, GameState::Playing, msg_log);
+            }
+
+            _ => {
+            }
+        }
+        return;
+    }
+
     match input {
         InputAction::Inventory => {
             change_state(settings, GameState::Playing, msg_log);
@@ -277,6 +331,39 @@ pub fn handle_input_help_menu(input: InputAction,
     }
 }
 
+pub fn handle_input_bestiary_menu(input: InputAction,
+                                  settings: &mut Settings,
+                                  msg_log: &mut MsgLog) {
+    match input {
+        InputAction::Inventory => {
+            change_state(settings, GameState::Inventory, msg_log);
+        }
+
+        InputAction::BestiaryMenu => {
+            change_state(settings, GameState::Playing, msg_log);
+        }
+
+        InputAction::HelpMenu => {
+            change_state(settings, GameState::HelpMenu, msg_log);
+        }
+
+        InputAction::SkillMenu => {
+            change_state(settings, GameState::SkillMenu, msg_log);
+        }
+
+        InputAction::ClassMenu => {
+            change_state(settings, GameState::ClassMenu, msg_log);
+        }
+
+        InputAction::Esc => {
+            change_state(settings, GameState::Playing, msg_log);
+        }
+
+        _ => {
+        }
+    }
+}
+
 pub fn handle_input_confirm_quit(input: InputAction, settings: &mut Settings, msg_log: &mut MsgLog) {
     match input {
         InputAction::Esc => {
@@ -293,6 +380,83 @@ pub fn handle_input_confirm_quit(input: InputAction, settings: &mut Settings, ms
             change_state(settings, GameState::Playing, msg_log);
         }
 
+        InputAction::ReturnToMenu(save) => {
+            msg_log.log(Msg::ReturnToMenu(save));
+
+            change_state(settings, GameState::MainMenu, msg_log);
+        }
+
+        _ => {
+        }
+    }
+}
+
+pub fn handle_input_main_menu(input: InputAction, settings: &mut Settings, msg_log: &mut MsgLog) {
+    match input {
+        // new game. A run that has not been played yet, such as the first run made from the
+        // configured seed, is kept as it is, and otherwise a new run is started with a new seed.
+        InputAction::SelectEntry(0) => {
+            if settings.turn_count > 0 || settings.level_num > 0 {
+                msg_log.log(Msg::Restart);
+            }
+
+            change_state(settings, GameState::Playing, msg_log);
+        }
+
+        // continue, which loads the save in place of the current game
+        InputAction::SelectEntry(1) => {
+            if settings.save_exists {
+                msg_log.log(Msg::ContinueGame);
+            }
+        }
+
+        InputAction::SelectEntry(2) | InputAction::Exit => {
+            change_state(settings, GameState::Exit, msg_log);
+        }
+
+        _ => {
+        }
+    }
+}
+
+/// The time up and death screens both end the run, so they share their inputs.
+pub fn handle_input_game_over(input: InputAction, settings: &mut Settings, msg_log: &mut MsgLog) {
+    match input {
+        InputAction::Restart => {
+            msg_log.log(Msg::Restart);
+
+            change_state(settings, GameState::Playing, msg_log);
+        }
+
+        InputAction::Exit => {
+            change_state(settings, GameState::Exit, msg_log);
+        }
+
+        _ => {
+        }
+    }
+}
+
+/// The win screen shows the run's totals before starting a new run or returning to the menu.
+pub fn handle_input_win(input: InputAction, settings: &mut Settings, msg_log: &mut MsgLog) {
+    match input {
+        InputAction::Restart => {
+            msg_log.log(Msg::Restart);
+
+            change_state(settings, GameState::Playing, msg_log);
+        }
+
+        InputAction::ReturnToMenu(_save) => {
+            // the run is over, so there is nothing to save
+            msg_log.log(Msg::ReturnToMenu(false));
+
+            change_state(settings, GameState::MainMenu, msg_log);
+        }
+
+        InputAction::Exit => {
+            change_state(settings, GameState::Exit, msg_log);
+        }
+
         _ => {
         }
     }
@@ -312,11 +476,16 @@ pub fn handle_input(input_action: InputAction,
             handle_input_use(input_action, level, settings, msg_log, config);
         }
 
-        GameState::Win | GameState::Lose => {
+        GameState::FreeCamera => {
+            handle_input_free_camera(input_action, level, settings, msg_log);
+        }
+
+        GameState::Win => {
+            handle_input_win(input_action, settings, msg_log);
         }
 
         GameState::Inventory => {
-            handle_input_inventory(input_action, settings, msg_log);
+            handle_input_inventory(input_action, level, settings, msg_log);
         }
 
         GameState::SkillMenu => {
@@ -331,10 +500,22 @@ pub fn handle_input(input_action: InputAction,
             handle_input_help_menu(input_action, settings, msg_log);
         }
 
+        GameState::BestiaryMenu => {
+            handle_input_bestiary_menu(input_action, settings, msg_log);
+        }
+
         GameState::ConfirmQuit => {
             handle_input_confirm_quit(input_action, settings, msg_log);
         }
 
+        GameState::MainMenu => {
+            handle_input_main_menu(input_action, settings, msg_log);
+        }
+
+        GameState::TimeUp | GameState::Lose => {
+            handle_input_game_over(input_action, settings, msg_log);
+        }
+
         GameState::Exit => {
         }
     }
@@ -344,7 +525,7 @@ pub fn handle_input_use(input_action: InputAction,
                         level: &Level,
                         settings: &mut Settings,
                         msg_log: &mut MsgLog,
-                        _config: &Config) {
+                        config: &Config) {
     let player_id = level.find_by_name(EntityName::Player).unwrap();
 
     let player_alive = level.entities.status[&player_id].alive;
@@ -386,7 +567,15 @@ pub fn handle_input_use(input_action: InputAction,
         (InputAction::DropItem, true) => {
             if let UseAction::Item(item_class) = settings.use_action {
                 if let Some(item_index) = level.find_item(item_class) {
-                    msg_log.log(Msg::DropItem(player_id, item_index as u64));
+                    let item_id = level.entities.inventory[&player_id][item_index];
+                    let item = level.entities.item[&item_id];
+
+                    if can_use_on_self(item, settings, config) {
+                        let player_pos = level.entities.pos[&player_id];
+                        msg_log.log(Msg::ItemThrow(player_id, item_id, player_pos, player_pos, false));
+                    } else {
+                        msg_log.log(Msg::DropItem(player_id, item_index as u64));
+                    }
 
                     settings.use_dir = None;
                     msg_log.log_info(InfoMsg::UseDirClear);
@@ -412,6 +601,14 @@ pub fn handle_input_use(input_action: InputAction,
             use_dir(dir, level, settings, msg_log);
         }
 
+        (InputAction::NextTarget, true) => {
+            cycle_use_target(true, level, settings, msg_log);
+        }
+
+        (InputAction::PrevTarget, true) => {
+            cycle_use_target(false, level, settings, msg_log);
+        }
+
         (InputAction::FinalizeUse, true) => {
             change_state(settings, GameState::Playing, msg_log);
 
@@ -506,6 +703,7 @@ pub fn handle_input_playing(input_action: InputAction,
 
         (InputAction::CursorReturn, _) => {
             if settings.cursor.is_some() {
+                settings.record_cursor_pos();
                 settings.cursor = Some(player_pos);
                 msg_log.log(Msg::CursorMove(player_pos));
             }
@@ -530,16 +728,29 @@ pub fn handle_input_playing(input_action: InputAction,
                 }
 
                 let new_pos = level.map.clamp(new_pos);
+                settings.record_cursor_pos();
                 settings.cursor = Some(new_pos);
                 msg_log.log(Msg::CursorMove(new_pos));
             }
         }
 
+        // Step the cursor back to where it was before its last move. This only moves the
+        // cursor, so the action chosen for cursor mode is kept.
+        (InputAction::CursorUndo, _) => {
+            if settings.cursor.is_some() {
+                if let Some(prev_pos) = settings.cursor_history.pop() {
+                    settings.cursor = Some(prev_pos);
+                    msg_log.log(Msg::CursorMove(prev_pos));
+                }
+            }
+        }
+
         (InputAction::CursorToggle, true) => {
             let cursor_pos;
             if let Some(current_pos) = settings.cursor {
                 cursor_pos = current_pos;
                 settings.cursor = None;
+                settings.cursor_history.clear();
             } else {
                 settings.cursor = Some(player_pos);
                 cursor_pos = player_pos;
@@ -565,8 +776,16 @@ pub fn handle_input_playing(input_action: InputAction,
                             let item_id = level.entities.inventory[&player_id][item_index];
                             let throw_pos = cursor_pos;
 
-                            // Throwing to the current tile does nothing.
-                            if player_pos != throw_pos {
+                            // Throwing to the current tile does nothing, unless the item is used on the player.
+                            let item = level.entities.item[&item_id];
+                            if item == Item::Sling {
+                                // a sling shoots a stone, as when it is used in a direction
+                                if let Some(stone_id) = level.has_item_in_inventory(player_id, Item::Stone) {
+                                    if player_pos != throw_pos {
+                                        msg_log.log(Msg::ItemThrow(player_id, stone_id, player_pos, throw_pos, true));
+                                    }
+                                }
+                            } else if player_pos != throw_pos || can_use_on_self(item, settings, config) {
                                 msg_log.log(Msg::ItemThrow(player_id, item_id, player_pos, throw_pos, false));
                             }
                         } else {
@@ -596,6 +815,10 @@ pub fn handle_input_playing(input_action: InputAction,
             msg_log.log(Msg::PickUp(player_id));
         }
 
+        (InputAction::CycleSlot(item_class), true) => {
+            msg_log.log(Msg::CycleSlot(player_id, item_class));
+        }
+
         (InputAction::Yell, true) => {
             msg_log.log(Msg::Yell(player_id));
         }
@@ -605,6 +828,10 @@ pub fn handle_input_playing(input_action: InputAction,
             msg_log.log(Msg::Overlay(settings.overlay));
         }
 
+        (InputAction::ToggleItemHighlight, _) => {
+            msg_log.log(Msg::ToggleItemHighlight);
+        }
+
         (InputAction::Inventory, _) => {
             change_state(settings, GameState::Inventory, msg_log);
         }
@@ -621,6 +848,21 @@ pub fn handle_input_playing(input_action: InputAction,
             change_state(settings, GameState::HelpMenu, msg_log);
         }
 
+        (InputAction::BestiaryMenu, _) => {
+            change_state(settings, GameState::BestiaryMenu, msg_log);
+        }
+
+        (InputAction::FreeCamera, _) if config.free_camera => {
+            ensure_leave_cursor(settings, msg_log);
+
+            settings.free_camera = Some(player_pos);
+            change_state(settings, GameState::FreeCamera, msg_log);
+            msg_log.log(Msg::FreeCamera(true, player_pos));
+
+            // this causes FoV information to be emitted, revealing the map.
+            settings.map_changed = true;
+        }
+
         (InputAction::Esc, _) => {
             if settings.cursor.is_none() {
                 change_state(settings, GameState::ConfirmQuit, msg_log);
@@ -634,6 +876,43 @@ pub fn handle_input_playing(input_action: InputAction,
     }
 }
 
+/// The free camera moves around the map without the player taking a turn, so only
+/// camera movement and leaving the mode are handled here.
+pub fn handle_input_free_camera(input_action: InputAction,
+                                level: &Level,
+                                settings: &mut Settings,
+                                msg_log: &mut MsgLog) {
+    match input_action {
+        InputAction::Move(dir) => {
+            if let Some(camera_pos) = settings.free_camera {
+                let new_pos = level.map.clamp(dir.offset_pos(camera_pos, 1));
+                settings.free_camera = Some(new_pos);
+                msg_log.log(Msg::FreeCamera(true, new_pos));
+            }
+        }
+
+        InputAction::OverlayToggle => {
+            settings.overlay = !settings.overlay;
+            msg_log.log(Msg::Overlay(settings.overlay));
+        }
+
+        InputAction::FreeCamera | InputAction::Esc => {
+            let player_id = level.find_by_name(EntityName::Player).unwrap();
+            let player_pos = level.entities.pos[&player_id];
+
+            settings.free_camera = None;
+            change_state(settings, GameState::Playing, msg_log);
+            msg_log.log(Msg::FreeCamera(false, player_pos));
+
+            // FoV information is emitted again to hide what the player can not see.
+            settings.map_changed = true;
+        }
+
+        _ => {
+        }
+    }
+}
+
 fn handle_throw_item(item_class: ItemClass, level: &Level, msg_log: &mut MsgLog, settings: &mut Settings) {
     if let Some(_item_index) = level.find_item(item_class) { 
         if settings.cursor == None {
@@ -655,6 +934,7 @@ fn ensure_leave_cursor(settings: &mut Settings, msg_log: &mut MsgLog) {
         msg_log.log(Msg::CursorState(false, pos));
         settings.cursor = None;
         settings.cursor_action = None;
+        settings.cursor_history.clear();
     }
 }
 
@@ -688,6 +968,65 @@ fn use_dir(dir: Direction, level: &Level, settings: &mut Settings, msg_log: &mut
     }
 }
 
+/// The enemies that can be hit in use-mode, each with a direction that hits them.
+/// Targets are ordered by distance from the player and then by entity id, so cycling
+/// through them visits each target once before wrapping around.
+pub fn use_targets(level: &Level, settings: &Settings) -> Vec<(Direction, EntityId)> {
+    let player_id = level.find_by_name(EntityName::Player).unwrap();
+    let player_pos = level.entities.pos[&player_id];
+
+    let mut targets: Vec<(i32, EntityId, Direction)> = Vec::new();
+    for dir in Direction::move_actions().iter() {
+        let use_result;
+        if let UseAction::Item(item_class) = settings.use_action {
+            if let Some(item_index) = level.find_item(item_class) {
+                use_result = level.calculate_use_item(player_id, item_index, *dir, settings.move_mode);
+            } else {
+                continue;
+            }
+        } else if let UseAction::Skill(skill, _action_mode) = settings.use_action {
+            use_result = level.calculate_use_skill(player_id, skill, *dir, settings.move_mode);
+        } else {
+            continue;
+        }
+
+        for hit_pos in use_result.hit_positions.iter() {
+            for entity_id in level.get_entities_at_pos(*hit_pos) {
+                let is_enemy = level.entities.typ.get(&entity_id) == Some(&EntityType::Enemy);
+                let alive = level.entities.status.get(&entity_id).map_or(false, |status| status.alive);
+                if is_enemy && alive {
+                    targets.push((distance(player_pos, *hit_pos), entity_id, *dir));
+                }
+            }
+        }
+    }
+
+    // the sort is stable, so an enemy hit from several directions keeps the first of them.
+    targets.sort_by_key(|(dist, entity_id, _dir)| (*dist, *entity_id));
+    targets.dedup_by_key(|(_dist, entity_id, _dir)| *entity_id);
+
+    return targets.iter().map(|(_dist, entity_id, dir)| (*dir, *entity_id)).collect();
+}
+
+fn cycle_use_target(forward: bool, level: &Level, settings: &mut Settings, msg_log: &mut MsgLog) {
+    let targets = use_targets(level, settings);
+    if targets.is_empty() {
+        return;
+    }
+
+    let num_targets = targets.len();
+    let index = match (settings.use_target, forward) {
+        (Some(index), true) => (index + 1) % num_targets,
+        (Some(index), false) => (index + num_targets - 1) % num_targets,
+        (None, true) => 0,
+        (None, false) => num_targets - 1,
+    };
+    settings.use_target = Some(index);
+
+    let (dir, _entity_id) = targets[index];
+    use_dir(dir, level, settings, msg_log);
+}
+
 fn finalize_use_skill(skill: Skill, action_mode: ActionMode, level: &Level, settings: &mut Settings, msg_log: &mut MsgLog) {
     let player_id = level.find_by_name(EntityName::Player).unwrap();
 
@@ -700,10 +1039,17 @@ fn finalize_use_skill(skill: Skill, action_mode: ActionMode, level: &Level, sett
     }
 }
 
+/// Whether an item is used on the player's own tile rather than dropped. This is the
+/// alternate use of items in the config's self_use_items, chosen by sneaking.
+fn can_use_on_self(item: Item, settings: &Settings, config: &Config) -> bool {
+    return settings.move_mode == MoveMode::Sneak && config.self_use_items.contains(&item);
+}
+
 fn use_item_throwable(item: Item) -> bool {
     return item == Item::Stone || item == Item::Lantern || item == Item::SeedOfStone || item == Item::SeedCache || 
            item == Item::Herb || item == Item::GlassEye || item == Item::SmokeBomb || item == Item::LookingGlass || 
-           item == Item::Thumper;
+           item == Item::Thumper || item == Item::PoisonVial || item == Item::SwapStone ||
+           item == Item::MarkingStone;
 }
 
 fn finalize_use_item(item_class: ItemClass, level: &Level, settings: &mut Settings, msg_log: &mut MsgLog) {
@@ -730,6 +1076,14 @@ fn finalize_use_item(item_class: ItemClass, level: &Level, settings: &mut Settin
         } else if item == Item::SpikeTrap || item == Item::SoundTrap || item == Item::BlinkTrap || item == Item::FreezeTrap {
             let place_pos = dir.offset_pos(player_pos, 1);
             msg_log.log(Msg::PlaceTrap(player_id, place_pos, item_id));
+        } else if item.kit_trap().is_some() {
+            // the use result only has a position if the tile is free to place the trap on
+            if let Some(place_pos) = use_result.pos {
+                msg_log.log(Msg::PlaceTrap(player_id, place_pos, item_id));
+            }
+        } else if item == Item::Boomerang {
+            let throw_pos = dir.offset_pos(player_pos, PLAYER_THROW_DIST as i32);
+            msg_log.log(Msg::BoomerangThrow(player_id, item_id, player_pos, throw_pos));
         } else if use_item_throwable(item) {
             let throw_pos = dir.offset_pos(player_pos, PLAYER_THROW_DIST as i32);
             msg_log.log(Msg::ItemThrow(player_id, item_id, player_pos, throw_pos, false));
@@ -752,8 +1106,13 @@ fn finalize_use_item(item_class: ItemClass, level: &Level, settings: &mut Settin
                     }
 
                     let weapon_type = item.weapon_type().expect("This item does not have a weapon type!");
+                    let charged = level.entities.status[&player_id].charged_attack
+                                       .map_or(false, |(charged_id, _dir, _turns)| charged_id == item_id);
+
                     let mut attack_type = AttackStyle::Normal;
-                    if item == Item::Spear && settings.move_mode == MoveMode::Run {
+                    if charged {
+                        attack_type = AttackStyle::Strong;
+                    } else if item == Item::Spear && settings.move_mode == MoveMode::Run {
                         attack_type = AttackStyle::Strong;
                     } else if item == Item::Dagger {
                         attack_type = AttackStyle::Stealth;
@@ -793,6 +1152,7 @@ fn initialize_use_mode(use_action: UseAction, settings: &mut Settings, msg_log:
     msg_log.log_info(InfoMsg::UseAction(settings.use_action));
 
     settings.use_dir = None;
+    settings.use_target = None;
     msg_log.log_info(InfoMsg::UseDirClear);
     msg_log.log_info(InfoMsg::UseHitPosClear);
 }
@@ -858,8 +1218,15 @@ fn start_use_skill(index: usize, action_mode: ActionMode, level: &Level, setting
 
             SkillMode::Immediate => {
                 // Handle the skill immediately, with no action location as the skill should not be
-                // directional or based on a position.
-                handle_skill_index(index, ActionLoc::None, action_mode, level, settings, msg_log);
+                // directional or based on a position. The exception is the alternate blink, which
+                // targets the enemy under the cursor.
+                let mut action_loc = ActionLoc::None;
+                if skill == Skill::Blink && action_mode == ActionMode::Alternate {
+                    if let Some(cursor_pos) = settings.cursor {
+                        action_loc = ActionLoc::Place(cursor_pos);
+                    }
+                }
+                handle_skill_index(index, action_loc, action_mode, level, settings, msg_log);
             }
 
             SkillMode::Cursor => {
@@ -885,6 +1252,12 @@ fn start_use_item(item_class: ItemClass, level: &Level, settings: &mut Settings,
 
         if level.entities.item[&item_id] == Item::Herb {
             msg_log.log(Msg::EatHerb(player_id, item_id));
+        } else if level.entities.item[&item_id] == Item::Hourglass {
+            msg_log.log(Msg::TimeStop(player_id, item_id));
+        } else if level.entities.item[&item_id] == Item::Mirror {
+            msg_log.log(Msg::RaiseMirror(player_id, item_id));
+        } else if level.entities.item[&item_id] == Item::RecallStone {
+            msg_log.log(Msg::Recall(player_id, item_id));
         } else if level.entities.item[&item_id] == Item::Stone {
             handle_throw_item(item_class, level, msg_log, settings);
         } else {
@@ -991,7 +1364,12 @@ pub fn handle_skill(skill: Skill,
         }
 
         Skill::Blink => {
-            msg_log.log(Msg::Blink(player_id));
+            // the alternate blink lands behind a targeted enemy instead of on a random tile
+            if action_mode == ActionMode::Alternate && matches!(action_loc, ActionLoc::Place(_)) {
+                msg_log.log(Msg::BlinkBehind(player_id, skill_pos));
+            } else {
+                msg_log.log(Msg::Blink(player_id));
+            }
         }
 
         Skill::GrassShoes => {
@@ -1020,21 +1398,8 @@ pub fn handle_skill(skill: Skill,
             let player_id = level.find_by_name(EntityName::Player).unwrap();
             let player_pos = level.entities.pos[&player_id];
 
-            if let Some(dir) = Direction::from_positions(player_pos, skill_pos) {
-                let target_pos = dir.offset_pos(player_pos, 1);
-
-                let blocked = level.map.path_blocked_move(player_pos, target_pos);
-                
-                if let Some(blocked) = blocked {
-                    if level.map.tile_is_blocking(blocked.end_pos) {
-                        let next = next_from_to(player_pos, blocked.end_pos);
-                        if  !level.map.tile_is_blocking(next) {
-                            msg_log.log(Msg::PassWall(player_id, next));
-                        }
-                    } else {
-                        msg_log.log(Msg::PassWall(player_id, skill_pos));
-                    }
-                }
+            if let Some(pass_pos) = pass_wall_pos(level, player_pos, skill_pos) {
+                msg_log.log(Msg::PassWall(player_id, pass_pos));
             }
         }
 
@@ -1145,11 +1510,28 @@ pub fn handle_skill(skill: Skill,
                 msg_log.log(Msg::TrySwift(player_id, direction));
             }
         }
+
+        Skill::Dash => {
+            if let Some(direction) = direction {
+                msg_log.log(Msg::TryDash(player_id, direction));
+            }
+        }
+
+        Skill::WarCry => {
+            msg_log.log(Msg::TryWarCry(player_id, SKILL_WAR_CRY_TURNS));
+        }
+
+        Skill::StoneVolley => {
+            if let Some(direction) = direction {
+                let throw_pos = direction.offset_pos(player_pos, PLAYER_THROW_DIST as i32);
+                msg_log.log(Msg::StoneVolley(player_id, player_pos, throw_pos));
+            }
+        }
     }
 }
 
 // TODO is this println okay to leave in? seems like it should be in stderr?
-fn change_state(settings: &mut Settings, new_state: GameState, msg_log: &mut MsgLog) {
+pub fn change_state(settings: &mut Settings, new_state: GameState, msg_log: &mut MsgLog) {
     if new_state != settings.state {
         settings.state = new_state;
 
@@ -1175,10 +1557,22 @@ fn change_state(settings: &mut Settings, new_state: GameState, msg_log: &mut Msg
                 println!("CONSOLE: Help menu");
             }
 
+            GameState::BestiaryMenu => {
+                println!("CONSOLE: Bestiary");
+            }
+
             GameState::ConfirmQuit => {
                 println!("CONSOLE: Confirm quit");
             }
 
+            GameState::MainMenu => {
+                println!("CONSOLE: Main menu");
+            }
+
+            GameState::TimeUp => {
+                println!("CONSOLE: Out of turns!");
+            }
+
             GameState::Win => {
                 println!("CONSOLE: Won Level!");
             }
@@ -1194,6 +1588,10 @@ fn change_state(settings: &mut Settings, new_state: GameState, msg_log: &mut Msg
             GameState::Use => {
                 println!("CONSOLE: Use");
             }
+
+            GameState::FreeCamera => {
+                println!("CONSOLE: Free camera");
+            }
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::{HashSet, VecDeque};
+
 use serde::{Serialize, Deserialize};
 use logging_timer::timer;
 
@@ -22,7 +24,7 @@ use crate::actions;
 use crate::actions::InputAction;
 use crate::generation::*;
 use crate::map_construct::map_construct;
-use crate::step::step_logic;
+use crate::step::{step_logic, ai_planned_move};
 use crate::input::*;
 use crate::vault::*;
 
@@ -34,8 +36,15 @@ pub struct Game {
     pub settings: Settings,
     pub msg_log: MsgLog,
     pub rng: Rand32,
+    pub seed: u64, // the seed the game was started with, for reproducing a run with --seed
     pub vaults: Vec<Vault>,
     pub input: Input,
+    pub bestiary: Vec<BestiaryEntry>,
+    // consumables the player has used at least once, when items start unidentified
+    pub identified: HashSet<Item>,
+    pub stats: RunStats,
+    pub input_queue: VecDeque<InputAction>, // moves pressed ahead, see queue_input
+    pub performance: Performance, // recent damage taken, for dynamic difficulty
 }
 
 impl Game {
@@ -54,19 +63,67 @@ impl Game {
 
         let vaults: Vec<Vault> = Vec::new();
 
-        let state = Game {
+        let mut state = Game {
             config,
             level,
             settings: Settings::new(),
             msg_log,
             rng: rng,
+            seed,
             vaults,
             input: Input::new(),
+            bestiary: Vec::new(),
+            identified: HashSet::new(),
+            stats: RunStats::default(),
+            input_queue: VecDeque::new(),
+            performance: Performance::default(),
         };
 
+        state.give_starting_inventory();
+
         return state;
     }
 
+    /// Give the player the items listed in the config's starting inventory. Items whose
+    /// slot is already taken are kept in the settings, and dropped at the player's start
+    /// position when the next level is constructed.
+    pub fn give_starting_inventory(&mut self) {
+        let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+        let player_pos = self.level.entities.pos[&player_id];
+
+        for item_name in self.config.starting_inventory.clone().iter() {
+            let item = match item_name.to_lowercase().parse::<Item>() {
+                Ok(item) => item,
+                Err(_) => {
+                    eprintln!("Skipping unknown starting item '{}'", item_name);
+                    continue;
+                }
+            };
+
+            let slot_taken = match item.class() {
+                ItemClass::Misc => false,
+                item_class => self.level.entities.item_by_class(player_id, item_class).is_some(),
+            };
+
+            let inventory_full =
+                self.level.entities.needs_new_slot(player_id, item) &&
+                self.level.entities.inventory_slots(player_id).len() >= self.config.inventory_slots;
+
+            if slot_taken || inventory_full {
+                self.settings.starting_drops.push(item);
+            } else {
+                let item_id = make_item(&mut self.level.entities, &self.config, item, player_pos, &mut self.msg_log);
+                self.level.entities.pick_up_item(player_id, item_id);
+            }
+        }
+    }
+
+    /// Copy the config's map settings onto the current map. Maps are built in several
+    /// places, and are also loaded from saves, so this is applied after each of them.
+    pub fn apply_map_config(&mut self) {
+        self.level.map.allow_corner_cutting = self.config.allow_diagonal_corner_cutting;
+    }
+
     pub fn clear_level_except_player(&mut self) {
         let mut dont_clear: Vec<EntityId> = Vec::new();
 
@@ -82,6 +139,11 @@ impl Game {
                 self.level.entities.remove_entity(*id);
             }
         }
+
+        self.level.scent.clear();
+
+        // a recall stone's mark refers to a tile on the old level.
+        self.level.entities.recall_pos.remove(&player_id);
     }
 
     pub fn load_vaults(&mut self, path: &str) {
@@ -121,11 +183,16 @@ impl Game {
                     self.level.entities.remove_item(player_id, key_id);
                 }
 
-                self.settings.state = GameState::Playing;
-
                 self.settings.level_num += 1;
 
-                map_construct(&self.config.map_load.clone(), self);
+                // the run is won once the last level is finished
+                if self.config.levels_to_win > 0 && self.settings.level_num >= self.config.levels_to_win {
+                    actions::change_state(&mut self.settings, GameState::Win, &mut self.msg_log);
+                } else {
+                    self.settings.state = GameState::Playing;
+
+                    map_construct(&self.config.map_load.clone(), self);
+                }
             }
 
             /* Check for explored tiles */
@@ -140,13 +207,44 @@ impl Game {
                         self.settings.test_mode;
                     if visible {
                         self.level.map[pos].explored = true;
+                        self.stats.tiles_explored += 1;
                     }
                 }
             }
 
+            self.record_seen_enemies();
+
             if self.level.entities.took_turn[&player_id] != 0 {
                 self.msg_log.log(Msg::StartTurn);
             }
+
+            let player_died =
+                !self.level.entities.status[&player_id].alive &&
+                (self.settings.state == GameState::Playing || self.settings.state == GameState::Use);
+            if self.level.entities.took_turn[&player_id] != 0 || player_died {
+                let max_hp = self.level.entities.hp[&player_id].max_hp;
+                self.performance.record_turn(self.stats.damage_taken, player_died, max_hp, self.config.dynamic_difficulty_window);
+            }
+
+            // End the run when the player dies, or send them back to the start of the level.
+            if player_died {
+                if self.config.practice_mode {
+                    self.spare_player();
+                } else if self.config.permadeath {
+                    actions::change_state(&mut self.settings, GameState::Lose, &mut self.msg_log);
+                } else {
+                    self.respawn_player();
+                }
+            }
+
+            // End the run when out of turns. Only turns taken while playing count, so
+            // the limit is not checked while in a menu or in cursor mode.
+            if self.config.turn_limit > 0 &&
+               self.settings.turn_count >= self.config.turn_limit &&
+               self.settings.state == GameState::Playing &&
+               !self.settings.is_cursor_mode() {
+                actions::change_state(&mut self.settings, GameState::TimeUp, &mut self.msg_log);
+            }
         }
 
         if input_action != InputAction::None {
@@ -165,9 +263,172 @@ impl Game {
             self.msg_log.log(Msg::PlayerTurn);
         }
 
+        if self.input_queue_interrupted() {
+            self.input_queue.clear();
+        }
+
         return self.settings.state != GameState::Exit;
     }
 
+    /// Buffer a move pressed while an earlier one may still be playing out. This returns
+    /// whether the action was taken by the buffer (true), or should be stepped right away (false).
+    /// Moves beyond the configured buffer size are dropped.
+    pub fn queue_input(&mut self, input_action: InputAction) -> bool {
+        if self.config.input_buffer_size == 0 {
+            return false;
+        }
+
+        match input_action {
+            InputAction::Move(_) | InputAction::Pass => {
+                if self.input_queue.len() < self.config.input_buffer_size {
+                    self.input_queue.push_back(input_action);
+                }
+                return true;
+            }
+
+            _ => {
+                return false;
+            }
+        }
+    }
+
+    pub fn next_queued_input(&mut self) -> Option<InputAction> {
+        return self.input_queue.pop_front();
+    }
+
+    /// Buffered moves are dropped when continuing them could walk the player into danger
+    /// or act in a different mode than the one they were pressed in.
+    fn input_queue_interrupted(&self) -> bool {
+        let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+
+        let player_died = !self.level.entities.status[&player_id].alive ||
+            self.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::Killed(_, attacked, _) if *attacked == player_id));
+
+        let enemy_alerted = self.msg_log.turn_messages.iter().any(|msg| {
+            matches!(msg, Msg::StateChange(_, Behavior::Alert(_)) | Msg::StateChange(_, Behavior::Attacking(_)))
+        });
+
+        return player_died || enemy_alerted || self.settings.state != GameState::Playing;
+    }
+
+    /// Return a dead player to the start of the level with full health. Their items and
+    /// status effects are lost, and the level is left as-is except that enemies lose track of them.
+    fn respawn_player(&mut self) {
+        let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+
+        // items are left where the player died, so a key can still be carried to the exit
+        while !self.level.entities.inventory[&player_id].is_empty() {
+            let num_items = self.level.entities.inventory[&player_id].len();
+            inventory_drop_item(player_id, 0, &mut self.level, &mut self.msg_log);
+            if self.level.entities.inventory[&player_id].len() == num_items {
+                break;
+            }
+        }
+
+        self.level.entities.status[&player_id] = StatusEffect::default();
+        self.level.entities.status[&player_id].alive = true;
+        self.level.entities.blocks[&player_id] = true;
+        self.level.entities.cooldowns[&player_id].clear();
+        self.level.entities.stance[&player_id] = Stance::Standing;
+        self.msg_log.log(Msg::Stance(player_id, Stance::Standing));
+
+        let hp = self.level.entities.hp[&player_id];
+        self.level.entities.hp[&player_id].hp = hp.max_hp;
+        self.msg_log.log(Msg::Healed(player_id, hp.max_hp - hp.hp, hp.max_hp));
+
+        for entity_id in self.level.entities.ids.clone() {
+            if self.level.entities.behavior.get(&entity_id).is_some() {
+                self.level.entities.behavior[&entity_id] = Behavior::Idle;
+                self.msg_log.log(Msg::StateChange(entity_id, Behavior::Idle));
+            }
+        }
+
+        self.settings.deaths += 1;
+
+        // the start of the level may be taken, such as by an enemy, so the nearest free tile is used
+        self.level.entities.set_pos(player_id, Pos::new(-1, -1));
+        let level_start = self.settings.level_start;
+        let mut start_pos = level_start;
+        let mut dist = 1;
+        while self.level.pos_blocked(start_pos) && dist < 10 {
+            let positions = floodfill(&self.level.map, level_start, dist);
+            if let Some(pos) = positions.iter().find(|pos| !self.level.pos_blocked(**pos)) {
+                start_pos = *pos;
+            }
+            dist += 1;
+        }
+        self.level.entities.set_pos(player_id, start_pos);
+        self.msg_log.log(Msg::Respawned(player_id, start_pos, self.settings.deaths));
+    }
+
+    /// Keep the player alive after a killing blow in practice mode. They are left with
+    /// one hp and stunned for a few turns. A player whose tile is blocked, such as by
+    /// the column that crushed them, is moved to the start of the level.
+    fn spare_player(&mut self) {
+        let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+
+        // the player no longer blocks their tile, so this only finds what killed them
+        let mut pos = self.level.entities.pos[&player_id];
+        if self.level.pos_blocked(pos) {
+            pos = self.settings.level_start;
+            self.level.entities.set_pos(player_id, pos);
+        }
+
+        self.level.entities.status[&player_id].alive = true;
+        self.level.entities.blocks[&player_id] = true;
+
+        let hp = self.level.entities.hp[&player_id];
+        self.level.entities.hp[&player_id].hp = 1;
+        self.msg_log.log(Msg::Healed(player_id, 1 - hp.hp, hp.max_hp));
+
+        self.level.entities.status[&player_id].frozen = self.config.practice_stun_turns;
+        self.msg_log.log(Msg::Frozen(player_id, true));
+
+        self.msg_log.log(Msg::Spared(player_id, pos));
+    }
+
+    /// Add enemies in the player's FoV to the bestiary the first time each kind is seen.
+    fn record_seen_enemies(&mut self) {
+        let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+
+        for entity_id in self.level.entities.ids.iter() {
+            if self.level.entities.typ[entity_id] != EntityType::Enemy ||
+               self.level.entities.status[entity_id].disguised {
+                continue;
+            }
+
+            let name = self.level.entities.name[entity_id];
+            if self.bestiary.iter().any(|entry| entry.name == name) {
+                continue;
+            }
+
+            if self.level.is_in_fov(player_id, *entity_id, &self.config) == FovResult::Inside {
+                let max_hp = self.level.entities.hp.get(entity_id).map_or(0, |hp| hp.max_hp);
+                let behavior = self.level.entities.behavior.get(entity_id).map_or(Behavior::Idle, |behavior| *behavior);
+                self.bestiary.push(BestiaryEntry { name, max_hp, behavior });
+            }
+        }
+    }
+
+    /// Whether the player knows what an item is. Only consumables start unidentified,
+    /// and only when unidentified items are enabled.
+    pub fn is_identified(&self, item: Item) -> bool {
+        return !self.config.unidentified_items ||
+               item.class() != ItemClass::Consumable ||
+               self.identified.contains(&item);
+    }
+
+    /// Reveal an item when the player uses it.
+    pub fn identify_item(&mut self, entity_id: EntityId, item_id: EntityId) {
+        if self.level.entities.typ[&entity_id] != EntityType::Player {
+            return;
+        }
+
+        if let Some(item) = self.level.entities.item.get(&item_id) {
+            self.identified.insert(*item);
+        }
+    }
+
     pub fn emit_state_messages(&mut self) {
         self.msg_log.log(Msg::StartTurn);
         self.emit_took_turn_state();
@@ -179,27 +440,27 @@ impl Game {
 
         let mut player_fov = Vec::new();
 
-        let (map_width, map_height) = self.level.map.size();
-        for y in 0..map_height {
-            for x in 0..map_width {
-                let pos = Pos::new(x, y);
-                let fov_result;
-                if self.settings.test_mode {
-                    fov_result = FovResult::Inside;
-                } else {
-                    fov_result = self.level.pos_in_fov_edge(player_id, pos);
+        // only positions inside or on the edge are sent- outside is most common, so it
+        // is assumed if no message is sent.
+        let mut fov_results = Vec::new();
+        // the free camera reveals the whole map, like test mode, so distant parts can be inspected.
+        if self.settings.test_mode || self.settings.state == GameState::FreeCamera {
+            let (map_width, map_height) = self.level.map.size();
+            for y in 0..map_height {
+                for x in 0..map_width {
+                    fov_results.push((Pos::new(x, y), FovResult::Inside));
                 }
+            }
+        } else {
+            fov_results = self.level.player_fov(player_id);
+        }
 
-                // only send if inside or on edge- outside is most common, so it is assumed
-                // if no message is sent.
-                if fov_result != FovResult::Outside {
-                    self.msg_log.log_info(InfoMsg::TileFov(pos, fov_result));
-                }
+        for (pos, fov_result) in fov_results {
+            self.msg_log.log_info(InfoMsg::TileFov(pos, fov_result));
 
-                // TODO should this be != Outside, to include Edge?
-                if fov_result == FovResult::Inside {
-                    player_fov.push(pos);
-                }
+            // TODO should this be != Outside, to include Edge?
+            if fov_result == FovResult::Inside {
+                player_fov.push(pos);
             }
         }
 
@@ -211,8 +472,8 @@ impl Game {
         let player_pos = self.level.entities.pos[&player_id];
 
         // emit whether entity is in player FOV
-        let mut in_fov = self.level.is_in_fov(player_id, entity_id);
-        if self.settings.test_mode {
+        let mut in_fov = self.level.is_in_fov(player_id, entity_id, &self.config);
+        if self.settings.test_mode || self.settings.state == GameState::FreeCamera {
             in_fov = FovResult::Inside;
         }
 
@@ -250,6 +511,13 @@ impl Game {
             }
         }
 
+        // emit the position an alert enemy plans to move to next turn
+        if in_fov == FovResult::Inside && typ == EntityType::Enemy && self.config.show_enemy_intent {
+            if let Some(intent_pos) = ai_planned_move(self, entity_id) {
+                self.msg_log.log_info(InfoMsg::EntityIntent(entity_id, intent_pos));
+            }
+        }
+
         // emit visible attack positions
         if in_fov == FovResult::Inside {
             if let Some(Behavior::Armed(_turns)) = self.level.entities.behavior.get(&entity_id) {
@@ -312,6 +580,35 @@ impl Game {
         // report current player inventory
         // this is here because picking up and dropping items does not take a turn
         self.emit_inventory();
+
+        // report whether the player's next attack is charged
+        let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+        if self.level.entities.status[&player_id].charged_attack.is_some() {
+            self.msg_log.log_info(InfoMsg::AttackCharged);
+        }
+
+        let hasted = self.level.entities.status[&player_id].hasted;
+        if hasted > 0 {
+            self.msg_log.log_info(InfoMsg::Hasted(hasted));
+        }
+
+        let time_stop = self.level.entities.status[&player_id].time_stop;
+        if time_stop > 0 {
+            self.msg_log.log_info(InfoMsg::TimeStop(time_stop));
+        }
+
+        self.msg_log.log_info(InfoMsg::RunStats(self.stats));
+
+        // report skills that are still cooling down
+        for (skill, turns) in self.level.entities.cooldowns[&player_id].iter() {
+            self.msg_log.log_info(InfoMsg::SkillCooldown(*skill, *turns));
+        }
+
+        if self.settings.state == GameState::BestiaryMenu {
+            for entry in self.bestiary.iter() {
+                self.msg_log.log_info(InfoMsg::BestiaryEntry(entry.name, entry.max_hp, entry.behavior));
+            }
+        }
     }
 
     pub fn emit_turn_messages(&mut self) {
@@ -336,6 +633,10 @@ impl Game {
             let item = self.level.entities.item[&item_id];
             let item_class = item.class();
             self.msg_log.log_info(InfoMsg::InventoryItem(item, item_class));
+
+            if !self.is_identified(item) {
+                self.msg_log.log_info(InfoMsg::UnidentifiedItem(item));
+            }
         }
     }
 
@@ -345,6 +646,20 @@ impl Game {
             for entity in entities {
                 self.msg_log.log_info(InfoMsg::EntityAtCursor(entity));
             }
+
+            // report whether a throw at the cursor would reach it, for planning throws.
+            // A sling shoots its stone further than a throw.
+            let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+            let player_pos = self.level.entities.pos[&player_id];
+            let mut hard = false;
+            if let Some(UseAction::Item(item_class)) = self.settings.cursor_action {
+                if let Some(item_index) = self.level.find_item(item_class) {
+                    let item_id = self.level.entities.inventory[&player_id][item_index];
+                    hard = self.level.entities.item[&item_id] == Item::Sling;
+                }
+            }
+            let line_of_fire = self.level.line_of_fire(player_pos, cursor_pos, hard);
+            self.msg_log.log_info(InfoMsg::LineOfFire(line_of_fire));
         }
     }
 
@@ -363,6 +678,45 @@ impl Game {
         }
     }
 
+    /// Emit the result a skill would have if used in the current use direction.
+    /// These use the same functions as the skill's resolution, so the preview
+    /// matches what happens when the skill is used.
+    fn emit_skill_preview(self: &mut Game, skill: Skill) {
+        let player_id = self.level.find_by_name(EntityName::Player).unwrap();
+        let player_pos = self.level.entities.pos[&player_id];
+
+        match skill {
+            Skill::GrassWall => {
+                if let Some(use_dir) = self.settings.use_dir {
+                    for (wall_pos, wall_dir) in grass_wall_positions(player_pos, use_dir) {
+                        if self.level.map.can_place_intertile_wall(wall_pos, wall_dir) {
+                            self.msg_log.log_info(InfoMsg::GrassWallPreview(wall_pos, wall_dir));
+                        }
+                    }
+                }
+            }
+
+            Skill::PassWall => {
+                if let Some(use_dir) = self.settings.use_dir {
+                    let skill_pos = use_dir.offset_pos(player_pos, 1);
+                    if let Some(pass_pos) = pass_wall_pos(&self.level, player_pos, skill_pos) {
+                        self.msg_log.log_info(InfoMsg::PassWallPreview(pass_pos));
+                    }
+                }
+            }
+
+            // NOTE Blink is currently an immediate skill, so this is only shown if it is
+            // used through use-mode.
+            Skill::Blink => {
+                for blink_pos in blink_candidates(player_pos, &self.level) {
+                    self.msg_log.log_info(InfoMsg::BlinkPreview(blink_pos));
+                }
+            }
+
+            _ => {}
+        }
+    }
+
     fn emit_use_mode_messages(self: &mut Game) {
         let player_id = self.level.find_by_name(EntityName::Player).unwrap();
 
@@ -388,6 +742,8 @@ impl Game {
                                                                self.settings.move_mode);
                 self.emit_use_result(use_result);
             }
+
+            self.emit_skill_preview(skill);
         } else if self.settings.use_action == UseAction::Interact {
             if let Some(dir) = self.settings.use_dir {
                 self.msg_log.log_info(InfoMsg::UseDir(dir));
@@ -415,7 +771,7 @@ impl Game {
                     let direction = Direction::from_dxy(dxy.x, dxy.y).unwrap();
 
                     let mut reach = reach_by_mode(MoveMode::Sneak);
-                    if !self.settings.is_cursor_mode() && self.input.shift {
+                    if !self.settings.is_cursor_mode() && self.input.run_modifier(&self.config) {
                         reach = reach_by_mode(MoveMode::Run);
                     }
 
@@ -436,6 +792,57 @@ impl Game {
     }
 }
 
+/// The damage the player took over their most recent turns. With dynamic difficulty
+/// enabled, this makes enemies slower to notice a struggling player, and quicker to
+/// notice one who is taking no damage. It only depends on what happened in the game,
+/// so replays play out the same way.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Performance {
+    pub turn_damage: VecDeque<i32>, // damage taken each turn, oldest first
+    pub damage_taken: i32, // the run's total damage taken at the last recorded turn
+}
+
+impl Performance {
+    /// Record a turn's damage. A death counts as losing the player's full health.
+    pub fn record_turn(&mut self, damage_taken: i32, died: bool, max_hp: i32, window: usize) {
+        // the run's total is reset on restart, so it can be lower than the last total
+        let mut damage = (damage_taken - self.damage_taken).max(0);
+        if died {
+            damage += max_hp;
+        }
+        self.damage_taken = damage_taken;
+
+        self.turn_damage.push_back(damage);
+        while self.turn_damage.len() > window {
+            self.turn_damage.pop_front();
+        }
+    }
+
+    pub fn recent_damage(&self) -> i32 {
+        return self.turn_damage.iter().sum();
+    }
+
+    /// A multiplier on how quickly enemies escalate from idle to alert. Taking more than
+    /// the target damage lowers it, and taking less raises it, within the configured bounds.
+    pub fn aggression(&self, config: &Config) -> f32 {
+        if !config.dynamic_difficulty {
+            return 1.0;
+        }
+
+        let damage_offset = (config.dynamic_difficulty_target_damage - self.recent_damage()) as f32;
+        let aggression = 1.0 + damage_offset * config.dynamic_difficulty_step;
+        return aggression.max(config.dynamic_difficulty_min).min(config.dynamic_difficulty_max);
+    }
+}
+
+/// An enemy recorded in the bestiary, as it was when first seen.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BestiaryEntry {
+    pub name: EntityName,
+    pub max_hp: i32,
+    pub behavior: Behavior,
+}
+
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum LevelExitCondition {
     RightEdge,
@@ -452,14 +859,34 @@ pub struct Settings {
     pub level_num: usize,
     pub running: bool,
     pub cursor: Option<Pos>,
+    pub cursor_history: Vec<Pos>, // previous cursor positions, most recent last, see CursorUndo
     pub use_action: UseAction,
     pub cursor_action: Option<UseAction>,
     pub use_dir: Option<Direction>,
+    pub use_target: Option<usize>, // index into the use-mode targets cycled with NextTarget/PrevTarget
     pub move_mode: MoveMode,
     pub debug_enabled: bool,
+    pub slow_motion: bool, // whether animations are slowed by the config's slow_motion_scale
     pub map_load_config: MapLoadConfig,
+    pub map_seed: u64, // the random number generator's state when the current map was generated, identifying the map
     pub map_changed: bool,
     pub exit_condition: LevelExitCondition,
+    pub level_start: Pos,
+    pub deaths: usize,
+    pub starting_drops: Vec<Item>, // starting items that did not fit in the inventory
+    pub save_exists: bool, // whether the main menu can continue a saved game
+    pub run_id: u64, // identifies the current run's autosaves
+    pub pending_pickup: Option<EntityId>, // item to pick up once an inventory slot is chosen to drop
+    pub free_camera: Option<Pos>, // camera position while inspecting the map in GameState::FreeCamera
+
+    // whether the game window has input focus. This is not part of the game state, so it is
+    // not saved, and a loaded game starts out focused.
+    #[serde(skip, default = "default_focused")]
+    pub focused: bool,
+}
+
+fn default_focused() -> bool {
+    return true;
 }
 
 impl Settings {
@@ -473,19 +900,44 @@ impl Settings {
             level_num: 0,
             running: true,
             cursor: None,
+            cursor_history: Vec::new(),
             use_action: UseAction::Interact,
             cursor_action: None,
             use_dir: None,
+            use_target: None,
             move_mode: MoveMode::Walk,
             debug_enabled: false,
+            slow_motion: false,
             map_load_config: MapLoadConfig::Empty,
+            map_seed: 0,
             map_changed: false,
             exit_condition: LevelExitCondition::RightEdge,
+            level_start: Pos::new(0, 0),
+            deaths: 0,
+            starting_drops: Vec::new(),
+            save_exists: false,
+            run_id: 0,
+            pending_pickup: None,
+            free_camera: None,
+            focused: true,
         };
     }
 
     pub fn is_cursor_mode(&self) -> bool {
         return self.cursor.is_some();
     }
+
+    /// Remember the cursor's position before it moves, so the move can be undone.
+    pub fn record_cursor_pos(&mut self) {
+        if let Some(cursor_pos) = self.cursor {
+            if self.cursor_history.last() != Some(&cursor_pos) {
+                self.cursor_history.push(cursor_pos);
+            }
+
+            if self.cursor_history.len() > CURSOR_HISTORY_LENGTH {
+                self.cursor_history.remove(0);
+            }
+        }
+    }
 }
 
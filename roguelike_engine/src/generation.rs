@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::collections::HashMap;
 
 use serde::{Serialize, Deserialize};
 
@@ -49,6 +50,7 @@ pub fn make_player(entities: &mut Entities, config: &Config, msg_log: &mut MsgLo
 
     let skill_set = Vec::new();
     entities.skills.insert(entity_id,  skill_set);
+    entities.cooldowns.insert(entity_id,  HashMap::new());
 
     let talents = vec!(Talent::Invigorate, Talent::StrongAttack, Talent::Sprint, Talent::Push, Talent::EnergyShield);
     for talent in talents.iter() {
@@ -87,6 +89,14 @@ pub fn make_statue(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
     return entity_id;
 }
 
+pub fn make_boulder(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Column, EntityName::Boulder, true);
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Boulder, entities.direction[&entity_id]));
+
+    return entity_id;
+}
+
 pub fn make_energy(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Energy, EntityName::Energy, false);
 
@@ -303,6 +313,10 @@ pub fn make_spire(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &
     entities.behavior.insert(entity_id,  Behavior::Idle);
     entities.movement.insert(entity_id,  Reach::Single(SPIRE_MOVE_DISTANCE));
     entities.attack.insert(entity_id,  Reach::Single(SPIRE_ATTACK_DISTANCE));
+    entities.fearless.insert(entity_id,  true);
+    if config.spire_venom_turns > 0 {
+        entities.venom.insert(entity_id,  config.spire_venom_turns);
+    }
     entities.status[&entity_id].alive = true;
     entities.direction.insert(entity_id,  Direction::Up);
     entities.stance.insert(entity_id,  Stance::Standing);
@@ -319,6 +333,55 @@ pub fn make_spire(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &
     return entity_id;
 }
 
+pub fn make_summoner(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, EntityName::Summoner, true);
+
+    entities.hp.insert(entity_id,  Hp { max_hp: 12, hp: 12, });
+    entities.ai.insert(entity_id,  Ai::Basic);
+    entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.movement.insert(entity_id,  Reach::Single(SUMMONER_MOVE_DISTANCE));
+    entities.attack.insert(entity_id,  Reach::Single(SUMMONER_ATTACK_DISTANCE));
+    entities.summon_cooldown.insert(entity_id,  0);
+    entities.status[&entity_id].alive = true;
+    entities.direction.insert(entity_id,  Direction::from_f32(rand_from_pos(pos)));
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.attack_type.insert(entity_id,  AttackType::Melee);
+    entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    entities.passive.insert(entity_id,  Passive::new());
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Summoner, entities.direction[&entity_id]));
+    msg_log.log(Msg::Stance(entity_id, entities.stance[&entity_id]));
+    msg_log.log(Msg::Healed(entity_id, entities.hp[&entity_id].hp, entities.hp[&entity_id].hp));
+    msg_log.log(Msg::StateChange(entity_id, Behavior::Idle));
+
+    return entity_id;
+}
+
+pub fn make_thief(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, EntityName::Thief, true);
+
+    entities.hp.insert(entity_id,  Hp { max_hp: 8, hp: 8, });
+    entities.ai.insert(entity_id,  Ai::Basic);
+    entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.movement.insert(entity_id,  Reach::Single(THIEF_MOVE_DISTANCE));
+    entities.attack.insert(entity_id,  Reach::Single(THIEF_ATTACK_DISTANCE));
+    entities.status[&entity_id].alive = true;
+    entities.direction.insert(entity_id,  Direction::from_f32(rand_from_pos(pos)));
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.attack_type.insert(entity_id,  AttackType::Melee);
+    entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    entities.passive.insert(entity_id,  Passive::new());
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Thief, entities.direction[&entity_id]));
+    msg_log.log(Msg::Stance(entity_id, entities.stance[&entity_id]));
+    msg_log.log(Msg::Healed(entity_id, entities.hp[&entity_id].hp, entities.hp[&entity_id].hp));
+    msg_log.log(Msg::StateChange(entity_id, Behavior::Idle));
+
+    return entity_id;
+}
+
 pub fn make_rook(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, EntityName::Rook, true);
 
@@ -343,6 +406,32 @@ pub fn make_rook(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &m
     return entity_id;
 }
 
+/// A mimic looks like an herb until the player comes close, and then attacks.
+pub fn make_mimic(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, EntityName::Mimic, true);
+
+    entities.hp.insert(entity_id,  Hp { max_hp: 10, hp: 10, });
+    entities.ai.insert(entity_id,  Ai::Basic);
+    entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.movement.insert(entity_id,  Reach::Single(MIMIC_MOVE_DISTANCE));
+    entities.attack.insert(entity_id,  Reach::Single(MIMIC_ATTACK_DISTANCE));
+    entities.status[&entity_id].alive = true;
+    entities.status[&entity_id].disguised = true;
+    entities.direction.insert(entity_id,  Direction::from_f32(rand_from_pos(pos)));
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.attack_type.insert(entity_id,  AttackType::Melee);
+    entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    entities.passive.insert(entity_id,  Passive::new());
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Mimic, entities.direction[&entity_id]));
+    msg_log.log(Msg::Stance(entity_id, entities.stance[&entity_id]));
+    msg_log.log(Msg::Healed(entity_id, entities.hp[&entity_id].hp, entities.hp[&entity_id].hp));
+    msg_log.log(Msg::StateChange(entity_id, entities.behavior[&entity_id]));
+
+    return entity_id;
+}
+
 pub fn make_armil(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, EntityName::Armil, true);
 
@@ -376,6 +465,15 @@ pub fn make_smoke(entities: &mut Entities, config: &Config, pos: Pos, amount: us
     return entity_id;
 } 
 
+pub fn make_fire(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Environment, EntityName::Fire, false);
+
+    entities.count_down.insert(entity_id,  config.fire_turns);
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Fire, entities.direction[&entity_id]));
+    
+    return entity_id;
+} 
+
 pub fn make_magnifier(entities: &mut Entities, _config: &Config, pos: Pos, amount: usize, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Environment, EntityName::Magnifier, false);
 
@@ -421,6 +519,16 @@ pub fn make_blink_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_
     return entity_id;
 }
 
+pub fn make_spike_trap_kit(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::SpikeTrapKit, EntityName::SpikeTrapKit, pos, msg_log);
+    return entity_id;
+}
+
+pub fn make_freeze_trap_kit(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::FreezeTrapKit, EntityName::FreezeTrapKit, pos, msg_log);
+    return entity_id;
+}
+
 pub fn make_freeze_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, EntityName::FreezeTrap, false);
 
@@ -443,6 +551,16 @@ pub fn make_gate_trigger(entities: &mut Entities, _config: &Config, pos: Pos, ms
     return entity_id;
 }
 
+pub fn make_hint_trigger(entities: &mut Entities, _config: &Config, pos: Pos, hint_index: usize, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Trigger, EntityName::HintTrigger, false);
+
+    entities.hint.insert(entity_id, (hint_index, false));
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::HintTrigger, entities.direction[&entity_id]));
+
+    return entity_id;
+}
+
 pub fn make_exit(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, EntityName::Exit, false);
 
@@ -466,6 +584,36 @@ pub fn make_smoke_bomb(entities: &mut Entities, _config: &Config, pos: Pos, msg_
     return entity_id;
 }
 
+pub fn make_poison_vial(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::PoisonVial, EntityName::PoisonVial, pos, msg_log);
+    return entity_id;
+}
+
+pub fn make_swap_stone(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::SwapStone, EntityName::SwapStone, pos, msg_log);
+    return entity_id;
+}
+
+pub fn make_marking_stone(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::MarkingStone, EntityName::MarkingStone, pos, msg_log);
+    return entity_id;
+}
+
+pub fn make_recall_stone(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::RecallStone, EntityName::RecallStone, pos, msg_log);
+    return entity_id;
+}
+
+pub fn make_hourglass(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::Hourglass, EntityName::Hourglass, pos, msg_log);
+    return entity_id;
+}
+
+pub fn make_mirror(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::Mirror, EntityName::Mirror, pos, msg_log);
+    return entity_id;
+}
+
 pub fn make_looking_glass(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = make_item_generic(entities, Item::LookingGlass, EntityName::LookingGlass, pos, msg_log);
     return entity_id;
@@ -496,6 +644,11 @@ pub fn make_sling(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
     return entity_id;
 }
 
+pub fn make_boomerang(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = make_item_generic(entities, Item::Boomerang, EntityName::Boomerang, pos, msg_log);
+    return entity_id;
+}
+
 pub fn make_lantern(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = make_item_generic(entities, Item::Lantern, EntityName::Lantern, pos, msg_log);
 
@@ -529,6 +682,7 @@ pub fn make_item(entities: &mut Entities, config: &Config, item: Item, pos: Pos,
 pub fn make_entity(entities: &mut Entities, config: &Config, entity_name: EntityName, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     match entity_name {
         EntityName::Column => make_column(entities, config, pos, msg_log),
+        EntityName::Boulder => make_boulder(entities, config, pos, msg_log),
         EntityName::Energy => make_energy(entities, config, pos, msg_log),
         EntityName::Dagger => make_dagger(entities, config, pos, msg_log),
         EntityName::Hammer => make_hammer(entities, config, pos, msg_log),
@@ -542,14 +696,25 @@ pub fn make_entity(entities: &mut Entities, config: &Config, entity_name: Entity
         EntityName::Cursor => make_cursor(entities, config, pos, msg_log),
         EntityName::Gol => make_gol(entities, config, pos, msg_log),
         EntityName::Spire => make_spire(entities, config, pos, msg_log),
+        EntityName::Summoner => make_summoner(entities, config, pos, msg_log),
+        EntityName::Thief => make_thief(entities, config, pos, msg_log),
         EntityName::Pawn => make_pawn(entities, config, pos, msg_log),
+        EntityName::Mimic => make_mimic(entities, config, pos, msg_log),
         EntityName::SoundTrap => make_sound_trap(entities, config, pos, msg_log),
         EntityName::SeedOfStone => make_seed_of_stone(entities, config, pos, msg_log),
         EntityName::LookingGlass => make_looking_glass(entities, config, pos, msg_log),
         EntityName::SmokeBomb => make_smoke_bomb(entities, config, pos, msg_log),
+        EntityName::PoisonVial => make_poison_vial(entities, config, pos, msg_log),
+        EntityName::SwapStone => make_swap_stone(entities, config, pos, msg_log),
+        EntityName::MarkingStone => make_marking_stone(entities, config, pos, msg_log),
+        EntityName::RecallStone => make_recall_stone(entities, config, pos, msg_log),
+        EntityName::Hourglass => make_hourglass(entities, config, pos, msg_log),
+        EntityName::Mirror => make_mirror(entities, config, pos, msg_log),
         EntityName::GlassEye => make_glass_eye(entities, config, pos, msg_log),
         EntityName::SpikeTrap => make_spike_trap(entities, config, pos, msg_log),
         EntityName::FreezeTrap => make_freeze_trap(entities, config, pos, msg_log),
+        EntityName::SpikeTrapKit => make_spike_trap_kit(entities, config, pos, msg_log),
+        EntityName::FreezeTrapKit => make_freeze_trap_kit(entities, config, pos, msg_log),
         EntityName::BlinkTrap => make_blink_trap(entities, config, pos, msg_log),
         EntityName::GateTrigger => make_gate_trigger(entities, config, pos, msg_log),
         EntityName::Exit => make_exit(entities, config, pos, msg_log),
@@ -558,6 +723,7 @@ pub fn make_entity(entities: &mut Entities, config: &Config, entity_name: Entity
         EntityName::Thumper => make_thumper(entities, config, pos, msg_log),
         EntityName::Teleporter => make_teleporter(entities, config, pos, msg_log),
         EntityName::Sling => make_sling(entities, config, pos, msg_log),
+        EntityName::Boomerang => make_boomerang(entities, config, pos, msg_log),
         EntityName::Herb => make_herb(entities, config, pos, msg_log),
         EntityName::SeedCache => make_seed_cache(entities, config, pos, msg_log),
         _ => {
@@ -708,7 +874,7 @@ pub fn make_island(level: &mut Level,
     let edge_pos = edge_positions[rng_range_u32(rng, 0, edge_positions.len() as u32) as usize];
 
     // make the random edge position the exit
-    level.map.tiles[edge_pos.x as usize][edge_pos.y as usize] = Tile::exit();
+    level.map[edge_pos] = Tile::exit();
 
     /* Ensure that objects placed outside of the island are removed */
     for pos in water_tile_positions {
@@ -908,3 +1074,34 @@ pub fn make_trap_test_map(entities: &mut Entities,
 
     return (map, Pos::from(position));
 }
+
+pub fn make_tutorial_map(entities: &mut Entities,
+                         config: &Config,
+                         msg_log: &mut MsgLog) -> (Map, Pos) {
+    let mut map = Map::from_dims(20, 9);
+    let position = (1, 4);
+
+    // walls along the top and bottom, with a short wall to duck behind
+    for x in 0..20 {
+        map[(x, 0)] = Tile::wall();
+        map[(x, 8)] = Tile::wall();
+    }
+    map[(12, 3)].left_wall = Wall::ShortWall;
+    map[(12, 4)].left_wall = Wall::ShortWall;
+    map[(12, 5)].left_wall = Wall::ShortWall;
+
+    // the first hint is given immediately, the rest are given when stepped on
+    msg_log.log(Msg::Hint(0));
+    make_hint_trigger(entities, config, Pos::new(3, 4), 1, msg_log);
+    make_dagger(entities, config, Pos::new(6, 4), msg_log);
+    make_hint_trigger(entities, config, Pos::new(5, 4), 2, msg_log);
+    make_hint_trigger(entities, config, Pos::new(8, 4), 3, msg_log);
+
+    let gol = make_gol(entities, config, Pos::new(15, 2), msg_log);
+    entities.direction[&gol] = Direction::Up;
+    make_hint_trigger(entities, config, Pos::new(10, 4), 4, msg_log);
+
+    make_hint_trigger(entities, config, Pos::new(17, 4), 5, msg_log);
+
+    return (map, Pos::from(position));
+}
@@ -20,6 +20,7 @@ const SKILL_KEYS: &[char] = &['a', 's', 'd', 'f'];
 const ITEM_KEYS: &[char] = &['z', 'x', 'c'];
 const CLASSES: &[ItemClass] = &[ItemClass::Primary, ItemClass::Consumable, ItemClass::Misc];
 const DEBUG_TOGGLE_KEY: char = '\\';
+const SLOW_MOTION_KEY: char = '`';
 
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Ord, PartialOrd, Display, FromStr, Serialize, Deserialize)]
 #[display(style = "lowercase")]
@@ -96,6 +97,7 @@ pub enum InputEvent {
     Alt(KeyDir),
     Enter(KeyDir),
     MousePos(i32, i32),
+    MouseWheel(i32), // scroll amount, positive away from the user
     MouseButton(MouseClick, Pos, KeyDir), // button clicked, mouse position, keydir
     Esc,
     Tab,
@@ -127,8 +129,28 @@ impl Input {
         };
     }
 
-    pub fn action_mode(&self) -> ActionMode {
-        if self.ctrl {
+    /// Whether the modifier key for running is held. This is shift, or ctrl
+    /// when the move modifiers are inverted.
+    pub fn run_modifier(&self, config: &Config) -> bool {
+        if config.invert_move_modifiers {
+            return self.ctrl;
+        } else {
+            return self.shift;
+        }
+    }
+
+    /// Whether the modifier key for sneaking is held. This is ctrl, or shift
+    /// when the move modifiers are inverted.
+    pub fn sneak_modifier(&self, config: &Config) -> bool {
+        if config.invert_move_modifiers {
+            return self.shift;
+        } else {
+            return self.ctrl;
+        }
+    }
+
+    pub fn action_mode(&self, config: &Config) -> ActionMode {
+        if self.sneak_modifier(config) {
             return ActionMode::Alternate;
         } else {
             return ActionMode::Primary;
@@ -163,6 +185,10 @@ impl Input {
                 // we don't use the mouse position within the game
             }
 
+            InputEvent::MouseWheel(_) => {
+                // the mouse wheel only zooms the display
+            }
+
             InputEvent::Quit => {
                 action = InputAction::ForceExit;
             }
@@ -172,7 +198,16 @@ impl Input {
             }
 
             InputEvent::Tab => {
-                action = InputAction::CursorReturn;
+                if settings.state == GameState::Use {
+                    // in use-mode tab cycles through the targets that can be hit
+                    if self.shift {
+                        action = InputAction::PrevTarget;
+                    } else {
+                        action = InputAction::NextTarget;
+                    }
+                } else {
+                    action = InputAction::CursorReturn;
+                }
             }
 
             InputEvent::Enter(dir) => {
@@ -186,8 +221,15 @@ impl Input {
                     self.ctrl = dir == KeyDir::Down;
                 }
 
+                let mode_action =
+                    if config.invert_move_modifiers {
+                        InputAction::Run
+                    } else {
+                        InputAction::Sneak
+                    };
+
                 match dir {
-                    KeyDir::Down => action = InputAction::Sneak,
+                    KeyDir::Down => action = mode_action,
                     KeyDir::Up => action = InputAction::Walk,
                     _ => {},
                 }
@@ -198,8 +240,15 @@ impl Input {
                     self.shift = dir == KeyDir::Down;
                 }
 
+                let mode_action =
+                    if config.invert_move_modifiers {
+                        InputAction::Sneak
+                    } else {
+                        InputAction::Run
+                    };
+
                 match dir {
-                    KeyDir::Down => action = InputAction::Run,
+                    KeyDir::Down => action = mode_action,
                     KeyDir::Up => action = InputAction::Walk,
                     _ => {},
                 }
@@ -230,11 +279,11 @@ impl Input {
     fn handle_char(&mut self, chr: char, dir: KeyDir, ticks: u32, settings: &Settings, config: &Config) -> InputAction {
         match dir {
             KeyDir::Up => {
-                return self.handle_char_up(chr, settings);
+                return self.handle_char_up(chr, settings, config);
             }
 
             KeyDir::Down => {
-                return self.handle_char_down(chr, settings);
+                return self.handle_char_down(chr, settings, config);
             }
 
             KeyDir::Held => {
@@ -243,7 +292,7 @@ impl Input {
         }
     }
 
-    fn handle_char_up(&mut self, chr: char, settings: &Settings) -> InputAction {
+    fn handle_char_up(&mut self, chr: char, settings: &Settings, config: &Config) -> InputAction {
         if let Some(index) = self.char_down_order.iter().position(|c| *c == chr) {
             self.char_down_order.remove(index);
         }
@@ -273,7 +322,7 @@ impl Input {
             } else if let Some(_index) = get_skill_index(chr) {
                 // Releasing a skill key does not take you out of use-mode.
             } else {
-                return self.apply_char(chr, settings);
+                return self.apply_char(chr, settings, config);
             }
 
             return InputAction::None;
@@ -283,7 +332,7 @@ impl Input {
                 self.clear_char_state(chr);
                 return InputAction::None;
             } else {
-                let action: InputAction = self.apply_char(chr, settings);
+                let action: InputAction = self.apply_char(chr, settings, config);
 
                 self.clear_char_state(chr);
 
@@ -292,7 +341,7 @@ impl Input {
         }
     }
 
-    fn handle_char_down_use_mode(&mut self, chr: char, _settings: &Settings) -> InputAction {
+    fn handle_char_down_use_mode(&mut self, chr: char, _settings: &Settings, config: &Config) -> InputAction {
         let mut action = InputAction::None;
 
         if let Some(input_dir) = InputDirection::from_chr(chr) {
@@ -321,25 +370,36 @@ impl Input {
                 self.target = None;
             } else {
                 self.target = Some(Target::Skill(index));
-                action = InputAction::StartUseSkill(index, self.action_mode());
+                action = InputAction::StartUseSkill(index, self.action_mode(config));
             }
         }
 
         return action;
     }
 
-    fn handle_char_down(&mut self, chr: char, settings: &Settings) -> InputAction {
+    fn handle_char_down(&mut self, chr: char, settings: &Settings, config: &Config) -> InputAction {
         // intercept debug toggle so it is not part of the regular control flow.
         if chr == DEBUG_TOGGLE_KEY {
             return InputAction::DebugToggle;
         }
 
+        if chr == SLOW_MOTION_KEY {
+            return InputAction::SlowMotionToggle;
+        }
+
         let mut action = InputAction::None;
 
         self.char_down_order.push(chr);
 
         if settings.state == GameState::Use {
-            action = self.handle_char_down_use_mode(chr, settings);
+            action = self.handle_char_down_use_mode(chr, settings, config);
+        } else if settings.state == GameState::FreeCamera {
+            // only directions are used to move the free camera- items and skills can not be used.
+            if chr == 'o' {
+                action = InputAction::OverlayToggle;
+            } else if let Some(input_dir) = InputDirection::from_chr(chr) {
+                self.direction = Some(input_dir);
+            }
         } else if !settings.state.is_menu() {
             if chr == 'o' {
                 action = InputAction::OverlayToggle;
@@ -347,18 +407,24 @@ impl Input {
                 action = InputAction::CursorToggle;
             } else if let Some(input_dir) = InputDirection::from_chr(chr) {
                 self.direction = Some(input_dir);
-            } else if !(settings.is_cursor_mode() && self.ctrl) {
+            } else if !(settings.is_cursor_mode() && self.sneak_modifier(config)) {
                 if let Some(index) = get_item_index(chr) {
                     let item_class = CLASSES[index];
-                    self.target = Some(Target::Item(item_class));
 
-                    action = InputAction::StartUseItem(item_class);
-                    // directions are cleared when entering use-mode
-                    self.direction = None;
+                    if self.shift {
+                        // shift with an item key cycles which item fills that slot
+                        action = InputAction::CycleSlot(item_class);
+                    } else {
+                        self.target = Some(Target::Item(item_class));
+
+                        action = InputAction::StartUseItem(item_class);
+                        // directions are cleared when entering use-mode
+                        self.direction = None;
+                    }
                 } else if let Some(index) = get_skill_index(chr) {
                     self.target = Some(Target::Skill(index));
 
-                    action = InputAction::StartUseSkill(index, self.action_mode());
+                    action = InputAction::StartUseSkill(index, self.action_mode(config));
                     // directions are cleared when entering use-mode
                     self.direction = None;
                 } else if let Some(index) = get_talent_index(chr) {
@@ -386,13 +452,15 @@ impl Input {
 
                 let new_repeats = (time_since as f32 / config.repeat_delay) as usize;
                 if new_repeats > held_state.repetitions {
-                    action = self.apply_char(chr, settings);
+                    action = self.apply_char(chr, settings, config);
 
                     if action == InputAction::OverlayToggle ||
+                       action == InputAction::ToggleItemHighlight ||
                        action == InputAction::Inventory     ||
                        action == InputAction::SkillMenu     ||
                        action == InputAction::Exit          ||
                        action == InputAction::CursorToggle  ||
+                       action == InputAction::FreeCamera    ||
                        action == InputAction::ClassMenu {
                         action = InputAction::None;
                     } else {
@@ -430,7 +498,7 @@ impl Input {
         }
     }
 
-    fn apply_char(&mut self, chr: char, settings: &Settings) -> InputAction {
+    fn apply_char(&mut self, chr: char, settings: &Settings, config: &Config) -> InputAction {
         let mut action: InputAction = InputAction::None;
 
         // check if the key being released is the one that set the input direction.
@@ -439,14 +507,14 @@ impl Input {
                 match input_dir {
                     InputDirection::Dir(dir) => {
                         if settings.is_cursor_mode() {
-                           action = InputAction::CursorMove(dir, self.ctrl, self.shift);
+                           action = InputAction::CursorMove(dir, self.sneak_modifier(config), self.run_modifier(config));
                         } else {
                             action = InputAction::Move(dir);
                         }
                     }
 
                     InputDirection::Current => {
-                        if settings.is_cursor_mode() && self.ctrl {
+                        if settings.is_cursor_mode() && self.sneak_modifier(config) {
                            action = InputAction::CursorReturn;
                         } else {
                             action = InputAction::Pass;
@@ -462,6 +530,9 @@ impl Input {
                     let item_class = CLASSES[index];
                     let cursor_pos = settings.cursor.unwrap();
                     action = InputAction::ThrowItem(cursor_pos, item_class);
+                } else if chr == 'u' {
+                    // in cursor mode, undo steps the cursor back instead of undoing a turn
+                    action = InputAction::CursorUndo;
                 }
             }
 
@@ -487,6 +558,18 @@ pub fn menu_alpha_up_to_action(chr: char, shift: bool) -> InputAction {
             input_action = InputAction::Exit;
         }
 
+        'm' => {
+            input_action = InputAction::ReturnToMenu(true);
+        }
+
+        'n' => {
+            input_action = InputAction::ReturnToMenu(false);
+        }
+
+        'e' => {
+            input_action = InputAction::ExportReplay;
+        }
+
         'i' => {
             input_action = InputAction::Inventory;
         }
@@ -511,6 +594,10 @@ pub fn menu_alpha_up_to_action(chr: char, shift: bool) -> InputAction {
             input_action = InputAction::ClassMenu;
         }
 
+        'b' => {
+            input_action = InputAction::BestiaryMenu;
+        }
+
         '/' => {
             // shift + / = ?
             if shift {
@@ -540,6 +627,10 @@ pub fn alpha_up_to_action(chr: char, shift: bool) -> InputAction {
             input_action = InputAction::Pickup;
         }
 
+        'u' => {
+            input_action = InputAction::UndoTurn;
+        }
+
         'i' => {
             input_action = InputAction::Inventory;
         }
@@ -548,6 +639,14 @@ pub fn alpha_up_to_action(chr: char, shift: bool) -> InputAction {
             input_action = InputAction::Yell;
         }
 
+        'v' => {
+            input_action = InputAction::ToggleItemHighlight;
+        }
+
+        'k' => {
+            input_action = InputAction::FreeCamera;
+        }
+
         'l' => {
             input_action = InputAction::ExploreAll;
         }
@@ -568,6 +667,10 @@ pub fn alpha_up_to_action(chr: char, shift: bool) -> InputAction {
             input_action = InputAction::ClassMenu;
         }
 
+        'b' => {
+            input_action = InputAction::BestiaryMenu;
+        }
+
         '/' => {
             // shift + / = ?
             if shift {
@@ -607,6 +710,11 @@ fn get_item_index(chr: char) -> Option<usize> {
     return  ITEM_KEYS.iter().position(|key| *key == chr);
 }
 
+/// The class of item used by an item key, such as 'z' for the primary item.
+pub fn item_key_class(chr: char) -> Option<ItemClass> {
+    return get_item_index(chr).map(|index| CLASSES[index]);
+}
+
 fn get_skill_index(chr: char) -> Option<usize> {
     return  SKILL_KEYS.iter().position(|key| *key == chr);
 }
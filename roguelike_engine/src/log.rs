@@ -1,9 +1,12 @@
 use std::fmt;
 use std::fs::File;
-use std::io::{stdout, Write};
+use std::io::{stdout, Write, BufRead, BufReader};
+use std::str::FromStr;
 
 use serde::{Serialize, Deserialize};
 
+use roguelike_map::MapLoadConfig;
+
 use crate::actions::*;
 
 pub struct Log {
@@ -15,6 +18,9 @@ pub struct Log {
 impl Log {
     pub const ACTION_LOG_NAME: &'static str = "action_log.txt";
     pub const MESSAGE_LOG_NAME: &'static str = "message_log.txt";
+    pub const RNG_STATE_PREFIX: &'static str = "rng_state";
+    pub const RUN_START_PREFIX: &'static str = "run_start";
+    pub const SAVE_LOADED_LINE: &'static str = "save_loaded";
 
     pub fn new() -> Log {
         let action_log = File::create(Log::ACTION_LOG_NAME)
@@ -32,12 +38,63 @@ impl Log {
     }
 
     pub fn log_action(&mut self, input_action: InputAction) {
-        if input_action != InputAction::None {
+        // exporting a replay does not change the game, so it is not part of the replay.
+        // Undoing a turn is logged so a replay undoes the same turn.
+        if input_action != InputAction::None &&
+           input_action != InputAction::ExportReplay {
             self.action_log.write(input_action.to_string().as_bytes()).unwrap();
             self.action_log.write("\n".as_bytes()).unwrap();
         }
     }
 
+    /// Record the random number generator's state at the start of the action log, so a
+    /// replay that starts from a save can check that it matches the recording.
+    pub fn log_rng_state(&mut self, state: (u64, u64)) {
+        self.action_log.write(format!("{} {} {}\n", Log::RNG_STATE_PREFIX, state.0, state.1).as_bytes()).unwrap();
+    }
+
+    /// Mark the start of a new run in the action log, along with the seed it started from.
+    /// A session can contain several runs, and only the last one is exported.
+    pub fn log_run_start(&mut self, seed: u64) {
+        self.action_log.write(format!("{} {}\n", Log::RUN_START_PREFIX, seed).as_bytes()).unwrap();
+    }
+
+    /// Mark that a save was loaded, after which the actions can not be replayed from a seed.
+    pub fn log_save_loaded(&mut self) {
+        self.action_log.write(format!("{}\n", Log::SAVE_LOADED_LINE).as_bytes()).unwrap();
+    }
+
+    /// Read the seed and actions of the last run recorded in an action log. Lines that are
+    /// not actions, such as the random number generator state, are skipped.
+    pub fn read_run(file_name: &str) -> Result<(u64, Vec<InputAction>), String> {
+        let file = File::open(file_name).map_err(|err| format!("Could not open '{}': {}", file_name, err))?;
+
+        let mut seed = None;
+        let mut actions = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|err| err.to_string())?;
+
+            if let Some(run_seed) = line.strip_prefix(Log::RUN_START_PREFIX) {
+                seed = run_seed.trim().parse::<u64>().ok();
+                actions.clear();
+            } else if line == Log::SAVE_LOADED_LINE {
+                seed = None;
+                actions.clear();
+            } else if let Ok(action) = InputAction::from_str(&line) {
+                actions.push(action);
+            }
+        }
+
+        let seed = seed.ok_or("the current run was continued from a save, so it can not be replayed from its seed".to_string())?;
+        return Ok((seed, actions));
+    }
+
+    /// Pack the actions of the current run into a replay string for the given map.
+    pub fn export_replay(map_config: &MapLoadConfig) -> Result<String, String> {
+        let (seed, actions) = Log::read_run(Log::ACTION_LOG_NAME)?;
+        return Ok(ReplayString::new(seed, map_config.clone(), actions).encode());
+    }
+
     pub fn log_output(&mut self, log_message: &str) {
         self.log(LogMsgType::Output, log_message);
     }
@@ -95,3 +152,122 @@ impl fmt::Display for LogMsgType {
     }
 }
 
+
+
+/// A run's seed, map configuration, and actions packed into a single string that can be
+/// pasted into a bug report and replayed with '--replay-string'.
+///
+/// The string is 'rlreplay', the format version, a ':', and then the base64 of a text
+/// payload. The payload has a 'seed' line, a 'map' line, one line per action, and ends
+/// with a CRC32 line so a truncated or mistyped string is caught when decoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayString {
+    pub seed: u64,
+    pub map_config: MapLoadConfig,
+    pub actions: Vec<InputAction>,
+}
+
+impl ReplayString {
+    pub const PREFIX: &'static str = "rlreplay";
+    pub const VERSION: u32 = 1;
+
+    pub fn new(seed: u64, map_config: MapLoadConfig, actions: Vec<InputAction>) -> ReplayString {
+        return ReplayString { seed, map_config, actions };
+    }
+
+    pub fn encode(&self) -> String {
+        let mut payload = String::new();
+        payload.push_str(&format!("seed {}\n", self.seed));
+        payload.push_str(&format!("map {}\n", self.map_config));
+        for action in self.actions.iter() {
+            payload.push_str(&format!("{}\n", action));
+        }
+        payload.push_str(&format!("checksum {:08x}", crc32fast::hash(payload.as_bytes())));
+
+        return format!("{}{}:{}", ReplayString::PREFIX, ReplayString::VERSION, base64::encode(payload.as_bytes()));
+    }
+
+    pub fn decode(string: &str) -> Result<ReplayString, String> {
+        let string = string.trim();
+
+        let rest = string.strip_prefix(ReplayString::PREFIX)
+                         .ok_or(format!("not a replay string, expected it to start with '{}'", ReplayString::PREFIX))?;
+        let (version, encoded) = rest.split_once(':')
+                                     .ok_or("replay string is missing its version".to_string())?;
+        let version = version.parse::<u32>()
+                             .map_err(|_| format!("replay string has an invalid version '{}'", version))?;
+        if version != ReplayString::VERSION {
+            return Err(format!("replay string is version {}, but only version {} is supported", version, ReplayString::VERSION));
+        }
+
+        let bytes = base64::decode(encoded).map_err(|err| format!("replay string is corrupted: {}", err))?;
+        let payload = String::from_utf8(bytes).map_err(|_| "replay string is corrupted: payload is not text".to_string())?;
+
+        let (body, checksum_line) = payload.rsplit_once('\n')
+                                           .ok_or("replay string is corrupted: payload is incomplete".to_string())?;
+        let body = format!("{}\n", body);
+        let expected = checksum_line.strip_prefix("checksum ")
+                                    .and_then(|value| u32::from_str_radix(value, 16).ok())
+                                    .ok_or("replay string is corrupted: checksum is missing".to_string())?;
+        if crc32fast::hash(body.as_bytes()) != expected {
+            return Err("replay string is corrupted: checksum does not match".to_string());
+        }
+
+        let mut lines = body.lines();
+        let seed = lines.next()
+                        .and_then(|line| line.strip_prefix("seed "))
+                        .and_then(|seed| seed.parse::<u64>().ok())
+                        .ok_or("replay string has no seed".to_string())?;
+        let map_config = lines.next()
+                              .and_then(|line| line.strip_prefix("map "))
+                              .and_then(|map| map.parse::<MapLoadConfig>().ok())
+                              .ok_or("replay string has no map configuration".to_string())?;
+
+        let mut actions = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let action = InputAction::from_str(line)
+                                     .map_err(|_| format!("replay string has an unknown action '{}' at action {}", line, index))?;
+            actions.push(action);
+        }
+
+        return Ok(ReplayString::new(seed, map_config, actions));
+    }
+}
+
+#[test]
+pub fn test_replay_string_round_trip() {
+    use roguelike_map::Direction;
+
+    let actions = vec!(InputAction::Move(Direction::Right), InputAction::Pass, InputAction::StartUseItem(roguelike_core::types::ItemClass::Primary));
+    let replay = ReplayString::new(12345, MapLoadConfig::Empty, actions);
+
+    let string = replay.encode();
+    assert!(string.starts_with("rlreplay1:"));
+    assert_eq!(Ok(replay.clone()), ReplayString::decode(&string));
+
+    // a changed character is caught by the checksum, and newer versions are reported as such
+    let mut corrupted = string.clone().into_bytes();
+    let last = corrupted.len() - 3;
+    corrupted[last] = if corrupted[last] == b'A' { b'B' } else { b'A' };
+    assert!(ReplayString::decode(&String::from_utf8(corrupted).unwrap()).is_err());
+    assert!(ReplayString::decode(&string.replacen("rlreplay1", "rlreplay2", 1)).unwrap_err().contains("version 2"));
+    assert!(ReplayString::decode("not a replay").is_err());
+}
+
+#[test]
+pub fn test_read_run_uses_last_run() {
+    use roguelike_map::Direction;
+
+    let file_name = std::env::temp_dir().join("test_read_run_uses_last_run.txt");
+    let file_name = file_name.to_str().unwrap();
+
+    // only the actions after the last run started belong to it, with that run's seed
+    std::fs::write(file_name, "rng_state 1 2\nrun_start 10\npass\nrun_start 20\nright\n").unwrap();
+    let (seed, actions) = Log::read_run(file_name).unwrap();
+    assert_eq!(20, seed);
+    assert_eq!(vec!(InputAction::Move(Direction::Right)), actions);
+
+    // a run continued from a save can not be replayed from its seed
+    std::fs::write(file_name, "run_start 10\npass\nsave_loaded\npass\n").unwrap();
+    assert!(Log::read_run(file_name).is_err());
+}
@@ -1,5 +1,6 @@
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
 
 use roguelike_utils::math::*;
 
@@ -22,6 +23,11 @@ pub fn map_construct(map_load_config: &MapLoadConfig, game: &mut Game) {
     game.clear_level_except_player();
     game.settings.map_load_config = map_load_config.clone();
 
+    // later levels are generated from the advanced random number generator rather than the
+    // game's seed, so its state is what identifies the map
+    let (rng_state, rng_inc) = game.rng.state();
+    game.settings.map_seed = rng_state ^ rng_inc;
+
     match map_load_config {
         MapLoadConfig::TestMap => {
             game.level.map = Map::from_dims(11, 12);
@@ -52,6 +58,12 @@ pub fn map_construct(map_load_config: &MapLoadConfig, game: &mut Game) {
             player_position = player_pos;
         }
 
+        MapLoadConfig::Symmetric(procgen_params, axis) => {
+            let (player_pos, _) = procgen(procgen_params, game);
+            mirror_level(*axis, game);
+            player_position = player_pos;
+        }
+
         MapLoadConfig::TestVaults => {
             player_position = Pos::new(0, 0);
 
@@ -138,6 +150,33 @@ pub fn map_construct(map_load_config: &MapLoadConfig, game: &mut Game) {
             player_position = position;
         }
 
+        MapLoadConfig::Tutorial => {
+            let (new_map, position) = make_tutorial_map(&mut game.level.entities, &game.config, &mut game.msg_log);
+            game.level.map = new_map;
+            player_position = position;
+        }
+
+        MapLoadConfig::TextFile(file_name) => {
+            // an absolute path is used as given, rather than within resources
+            let file_name = Path::new("resources").join(file_name).display().to_string();
+            let loaded = std::fs::read_to_string(&file_name)
+                                 .map_err(|err| err.to_string())
+                                 .and_then(|map_text| read_map_text(&map_text, game));
+            match loaded {
+                Ok(position) => {
+                    player_position = position;
+                }
+
+                Err(err) => {
+                    // a missing or invalid map leaves the level untouched, so the empty map is loaded
+                    // in its place, and recorded as the map that was loaded.
+                    eprintln!("Could not load map {}: {}. Loading the empty map instead.", file_name, err);
+                    map_construct(&MapLoadConfig::Empty, game);
+                    return;
+                }
+            }
+        }
+
         MapLoadConfig::TestGen(procgen_file) => {
             player_position = Pos::new(0, 0);
             for index in 0..1000 {
@@ -150,21 +189,87 @@ pub fn map_construct(map_load_config: &MapLoadConfig, game: &mut Game) {
         }
     }
 
+    // Generated levels never place disabled entities, but fixed maps and test maps
+    // place their entities directly, so any disabled entities are removed here.
+    remove_disabled_entities(game);
+
     let player_id = game.level.find_by_name(EntityName::Player).unwrap();
     game.msg_log.log(Msg::SetPos(player_id, player_position));
     game.level.entities.set_pos(player_id, player_position);
+    game.settings.level_start = player_position;
+
+    for item in std::mem::take(&mut game.settings.starting_drops) {
+        make_item(&mut game.level.entities, &game.config, item, player_position, &mut game.msg_log);
+    }
 
     /* Create a file measuring the emptyness of the generated level */
     if game.config.write_map_distribution {
         write_map_distribution(game);
     }
 
-    game.settings.exit_condition = LevelExitCondition::RightEdge;
+    // levels with a key and an exit, such as generated levels, can require carrying the key
+    // to the exit. Without both, the level is left by walking off its right edge.
+    let has_key = game.level.find_by_name(EntityName::Key).is_some();
+    let has_exit = game.level.find_by_name(EntityName::Exit).is_some();
+    if game.config.objective_exit && has_key && has_exit {
+        game.settings.exit_condition = LevelExitCondition::KeyAndGoal;
+    } else {
+        game.settings.exit_condition = LevelExitCondition::RightEdge;
+    }
+
+    game.apply_map_config();
+
+    match map_load_config {
+        // generated maps already scale their enemy counts during placement
+        MapLoadConfig::ProcGen(_) | MapLoadConfig::TestGen(_) | MapLoadConfig::Symmetric(_, _) => {}
+
+        // fixed maps have a fixed number of enemies, so scale their hp instead
+        _ => scale_enemy_hp(game),
+    }
 
     game.msg_log.log(Msg::NewLevel);
     game.settings.map_changed = true;
 }
 
+fn remove_disabled_entities(game: &mut Game) {
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    for entity_id in game.level.entities.ids.clone() {
+        // the player and their inventory carry over between levels, so they are kept.
+        // An item held by a thief is not in the inventory, so it is lost with the thief.
+        if entity_id == player_id || game.level.entities.inventory[&player_id].contains(&entity_id) {
+            continue;
+        }
+
+        if game.config.entity_disabled(game.level.entities.name[&entity_id]) {
+            game.msg_log.log(Msg::RemovedEntity(entity_id));
+            game.level.entities.remove_entity(entity_id);
+        }
+    }
+}
+
+fn scale_enemy_hp(game: &mut Game) {
+    let difficulty = game.config.difficulty;
+    if difficulty == 1.0 {
+        return;
+    }
+
+    for entity_id in game.level.entities.ids.clone() {
+        if game.level.entities.typ[&entity_id] != EntityType::Enemy {
+            continue;
+        }
+
+        if let Some(hp) = game.level.entities.hp.get(&entity_id).map(|hp| *hp) {
+            let max_hp = std::cmp::max(1, (hp.max_hp as f32 * difficulty).round() as i32);
+            let new_hp = std::cmp::min(max_hp, std::cmp::max(1, (hp.hp as f32 * difficulty).round() as i32));
+
+            game.level.entities.hp[&entity_id].max_hp = max_hp;
+            game.level.entities.hp[&entity_id].hp = new_hp;
+            game.msg_log.log(Msg::Healed(entity_id, new_hp - hp.hp, max_hp));
+        }
+    }
+}
+
 fn procgen(procgen_file: &str, game: &mut Game) -> (Pos, bool) {
     let file_name = format!("resources/procgen/{}", procgen_file);
     let cmds = ProcCmd::from_file(&file_name);
@@ -177,9 +282,76 @@ fn procgen(procgen_file: &str, game: &mut Game) -> (Pos, bool) {
     }
 
     game.level.map = generate_bare_map(MAP_WIDTH as u32, MAP_HEIGHT as u32, &template_file, &mut game.rng);
+    // placement checks paths through the level, so the map needs its settings first
+    game.apply_map_config();
     return saturate_map(game, &cmds);
 }
 
+/// Mirror a generated level to make a symmetric arena. The mirrored half of the map is
+/// replaced by a reflection of the other half, along with the entities placed there.
+/// The player, key and exit are unique so they are never duplicated, and entities on the
+/// axis itself are their own reflection.
+fn mirror_level(axis: MirrorAxis, game: &mut Game) {
+    mirror_map_half(&mut game.level.map, axis);
+
+    let (width, height) = game.level.map.size();
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    for entity_id in game.level.entities.ids.clone() {
+        if entity_id == player_id || game.level.entities.inventory[&player_id].contains(&entity_id) {
+            continue;
+        }
+
+        let pos = game.level.entities.pos[&entity_id];
+        let name = game.level.entities.name[&entity_id];
+        let unique = matches!(name, EntityName::Key | EntityName::Exit);
+
+        if axis.in_mirrored_half(pos, width, height) {
+            if !unique {
+                // the tile this entity was placed on has been overwritten.
+                game.msg_log.log(Msg::RemovedEntity(entity_id));
+                game.level.entities.remove_entity(entity_id);
+            }
+            continue;
+        }
+
+        let mirror_pos = axis.mirror(pos, width, height);
+        if unique || mirror_pos == pos {
+            continue;
+        }
+
+        let mirror_id = match name {
+            EntityName::Grass => ensure_grass(&mut game.level, mirror_pos, &mut game.msg_log),
+            EntityName::Rook => make_rook(&mut game.level.entities, &game.config, mirror_pos, &mut game.msg_log),
+            EntityName::Armil => make_armil(&mut game.level.entities, &game.config, mirror_pos, &mut game.msg_log),
+            EntityName::Statue => make_statue(&mut game.level.entities, &game.config, mirror_pos, &mut game.msg_log),
+
+            // entities that need more than a name and position to create are not mirrored.
+            EntityName::HintTrigger | EntityName::Mouse | EntityName::Smoke |
+            EntityName::Fire | EntityName::Magnifier | EntityName::Other => continue,
+
+            _ => make_entity(&mut game.level.entities, &game.config, name, mirror_pos, &mut game.msg_log),
+        };
+        if let Some(direction) = game.level.entities.direction.get(&entity_id).map(|dir| *dir) {
+            let mirror_dir = direction.mirror(axis);
+            game.level.entities.direction.insert(mirror_id, mirror_dir);
+            game.msg_log.log(Msg::Facing(mirror_id, mirror_dir));
+        }
+    }
+
+    // the unique entities keep their place, so make sure the mirrored map does not bury them.
+    for entity_id in game.level.entities.ids.clone() {
+        let pos = game.level.entities.pos[&entity_id];
+        let name = game.level.entities.name[&entity_id];
+        if matches!(name, EntityName::Player | EntityName::Key | EntityName::Exit) &&
+           game.level.map.is_within_bounds(pos) &&
+           axis.in_mirrored_half(pos, width, height) &&
+           game.level.map[pos].block_move {
+            game.level.map[pos] = Tile::empty();
+        }
+    }
+}
+
 fn write_map_distribution(game: &mut Game) {
     let max = (2 * TILE_FILL_METRIC_DIST + 1).pow(2);
     let mut counts = vec![0; max + 1];
@@ -195,6 +367,133 @@ fn write_map_distribution(game: &mut Game) {
     }
 }
 
+/// Build the level from a plain-text map, one line per row of tiles, returning the player's position.
+/// Floor is '.', walls are '#', grass is '"', tall grass is ';', water is '~',
+/// the player is '@', a gol is 'g', a pawn is 'p', a column is 'c', a stone is 'o',
+/// a key is 'k' and an exit is 'e'.
+/// Leading and trailing whitespace and blank lines are ignored. Any other character
+/// is reported with its line and column.
+/// A line 'sprite x y name' draws the entity placed at (x, y) with the named sprite,
+/// allowing reskinned entities without a new entity name.
+pub fn read_map_text(map_text: &str, game: &mut Game) -> Result<Pos, String> {
+    let (sprite_lines, rows): (Vec<(usize, &str)>, Vec<(usize, &str)>) =
+        map_text.lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .partition(|(_, line)| line.trim().starts_with("sprite "));
+
+    // check the whole map before changing the level so a bad map leaves it untouched.
+    let mut sprite_overrides = Vec::new();
+    for (line_index, line) in sprite_lines.iter() {
+        let sprite_override = parse_sprite_override(line.trim())
+            .ok_or(format!("invalid sprite override at line {}, expected 'sprite x y name'", line_index + 1))?;
+        sprite_overrides.push(sprite_override);
+    }
+
+    for (line_index, line) in rows.iter() {
+        let indent = line.chars().take_while(|chr| chr.is_whitespace()).count();
+        for (col_index, chr) in line.trim().chars().enumerate() {
+            if !MAP_TEXT_TILES.contains(chr) && chr != '@' && map_text_entity(chr).is_none() {
+                return Err(format!("unexpected character '{}' at line {}, column {}", chr, line_index + 1, indent + col_index + 1));
+            }
+        }
+    }
+
+    let rows = rows.iter().map(|(_, line)| line.trim()).collect::<Vec<&str>>();
+
+    if !rows.iter().any(|row| row.contains('@')) {
+        return Err("map has no player position".to_string());
+    }
+
+    for (pos, _) in sprite_overrides.iter() {
+        let chr = rows.get(pos.y as usize).and_then(|row| row.chars().nth(pos.x as usize));
+        if chr != Some('@') && chr.and_then(map_text_entity).is_none() {
+            return Err(format!("sprite override at ({}, {}) has no entity", pos.x, pos.y));
+        }
+    }
+
+    let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+    game.level.map = Map::from_dims(width as u32, rows.len() as u32);
+    game.apply_map_config();
+
+    let mut player_pos = Pos::new(0, 0);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, chr) in row.chars().enumerate() {
+            let pos = Pos::new(x as i32, y as i32);
+            match chr {
+                '#' => game.level.map[pos] = Tile::wall(),
+
+                '~' => game.level.map[pos] = Tile::water(),
+
+                '"' => {
+                    ensure_grass(&mut game.level, pos, &mut game.msg_log);
+                }
+
+                ';' => {
+                    ensure_tall_grass(&mut game.level, pos, &mut game.msg_log);
+                }
+
+                '@' => {
+                    player_pos = pos;
+                }
+
+                _ => {
+                    if let Some(entity_name) = map_text_entity(chr) {
+                        make_entity(&mut game.level.entities, &game.config, entity_name, pos, &mut game.msg_log);
+                    }
+                }
+            }
+        }
+    }
+
+    // the player is moved to its position after the map is read, so it is matched separately.
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    for (pos, sprite_name) in sprite_overrides {
+        let entity_id =
+            if pos == player_pos {
+                Some(player_id)
+            } else {
+                game.level.entities.ids.iter().find(|id| game.level.entities.pos[id] == pos && **id != player_id).map(|id| *id)
+            };
+
+        if let Some(entity_id) = entity_id {
+            game.level.entities.sprite_override.insert(entity_id, sprite_name);
+        }
+    }
+
+    return Ok(player_pos);
+}
+
+/// The characters for tiles in a text map. The player and other entities are
+/// placed with the characters given by `map_text_entity`.
+const MAP_TEXT_TILES: &str = ".#~\";";
+
+/// The entity placed by a character in a text map, other than the player.
+fn map_text_entity(chr: char) -> Option<EntityName> {
+    match chr {
+        'g' => Some(EntityName::Gol),
+        'p' => Some(EntityName::Pawn),
+        'c' => Some(EntityName::Column),
+        'o' => Some(EntityName::Stone),
+        'k' => Some(EntityName::Key),
+        'e' => Some(EntityName::Exit),
+        _ => None,
+    }
+}
+
+/// Parse a 'sprite x y name' line into the entity position and sprite name.
+fn parse_sprite_override(line: &str) -> Option<(Pos, String)> {
+    let parts = line.split_whitespace().collect::<Vec<&str>>();
+    if parts.len() != 4 {
+        return None;
+    }
+
+    let x = parts[1].parse::<i32>().ok()?;
+    let y = parts[2].parse::<i32>().ok()?;
+
+    return Some((Pos::new(x, y), parts[3].to_string()));
+}
+
 /// Read file into a vector of lines
 pub fn parse_map_file(file_name: &str) -> Vec<String> {
     let file_contents =
@@ -380,6 +380,10 @@ fn place_items(game: &mut Game, cmds: &Vec<ProcCmd>) {
 
     for cmd in cmds.iter() {
         if let ProcCmd::Items(typ, min, max) = cmd {
+            if game.config.entity_disabled(typ.name()) {
+                continue;
+            }
+
             let num_gen = rng_range_u32(&mut game.rng, *min as u32, (*max + 1) as u32) as usize;
             for _ in 0..num_gen {
                 num_items += 1;
@@ -404,6 +408,13 @@ fn place_items(game: &mut Game, cmds: &Vec<ProcCmd>) {
                     Item::Spear => { make_spear(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
                     Item::Stone => { make_stone(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
                     Item::Lantern => { make_lantern(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::Mirror => { make_mirror(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::SwapStone => { make_swap_stone(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::Hourglass => { make_hourglass(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::SpikeTrapKit => { make_spike_trap_kit(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::FreezeTrapKit => { make_freeze_trap_kit(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::RecallStone => { make_recall_stone(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
+                    Item::Boomerang => { make_boomerang(&mut game.level.entities, &game.config, pos, &mut game.msg_log); },
                     _ => {},
                 }
             }
@@ -478,6 +489,10 @@ fn place_traps(game: &mut Game, cmds: &Vec<ProcCmd>) {
 
     for cmd in cmds.iter() {
         if let ProcCmd::Traps(typ, min, max) = cmd {
+            if game.config.entity_disabled(typ.name()) {
+                continue;
+            }
+
             let num_gen = rng_range_u32(&mut game.rng, *min as u32, (*max + 1) as u32);
             for _ in 0..num_gen {
                 num_traps += 1;
@@ -521,7 +536,12 @@ fn place_monsters(game: &mut Game, player_id: EntityId, cmds: &Vec<ProcCmd>) {
 
     for cmd in cmds.iter() {
         if let ProcCmd::Entities(typ, min, max) = cmd {
+            if game.config.entity_disabled(*typ) {
+                continue;
+            }
+
             let num_gen = rng_range_u32(&mut game.rng, *min as u32, *max as u32) as usize;
+            let num_gen = scale_enemy_count(num_gen, *min, game.config.difficulty);
 
             for _ in 0..num_gen {
                 let len = potential_pos.len();
@@ -538,13 +558,16 @@ fn place_monsters(game: &mut Game, player_id: EntityId, cmds: &Vec<ProcCmd>) {
                     EntityName::Gol => { id = Some(make_gol(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
                     EntityName::Pawn => { id = Some(make_pawn(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
                     EntityName::Spire => { id = Some(make_spire(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
+                    EntityName::Summoner => { id = Some(make_summoner(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
+                    EntityName::Thief => { id = Some(make_thief(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
                     EntityName::Armil => { id = Some(make_armil(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
                     EntityName::Rook => { id = Some(make_rook(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
+                    EntityName::Mimic => { id = Some(make_mimic(&mut game.level.entities, &game.config, pos, &mut game.msg_log)); },
                     _ => { id = None; },
                 }
                 if let Some(id) = id {
                     // if the entity can see the player, turn it around so it is facing away.
-                    if game.level.is_in_fov(id, player_id) == FovResult::Inside {
+                    if game.level.is_in_fov(id, player_id, &game.config) == FovResult::Inside {
                         game.level.entities.direction[&id] = 
                             game.level.entities.direction[&id].reverse();
                     }
@@ -559,6 +582,13 @@ fn place_monsters(game: &mut Game, player_id: EntityId, cmds: &Vec<ProcCmd>) {
     }
 }
 
+/// Scale a number of entities by the difficulty, rounding to the nearest
+/// count and never going below the given minimum.
+pub fn scale_enemy_count(count: usize, min: usize, difficulty: f32) -> usize {
+    let scaled = (count as f32 * difficulty.max(0.0)).round() as usize;
+    return std::cmp::max(min, scaled);
+}
+
 // TODO choose based on frequency given by tags
 fn place_vaults(game: &mut Game, cmds: &Vec<ProcCmd>) {
     for cmd in cmds.iter() {
@@ -661,6 +691,9 @@ pub fn place_vault_with(level: &mut Level, vault: &Vault, offset: Pos, rotation:
     }
 
     for id in entities.ids.iter() {
+        if config.entity_disabled(entities.name[id]) {
+            continue;
+        }
         make_entity(&mut level.entities, config, entities.name[id], entities.pos[id], msg_log);
     }
 }
@@ -1128,3 +1161,44 @@ pub fn add_obstacle(map: &mut Map, pos: Pos, obstacle: Obstacle, rng: &mut Rand3
     }
 }
 
+
+#[test]
+fn test_scale_enemy_count() {
+    assert_eq!(3, scale_enemy_count(3, 1, 1.0));
+    assert_eq!(6, scale_enemy_count(3, 1, 2.0));
+    assert_eq!(5, scale_enemy_count(3, 1, 1.5));
+    assert_eq!(2, scale_enemy_count(3, 1, 0.5));
+
+    // never below the minimum count
+    assert_eq!(2, scale_enemy_count(3, 2, 0.1));
+    assert_eq!(0, scale_enemy_count(3, 0, 0.0));
+}
+
+#[test]
+fn test_map_bytes_round_trip() {
+    let mut rng = Rand32::new(0);
+    let (width, height) = (roguelike_core::constants::MAP_WIDTH as u32, roguelike_core::constants::MAP_HEIGHT as u32);
+    let mut map = generate_bare_map(width, height, "../resources/wfc/wfc_seed_2.png", &mut rng);
+
+    // the bare map has only full tile walls, so add inter-tile walls and surfaces as well
+    map[(1, 1)].left_wall = Wall::ShortWall;
+    map[(1, 1)].left_material = Surface::Grass;
+    map[(2, 3)].bottom_wall = Wall::TallWall;
+    map[(2, 3)].bottom_material = Surface::Rubble;
+    map[(4, 4)] = Tile::tall_grass();
+    map[(5, 4)] = Tile::water();
+    map[(6, 4)].surface = Surface::Rubble;
+    map[(6, 4)].explored = true;
+    map.allow_corner_cutting = false;
+
+    let loaded = Map::from_bytes(&map.to_bytes()).unwrap();
+    assert_eq!(map.size(), loaded.size());
+    assert_eq!(map.allow_corner_cutting, loaded.allow_corner_cutting);
+    for pos in map.get_all_pos() {
+        assert_eq!(map[pos], loaded[pos]);
+    }
+
+    // truncated data, or data that is not a map, is rejected
+    assert!(Map::from_bytes(&map.to_bytes()[0..20]).is_err());
+    assert!(Map::from_bytes(b"not a map").is_err());
+}
@@ -19,13 +19,17 @@ use roguelike_core::utils::*;
 use roguelike_core::level::*;
 
 #[cfg(test)]
-use crate::actions::InputAction;
+use crate::actions::{InputAction, change_state};
 #[cfg(test)]
 use crate::generation::*;
 
-use crate::generation::{make_energy, make_light, ensure_grass, ensure_tall_grass, make_smoke, make_magnifier};
-use crate::game::Game;
+use crate::generation::{make_energy, make_light, ensure_grass, ensure_tall_grass, make_smoke, make_magnifier, make_gol, make_fire};
+use crate::game::{Game, Performance};
 use crate::map_construct::map_construct;
+#[cfg(test)]
+use crate::map_construct::read_map_text;
+#[cfg(test)]
+use crate::step::ai_planned_move;
 
 
 pub fn resolve_messages(game: &mut Game) {
@@ -47,6 +51,9 @@ pub fn resolve_messages(game: &mut Game) {
 
 pub fn resolve_message(game: &mut Game, msg: Msg) {
     let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    record_run_stats(game, msg, player_id);
+
     match msg {
         Msg::NewLevel => {
             resolve_new_level(game);
@@ -74,7 +81,7 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         }
 
         Msg::ReactToSound(entity_id, sound_pos) => {
-            if game.level.is_in_fov(entity_id, player_id) == FovResult::Inside {
+            if game.level.is_in_fov(entity_id, player_id, &game.config) == FovResult::Inside {
                 let player_pos = game.level.entities.pos[&player_id];
                 game.msg_log.log(Msg::StateChange(entity_id, Behavior::Alert(player_pos)));
                 game.level.entities.took_turn[&entity_id] |= Turn::Pass.turn();
@@ -88,16 +95,42 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         }
 
         Msg::ItemThrow(entity_id, item_id, start, end, hard) => {
+            game.identify_item(entity_id, item_id);
             resolve_throw_item(entity_id, item_id, start, end, hard, &mut game.level, &mut game.rng, &mut game.msg_log, &game.config);
         }
 
+        Msg::BoomerangThrow(entity_id, item_id, start, end) => {
+            resolve_boomerang_throw(entity_id, item_id, start, end, &mut game.level, &mut game.msg_log, &game.config);
+        }
+
+        Msg::BoomerangReturn(entity_id, item_id) => {
+            resolve_boomerang_return(entity_id, item_id, &mut game.level, &mut game.msg_log, &game.config);
+        }
+
+        Msg::StoneVolley(entity_id, start, end) => {
+            if try_use_energy(entity_id, Skill::StoneVolley, &mut game.level, &mut game.msg_log, &game.config) {
+                resolve_stone_volley(entity_id, start, end, game);
+                game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+            }
+        }
+
         Msg::JumpWall(entity_id, _start, end) => {
             game.msg_log.log_front(Msg::Sound(entity_id, end, game.config.sound_radius_run));
         }
 
         Msg::Blink(entity_id) => {
-            if try_use_energy(entity_id, Skill::Blink, &mut game.level, &mut game.msg_log) {
-                resolve_blink(entity_id, &mut game.level, &mut game.rng, &mut game.msg_log);
+            if try_use_energy(entity_id, Skill::Blink, &mut game.level, &mut game.msg_log, &game.config) {
+                resolve_blink(entity_id, None, &mut game.level, &mut game.rng, &mut game.msg_log);
+            }
+        }
+
+        Msg::BlinkBehind(entity_id, target_pos) => {
+            // a targeted blink that can not land fails before using energy, rather than
+            // blinking somewhere random.
+            if find_blink_behind_pos(entity_id, target_pos, &game.level).is_none() {
+                game.msg_log.log(Msg::FailedBlink(entity_id));
+            } else if try_use_energy(entity_id, Skill::Blink, &mut game.level, &mut game.msg_log, &game.config) {
+                resolve_blink(entity_id, Some(target_pos), &mut game.level, &mut game.rng, &mut game.msg_log);
             }
         }
 
@@ -113,8 +146,13 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
             remove_entity(entity_id, &mut game.level);
         }
 
-        Msg::Killed(_attacker, attacked, _damage) => {
-            resolve_killed_entity(attacked, &mut game.level, &mut game.msg_log, &game.config);
+        Msg::Killed(attacker, attacked, _damage) => {
+            // the killing blow is always attributed, even for deaths that do not go through
+            // take_damage such as being crushed.
+            if let Some(status) = game.level.entities.status.get_mut(&attacked) {
+                status.last_damager = Some(attacker);
+            }
+            resolve_killed_entity(attacked, &mut game.level, &mut game.rng, &mut game.msg_log, &game.config);
         }
 
         Msg::Attack(attacker, attacked, _damage) => {
@@ -124,7 +162,7 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
 
         Msg::HammerRaise(entity_id, item_index, dir) => {
             let item_id = game.level.entities.inventory[&entity_id][item_index];
-            game.level.entities.status[&entity_id].hammer_raised = Some((item_id, dir, 1));
+            game.level.entities.status[&entity_id].charged_attack = Some((item_id, dir, 1));
             game.level.entities.took_turn[&entity_id] |= Turn::Pass.turn();
         }
 
@@ -150,18 +188,38 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         }
 
         Msg::PickUp(entity_id) => {
-            resolve_pick_item_up(entity_id, &mut game.level, &mut game.msg_log);
+            resolve_pick_item_up(entity_id, &mut game.level, &mut game.msg_log, &game.config);
 
             // this is necessary to re-emit entity information about the item being picked up
             game.settings.map_changed = true;
         }
 
+        Msg::MimicRevealed(entity_id) => {
+            // once revealed, a mimic stays revealed
+            let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+            game.level.entities.status[&entity_id].disguised = false;
+            game.msg_log.log(Msg::StateChange(entity_id, Behavior::Attacking(player_id)));
+        }
+
+        Msg::Ignite(pos) => {
+            resolve_ignite(pos, &mut game.level, &game.config, &mut game.msg_log);
+        }
+
+        Msg::Summon(summoner_id, pos) => {
+            resolve_summon(summoner_id, pos, game);
+        }
+
+        Msg::Stole(thief_id, target_id, item_id) => {
+            resolve_stole(thief_id, target_id, item_id, &mut game.level);
+        }
+
         Msg::StateChange(entity_id, behavior) => {
-            resolve_state_change(entity_id, behavior, &mut game.level, &mut game.msg_log, &game.config);
+            let aggression = game.performance.aggression(&game.config);
+            resolve_state_change(entity_id, behavior, aggression, &mut game.level, &mut game.rng, &mut game.msg_log, &game.config);
         }
 
         Msg::SpikeTrapTriggered(trap, entity_id) => {
-            game.level.entities.take_damage(entity_id, SPIKE_DAMAGE);
+            game.level.entities.take_damage(entity_id, trap, SPIKE_DAMAGE);
 
             if game.level.entities.hp[&entity_id].hp <= 0 {
                 game.level.entities.status[&entity_id].alive = false;
@@ -186,6 +244,14 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
             }
         }
 
+        Msg::Poisoned(entity_id, poisoner, num_turns) => {
+            if game.level.entities.hp.get(&entity_id).is_some() && game.level.entities.status[&entity_id].alive {
+                let status = &mut game.level.entities.status[&entity_id];
+                status.poisoned = std::cmp::max(status.poisoned, num_turns);
+                status.poisoner = Some(poisoner);
+            }
+        }
+
         Msg::Froze(entity_id, num_turns) => {
             if entity_id == player_id || game.level.entities.ai.get(&entity_id).is_some() {
                 game.level.entities.status[&entity_id].frozen = num_turns;
@@ -216,9 +282,12 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
             freeze_trap_triggered(trap, cause_id, &mut game.level, &mut game.msg_log, &game.config);
         }
 
-        Msg::Untriggered(_trigger, _entity_id) => {
-            // NOTE nothing untriggers yet
-            //untriggered(trigger, level, &mut game.msg_log);
+        Msg::Untriggered(trigger, entity_id) => {
+            // NOTE only boulders untrigger gates, acting as a weight on the pressure plate.
+            if game.level.entities.name[&entity_id] == EntityName::Boulder &&
+               game.level.entities.name[&trigger] == EntityName::GateTrigger {
+                resolve_triggered(trigger, entity_id, &mut game.level, &mut game.msg_log);
+            }
         }
 
         Msg::Triggered(trigger, entity_id) => {
@@ -252,49 +321,83 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
             inventory_drop_item(entity_id, item_index as usize, &mut game.level, &mut game.msg_log);
         }
 
+        Msg::InventoryFull(entity_id, item_id) => {
+            // the player chooses an item to drop in the inventory menu
+            if entity_id == player_id {
+                game.settings.pending_pickup = Some(item_id);
+                change_state(&mut game.settings, GameState::Inventory, &mut game.msg_log);
+            }
+        }
+
+        Msg::DropToPickUp(entity_id, slot_index, item_id) => {
+            resolve_drop_to_pick_up(entity_id, slot_index, item_id, &mut game.level, &mut game.msg_log);
+            game.settings.map_changed = true;
+        }
+
+        Msg::CycleSlot(entity_id, item_class) => {
+            // reordering items does not take a turn
+            game.level.entities.cycle_item_class(entity_id, item_class);
+        }
+
         Msg::GrassWall(entity_id, direction) => {
-            if try_use_energy(entity_id, Skill::GrassWall, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::GrassWall, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_grass_wall(entity_id, direction, game);
             }
         }
 
         Msg::GrassThrow(entity_id, direction) => {
-            if try_use_energy(entity_id, Skill::GrassThrow, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::GrassThrow, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_grass_throw(entity_id, direction, game);
             }
         }
 
         Msg::GrassShoes(entity_id, _action_mode) => {
-            if try_use_energy(entity_id, Skill::GrassShoes, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::GrassShoes, &mut game.level, &mut game.msg_log, &game.config) {
                 game.level.entities.status[&entity_id].soft_steps = SKILL_GRASS_SHOES_TURNS;
                 game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
             }
         }
 
         Msg::GrassCover(entity_id, _action_mode) => {
-            if try_use_energy(entity_id, Skill::GrassCover, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::GrassCover, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_grass_cover(entity_id, game);
             }
         }
 
         Msg::Illuminate(entity_id, pos, amount) => {
-            if try_use_energy(entity_id, Skill::Illuminate, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Illuminate, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_illuminate(entity_id, pos, amount, game);
             }
         }
 
         Msg::HealSkill(entity_id, amount) => {
-            if try_use_energy(entity_id, Skill::Heal, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Heal, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_heal(entity_id, amount, game);
             }
         }
 
         Msg::EatHerb(entity_id, item_id) => {
+            game.identify_item(entity_id, item_id);
             resolve_eat_herb(entity_id, item_id, game);
         }
 
+        Msg::TimeStop(entity_id, item_id) => {
+            game.identify_item(entity_id, item_id);
+            resolve_time_stop(entity_id, item_id, game);
+        }
+
+        Msg::RaiseMirror(entity_id, item_id) => {
+            game.identify_item(entity_id, item_id);
+            resolve_raise_mirror(entity_id, item_id, game);
+        }
+
+        Msg::Recall(entity_id, item_id) => {
+            game.identify_item(entity_id, item_id);
+            resolve_recall(entity_id, item_id, game);
+        }
+
         Msg::TryFarSight(entity_id, amount) => {
-            if try_use_energy(entity_id, Skill::FarSight, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::FarSight, &mut game.level, &mut game.msg_log, &game.config) {
                 game.level.entities.status[&entity_id].extra_fov += amount;
                 game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
                 game.msg_log.log(Msg::FarSight(entity_id, amount));
@@ -302,28 +405,47 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         }
 
         Msg::Ping(entity_id, pos) => {
-            if try_use_energy(entity_id, Skill::Ping, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Ping, &mut game.level, &mut game.msg_log, &game.config) {
                 game.msg_log.log_front(Msg::Sound(entity_id, pos, game.config.ping_sound_radius));
                 game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+
+                // mark enemies within the ping's radius, even outside of the FoV. These
+                // markers are only drawn, and do not make the enemy a valid target.
+                // A disguised mimic passes for an item, so it is not marked.
+                for ping_pos in game.level.map.pos_in_radius(pos, game.config.ping_sound_radius as i32) {
+                    for pinged_id in game.level.get_entities_at_pos(ping_pos) {
+                        if game.level.entities.typ[&pinged_id] == EntityType::Enemy &&
+                           game.level.entities.status[&pinged_id].alive &&
+                           !game.level.entities.status[&pinged_id].disguised {
+                            game.msg_log.log_info(InfoMsg::PingMarker(ping_pos));
+                        }
+                    }
+                }
             }
         }
 
         Msg::Sprint(entity_id, direction, amount) => {
-            if try_use_energy(entity_id, Skill::Sprint, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Sprint, &mut game.level, &mut game.msg_log, &game.config) {
                 game.msg_log.log(Msg::TryMove(entity_id, direction, amount, MoveMode::Run));
                 game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
             }
         }
 
         Msg::Roll(entity_id, direction, amount) => {
-            if try_use_energy(entity_id, Skill::Roll, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Roll, &mut game.level, &mut game.msg_log, &game.config) {
+                game.level.entities.status[&entity_id].rolling = true;
                 game.msg_log.log(Msg::TryMove(entity_id, direction, amount, MoveMode::Sneak));
                 game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
             }
         }
 
+        Msg::RollLanded(entity_id) => {
+            let pos = game.level.entities.pos[&entity_id];
+            resolve_triggered_traps(entity_id, pos, MoveType::Move, &mut game.level, &mut game.rng, &mut game.msg_log);
+        }
+
         Msg::Rubble(entity_id, rubble_pos) => {
-            if try_use_energy(entity_id, Skill::Rubble, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Rubble, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_rubble_skill(entity_id, rubble_pos, game);
             }
         }
@@ -333,7 +455,7 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         }
 
         Msg::GrassBlade(entity_id, action_mode, direction) => {
-            if try_use_energy(entity_id, Skill::GrassBlade, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::GrassBlade, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_grass_blade(entity_id, action_mode, direction, game);
             }
         }
@@ -341,7 +463,7 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         Msg::Reform(entity_id, pos) => {
             if game.level.map[pos].surface == Surface::Rubble &&
                game.level.has_blocking_entity(pos).is_none() {
-                if try_use_energy(entity_id, Skill::Reform, &mut game.level, &mut game.msg_log) {
+                if try_use_energy(entity_id, Skill::Reform, &mut game.level, &mut game.msg_log, &game.config) {
                     game.level.map[pos].surface = Surface::Floor;
                     game.level.map[pos].block_move = true;
                     game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
@@ -355,13 +477,13 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         }
 
         Msg::Swap(entity_id, target_id) => {
-            if try_use_energy(entity_id, Skill::Swap, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Swap, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_swap(entity_id, target_id, game);
             }
         }
 
         Msg::PassWall(entity_id, pos) => {
-            if try_use_energy(entity_id, Skill::PassWall, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::PassWall, &mut game.level, &mut game.msg_log, &game.config) {
                 //game.level.entities.set_pos(entity_id, pos);
                 game.msg_log.log(Msg::Moved(entity_id, MoveType::Misc, MoveMode::Walk, pos));
 
@@ -382,16 +504,28 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         }
 
         Msg::PlaceTrap(entity_id, place_pos, trap_id) => {
-            game.level.entities.set_pos(trap_id, place_pos);
-            game.level.entities.armed[&trap_id] = true;
-
+            game.identify_item(entity_id, trap_id);
             game.level.entities.remove_from_inventory(entity_id, trap_id);
+
+            if let Some(trap) = game.level.entities.item[&trap_id].kit_trap() {
+                // a kit is used up, setting up a new trap in its place
+                remove_entity(trap_id, &mut game.level);
+                let placed_id = match trap {
+                    Trap::Freeze => make_freeze_trap(&mut game.level.entities, &game.config, place_pos, &mut game.msg_log),
+                    _ => make_spike_trap(&mut game.level.entities, &game.config, place_pos, &mut game.msg_log),
+                };
+                game.level.entities.status[&placed_id].fresh_trap = true;
+            } else {
+                game.level.entities.set_pos(trap_id, place_pos);
+                game.level.entities.armed[&trap_id] = true;
+            }
+
             game.level.entities.took_turn[&entity_id] |= Turn::InteractTrap.turn();
         }
 
 
         Msg::Push(entity_id, direction, amount) => {
-            if try_use_energy(entity_id, Skill::Push, &mut game.level, &mut game.msg_log) {
+            if try_use_energy(entity_id, Skill::Push, &mut game.level, &mut game.msg_log, &game.config) {
                 resolve_push_skill(entity_id, direction, amount, &mut game.level, &mut game.msg_log);
             }
         }
@@ -436,7 +570,7 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
         Msg::ExplosionHit(source_id, hit_entity) => {
             if game.level.entities.typ[&hit_entity] == EntityType::Player {
                 let damage_amount = 1;
-                if game.level.entities.take_damage(hit_entity, damage_amount) {
+                if game.level.entities.take_damage(hit_entity, source_id, damage_amount) {
                     if game.level.entities.hp[&hit_entity].hp <= 0 {
                         game.msg_log.log(Msg::Killed(source_id, hit_entity, damage_amount));
                     }
@@ -464,6 +598,21 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
             resolve_swift(entity_id, direction, game);
         }
 
+        Msg::TryDash(entity_id, direction) => {
+            if try_use_energy(entity_id, Skill::Dash, &mut game.level, &mut game.msg_log, &game.config) {
+                resolve_dash(entity_id, direction, game);
+                game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+            }
+        }
+
+        Msg::TryWarCry(entity_id, turns) => {
+            if try_use_energy(entity_id, Skill::WarCry, &mut game.level, &mut game.msg_log, &game.config) {
+                game.level.entities.status[&entity_id].hasted = turns;
+                game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+                game.msg_log.log(Msg::WarCry(entity_id, turns));
+            }
+        }
+
         Msg::Forget(entity_id) => {
             game.msg_log.log(Msg::StateChange(entity_id, Behavior::Idle));
         }
@@ -484,10 +633,48 @@ pub fn resolve_message(game: &mut Game, msg: Msg) {
     }
 }
 
+/// The melee weapon an entity can charge a strong attack with, if any.
+/// The hammer has its own raise and swing, and the sling and boomerang are not melee weapons.
+fn chargeable_weapon(entity_id: EntityId, level: &Level) -> Option<EntityId> {
+    for item_id in level.entities.inventory[&entity_id].iter() {
+        let item = level.entities.item[item_id];
+        if item.class() == ItemClass::Primary &&
+           item.weapon_type().is_some() &&
+           item != Item::Hammer &&
+           item != Item::Sling &&
+           item != Item::Boomerang {
+            return Some(*item_id);
+        }
+    }
+
+    return None;
+}
+
+/// The weapon type of the charged attack an entity is holding, if it still carries the
+/// weapon it charged. The raised hammer is left to its own swing.
+fn charged_weapon_type(entity_id: EntityId, level: &Level) -> Option<WeaponType> {
+    let (item_id, _dir, _turns) = level.entities.status[&entity_id].charged_attack?;
+    if !level.entities.inventory[&entity_id].contains(&item_id) {
+        return None;
+    }
+
+    let item = level.entities.item[&item_id];
+    if item == Item::Hammer {
+        return None;
+    }
+
+    return item.weapon_type();
+}
+
 fn resolve_hit(entity_id: EntityId, hit_pos: Pos, weapon_type: WeaponType, attack_style: AttackStyle, level: &mut Level, msg_log: &mut MsgLog, config: &Config) {
     // Hitting always takes a turn currently.
     level.entities.took_turn[&entity_id] |= Turn::Attack.turn();
 
+    // a charged attack is used up by the hit it strengthened.
+    if let Some(status) = level.entities.status.get_mut(&entity_id) {
+        status.charged_attack = None;
+    }
+
     let entity_pos = level.entities.pos[&entity_id];
 
     if let Some(hit_entity) = level.has_blocking_entity(hit_pos) {
@@ -546,14 +733,15 @@ fn resolve_attack(entity_id: EntityId,
                   _attack_pos: Pos,
                   level: &mut Level,
                   msg_log: &mut MsgLog,
-                  _config: &Config) {
+                  config: &Config) {
     // Any time an entity attacks, they change to standing stance.
     level.entities.stance[&entity_id] = Stance::Standing;
     msg_log.log(Msg::Stance(entity_id, level.entities.stance[&entity_id]));
 
-    match attack_info {
+    let target_id = match attack_info {
         Attack::Attack(target_id) => {
             attack(entity_id, target_id, level, msg_log);
+            target_id
         }
 
         Attack::Stab(_target_id, _move_into) => {
@@ -562,7 +750,22 @@ fn resolve_attack(entity_id: EntityId,
 
         Attack::Push(target_id, direction, amount) => {
             msg_log.log(Msg::Pushed(entity_id, target_id, direction, amount, true));
+            target_id
         }
+    };
+
+    // a charged attack strengthens a bump attack just as it does a weapon's swing,
+    // with the extra stun and sound of a strong hit.
+    if let Some(weapon_type) = charged_weapon_type(entity_id, level) {
+        level.entities.status[&entity_id].charged_attack = None;
+
+        let target_pos = level.entities.pos[&target_id];
+        if level.entities.typ[&target_id] == EntityType::Enemy && level.entities.status[&target_id].alive {
+            let stun_turns = weapon_type.stun_turns(config) + config.stun_turns_extra;
+            msg_log.log(Msg::Froze(target_id, stun_turns));
+        }
+        let sound_radius = weapon_type.sound_radius(config) + config.sound_radius_extra;
+        msg_log.log(Msg::Sound(entity_id, target_pos, sound_radius));
     }
 
     level.entities.took_turn[&entity_id] |= Turn::Attack.turn();
@@ -581,6 +784,14 @@ fn resolve_try_move(entity_id: EntityId,
 
     level.entities.move_mode[&entity_id] = move_mode;
 
+    // haste adds a tile to the first step of a move, on top of any run or sprint distance.
+    // each tile is still a separate step, so the extra tile stops at walls like any other.
+    let moved_this_turn = level.entities.took_turn[&entity_id] & (Turn::Walk.turn() | Turn::Run.turn()) != 0;
+    let mut amount = amount;
+    if amount > 0 && level.entities.status[&entity_id].hasted > 0 && !moved_this_turn {
+        amount += 1;
+    }
+
     let reach = level.entities.movement[&entity_id];
     let reach = reach.with_dist(1);
 
@@ -710,7 +921,7 @@ fn resolve_try_movement(entity_id: EntityId,
             }
         }
 
-        MoveType::Misc => {
+        MoveType::Misc | MoveType::Pushed => {
             msg_log.log(Msg::Moved(entity_id, movement.typ, MoveMode::Walk, movement.pos));
         }
     }
@@ -871,7 +1082,28 @@ fn resolve_pushed_entity(pusher: EntityId,
     let pushed_pos = level.entities.pos[&pushed];
     let pusher_pos = level.entities.pos[&pusher];
 
-    if level.entities.typ[&pushed] == EntityType::Column {
+    if level.entities.name[&pushed] == EntityName::Boulder {
+        // Boulders roll one tile rather than toppling. They stop against walls and other
+        // blocking entities, and sink if pushed into water.
+        let next_pos = direction.offset_pos(pushed_pos, 1);
+        let blocked = !level.map.is_within_bounds(next_pos) ||
+                      level.map.path_blocked_move(pushed_pos, next_pos).is_some() ||
+                      level.has_blocking_entity(next_pos).is_some();
+
+        if !blocked {
+            // the move is still resolved when sinking so that any trigger it rested on is released.
+            msg_log.log_front(Msg::Moved(pushed, MoveType::Misc, MoveMode::Walk, next_pos));
+
+            if level.map[next_pos].tile_type == TileType::Water {
+                level.entities.mark_for_removal(pushed);
+                msg_log.log_front(Msg::Sound(pushed, next_pos, config.sound_radius_stone));
+            }
+
+            if move_into && pos_on_map(level.entities.pos[&pusher]) {
+                msg_log.log(Msg::Moved(pusher, MoveType::Misc, MoveMode::Walk, pushed_pos));
+            }
+        }
+    } else if level.entities.typ[&pushed] == EntityType::Column {
         let entity_diff = sub_pos(pushed_pos, pusher_pos);
         let next_pos = next_pos(pusher_pos, entity_diff);
         let blocked = level.map.path_blocked_move(pushed_pos, next_pos); 
@@ -885,6 +1117,20 @@ fn resolve_pushed_entity(pusher: EntityId,
                 msg_log.log(Msg::Moved(pusher, MoveType::Misc, MoveMode::Walk, pushed_pos));
             }
         }
+    } else if level.entities.typ[&pushed] == EntityType::Player &&
+              level.entities.status[&pushed].stone > 0 &&
+              level.entities.typ[&pusher] == EntityType::Enemy {
+        // Stone skin reflects the push back onto the pusher. If there is no room behind
+        // the pusher it is simply stopped rather than crushed.
+        let back_pos = direction.reverse().offset_pos(pusher_pos, 1);
+        let blocked = !level.map.is_within_bounds(back_pos) ||
+                      level.map.path_blocked_move(pusher_pos, back_pos).is_some() ||
+                      level.has_blocking_entity(back_pos).is_some();
+
+        if !blocked {
+            level.entities.status[&pusher].frozen += config.push_stun_turns;
+            msg_log.log(Msg::Moved(pusher, MoveType::Pushed, MoveMode::Walk, back_pos));
+        }
     } else if level.entities.status[&pushed].alive {
         let continue_push = 
             push_attack(pusher, pushed, direction, move_into, level, config, msg_log);
@@ -930,6 +1176,27 @@ fn resolve_crushed(entity_id: EntityId, pos: Pos, level: &mut Level, msg_log: &m
     msg_log.log_front(Msg::Sound(entity_id, pos, config.sound_radius_crushed));
 }
 
+fn resolve_stone_volley(entity_id: EntityId, start_pos: Pos, end_pos: Pos, game: &mut Game) {
+    let stones: Vec<EntityId> =
+        game.level.entities.inventory[&entity_id].iter()
+                                                 .filter(|item_id| game.level.entities.item.get(item_id) == Some(&Item::Stone))
+                                                 .take(STONE_VOLLEY_COUNT)
+                                                 .copied()
+                                                 .collect();
+
+    for stone_id in stones {
+        // Each stone lands somewhere around the target, so the volley trades accuracy for coverage.
+        let scatter_x = rng_range_i32(&mut game.rng, -STONE_VOLLEY_SCATTER, STONE_VOLLEY_SCATTER + 1);
+        let scatter_y = rng_range_i32(&mut game.rng, -STONE_VOLLEY_SCATTER, STONE_VOLLEY_SCATTER + 1);
+        let throw_pos = game.level.map.clamp(move_by(end_pos, Pos::new(scatter_x, scatter_y)));
+
+        // A stone scattered back onto the thrower is thrown at the original target instead.
+        let throw_pos = if throw_pos == start_pos { end_pos } else { throw_pos };
+
+        resolve_throw_item(entity_id, stone_id, start_pos, throw_pos, false, &mut game.level, &mut game.rng, &mut game.msg_log, &game.config);
+    }
+}
+
 fn resolve_throw_item(player_id: EntityId,
                       item_id: EntityId,
                       start_pos: Pos,
@@ -939,17 +1206,19 @@ fn resolve_throw_item(player_id: EntityId,
                       rng: &mut Rand32,
                       msg_log: &mut MsgLog,
                       config: &Config) {
+    let hit_pos;
     if start_pos == end_pos {
-        panic!("Is it possible to throw an item and have it end where it started? Apparently yes")
-    }
-
-    let throw_line = line(start_pos, end_pos);
+        // an item used on the thrower's own tile lands there without an arc
+        hit_pos = start_pos;
+    } else {
+        let throw_line = line(start_pos, end_pos);
 
-    // get target position in direction of player click
-    let end_pos =
-        throw_line.into_iter().take(PLAYER_THROW_DIST).last().unwrap();
+        // get target position in direction of player click
+        let end_pos =
+            throw_line.into_iter().take(throw_range(hard)).last().unwrap();
 
-    let hit_pos = level.throw_towards(start_pos, end_pos);
+        hit_pos = level.throw_towards(start_pos, end_pos);
+    }
 
     if let Some(hit_entity) = level.has_blocking_entity(hit_pos) {
         if level.entities.typ[&hit_entity] == EntityType::Enemy {
@@ -970,6 +1239,10 @@ fn resolve_throw_item(player_id: EntityId,
 
             let player_pos = level.entities.pos[&player_id];
             level.entities.messages[&hit_entity].push(Message::Hit(player_pos));
+
+            if level.entities.status[&hit_entity].disguised {
+                msg_log.log(Msg::MimicRevealed(hit_entity));
+            }
         }
     }
 
@@ -994,6 +1267,11 @@ fn resolve_throw_item(player_id: EntityId,
             remove_entity(entity_id, level);
         }
         remove_entity(item_id, level);
+    } else if level.entities.item[&item_id] == Item::Lantern {
+        // a lantern sets grass alight where it lands
+        if level.map[hit_pos].surface == Surface::Grass {
+            msg_log.log(Msg::Ignite(hit_pos));
+        }
     } else if level.entities.item[&item_id] == Item::SeedCache {
         for seed_pos in floodfill(&level.map, hit_pos, SEED_CACHE_RADIUS) {
             if rng_trial(rng, 0.70) {
@@ -1009,6 +1287,31 @@ fn resolve_throw_item(player_id: EntityId,
                 }
             }
         }
+    } else if level.entities.item[&item_id] == Item::PoisonVial {
+        if let Some(hit_entity) = level.has_blocking_entity(hit_pos) {
+            if level.entities.hp.get(&hit_entity).is_some() {
+                msg_log.log(Msg::Poisoned(hit_entity, player_id, config.poison_vial_turns));
+            }
+        }
+        // the vial shatters on impact
+        remove_entity(item_id, level);
+    } else if level.entities.item[&item_id] == Item::SwapStone {
+        if let Some(hit_entity) = level.has_blocking_entity(hit_pos) {
+            // only enemies can be swapped with, and only if the player could stand where they are
+            let target_pos = level.entities.pos[&hit_entity];
+            let can_stand = !level.map[target_pos].block_move && level.map[target_pos].tile_type != TileType::Water;
+            if level.entities.typ[&hit_entity] == EntityType::Enemy && can_stand {
+                swap_positions(player_id, hit_entity, level, msg_log);
+            }
+        }
+        remove_entity(item_id, level);
+    } else if level.entities.item[&item_id] == Item::MarkingStone {
+        // the mark is tracked on the enemy, so its impression follows it until the mark fades
+        if let Some(hit_entity) = level.has_blocking_entity(hit_pos) {
+            if level.entities.typ[&hit_entity] == EntityType::Enemy && level.entities.status[&hit_entity].alive {
+                level.entities.status[&hit_entity].marked = Some((config.marking_stone_turns, hit_pos));
+            }
+        }
     } else if level.entities.item[&item_id] == Item::LookingGlass {
         make_magnifier(&mut level.entities, config, hit_pos, config.looking_glass_magnify_amount, msg_log);
     } else if level.entities.item[&item_id] == Item::GlassEye {
@@ -1018,7 +1321,7 @@ fn resolve_throw_item(player_id: EntityId,
                 // and entities on the edge should already have impressions, so
                 // we don't need to make one here.
                 if level.entities.typ[&eyed_id] == EntityType::Enemy &&
-                   level.is_in_fov(player_id, eyed_id) == FovResult::Outside {
+                   level.is_in_fov(player_id, eyed_id, config) == FovResult::Outside {
                     msg_log.log_info(InfoMsg::Impression(pos));
                 }
             }
@@ -1077,7 +1380,7 @@ fn resolve_moved_message(entity_id: EntityId,
         level.entities.took_turn[&entity_id] |= Turn::Walk.turn();
     }
 
-    if move_type != MoveType::Blink {
+    if move_type != MoveType::Blink && move_type != MoveType::Pushed {
         if pos != original_pos && level.entities.typ[&entity_id] == EntityType::Enemy {
             msg_log.log_front(Msg::Sound(entity_id, original_pos, config.sound_radius_monster));
             msg_log.log_front(Msg::Sound(entity_id, pos, config.sound_radius_monster));
@@ -1088,7 +1391,7 @@ fn resolve_moved_message(entity_id: EntityId,
             }
         } else {
             // Only normal movements update the stance. Others like Blink leave it as-is.
-            if move_type != MoveType::Blink && move_type != MoveType::Misc {
+            if move_type != MoveType::Blink && move_type != MoveType::Misc && move_type != MoveType::Pushed {
                 if let Some(stance) = level.entities.stance.get(&entity_id) {
                     level.entities.stance[&entity_id] = update_stance(move_type, move_mode, *stance);
                     msg_log.log(Msg::Stance(entity_id, level.entities.stance[&entity_id]));
@@ -1105,6 +1408,11 @@ fn resolve_moved_message(entity_id: EntityId,
     // This is cleared in the start of the next turn when the game is stepped.
     level.entities.status[&entity_id].blinked = move_type == MoveType::Blink;
 
+    // the player leaves a scent trail, unless they are stepping softly
+    if entity_id == player_id && level.entities.status[&player_id].soft_steps == 0 {
+        level.leave_scent(pos, config.scent_turns);
+    }
+
     // check if player walks on energy
     if entity_id == player_id {
         for other_id in level.entities.ids.clone().iter() {
@@ -1118,28 +1426,53 @@ fn resolve_moved_message(entity_id: EntityId,
     }
 
     if original_pos != pos {
-        resolve_triggered_traps(entity_id, original_pos, level, rng, msg_log);
+        resolve_triggered_traps(entity_id, original_pos, move_type, level, rng, msg_log);
     }
 
-    // check for passing turn while the hammer is raised
-    if move_type == MoveType::Pass {
-        if let Some((item_id, dir, turns)) = level.entities.status[&entity_id].hammer_raised {
-            if turns == 0 {
-                let hit_pos = dir.offset_pos(original_pos, 1);
-                msg_log.log(Msg::HammerSwing(entity_id, item_id, hit_pos));
-                level.entities.status[&entity_id].hammer_raised = None;
+    // disguised mimics reveal themselves when the player comes next to them
+    if entity_id == player_id {
+        for other_id in level.entities.ids.iter() {
+            if level.entities.status[other_id].disguised &&
+               distance_maximum(level.entities.pos[other_id], pos) <= 1 {
+                msg_log.log(Msg::MimicRevealed(*other_id));
             }
         }
     }
 
+    // check for passing turn while an attack is charged. A raised hammer swings, while
+    // other melee weapons hold their charge for another turn. Passing with a melee weapon
+    // and no charge starts charging a strong attack.
+    if move_type == MoveType::Pass && entity_id == player_id {
+        if let Some((item_id, dir, turns)) = level.entities.status[&entity_id].charged_attack {
+            if level.entities.item.get(&item_id) == Some(&Item::Hammer) {
+                if turns == 0 {
+                    let hit_pos = dir.offset_pos(original_pos, 1);
+                    msg_log.log(Msg::HammerSwing(entity_id, item_id, hit_pos));
+                    level.entities.status[&entity_id].charged_attack = None;
+                }
+            } else {
+                level.entities.status[&entity_id].charged_attack = Some((item_id, dir, 1));
+            }
+        } else if let Some(item_id) = chargeable_weapon(entity_id, level) {
+            let dir = level.entities.direction[&entity_id];
+            level.entities.status[&entity_id].charged_attack = Some((item_id, dir, 1));
+            msg_log.log(Msg::AttackCharged(entity_id, item_id));
+        }
+    }
+
     // Check if we trampled any grass.
     // This only happens for non-item moves that change position, and are not teleports.
     if level.entities.item.get(&entity_id).is_none() && original_pos != pos && move_type != MoveType::Blink {
         trample_grass_walls(level, original_pos, pos);
     }
 
-    if level.map[pos].block_sight && level.map[pos].surface == Surface::Grass {
-        level.map[pos].block_sight = false;
+    // With strict grass, tall grass stays up around an entity standing in it, hiding them,
+    // and is trampled behind them once they leave.
+    let trampled_pos = if config.grass_blocks_sight_strict { original_pos } else { pos };
+    if level.map[trampled_pos].block_sight && level.map[trampled_pos].surface == Surface::Grass {
+        if !config.grass_blocks_sight_strict || (original_pos != pos && level.entities.item.get(&entity_id).is_none()) {
+            level.map[trampled_pos].block_sight = false;
+        }
     }
 
     // if entity is a monster, which is also alert, and there is a path to the player,
@@ -1167,9 +1500,15 @@ fn resolve_moved_message(entity_id: EntityId,
 
 fn resolve_triggered_traps(entity_id: EntityId,
                            original_pos: Pos,
+                           move_type: MoveType,
                            level: &mut Level,
                            rng: &mut Rand32,
                            msg_log: &mut MsgLog) {
+    // a dodge roll passes over traps. The tile it lands on is checked once the roll ends.
+    if level.entities.status[&entity_id].rolling {
+        return;
+    }
+
     // check for light touch first, in case it prevents a trap from triggering.
     if level.entities.passive.get(&entity_id).is_some() &&
        level.entities.passive[&entity_id].light_touch   &&
@@ -1178,7 +1517,17 @@ fn resolve_triggered_traps(entity_id: EntityId,
     }
 
     // get a list of triggered traps
-    let traps: Vec<EntityId> = level.entities.triggered_traps(level.entities.pos[&entity_id]);
+    let mut traps: Vec<EntityId> = level.entities.triggered_traps(level.entities.pos[&entity_id]);
+
+    // the player can step back over a trap they just set up
+    if level.entities.typ[&entity_id] == EntityType::Player {
+        traps.retain(|trap| !level.entities.status[trap].fresh_trap);
+    }
+
+    // an entity pushed onto a trap did not step on it, although it still weighs down triggers
+    if move_type == MoveType::Pushed {
+        traps.clear();
+    }
 
     // Check if the entity hit a trap
     for trap in traps.iter() {
@@ -1250,11 +1599,24 @@ fn resolve_ai_attack(entity_id: EntityId,
             }
         }
 
-        if can_attack {
+        if can_attack && level.entities.status[&target_id].reflecting {
+            // a raised mirror turns only the first attack back on the attacker. The attacker
+            // takes its own attack, not the player's weapon. A ranged attack is sent back along
+            // its line and lands on the attacker's tile, making the usual attack sound there.
+            level.entities.status[&target_id].reflecting = false;
+            msg_log.log(Msg::Reflected(target_id, entity_id));
+
+            level.entities.stance[&entity_id] = Stance::Standing;
+            msg_log.log(Msg::Stance(entity_id, level.entities.stance[&entity_id]));
+
+            attack_as(entity_id, target_id, entity_id, level, msg_log);
+
+            level.entities.took_turn[&entity_id] |= Turn::Attack.turn();
+        } else if can_attack {
             let attack_info = Attack::Attack(target_id);
             msg_log.log(Msg::TryAttack(entity_id, attack_info, target_pos));
         }
-    } else if level.is_in_fov(entity_id, target_id) != FovResult::Inside {
+    } else if level.is_in_fov(entity_id, target_id, config) != FovResult::Inside {
         // If the target disappeared, change to idle- there is no need to
         // pursue their last position if we saw them blink away.
         if level.entities.target_disappeared(entity_id).is_some() {
@@ -1289,10 +1651,16 @@ fn resolve_yell(entity_id: EntityId, level: &mut Level, msg_log: &mut MsgLog, co
     level.entities.took_turn[&entity_id] |= Turn::Pass.turn();
 }
 
-fn resolve_blink(entity_id: EntityId, level: &mut Level, rng: &mut Rand32, msg_log: &mut MsgLog) {
+/// Blink to a random nearby tile, or to the tile behind the enemy at the target position.
+fn resolve_blink(entity_id: EntityId, target_pos: Option<Pos>, level: &mut Level, rng: &mut Rand32, msg_log: &mut MsgLog) {
     let entity_pos = level.entities.pos[&entity_id];
 
-    if let Some(blink_pos) = find_blink_pos(entity_pos, rng, level) {
+    let blink_pos = match target_pos {
+        Some(target_pos) => find_blink_behind_pos(entity_id, target_pos, level),
+        None => find_blink_pos(entity_pos, rng, level),
+    };
+
+    if let Some(blink_pos) = blink_pos {
         msg_log.log_front(Msg::Moved(entity_id, MoveType::Blink, MoveMode::Walk, blink_pos));
     } else {
         msg_log.log(Msg::FailedBlink(entity_id));
@@ -1301,14 +1669,14 @@ fn resolve_blink(entity_id: EntityId, level: &mut Level, rng: &mut Rand32, msg_l
     level.entities.took_turn[&entity_id] |= Turn::Pass.turn();
 }
 
-fn resolve_state_change(entity_id: EntityId, behavior: Behavior, level: &mut Level, msg_log: &mut MsgLog, config: &Config) {
+fn resolve_state_change(entity_id: EntityId, behavior: Behavior, aggression: f32, level: &mut Level, rng: &mut Rand32, msg_log: &mut MsgLog, config: &Config) {
     let original_behavior = level.entities.behavior[&entity_id];
 
     // If the entity hasn't completed a turn, the state change continues their turn.
     if level.entities.took_turn[&entity_id] != 0 &&
        level.entities.behavior[&entity_id] != original_behavior &&
         !matches!(behavior, Behavior::Investigating(_)) {
-       ai_take_turn(entity_id, level, config, msg_log);
+       ai_take_turn(entity_id, level, config, aggression, rng, msg_log);
     }
 
     level.entities.behavior[&entity_id] = behavior;
@@ -1334,6 +1702,8 @@ fn resolve_add_class(class: EntityClass, game: &mut Game) {
             add_skill(game, player_id, Skill::Blink);
             add_skill(game, player_id, Skill::Sprint);
             add_skill(game, player_id, Skill::Roll);
+            add_skill(game, player_id, Skill::Dash);
+            add_skill(game, player_id, Skill::WarCry);
         }
 
         EntityClass::Monolith => {
@@ -1342,6 +1712,7 @@ fn resolve_add_class(class: EntityClass, game: &mut Game) {
             add_skill(game, player_id, Skill::StoneThrow);
             add_skill(game, player_id, Skill::Reform);
             add_skill(game, player_id, Skill::StoneSkin);
+            add_skill(game, player_id, Skill::StoneVolley);
         }
 
         EntityClass::Grass => {
@@ -1365,21 +1736,8 @@ fn resolve_grass_wall(entity_id: EntityId, direction: Direction, game: &mut Game
 
     game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
 
-    match direction {
-        Direction::Left | Direction::Right | Direction::Up | Direction::Down => {
-            game.level.map.place_intertile_wall(entity_pos, Surface::Grass, direction);
-            let next_to = direction.clockwise().clockwise().offset_pos(entity_pos, 1);
-            game.level.map.place_intertile_wall(next_to, Surface::Grass, direction);
-            let next_to = direction.counterclockwise().counterclockwise().offset_pos(entity_pos, 1);
-            game.level.map.place_intertile_wall(next_to, Surface::Grass, direction);
-        }
-
-        Direction::DownLeft | Direction::DownRight | Direction::UpLeft | Direction::UpRight => {
-            let next_to = direction.clockwise().offset_pos(entity_pos, 1);
-            game.level.map.place_intertile_wall(next_to, Surface::Grass, direction.counterclockwise());
-            let next_to = direction.counterclockwise().offset_pos(entity_pos, 1);
-            game.level.map.place_intertile_wall(next_to, Surface::Grass, direction.clockwise());
-        }
+    for (wall_pos, wall_dir) in grass_wall_positions(entity_pos, direction) {
+        game.level.map.place_intertile_wall(wall_pos, Surface::Grass, wall_dir);
     }
 }
 
@@ -1464,6 +1822,49 @@ fn resolve_eat_herb(entity_id: EntityId, item_id: EntityId, game: &mut Game) {
     game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
 }
 
+fn resolve_time_stop(entity_id: EntityId, item_id: EntityId, game: &mut Game) {
+    game.level.entities.status[&entity_id].time_stop = game.config.hourglass_turns;
+
+    game.level.entities.remove_item(entity_id, item_id);
+    game.msg_log.log(Msg::Remove(item_id));
+
+    game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+}
+
+fn resolve_raise_mirror(entity_id: EntityId, item_id: EntityId, game: &mut Game) {
+    game.level.entities.status[&entity_id].reflecting = true;
+
+    game.level.entities.remove_item(entity_id, item_id);
+    game.msg_log.log(Msg::Remove(item_id));
+
+    game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+}
+
+/// The first use of a recall stone marks the entity's tile, and the next use blinks them back to it,
+/// using up the stone. If the marked tile is blocked the recall fails and the mark is kept.
+fn resolve_recall(entity_id: EntityId, item_id: EntityId, game: &mut Game) {
+    let entity_pos = game.level.entities.pos[&entity_id];
+
+    if let Some(recall_pos) = game.level.entities.recall_pos.get(&entity_id).map(|pos| *pos) {
+        let blocked_by_entity = game.level.has_blocking_entity(recall_pos).filter(|id| *id != entity_id).is_some();
+        if game.level.map[recall_pos].block_move || blocked_by_entity {
+            game.msg_log.log(Msg::FailedRecall(entity_id));
+            return;
+        }
+
+        game.level.entities.recall_pos.remove(&entity_id);
+        game.level.entities.remove_item(entity_id, item_id);
+        game.msg_log.log(Msg::Remove(item_id));
+
+        game.msg_log.log_front(Msg::Moved(entity_id, MoveType::Blink, MoveMode::Walk, recall_pos));
+    } else {
+        game.level.entities.recall_pos.insert(entity_id, entity_pos);
+        game.msg_log.log(Msg::RecallMarked(entity_id, entity_pos));
+    }
+
+    game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+}
+
 fn resolve_rubble_skill(entity_id: EntityId, rubble_pos: Pos, game: &mut Game) {
     let pos = game.level.entities.pos[&entity_id];
     let blocked = game.level.map.path_blocked_move(pos, rubble_pos);
@@ -1524,34 +1925,180 @@ fn resolve_grass_blade(entity_id: EntityId, _action_mode: ActionMode, direction:
 }
 
 fn resolve_swap(entity_id: EntityId, target_id: EntityId, game: &mut Game) {
-    let entity_dir = game.level.entities.direction[&entity_id];
-    let target_dir = game.level.entities.direction[&target_id];
+    swap_positions(entity_id, target_id, &mut game.level, &mut game.msg_log);
+
+    game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+}
 
-    let start_pos = game.level.entities.pos[&entity_id];
-    let end_pos = game.level.entities.pos[&target_id];
-    game.level.entities.set_pos(entity_id, end_pos);
-    game.level.entities.set_pos(target_id, start_pos);
+fn swap_positions(entity_id: EntityId, target_id: EntityId, level: &mut Level, msg_log: &mut MsgLog) {
+    let entity_dir = level.entities.direction[&entity_id];
+    let target_dir = level.entities.direction[&target_id];
 
-    game.msg_log.log(Msg::SetFacing(entity_id, target_dir));
-    game.msg_log.log(Msg::SetFacing(target_id, entity_dir));
+    let start_pos = level.entities.pos[&entity_id];
+    let end_pos = level.entities.pos[&target_id];
+    level.entities.set_pos(entity_id, end_pos);
+    level.entities.set_pos(target_id, start_pos);
 
-    game.level.entities.took_turn[&entity_id] |= Turn::Skill.turn();
+    msg_log.log(Msg::SetFacing(entity_id, target_dir));
+    msg_log.log(Msg::SetFacing(target_id, entity_dir));
 }
 
-fn resolve_restart(game: &mut Game) {
-    map_construct(&game.settings.map_load_config.clone(), game);
+/// Throw a boomerang out along a line, hitting every enemy it passes over. It lies
+/// where it lands until it flies back on the thrower's next turn.
+fn resolve_boomerang_throw(entity_id: EntityId,
+                           item_id: EntityId,
+                           start_pos: Pos,
+                           end_pos: Pos,
+                           level: &mut Level,
+                           msg_log: &mut MsgLog,
+                           config: &Config) {
+    let path = level.boomerang_path(start_pos, end_pos);
+    let land_pos = path.last().copied().unwrap_or(start_pos);
+
+    boomerang_hits(entity_id, &path, level, msg_log);
+
+    level.entities.remove_item(entity_id, item_id);
+    level.entities.set_pos(item_id, start_pos);
+    msg_log.log(Msg::Moved(item_id, MoveType::Misc, MoveMode::Walk, land_pos));
+    msg_log.log_front(Msg::Sound(entity_id, land_pos, config.sound_radius_stone));
+
+    level.entities.status[&item_id].boomerang = Some((entity_id, 1));
+    level.entities.took_turn[&entity_id] |= Turn::Attack.turn();
+}
 
+/// Fly a boomerang back towards the thrower's current position, hitting enemies on the way.
+/// The thrower catches it if it reaches them. Otherwise it lands as a normal item, either
+/// where it was stopped or, if the thrower has died, where it already lies.
+fn resolve_boomerang_return(entity_id: EntityId, item_id: EntityId, level: &mut Level, msg_log: &mut MsgLog, config: &Config) {
+    // the boomerang may have been picked up already, or left behind on another level
+    if !level.entities.ids.contains(&item_id) || level.entities.inventory[&entity_id].contains(&item_id) {
+        return;
+    }
+
+    if !level.entities.status[&entity_id].alive {
+        return;
+    }
+
+    let item_pos = level.entities.pos[&item_id];
+    let target_pos = level.entities.pos[&entity_id];
+
+    let path = level.boomerang_path(item_pos, target_pos);
+    let land_pos = path.last().copied().unwrap_or(item_pos);
+
+    boomerang_hits(entity_id, &path, level, msg_log);
+
+    let inventory_full = level.entities.needs_new_slot(entity_id, Item::Boomerang) &&
+                         level.entities.inventory_slots(entity_id).len() >= config.inventory_slots;
+    if land_pos == target_pos && !inventory_full {
+        msg_log.log(Msg::PickedUp(entity_id, item_id));
+
+        if let Some(to_drop_index) = level.entities.pick_up_item(entity_id, item_id) {
+            msg_log.log(Msg::DropItem(entity_id, to_drop_index as u64));
+        }
+    } else if land_pos != item_pos {
+        msg_log.log(Msg::Moved(item_id, MoveType::Misc, MoveMode::Walk, land_pos));
+    }
+}
+
+fn boomerang_hits(entity_id: EntityId, path: &Vec<Pos>, level: &Level, msg_log: &mut MsgLog) {
+    let weapon_type = Item::Boomerang.weapon_type().unwrap();
+    for pos in path.iter() {
+        if let Some(hit_entity) = level.has_blocking_entity(*pos) {
+            if level.entities.typ[&hit_entity] == EntityType::Enemy {
+                msg_log.log(Msg::Hit(entity_id, *pos, weapon_type, AttackStyle::Normal));
+            }
+        }
+    }
+}
+
+/// Add the player's part in a message to the run's totals.
+fn record_run_stats(game: &mut Game, msg: Msg, player_id: EntityId) {
+    match msg {
+        Msg::Attack(attacker, attacked, damage) => {
+            if attacker == player_id {
+                game.stats.damage_dealt += damage;
+            }
+
+            if attacked == player_id {
+                game.stats.damage_taken += damage;
+            }
+        }
+
+        Msg::PoisonDamage(entity_id, poisoner, damage) => {
+            if poisoner == player_id {
+                game.stats.damage_dealt += damage;
+            }
+
+            if entity_id == player_id {
+                game.stats.damage_taken += damage;
+            }
+        }
+
+        Msg::Burned(entity_id, _fire_id, damage) => {
+            if entity_id == player_id {
+                game.stats.damage_taken += damage;
+            }
+        }
+
+        Msg::Killed(_attacker, attacked, _damage) => {
+            if game.level.entities.typ.get(&attacked) == Some(&EntityType::Enemy) {
+                game.stats.enemies_killed += 1;
+            }
+        }
+
+        Msg::UsedSkill(entity_id, _skill) => {
+            if entity_id == player_id {
+                game.stats.skills_used += 1;
+            }
+        }
+
+        Msg::ItemThrow(entity_id, _item_id, _start, _end, _hard) => {
+            if entity_id == player_id {
+                game.stats.items_thrown += 1;
+            }
+        }
+
+        _ => {}
+    }
+}
+
+fn resolve_restart(game: &mut Game) {
     let player_id = game.level.find_by_name(EntityName::Player).unwrap();
 
-    game.level.entities.skills[&player_id].clear();
+    // the old inventory is cleared before the map is constructed, so its items are
+    // removed with the rest of the level and the starting items can be dropped there.
     game.level.entities.inventory[&player_id].clear();
+    game.settings.starting_drops.clear();
+    game.give_starting_inventory();
+
+    // the new run gets its own seed, so it can be reproduced with --seed like the first run
+    game.seed = game.rng.rand_u64();
+    game.rng = Rand32::new(game.seed);
+
+    map_construct(&game.settings.map_load_config.clone(), game);
+
+    game.level.entities.skills[&player_id].clear();
+    game.level.entities.cooldowns[&player_id].clear();
     game.level.entities.class[&player_id] = EntityClass::Body;
     game.level.entities.status[&player_id] = StatusEffect::default();
     game.level.entities.status[&player_id].alive = true;
 
     game.settings.turn_count = 0;
+    game.settings.level_num = 0;
+    game.settings.deaths = 0;
+    game.stats = RunStats::default();
+    game.performance = Performance::default();
     game.settings.cursor = None;
+    game.settings.cursor_history.clear();
     game.settings.use_dir = None;
+
+    if !game.config.bestiary_persists {
+        game.bestiary.clear();
+    }
+
+    if !game.config.identified_items_persist {
+        game.identified.clear();
+    }
 }
 
 fn resolve_passthrough(entity_id: EntityId, direction: Direction, game: &mut Game) {
@@ -1624,9 +2171,53 @@ fn resolve_swift(entity_id: EntityId, direction: Direction, game: &mut Game) {
     }
 }
 
-fn resolve_killed_entity(attacked: EntityId, level: &mut Level, msg_log: &mut MsgLog, config: &Config) {
+fn resolve_dash(entity_id: EntityId, direction: Direction, game: &mut Game) {
+    let entity_pos = game.level.entities.pos[&entity_id];
+
+    // Walk the path up front, stopping at the first wall or blocking entity.
+    // Each step is logged as a move so that the tiles are entered in order
+    // before any attack at the end of the dash.
+    let mut pos = entity_pos;
+    let mut target = None;
+    for _ in 0..SKILL_DASH_DISTANCE {
+        let next_pos = direction.offset_pos(pos, 1);
+        if !game.level.map.is_within_bounds(next_pos) {
+            break;
+        }
+
+        if game.level.map.move_blocked(pos, next_pos, BlockedType::Move).is_some() {
+            break;
+        }
+
+        if let Some(blocking_id) = game.level.has_blocking_entity(next_pos) {
+            if game.level.entities.typ[&blocking_id] == EntityType::Enemy {
+                target = Some(blocking_id);
+            }
+            break;
+        }
+
+        game.msg_log.log(Msg::Moved(entity_id, MoveType::Move, MoveMode::Walk, next_pos));
+        pos = next_pos;
+    }
+
+    if let Some(target_id) = target {
+        let target_pos = game.level.entities.pos[&target_id];
+        game.msg_log.log(Msg::TryAttack(entity_id, Attack::Attack(target_id), target_pos));
+        game.msg_log.log(Msg::Froze(target_id, SKILL_DASH_STUN_TURNS));
+    }
+}
+
+fn resolve_killed_entity(attacked: EntityId, level: &mut Level, rng: &mut Rand32, msg_log: &mut MsgLog, config: &Config) {
     let attacked_pos = level.entities.pos[&attacked];
 
+    if level.entities.typ[&attacked] == EntityType::Enemy {
+        if let Some(item) = roll_loot(level.entities.name[&attacked], rng, config) {
+            if let Some(drop_pos) = find_drop_pos(attacked_pos, attacked, level) {
+                make_item(&mut level.entities, config, item, drop_pos, msg_log);
+            }
+        }
+    }
+
     // if the attacked entities position is not blocked
     if !level.map[attacked_pos].block_move {
         // all non-player entities leave rubble
@@ -1644,13 +2235,224 @@ fn resolve_killed_entity(attacked: EntityId, level: &mut Level, msg_log: &mut Ms
         hp.hp = 0;
     }
 
+    if config.summon_minions_die_with_summoner && level.entities.name[&attacked] == EntityName::Summoner {
+        for (minion_id, summoner_id) in level.entities.summoned_by.iter() {
+            if *summoner_id == attacked && level.entities.status[&minion_id].alive {
+                msg_log.log(Msg::Killed(attacked, minion_id, level.entities.hp[&minion_id].hp));
+            }
+        }
+    }
+
+    // a thief drops whatever it stole where it fell
+    if let Some(item_id) = level.entities.stolen.get(&attacked).map(|id| *id) {
+        level.entities.stolen.remove(&attacked);
+        level.entities.set_pos(item_id, attacked_pos);
+        msg_log.log(Msg::DroppedItem(attacked, item_id));
+        msg_log.log(Msg::Moved(item_id, MoveType::Blink, MoveMode::Walk, attacked_pos));
+    }
+
     remove_entity(attacked, level);
 }
 
-fn resolve_pick_item_up(entity_id: EntityId, level: &mut Level, msg_log: &mut MsgLog) {
+/// Roll the loot table for a killed entity. Entities without entries in the table drop
+/// nothing and do not use the RNG. The item name 'nothing' is a weighted chance of no drop.
+fn roll_loot(name: EntityName, rng: &mut Rand32, config: &Config) -> Option<Item> {
+    let entries = config.loot_table.iter()
+                                   .filter(|(entity_name, _item_name, _weight)| *entity_name == name)
+                                   .collect::<Vec<&(EntityName, String, u32)>>();
+    let total_weight: u32 = entries.iter().map(|(_entity_name, _item_name, weight)| *weight).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut roll = rng_range_u32(rng, 0, total_weight);
+    for (_entity_name, item_name, weight) in entries {
+        if roll >= *weight {
+            roll -= *weight;
+            continue;
+        }
+
+        if item_name.to_lowercase() == "nothing" {
+            return None;
+        }
+
+        match item_name.to_lowercase().parse::<Item>() {
+            Ok(item) => return Some(item),
+            Err(_) => {
+                eprintln!("Skipping unknown loot item '{}' for {}", item_name, name);
+                return None;
+            }
+        }
+    }
+
+    return None;
+}
+
+/// Find where a killed entity's loot lands. This is the tile it died on, unless that tile
+/// is blocked, such as by a wall that crushed it, in which case the closest open tile is used.
+fn find_drop_pos(pos: Pos, killed_id: EntityId, level: &Level) -> Option<Pos> {
+    let open = |check_pos: Pos| {
+        level.map.is_within_bounds(check_pos) &&
+        !level.map[check_pos].block_move &&
+        level.has_blocking_entity(check_pos).map_or(true, |entity_id| entity_id == killed_id)
+    };
+
+    if open(pos) {
+        return Some(pos);
+    }
+
+    for radius in 1..=LOOT_SCATTER_RADIUS {
+        for offset_y in -radius..=radius {
+            for offset_x in -radius..=radius {
+                // only the ring at this radius, as the tiles inside it were already checked
+                let check_pos = Pos::new(pos.x + offset_x, pos.y + offset_y);
+                if offset_x.abs().max(offset_y.abs()) == radius && open(check_pos) {
+                    return Some(check_pos);
+                }
+            }
+        }
+    }
+
+    return None;
+}
+
+/// Start a fire at the given position, returning whether it caught. Fire cannot burn
+/// in walls or water, and the number of burning tiles is capped to keep it from spreading forever.
+pub fn resolve_ignite(pos: Pos, level: &mut Level, config: &Config, msg_log: &mut MsgLog) -> bool {
+    if !level.map.is_within_bounds(pos) ||
+       level.map.tile_is_blocking(pos) ||
+       level.map[pos].tile_type == TileType::Water {
+        return false;
+    }
+
+    let mut num_fires = 0;
+    for entity_id in level.entities.ids.iter() {
+        if level.entities.name[entity_id] == EntityName::Fire && !level.entities.needs_removal[entity_id] {
+            if level.entities.pos[entity_id] == pos {
+                return false;
+            }
+            num_fires += 1;
+        }
+    }
+
+    if num_fires >= config.fire_max_tiles {
+        return false;
+    }
+
+    make_fire(&mut level.entities, config, pos, msg_log);
+    return true;
+}
+
+/// Burn everything standing in fire, and spread fire onto neighboring grass.
+/// Fire only spreads from tiles that were already burning at the start of the turn.
+pub fn resolve_fire(game: &mut Game) {
+    let fire_ids = game.level.entities.ids.iter()
+                                          .filter(|id| game.level.entities.name[id] == EntityName::Fire)
+                                          .filter(|id| !game.level.entities.needs_removal[id])
+                                          .map(|id| *id)
+                                          .collect::<Vec<EntityId>>();
+
+    for fire_id in fire_ids {
+        let fire_pos = game.level.entities.pos[&fire_id];
+
+        for entity_id in game.level.get_entities_at_pos(fire_pos) {
+            if game.level.entities.name[&entity_id] == EntityName::Grass {
+                remove_entity(entity_id, &mut game.level);
+            } else if game.level.entities.take_damage(entity_id, fire_id, game.config.fire_damage) {
+                game.msg_log.log(Msg::Burned(entity_id, fire_id, game.config.fire_damage));
+
+                if game.level.entities.hp[&entity_id].hp <= 0 {
+                    game.msg_log.log(Msg::Killed(fire_id, entity_id, game.config.fire_damage));
+                }
+            }
+        }
+
+        // burning grass leaves smoke behind, and spreads to the grass around it
+        if game.level.map[fire_pos].surface == Surface::Grass {
+            game.level.map[fire_pos].surface = Surface::Floor;
+            game.level.map[fire_pos].block_sight = false;
+            make_smoke(&mut game.level.entities, &game.config, fire_pos, 1, &mut game.msg_log);
+        }
+
+        for dir in Direction::directions().iter() {
+            let next_pos = dir.offset_pos(fire_pos, 1);
+            if game.level.map.is_within_bounds(next_pos) &&
+               game.level.map[next_pos].surface == Surface::Grass &&
+               game.level.map.path_blocked_move(fire_pos, next_pos).is_none() {
+                resolve_ignite(next_pos, &mut game.level, &game.config, &mut game.msg_log);
+            }
+        }
+    }
+}
+
+/// Apply poison damage at the start of each turn, crediting the poisoner with any kills.
+pub fn resolve_poison(game: &mut Game) {
+    for entity_id in game.level.entities.ids.clone() {
+        let status = game.level.entities.status[&entity_id];
+        if status.poisoned == 0 || !status.alive {
+            continue;
+        }
+
+        game.level.entities.status[&entity_id].poisoned -= 1;
+
+        // if the poisoner is gone, the poison is credited to the entity itself
+        let mut poisoner = status.poisoner.unwrap_or(entity_id);
+        if !game.level.entities.ids.contains(&poisoner) {
+            poisoner = entity_id;
+        }
+
+        let damage = 1;
+        if game.level.entities.take_damage(entity_id, poisoner, damage) {
+            game.msg_log.log(Msg::PoisonDamage(entity_id, poisoner, damage));
+
+            if game.level.entities.hp[&entity_id].hp <= 0 {
+                game.msg_log.log(Msg::Killed(poisoner, entity_id, damage));
+            }
+        }
+
+        if game.level.entities.status[&entity_id].poisoned == 0 {
+            game.level.entities.status[&entity_id].poisoner = None;
+        }
+    }
+}
+
+fn resolve_summon(summoner_id: EntityId, pos: Pos, game: &mut Game) {
+    // the tile may have been taken since the summon was chosen
+    if game.level.pos_blocked(pos) {
+        return;
+    }
+
+    let minion_id = make_gol(&mut game.level.entities, &game.config, pos, &mut game.msg_log);
+    game.level.entities.summoned_by.insert(minion_id, summoner_id);
+
+    // minions join in on whatever their summoner is doing
+    let behavior = game.level.entities.behavior[&summoner_id];
+    game.msg_log.log(Msg::StateChange(minion_id, behavior));
+}
+
+/// Move an item from the target's inventory to the thief. The item stays off the map
+/// until the thief is killed, and is lost with the thief if the level is left first.
+fn resolve_stole(thief_id: EntityId, target_id: EntityId, item_id: EntityId, level: &mut Level) {
+    // the item may have been used or dropped since the theft was chosen
+    if !level.entities.inventory[&target_id].contains(&item_id) {
+        return;
+    }
+
+    level.entities.remove_item(target_id, item_id);
+    level.entities.stolen.insert(thief_id, item_id);
+}
+
+fn resolve_pick_item_up(entity_id: EntityId, level: &mut Level, msg_log: &mut MsgLog, config: &Config) {
     let entity_pos = level.entities.pos[&entity_id];
 
     if let Some(item_id) = level.item_at_pos(entity_pos) {
+        let item = level.entities.item[&item_id];
+        if level.entities.needs_new_slot(entity_id, item) &&
+           level.entities.inventory_slots(entity_id).len() >= config.inventory_slots {
+            msg_log.log(Msg::InventoryFull(entity_id, item_id));
+            return;
+        }
+
         msg_log.log(Msg::PickedUp(entity_id, item_id));
 
         let to_drop_index = level.entities.pick_up_item(entity_id, item_id);
@@ -1661,11 +2463,57 @@ fn resolve_pick_item_up(entity_id: EntityId, level: &mut Level, msg_log: &mut Ms
     }
 }
 
+/// Drop every item in an inventory slot, and pick up the given item in its place.
+/// Nothing happens if the item is no longer under the entity.
+fn resolve_drop_to_pick_up(entity_id: EntityId, slot_index: usize, item_id: EntityId, level: &mut Level, msg_log: &mut MsgLog) {
+    let entity_pos = level.entities.pos[&entity_id];
+    if level.item_at_pos(entity_pos) != Some(item_id) {
+        return;
+    }
+
+    let slots = level.entities.inventory_slots(entity_id);
+    if let Some(slot) = slots.get(slot_index) {
+        // drop from the back so earlier indices stay valid
+        for item_index in slot.iter().rev() {
+            inventory_drop_item(entity_id, *item_index, level, msg_log);
+        }
+
+        msg_log.log(Msg::PickedUp(entity_id, item_id));
+
+        if let Some(to_drop_index) = level.entities.pick_up_item(entity_id, item_id) {
+            msg_log.log(Msg::DropItem(entity_id, to_drop_index as u64));
+        }
+    }
+}
+
 fn resolve_triggered(trigger: EntityId, entity_id: EntityId, level: &mut Level, msg_log: &mut MsgLog) {
     if level.entities.name[&trigger] == EntityName::GateTrigger {
         let wall_pos = level.entities.gate_pos[&trigger];
 
-        if level.entities.status[&trigger].active {
+        // a boulder on the trigger holds the gate open for as long as it stays there, so
+        // the gate follows the trigger's current weight rather than toggling on each event.
+        let trigger_pos = level.entities.pos[&trigger];
+        let weighed_down = level.entities.ids.iter().any(|id| {
+            level.entities.name[id] == EntityName::Boulder &&
+            level.entities.pos[id] == trigger_pos &&
+            !level.entities.needs_removal[id]
+        });
+
+        let open;
+        if level.entities.name[&entity_id] == EntityName::Boulder || weighed_down {
+            open = weighed_down;
+        } else {
+            open = !level.entities.status[&trigger].active;
+        }
+
+        if level.entities.status[&trigger].active == open {
+            return;
+        }
+
+        if open {
+            level.entities.status[&trigger].active = true;
+            level.map[wall_pos] = Tile::empty();
+        } else {
             // raise the gate
             level.entities.status[&trigger].active = false;
 
@@ -1674,12 +2522,18 @@ fn resolve_triggered(trigger: EntityId, entity_id: EntityId, level: &mut Level,
             if level.has_entity(wall_pos).is_none() {
                 level.map[wall_pos] = Tile::wall();
             }
-        } else {
-            level.entities.status[&trigger].active = true;
-            level.map[wall_pos] = Tile::empty();
         }
 
         msg_log.log(Msg::GateTriggered(trigger, entity_id));
+    } else if level.entities.name[&trigger] == EntityName::HintTrigger {
+        // only the player sees hints, and each hint is only given once.
+        if level.entities.typ[&entity_id] == EntityType::Player {
+            let (hint_index, consumed) = level.entities.hint[&trigger];
+            if !consumed {
+                level.entities.hint[&trigger] = (hint_index, true);
+                msg_log.log(Msg::Hint(hint_index));
+            }
+        }
     }
 }
 
@@ -1721,6 +2575,388 @@ fn resolve_sound_hit_tile(cause_id: EntityId, source_pos: Pos, tile_pos: Pos, ga
     }
 }
 
+/// Build a game from an ASCII map in the format read by `read_map_text`.
+#[cfg(test)]
+pub fn setup_test_game(map_str: &str) -> Game {
+    return setup_test_game_config(map_str, Config::from_file("../config.yaml"));
+}
+
+/// Set up a test game from a text map, as with setup_test_game, using the given config.
+#[cfg(test)]
+pub fn setup_test_game_config(map_str: &str, config: Config) -> Game {
+    let mut config = config;
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+    map_construct(&config.map_load, &mut game);
+
+    let player_pos = read_map_text(map_str, &mut game).expect("Invalid test map");
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.set_pos(player_id, player_pos);
+
+    return game;
+}
+
+#[test]
+pub fn test_setup_test_game() {
+    let game = setup_test_game("
+        @.#
+        .~\"
+        g.;
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let gol_id = game.level.find_by_name(EntityName::Gol).unwrap();
+
+    assert_eq!((3, 3), game.level.map.size());
+    assert_eq!(Pos::new(0, 0), game.level.entities.pos[&player_id]);
+    assert_eq!(Pos::new(0, 2), game.level.entities.pos[&gol_id]);
+    assert_eq!(TileType::Wall, game.level.map[(2, 0)].tile_type);
+    assert_eq!(TileType::Water, game.level.map[(1, 1)].tile_type);
+    assert_eq!(Surface::Grass, game.level.map[(2, 1)].surface);
+    assert!(game.level.map[(2, 2)].block_sight);
+}
+
+#[test]
+pub fn test_read_map_text_errors() {
+    let mut game = setup_test_game("
+        @.
+    ");
+
+    let result = read_map_text("..\n.x\n", &mut game);
+    assert_eq!(Err("unexpected character 'x' at line 2, column 2".to_string()), result);
+
+    let result = read_map_text("..\n..\n", &mut game);
+    assert!(result.is_err());
+
+    // a bad map leaves the current level in place
+    assert_eq!((2, 1), game.level.map.size());
+}
+
+#[test]
+pub fn test_text_file_map_missing() {
+    let mut game = Game::new(0, Config::from_file("../config.yaml"));
+
+    // a missing map file loads the empty map in its place
+    map_construct(&MapLoadConfig::TextFile("no_such_map.txt".to_string()), &mut game);
+    assert_eq!(MapLoadConfig::Empty, game.settings.map_load_config);
+    assert_eq!((10, 10), game.level.map.size());
+}
+
+#[test]
+pub fn test_read_map_text_sprite_override() {
+    let mut game = setup_test_game("
+        @.g
+        sprite 2 0 statue_2
+    ");
+
+    let gol_id = game.level.find_by_name(EntityName::Gol).unwrap();
+    assert_eq!((3, 1), game.level.map.size());
+    assert_eq!(Some(&"statue_2".to_string()), game.level.entities.sprite_override.get(&gol_id));
+
+    let result = read_map_text("@.g\nsprite 1 0 statue_2\n", &mut game);
+    assert_eq!(Err("sprite override at (1, 0) has no entity".to_string()), result);
+
+    let result = read_map_text("@.g\nsprite 2 statue_2\n", &mut game);
+    assert!(result.is_err());
+}
+
+#[test]
+pub fn test_dash_stops_at_wall() {
+    let mut game = setup_test_game("
+        @...#.
+        ......
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    game.msg_log.log(Msg::TryDash(player_id, Direction::Right));
+    resolve_messages(&mut game);
+
+    assert_eq!(Pos::new(3, 0), game.level.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_war_cry_haste_stops_at_wall() {
+    let mut game = setup_test_game("
+        @...#.
+        ......
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.energy[&player_id] = 1;
+
+    game.msg_log.log(Msg::TryWarCry(player_id, SKILL_WAR_CRY_TURNS));
+    resolve_messages(&mut game);
+    assert_eq!(SKILL_WAR_CRY_TURNS, game.level.entities.status[&player_id].hasted);
+
+    // a hasted walk covers an extra tile
+    game.level.entities.took_turn[&player_id] = 0;
+    game.msg_log.log(Msg::TryMove(player_id, Direction::Right, 1, MoveMode::Walk));
+    resolve_messages(&mut game);
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&player_id]);
+
+    // but the extra tile does not carry the player into a wall
+    game.level.entities.took_turn[&player_id] = 0;
+    game.msg_log.log(Msg::TryMove(player_id, Direction::Right, 1, MoveMode::Walk));
+    resolve_messages(&mut game);
+    assert_eq!(Pos::new(3, 0), game.level.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_ping_marks_enemies_in_radius() {
+    let mut game = setup_test_game("
+        @.p.....p
+        .........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.energy[&player_id] = 1;
+
+    // a disguised mimic within the radius is not given away
+    make_mimic(&mut game.level.entities, &game.config, Pos::new(2, 1), &mut game.msg_log);
+    resolve_messages(&mut game);
+    game.msg_log.info_messages.clear();
+
+    game.msg_log.log(Msg::Ping(player_id, Pos::new(0, 0)));
+    resolve_messages(&mut game);
+
+    let markers = game.msg_log.info_messages.iter().filter_map(|msg| {
+        if let InfoMsg::PingMarker(pos) = msg { Some(*pos) } else { None }
+    }).collect::<Vec<Pos>>();
+    assert_eq!(vec!(Pos::new(2, 0)), markers);
+}
+
+#[test]
+pub fn test_mimic_revealed() {
+    let mut game = setup_test_game("
+        @.......
+        ........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let near_mimic = make_mimic(&mut game.level.entities, &game.config, Pos::new(3, 1), &mut game.msg_log);
+    let far_mimic = make_mimic(&mut game.level.entities, &game.config, Pos::new(5, 0), &mut game.msg_log);
+    resolve_messages(&mut game);
+    assert!(game.level.entities.status[&near_mimic].disguised);
+
+    // not yet adjacent, so the mimic stays hidden
+    game.msg_log.log(Msg::TryMove(player_id, Direction::Right, 1, MoveMode::Walk));
+    resolve_messages(&mut game);
+    assert!(game.level.entities.status[&near_mimic].disguised);
+
+    // stepping next to the mimic reveals it
+    game.level.entities.took_turn[&player_id] = 0;
+    game.msg_log.log(Msg::TryMove(player_id, Direction::Right, 1, MoveMode::Walk));
+    resolve_messages(&mut game);
+    assert!(!game.level.entities.status[&near_mimic].disguised);
+    assert_eq!(Behavior::Attacking(player_id), game.level.entities.behavior[&near_mimic]);
+    assert!(game.level.entities.status[&far_mimic].disguised);
+
+    // a thrown stone also reveals a mimic
+    let player_pos = game.level.entities.pos[&player_id];
+    let stone_id = make_stone(&mut game.level.entities, &game.config, player_pos, &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, stone_id);
+    game.msg_log.log(Msg::ItemThrow(player_id, stone_id, player_pos, Pos::new(5, 0), false));
+    resolve_messages(&mut game);
+    assert!(!game.level.entities.status[&far_mimic].disguised);
+
+    // backing away does not disguise the mimic again
+    game.level.entities.took_turn[&player_id] = 0;
+    game.msg_log.log(Msg::TryMove(player_id, Direction::Left, 1, MoveMode::Walk));
+    resolve_messages(&mut game);
+    assert!(!game.level.entities.status[&near_mimic].disguised);
+}
+
+#[test]
+pub fn test_boulder_holds_gate_trigger() {
+    let mut game = setup_test_game("
+        @......
+        ......#
+        ..~....
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let boulder = make_boulder(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    let trigger = make_gate_trigger(&mut game.level.entities, &game.config, Pos::new(2, 0), &mut game.msg_log);
+    let gate_pos = Pos::new(4, 1);
+    game.level.entities.gate_pos[&trigger] = gate_pos;
+    game.level.map[gate_pos] = Tile::wall();
+    resolve_messages(&mut game);
+
+    // pushing the boulder onto the trigger opens the gate
+    game.msg_log.log(Msg::TryMove(player_id, Direction::Right, 1, MoveMode::Walk));
+    resolve_messages(&mut game);
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&boulder]);
+    assert_eq!(Pos::new(1, 0), game.level.entities.pos[&player_id]);
+    assert!(game.level.entities.status[&trigger].active);
+    assert_ne!(TileType::Wall, game.level.map[gate_pos].tile_type);
+
+    // the gate follows the boulder on the trigger, so repeated or out of order
+    // trigger events do not flip it
+    game.msg_log.log(Msg::Triggered(trigger, boulder));
+    game.msg_log.log(Msg::Untriggered(trigger, boulder));
+    resolve_messages(&mut game);
+    assert!(game.level.entities.status[&trigger].active);
+    assert_ne!(TileType::Wall, game.level.map[gate_pos].tile_type);
+
+    // rolling the boulder off the trigger closes the gate again
+    game.msg_log.log(Msg::Pushed(player_id, boulder, Direction::Down, 1, false));
+    resolve_messages(&mut game);
+    assert_eq!(Pos::new(2, 1), game.level.entities.pos[&boulder]);
+    assert!(!game.level.entities.status[&trigger].active);
+    assert_eq!(TileType::Wall, game.level.map[gate_pos].tile_type);
+
+    // a boulder pushed into water sinks
+    game.msg_log.log(Msg::Pushed(player_id, boulder, Direction::Down, 1, false));
+    resolve_messages(&mut game);
+    assert!(game.level.entities.needs_removal[&boulder]);
+
+    // a boulder pushed into a wall stops, leaving the wall in place
+    let other_boulder = make_boulder(&mut game.level.entities, &game.config, Pos::new(5, 1), &mut game.msg_log);
+    resolve_messages(&mut game);
+    game.msg_log.log(Msg::Pushed(player_id, other_boulder, Direction::Right, 1, false));
+    resolve_messages(&mut game);
+    assert_eq!(Pos::new(5, 1), game.level.entities.pos[&other_boulder]);
+    assert!(!game.level.entities.needs_removal[&other_boulder]);
+    assert_eq!(TileType::Wall, game.level.map[(6, 1)].tile_type);
+}
+
+#[test]
+pub fn test_attack_positions_melee() {
+    let mut game = setup_test_game("
+        .....
+        .#...
+        ..@..
+        .....
+        ....p
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let pawn_id = game.level.find_by_name(EntityName::Pawn).unwrap();
+
+    let config = game.config.clone();
+    let positions = game.level.attack_positions_for(pawn_id, player_id, &config);
+
+    // every tile next to the player except the wall
+    assert_eq!(7, positions.len());
+    assert!(!positions.contains(&Pos::new(1, 1)));
+    assert!(positions.contains(&Pos::new(3, 3)));
+
+    // the pawn is left where it was
+    assert_eq!(Pos::new(4, 4), game.level.entities.pos[&pawn_id]);
+}
+
+#[test]
+pub fn test_attack_positions_ranged() {
+    let mut game = setup_test_game("
+        .......
+        .......
+        ..#....
+        ...@...
+        .......
+        .......
+        ......g
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let gol_id = game.level.find_by_name(EntityName::Gol).unwrap();
+
+    let config = game.config.clone();
+    let positions = game.level.attack_positions_for(gol_id, player_id, &config);
+
+    // diagonals further than one tile are included
+    assert!(positions.contains(&Pos::new(5, 1)));
+    assert!(positions.contains(&Pos::new(1, 5)));
+    assert!(positions.contains(&Pos::new(5, 5)));
+
+    // the wall and the tiles behind it are excluded
+    assert!(!positions.contains(&Pos::new(2, 2)));
+    assert!(!positions.contains(&Pos::new(1, 1)));
+    assert!(!positions.contains(&Pos::new(0, 0)));
+
+    // the gol's own position is not included
+    assert!(!positions.contains(&Pos::new(6, 6)));
+}
+
+#[test]
+pub fn test_stone_skin_reflects_push() {
+    let mut game = setup_test_game("
+        ......
+        .@g...
+        ......
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let gol_id = game.level.find_by_name(EntityName::Gol).unwrap();
+    game.level.entities.status[&player_id].stone = 2;
+
+    let trap_id = make_spike_trap(&mut game.level.entities, &game.config, Pos::new(3, 1), &mut game.msg_log);
+
+    game.msg_log.log(Msg::Pushed(gol_id, player_id, Direction::Left, 1, true));
+    resolve_messages(&mut game);
+
+    assert_eq!(Pos::new(1, 1), game.level.entities.pos[&player_id]);
+    assert_eq!(Pos::new(3, 1), game.level.entities.pos[&gol_id]);
+    assert!(game.level.entities.status[&gol_id].alive);
+
+    // the gol was pushed rather than stepping, so it makes no footstep sound and springs no trap
+    let sound_radius = game.config.sound_radius_monster;
+    assert!(!game.msg_log.turn_messages.contains(&Msg::Sound(gol_id, Pos::new(3, 1), sound_radius)));
+    assert!(!game.msg_log.turn_messages.contains(&Msg::SpikeTrapTriggered(trap_id, gol_id)));
+}
+
+#[test]
+pub fn test_stone_skin_reflect_blocked_by_wall() {
+    let mut game = setup_test_game("
+        .@g#.
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let gol_id = game.level.find_by_name(EntityName::Gol).unwrap();
+    game.level.entities.status[&player_id].stone = 2;
+
+    game.msg_log.log(Msg::Pushed(gol_id, player_id, Direction::Left, 1, true));
+    resolve_messages(&mut game);
+
+    // the gol can not be pushed into the wall, so it stays put and is not crushed
+    assert_eq!(Pos::new(1, 0), game.level.entities.pos[&player_id]);
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&gol_id]);
+    assert!(game.level.entities.status[&gol_id].alive);
+}
+
+#[test]
+pub fn test_planned_move_for_alert_enemy() {
+    let mut game = setup_test_game("
+        @...p
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let pawn_id = game.level.find_by_name(EntityName::Pawn).unwrap();
+    game.level.entities.direction[&pawn_id] = Direction::Left;
+
+    // an idle pawn has no planned move
+    game.level.entities.behavior[&pawn_id] = Behavior::Idle;
+    assert_eq!(None, ai_planned_move(&mut game, pawn_id));
+
+    // an attacking pawn steps towards the player
+    game.level.entities.behavior[&pawn_id] = Behavior::Attacking(player_id);
+    let planned_pos = ai_planned_move(&mut game, pawn_id);
+    assert_eq!(Some(Pos::new(3, 0)), planned_pos);
+
+    // planning does not move the pawn, and the pawn's turn goes to the planned position
+    assert_eq!(Pos::new(4, 0), game.level.entities.pos[&pawn_id]);
+    let mut turn_game = game.clone();
+    turn_game.level.entities.took_turn[&pawn_id] = 0;
+    turn_game.msg_log.clear();
+    ai_take_turn(pawn_id, &mut turn_game.level, &turn_game.config, 1.0, &mut turn_game.rng, &mut turn_game.msg_log);
+    resolve_messages(&mut turn_game);
+    assert_eq!(planned_pos, Some(turn_game.level.entities.pos[&pawn_id]));
+
+    // once the player moves into reach, the pawn attacks instead of moving
+    game.level.entities.pos[&player_id] = Pos::new(3, 0);
+    assert_eq!(None, ai_planned_move(&mut game, pawn_id));
+}
+
 #[test]
 pub fn test_ai_start_investigating_doesnt_take_turn() {
     let mut config = Config::from_file("../config.yaml");
@@ -1746,3 +2982,173 @@ pub fn test_ai_start_investigating_doesnt_take_turn() {
     assert!(matches!(game.level.entities.behavior[&gol], Behavior::Investigating(_)));
     assert_eq!(Pos::new(2, 0), game.level.entities.pos[&gol]);
 }
+
+#[test]
+pub fn test_isolated_enemy_hesitates() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+    ");
+    game.config.morale_hesitate_chance = 1.0;
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(2, 0), &mut game.msg_log);
+    let spire = make_spire(&mut game.level.entities, &game.config, Pos::new(9, 1), &mut game.msg_log);
+    resolve_messages(&mut game);
+
+    // spires are fearless, while a golem on its own loses its nerve
+    assert!(roguelike_core::ai::ai_hesitates(gol, &game.level, &mut game.rng, &game.config));
+    assert!(!roguelike_core::ai::ai_hesitates(spire, &game.level, &mut game.rng, &game.config));
+
+    game.level.entities.behavior[&gol] = Behavior::Attacking(player_id);
+    game.step_game(InputAction::Pass);
+    assert!(matches!(game.level.entities.behavior[&gol], Behavior::Investigating(_)));
+
+    // an ally nearby keeps the golem from hesitating
+    make_pawn(&mut game.level.entities, &game.config, Pos::new(3, 1), &mut game.msg_log);
+    resolve_messages(&mut game);
+    assert!(!roguelike_core::ai::ai_hesitates(gol, &game.level, &mut game.rng, &game.config));
+}
+
+#[test]
+pub fn test_summoner_spawns_minions() {
+    let mut game = setup_test_game("
+        @...#.#
+        ....#.#
+    ");
+    game.config.summon_minions_die_with_summoner = true;
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let summoner = make_summoner(&mut game.level.entities, &game.config, Pos::new(5, 0), &mut game.msg_log);
+    resolve_messages(&mut game);
+
+    // the only free tile next to the summoner is below it
+    assert!(roguelike_core::ai::ai_summon(summoner, &mut game.level, &mut game.rng, &mut game.msg_log, &game.config));
+    resolve_messages(&mut game);
+
+    let minion = game.level.find_by_name(EntityName::Gol).unwrap();
+    assert_eq!(Pos::new(5, 1), game.level.entities.pos[&minion]);
+    assert_eq!(Some(&summoner), game.level.entities.summoned_by.get(&minion));
+
+    // the summoner waits out its cooldown, and then has nowhere left to summon
+    assert!(!roguelike_core::ai::ai_summon(summoner, &mut game.level, &mut game.rng, &mut game.msg_log, &game.config));
+    game.level.entities.summon_cooldown[&summoner] = 0;
+    assert!(!roguelike_core::ai::ai_summon(summoner, &mut game.level, &mut game.rng, &mut game.msg_log, &game.config));
+
+    // killing the summoner takes its minions with it
+    game.msg_log.log(Msg::Killed(player_id, summoner, 12));
+    resolve_messages(&mut game);
+    assert!(!game.level.entities.status[&minion].alive);
+}
+
+#[test]
+pub fn test_fire_spreads_through_grass() {
+    let mut game = setup_test_game("
+        @.....
+        .\"\".~\"
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let start_hp = game.level.entities.hp[&player_id].hp;
+
+    // fire does not catch in water
+    assert!(!resolve_ignite(Pos::new(4, 1), &mut game.level, &game.config, &mut game.msg_log));
+
+    game.msg_log.log(Msg::Ignite(Pos::new(0, 0)));
+    game.msg_log.log(Msg::Ignite(Pos::new(1, 1)));
+    resolve_messages(&mut game);
+
+    game.step_game(InputAction::Pass);
+    game.step_game(InputAction::Pass);
+
+    // the player is burned, and the fire burns away the grass it started on
+    assert!(game.level.entities.hp[&player_id].hp < start_hp);
+    assert_eq!(Surface::Floor, game.level.map[(1, 1)].surface);
+
+    for _ in 0..4 {
+        game.step_game(InputAction::Pass);
+    }
+
+    // the fire spreads to the neighboring grass, but stops at the floor and water, leaving the far grass untouched
+    assert_eq!(Surface::Floor, game.level.map[(2, 1)].surface);
+    assert_eq!(Surface::Grass, game.level.map[(5, 1)].surface);
+    assert_eq!(0, game.level.entities.ids.iter().filter(|id| game.level.entities.name[id] == EntityName::Fire).count());
+
+    // the number of burning tiles is capped
+    game.config.fire_max_tiles = 0;
+    assert!(!resolve_ignite(Pos::new(5, 1), &mut game.level, &game.config, &mut game.msg_log));
+}
+
+#[test]
+pub fn test_thief_steals_and_drops_item() {
+    let mut game = setup_test_game("
+        @....
+        .....
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let dagger = make_dagger(&mut game.level.entities, &game.config, Pos::new(4, 1), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, dagger);
+
+    let thief = make_thief(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    let other_thief = make_thief(&mut game.level.entities, &game.config, Pos::new(0, 1), &mut game.msg_log);
+    resolve_messages(&mut game);
+
+    // the thief takes the only item, and runs
+    assert!(roguelike_core::ai::ai_steal(thief, player_id, &mut game.level, &mut game.rng, &mut game.msg_log));
+    resolve_messages(&mut game);
+    assert_eq!(0, game.level.entities.inventory[&player_id].len());
+    assert_eq!(Some(&dagger), game.level.entities.stolen.get(&thief));
+    assert_eq!(Behavior::Fleeing(player_id), game.level.entities.behavior[&thief]);
+
+    // with nothing left to steal, the other thief attacks instead
+    assert!(!roguelike_core::ai::ai_steal(other_thief, player_id, &mut game.level, &mut game.rng, &mut game.msg_log));
+
+    // killing the thief drops the item where it fell
+    let thief_pos = game.level.entities.pos[&thief];
+    game.msg_log.log(Msg::Killed(player_id, thief, 8));
+    resolve_messages(&mut game);
+    assert_eq!(thief_pos, game.level.entities.pos[&dagger]);
+    assert_eq!(Some(dagger), game.level.item_at_pos(thief_pos));
+}
+
+#[test]
+pub fn test_enemy_loot_drops() {
+    let mut game = setup_test_game("
+        @....
+        .....
+        .....
+    ");
+    game.config.loot_table = vec!((EntityName::Gol, "herb".to_string(), 1));
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    // loot is dropped where the enemy died
+    let gol_pos = Pos::new(2, 0);
+    let gol = make_gol(&mut game.level.entities, &game.config, gol_pos, &mut game.msg_log);
+    resolve_messages(&mut game);
+    game.msg_log.log(Msg::Killed(player_id, gol, 10));
+    resolve_messages(&mut game);
+    let herb = game.level.item_at_pos(gol_pos).unwrap();
+    assert_eq!(Some(&Item::Herb), game.level.entities.item.get(&herb));
+
+    // an enemy crushed into a wall drops its loot on an open tile next to it
+    let gol_pos = Pos::new(2, 2);
+    let gol = make_gol(&mut game.level.entities, &game.config, gol_pos, &mut game.msg_log);
+    resolve_messages(&mut game);
+    game.level.map[gol_pos] = Tile::wall();
+    game.msg_log.log(Msg::Killed(player_id, gol, 10));
+    resolve_messages(&mut game);
+    assert!(game.level.item_at_pos(gol_pos).is_none());
+    let num_herbs = game.level.entities.ids.iter().filter(|id| game.level.entities.item.get(id) == Some(&Item::Herb)).count();
+    assert_eq!(2, num_herbs);
+
+    // a table of only 'nothing' never drops anything
+    game.config.loot_table = vec!((EntityName::Gol, "nothing".to_string(), 5));
+    let gol_pos = Pos::new(4, 0);
+    let gol = make_gol(&mut game.level.entities, &game.config, gol_pos, &mut game.msg_log);
+    resolve_messages(&mut game);
+    game.msg_log.log(Msg::Killed(player_id, gol, 10));
+    resolve_messages(&mut game);
+    assert!(game.level.item_at_pos(gol_pos).is_none());
+}
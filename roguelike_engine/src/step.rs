@@ -1,16 +1,15 @@
+use std::collections::HashSet;
+
 use logging_timer::timer;
 
 use roguelike_utils::comp::*;
-
-#[cfg(test)]
 use roguelike_utils::math::Pos;
 
-#[cfg(test)]
 use roguelike_map::*;
 
 use roguelike_core::types::*;
 use roguelike_core::ai::*;
-use roguelike_core::messaging::{Msg, MsgLog};
+use roguelike_core::messaging::{Msg, MsgLog, InfoMsg};
 
 #[cfg(test)]
 use roguelike_core::movement::MoveMode;
@@ -26,194 +25,2119 @@ use roguelike_core::level::*;
 use roguelike_core::constants::*;
 
 #[cfg(test)]
-use crate::actions::InputAction;
+use crate::actions::{InputAction, use_targets};
 #[cfg(test)]
 use crate::generation::*;
 #[cfg(test)]
 use crate::map_construct::*;
 
-use crate::resolve::resolve_messages;
-use crate::game::*;
+use crate::resolve::{resolve_messages, resolve_fire, resolve_poison};
+#[cfg(test)]
+use crate::resolve::{setup_test_game, setup_test_game_config};
+use crate::game::*;
+
+
+pub fn step_logic(game: &mut Game) -> bool {
+    // clean up removable entities
+    clean_entities(&mut game.level.entities, &mut game.msg_log);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    // Thaw entities at the start of the player's turn.
+    if game.level.entities.took_turn[&player_id] != 0 {
+        for id in game.level.entities.ids.iter() {
+            game.level.entities.took_turn[id] = 0;
+            game.level.entities.status[id].blinked = false;
+
+            // Slowly thaw any frozen entities.
+            if let Some(status) = game.level.entities.status.get_mut(id) {
+                if status.frozen > 0 {
+                    game.msg_log.log(Msg::Thaw(*id, 1));
+                }
+            }
+        }
+
+        resolve_poison(game);
+
+        resolve_fire(game);
+    }
+
+    resolve_messages(game);
+
+    // the player's roll has finished moving, so it lands on its final tile
+    if game.level.entities.status[&player_id].rolling {
+        game.level.entities.status[&player_id].rolling = false;
+        game.msg_log.log(Msg::RollLanded(player_id));
+        resolve_messages(game);
+    }
+
+    let won_level = level_exit_condition_met(&game.level, game.settings.exit_condition);
+
+    // resolve enemy action
+    let monster = timer!("MONSTER");
+
+    if game.level.entities.status[&player_id].alive && !won_level {
+        let turn = game.level.entities.took_turn[&player_id];
+        if turn != 0 {
+            // Handle player stamina change if they took a turn
+            if turn & (Turn::Run.turn() | Turn::Jump.turn() | Turn::Attack.turn()) != 0 {
+                if game.level.entities.stamina[&player_id] > 0 {
+                    game.msg_log.log(Msg::UsedStamina(player_id, 1));
+                }
+            } else {
+                if game.level.entities.stamina[&player_id] < game.config.player_stamina_max {
+                    game.msg_log.log(Msg::GainStamina(player_id, 1));
+                }
+            }
+
+            // while time is stopped enemies do not act, though the rest of the level,
+            // such as traps the player steps on, still does.
+            if game.level.entities.status[&player_id].time_stop > 0 {
+                game.level.entities.status[&player_id].time_stop -= 1;
+            } else {
+                step_ai(game);
+            }
+            run_thumpers(game);
+
+            // a mirror only lasts for the enemies' turn after it is raised
+            game.level.entities.status[&player_id].reflecting = false;
+        }
+    }
+    drop(monster);
+
+    resolve_messages(game);
+
+    // check status effects
+    for entity_id in game.level.entities.ids.iter() {
+        if let Some(mut status) = game.level.entities.status.get_mut(entity_id) {
+            // frozen status is handled separately.
+
+            if status.stone > 0 {
+                status.stone -= 1;
+            }
+
+            if status.soft_steps > 0 {
+                status.soft_steps -= 1;
+            }
+
+            if status.hasted > 0 {
+                status.hasted -= 1;
+            }
+        }
+    }
+
+    if game.level.entities.took_turn[&player_id] != 0 {
+        game.settings.turn_count += 1;
+        game.stats.turns += 1;
+
+        game.level.decay_scent();
+
+        game.level.entities.count_down_cooldowns(player_id);
+
+        resolve_marks(game);
+
+        // a charged attack is lost if it is not used or held by the end of the next turn
+        if let Some((item_id, dir, turns)) = game.level.entities.status[&player_id].charged_attack {
+            if turns == 0 {
+                game.level.entities.status[&player_id].charged_attack = None;
+            } else {
+                game.level.entities.status[&player_id].charged_attack = Some((item_id, dir, turns - 1));
+            }
+        }
+
+        // a trap set up from a kit only lets the player past it on the turn after it was placed
+        if game.level.entities.took_turn[&player_id] & Turn::InteractTrap.turn() == 0 {
+            for entity_id in game.level.entities.ids.iter() {
+                game.level.entities.status[entity_id].fresh_trap = false;
+            }
+        }
+
+        // each thrown boomerang flies back towards its thrower on the turn after it was thrown
+        let boomerangs: Vec<EntityId> =
+            game.level.entities.ids.iter()
+                                   .filter(|id| game.level.entities.status[id].boomerang.is_some())
+                                   .copied()
+                                   .collect();
+        for item_id in boomerangs {
+            if let Some((thrower_id, turns)) = game.level.entities.status[&item_id].boomerang {
+                if turns == 0 {
+                    game.level.entities.status[&item_id].boomerang = None;
+                    game.msg_log.log(Msg::BoomerangReturn(thrower_id, item_id));
+                } else {
+                    game.level.entities.status[&item_id].boomerang = Some((thrower_id, turns - 1));
+                }
+            }
+        }
+    }
+
+    if game.msg_log.messages.len() > 0 {
+        resolve_messages(game);
+    }
+
+    // perform count down of entities waiting to be removed
+    game.level.entities.count_down();
+
+    for id in game.level.entities.ids.iter() {
+        if game.level.entities.needs_removal[id] {
+            game.msg_log.log(Msg::MarkedForRemoval(*id));
+        }
+    }
+
+    return level_exit_condition_met(&game.level, game.settings.exit_condition);
+}
+
+/// Check whether the exit condition for the game is met.
+fn level_exit_condition_met(level: &Level, exit_condition: LevelExitCondition) -> bool {
+    // loop over objects in inventory, and check whether any
+    // are the key object.
+    let player_id = level.find_by_name(EntityName::Player).unwrap();
+    let player_pos = level.entities.pos[&player_id];
+
+    let mut exit_condition_met = false;
+
+    match exit_condition {
+        LevelExitCondition::RightEdge => {
+            if (player_pos.x + 1) == level.map.width() {
+                exit_condition_met = true;
+            }
+        }
+
+        LevelExitCondition::KeyAndGoal => {
+            // Have Key, On exit tile
+            if let Some(exit_id) = level.find_by_name(EntityName::Exit) {
+                let exit_pos = level.entities.pos[&exit_id];
+
+                let has_key = level.is_in_inventory(player_id, Item::Key).is_some();
+
+                let on_exit_tile = exit_pos == player_pos;
+
+                exit_condition_met = has_key && on_exit_tile;
+            }
+        }
+    }
+
+    return exit_condition_met;
+}
+
+/// Show an impression for each marked enemy outside of the player's FoV, moving the
+/// impression along with the enemy. Marks fade after their duration or when the enemy dies.
+fn resolve_marks(game: &mut Game) {
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    for entity_id in game.level.entities.ids.clone() {
+        if let Some((turns, shown_pos)) = game.level.entities.status[&entity_id].marked {
+            game.msg_log.log_info(InfoMsg::ImpressionCleared(shown_pos));
+
+            if turns == 0 || !game.level.entities.status[&entity_id].alive {
+                game.level.entities.status[&entity_id].marked = None;
+            } else {
+                let pos = game.level.entities.pos[&entity_id];
+                if game.level.is_in_fov(player_id, entity_id, &game.config) != FovResult::Inside {
+                    game.msg_log.log_info(InfoMsg::Impression(pos));
+                }
+                game.level.entities.status[&entity_id].marked = Some((turns - 1, pos));
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_game_step() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+    let mut input_action;
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    map_construct(&MapLoadConfig::Empty, &mut game);
+    game.level.entities.pos[&player_id] = Pos::new(0, 0);
+
+    input_action = InputAction::Move(Direction::Right);
+    game.step_game(input_action);
+    let player_pos = game.level.entities.pos[&player_id];
+    assert_eq!(Pos::new(1, 0), player_pos);
+
+    input_action = InputAction::Move(Direction::Down);
+    game.step_game(input_action);
+    let player_pos = game.level.entities.pos[&player_id];
+    assert_eq!(Pos::new(1, 1), player_pos);
+
+    input_action = InputAction::Move(Direction::Left);
+    game.step_game(input_action);
+    let player_pos = game.level.entities.pos[&player_id];
+    assert_eq!(Pos::new(0, 1), player_pos);
+
+    input_action = InputAction::Move(Direction::Up);
+    game.step_game(input_action);
+    let player_pos = game.level.entities.pos[&player_id];
+    assert_eq!(Pos::new(0, 0), player_pos);
+}
+
+#[test]
+pub fn test_death_records_last_damager() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let trap_id = make_spike_trap(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+
+    // walking onto the spike trap kills the player, and the trap is credited
+    game.step_game(InputAction::Move(Direction::Right));
+    assert!(!game.level.entities.status[&player_id].alive);
+    assert_eq!(Some(trap_id), game.level.entities.status[&player_id].last_damager);
+    assert_eq!(GameState::Lose, game.settings.state);
+
+    game.step_game(InputAction::Restart);
+    assert_eq!(GameState::Playing, game.settings.state);
+}
+
+#[test]
+pub fn test_exit_from_end_screens() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    // quitting from an end screen exits, rather than asking to confirm leaving a finished game
+    for state in [GameState::Lose, GameState::TimeUp, GameState::Win] {
+        game.settings.state = state;
+        game.step_game(InputAction::Exit);
+        assert_eq!(GameState::Exit, game.settings.state);
+    }
+}
+
+#[test]
+pub fn test_restart_new_seed() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    // the restarted run has its own seed, and its map is generated from that seed
+    game.step_game(InputAction::Restart);
+    assert_ne!(0, game.seed);
+
+    let (rng_state, rng_inc) = roguelike_utils::rng::Rand32::new(game.seed).state();
+    assert_eq!(rng_state ^ rng_inc, game.settings.map_seed);
+}
+
+#[test]
+pub fn test_trap_kit() {
+    let mut game = setup_test_game("
+        ..........
+        .@........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let kit = make_spike_trap_kit(&mut game.level.entities, &game.config, Pos::new(1, 1), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, kit);
+
+    // a kit can not be set up on a wall
+    game.level.map[(1, 0)].block_move = true;
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    game.step_game(InputAction::UseDir(Direction::Up));
+    game.step_game(InputAction::FinalizeUse);
+    assert!(game.level.entities.inventory[&player_id].contains(&kit));
+
+    // placing the kit sets up an armed spike trap next to the player
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    game.step_game(InputAction::UseDir(Direction::Right));
+    game.step_game(InputAction::FinalizeUse);
+    assert!(game.level.entities.inventory[&player_id].is_empty());
+    let trap_id = game.level.has_trap(Pos::new(2, 1)).unwrap();
+    assert_eq!(Some(&true), game.level.entities.armed.get(&trap_id));
+
+    // the player can step over the trap on the next turn, but not after that
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(Pos::new(2, 1), game.level.entities.pos[&player_id]);
+    assert!(game.level.entities.status[&player_id].alive);
+
+    game.step_game(InputAction::Move(Direction::Left));
+    game.step_game(InputAction::Move(Direction::Right));
+    assert!(!game.level.entities.status[&player_id].alive);
+}
+
+#[test]
+pub fn test_strict_grass_hides() {
+    let mut config = Config::from_file("../config.yaml");
+    config.grass_blocks_sight_strict = true;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    game.level.entities.direction[&gol] = Direction::Left;
+    game.level.map[(3, 0)] = Tile::tall_grass();
+    game.level.map[(1, 0)] = Tile::tall_grass();
+    game.step_game(InputAction::Pass);
+
+    // the golem is hidden in its grass
+    assert_eq!(FovResult::Outside, game.level.is_in_fov(player_id, gol, &game.config));
+
+    // the player is hidden once in the grass, and the grass stays up while they stand in it
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(Pos::new(1, 0), game.level.entities.pos[&player_id]);
+    assert!(game.level.map[(1, 0)].block_sight);
+    assert_eq!(FovResult::Outside, game.level.is_in_fov(gol, player_id, &game.config));
+
+    // leaving the grass tramples it
+    game.step_game(InputAction::Move(Direction::Down));
+    assert!(!game.level.map[(1, 0)].block_sight);
+}
+
+#[test]
+pub fn test_diagonal_corner_cutting() {
+    let mut config = Config::from_file("../config.yaml");
+    config.allow_diagonal_corner_cutting = false;
+    let map_str = "
+        ..........
+        .@#.......
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ";
+    let mut game = setup_test_game_config(map_str, config.clone());
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    // a wall on only one side of the corner leaves room to step past it
+    game.step_game(InputAction::Move(Direction::UpRight));
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&player_id]);
+
+    // with both sides blocked the player can't squeeze through the corner
+    let mut game = setup_test_game_config(&map_str.replacen("..........", ".#........", 1), config.clone());
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.step_game(InputAction::Move(Direction::UpRight));
+    assert_eq!(Pos::new(1, 1), game.level.entities.pos[&player_id]);
+
+    config.allow_diagonal_corner_cutting = true;
+    let mut game = setup_test_game_config(&map_str.replacen("..........", ".#........", 1), config);
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.step_game(InputAction::Move(Direction::UpRight));
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_main_menu() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    // leaving the game without saving returns to the main menu
+    game.settings.state = GameState::ConfirmQuit;
+    game.step_game(InputAction::ReturnToMenu(false));
+    assert_eq!(GameState::MainMenu, game.settings.state);
+    assert!(game.msg_log.turn_messages.contains(&Msg::ReturnToMenu(false)));
+
+    // continue does nothing without a save
+    game.msg_log.clear();
+    game.settings.save_exists = false;
+    game.step_game(InputAction::SelectEntry(1));
+    assert!(!game.msg_log.turn_messages.contains(&Msg::ContinueGame));
+    assert_eq!(GameState::MainMenu, game.settings.state);
+
+    game.settings.save_exists = true;
+    game.step_game(InputAction::SelectEntry(1));
+    assert!(game.msg_log.turn_messages.contains(&Msg::ContinueGame));
+
+    // a new game keeps a run that has not been played, along with its seed
+    game.msg_log.clear();
+    game.step_game(InputAction::SelectEntry(0));
+    assert!(!game.msg_log.turn_messages.contains(&Msg::Restart));
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(0, game.seed);
+
+    // once the run has been played, a new game starts playing a rebuilt level
+    game.step_game(InputAction::Pass);
+    game.settings.state = GameState::MainMenu;
+    game.msg_log.clear();
+    game.step_game(InputAction::SelectEntry(0));
+    assert!(game.msg_log.turn_messages.contains(&Msg::Restart));
+    assert_eq!(GameState::Playing, game.settings.state);
+}
+
+#[test]
+pub fn test_respawn_without_permadeath() {
+    let mut config = Config::from_file("../config.yaml");
+    config.permadeath = false;
+    let mut game = setup_test_game_config("
+        ..@.......
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let sword = make_item(&mut game.level.entities, &game.config, Item::Sword, Pos::new(2, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, sword);
+    make_spike_trap(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+
+    // dying sends the player back to the start of the level, leaving their items where they died
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(game.level.entities.status[&player_id].alive);
+    assert_eq!(game.settings.level_start, game.level.entities.pos[&player_id]);
+    assert_eq!(game.level.entities.hp[&player_id].max_hp, game.level.entities.hp[&player_id].hp);
+    assert!(game.level.entities.inventory[&player_id].is_empty());
+    assert!(game.level.entities.ids.contains(&sword));
+    assert!(distance(Pos::new(3, 0), game.level.entities.pos[&sword]) <= 1);
+    assert_eq!(1, game.settings.deaths);
+
+    // a taken start position moves the player to the nearest free tile
+    let level_start = game.settings.level_start;
+    game.level.entities.pos[&player_id] = Pos::new(2, 0);
+    make_column(&mut game.level.entities, &game.config, level_start, &mut game.msg_log);
+    make_spike_trap(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    game.step_game(InputAction::Move(Direction::Right));
+    assert!(game.level.entities.status[&player_id].alive);
+    assert_eq!(1, distance_maximum(level_start, game.level.entities.pos[&player_id]));
+    assert_eq!(2, game.settings.deaths);
+}
+
+#[test]
+pub fn test_practice_mode_spares_player() {
+    let mut config = Config::from_file("../config.yaml");
+    config.practice_mode = true;
+    let mut game = setup_test_game_config("
+        ..@.......
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    make_spike_trap(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+
+    // a killing blow leaves the player where they stood, stunned with one hp
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(game.level.entities.status[&player_id].alive);
+    assert_eq!(Pos::new(3, 0), game.level.entities.pos[&player_id]);
+    assert_eq!(1, game.level.entities.hp[&player_id].hp);
+    assert_eq!(game.config.practice_stun_turns, game.level.entities.status[&player_id].frozen);
+    assert_eq!(0, game.settings.deaths);
+}
+
+#[test]
+pub fn test_dynamic_difficulty_aggression() {
+    let mut config = Config::from_file("../config.yaml");
+    config.dynamic_difficulty = true;
+    config.dynamic_difficulty_window = 3;
+    config.dynamic_difficulty_target_damage = 2;
+    config.dynamic_difficulty_step = 0.1;
+    config.dynamic_difficulty_min = 0.5;
+    config.dynamic_difficulty_max = 1.25;
+
+    let mut performance = Performance::default();
+    assert_eq!(1.2, performance.aggression(&config));
+
+    // only the last few turns count, and the run's total damage is used to find each turn's damage
+    performance.record_turn(1, false, 10, config.dynamic_difficulty_window);
+    performance.record_turn(1, false, 10, config.dynamic_difficulty_window);
+    performance.record_turn(3, false, 10, config.dynamic_difficulty_window);
+    assert_eq!(3, performance.recent_damage());
+    performance.record_turn(3, false, 10, config.dynamic_difficulty_window);
+    assert_eq!(2, performance.recent_damage());
+    assert_eq!(1.0, performance.aggression(&config));
+
+    // a death counts as losing all health, which is bounded by the minimum
+    performance.record_turn(3, true, 10, config.dynamic_difficulty_window);
+    assert_eq!(0.5, performance.aggression(&config));
+
+    // taking no damage is bounded by the maximum
+    config.dynamic_difficulty_target_damage = 10;
+    performance = Performance::default();
+    assert_eq!(1.25, performance.aggression(&config));
+
+    config.dynamic_difficulty = false;
+    assert_eq!(1.0, performance.aggression(&config));
+}
+
+#[test]
+pub fn test_charged_attack() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let sword = make_item(&mut game.level.entities, &game.config, Item::Sword, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, sword);
+
+    // passing with a sword charges an attack, and passing again holds the charge
+    game.step_game(InputAction::Pass);
+    assert_eq!(Some(sword), game.level.entities.status[&player_id].charged_attack.map(|charge| charge.0));
+    game.step_game(InputAction::Pass);
+    assert!(game.level.entities.status[&player_id].charged_attack.is_some());
+
+    // doing anything else lets the charge lapse
+    game.step_game(InputAction::Move(Direction::Right));
+    assert!(game.level.entities.status[&player_id].charged_attack.is_none());
+
+    // taking damage cancels the charge
+    game.step_game(InputAction::Pass);
+    assert!(game.level.entities.status[&player_id].charged_attack.is_some());
+    game.level.entities.take_damage(player_id, player_id, 1);
+    assert!(game.level.entities.status[&player_id].charged_attack.is_none());
+}
+
+#[test]
+pub fn test_charged_bump_attack() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let sword = make_item(&mut game.level.entities, &game.config, Item::Sword, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, sword);
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+
+    // walking into the golem with a charged attack pushes it with a strong hit
+    game.level.entities.status[&player_id].charged_attack = Some((sword, Direction::Right, 0));
+    game.msg_log.log(Msg::TryMove(player_id, Direction::Right, 1, MoveMode::Walk));
+    resolve_messages(&mut game);
+
+    let stun_turns = WeaponType::Slash.stun_turns(&game.config) + game.config.stun_turns_extra;
+    let sound_radius = WeaponType::Slash.sound_radius(&game.config) + game.config.sound_radius_extra;
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&gol]);
+    assert_eq!(stun_turns, game.level.entities.status[&gol].frozen);
+    assert!(game.msg_log.turn_messages.contains(&Msg::Sound(player_id, Pos::new(1, 0), sound_radius)));
+
+    // the charge is used up by the attack
+    assert!(game.level.entities.status[&player_id].charged_attack.is_none());
+}
+
+#[test]
+pub fn test_skill_cooldown() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.energy[&player_id] = 10;
+    game.level.entities.skills[&player_id].clear();
+    game.level.entities.add_skill(player_id, Skill::GrassShoes);
+
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Primary));
+    assert_eq!(9, game.level.entities.energy[&player_id]);
+    assert_eq!(game.config.skill_cooldown(Skill::GrassShoes) - 1, game.level.entities.skill_cooldown(player_id, Skill::GrassShoes));
+
+    // using the skill again while it cools down fails without using energy
+    game.msg_log.clear();
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Primary));
+    assert_eq!(9, game.level.entities.energy[&player_id]);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::SkillOnCooldown(_, Skill::GrassShoes, _))));
+
+    for _ in 1..game.config.skill_cooldown(Skill::GrassShoes) {
+        game.step_game(InputAction::Pass);
+    }
+    assert_eq!(0, game.level.entities.skill_cooldown(player_id, Skill::GrassShoes));
+
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Primary));
+    assert_eq!(8, game.level.entities.energy[&player_id]);
+
+    // with no configured cooldowns, a skill can be used on back to back turns
+    game.config.skill_cooldowns.clear();
+    game.level.entities.cooldowns[&player_id].clear();
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Primary));
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Primary));
+    assert_eq!(6, game.level.entities.energy[&player_id]);
+}
+
+#[test]
+pub fn test_turn_limit() {
+    let mut config = Config::from_file("../config.yaml");
+    config.turn_limit = 2;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(GameState::Playing, game.settings.state);
+
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(GameState::TimeUp, game.settings.state);
+
+    // restarting resets the turn counter
+    game.step_game(InputAction::Restart);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(0, game.settings.turn_count);
+}
+
+#[test]
+pub fn test_bestiary_records_seen_enemies() {
+    let mut config = Config::from_file("../config.yaml");
+    config.bestiary_persists = false;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    make_gol(&mut game.level.entities, &game.config, Pos::new(2, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Pass);
+    game.step_game(InputAction::Pass);
+    assert_eq!(1, game.bestiary.len());
+    assert_eq!(EntityName::Gol, game.bestiary[0].name);
+
+    // the bestiary is cleared on restart when it does not persist
+    game.msg_log.log(Msg::Restart);
+    resolve_messages(&mut game);
+    assert_eq!(0, game.bestiary.len());
+}
+
+#[test]
+pub fn test_items_identified_on_use() {
+    let mut config = Config::from_file("../config.yaml");
+    config.unidentified_items = true;
+    config.identified_items_persist = false;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let smoke_bomb = make_smoke_bomb(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, smoke_bomb);
+
+    // only consumables start unidentified
+    assert!(!game.is_identified(Item::SmokeBomb));
+    assert!(!game.is_identified(Item::Herb));
+    assert!(game.is_identified(Item::Dagger));
+
+    game.msg_log.log(Msg::ItemThrow(player_id, smoke_bomb, Pos::new(0, 0), Pos::new(3, 0), false));
+    resolve_messages(&mut game);
+    assert!(game.is_identified(Item::SmokeBomb));
+    assert!(!game.is_identified(Item::Herb));
+
+    // identification is forgotten on restart when it does not persist
+    game.msg_log.log(Msg::Restart);
+    resolve_messages(&mut game);
+    assert!(!game.is_identified(Item::SmokeBomb));
+}
+
+#[test]
+pub fn test_run_stats() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    let stone = make_stone(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, stone);
+
+    game.step_game(InputAction::Pass);
+    assert_eq!(1, game.stats.turns);
+    assert!(game.stats.tiles_explored > 0);
+
+    game.msg_log.log(Msg::Attack(player_id, gol, 1));
+    game.msg_log.log(Msg::Killed(player_id, gol, 1));
+    game.msg_log.log(Msg::Attack(gol, player_id, 2));
+    game.msg_log.log(Msg::ItemThrow(player_id, stone, Pos::new(0, 0), Pos::new(3, 0), false));
+    resolve_messages(&mut game);
+    assert_eq!(1, game.stats.damage_dealt);
+    assert_eq!(2, game.stats.damage_taken);
+    assert_eq!(1, game.stats.enemies_killed);
+    assert_eq!(1, game.stats.items_thrown);
+
+    // the totals are reset when a new run starts
+    game.msg_log.log(Msg::Restart);
+    resolve_messages(&mut game);
+    assert_eq!(RunStats::default(), game.stats);
+}
+
+#[test]
+pub fn test_input_buffer() {
+    let mut config = Config::from_file("../config.yaml");
+    config.input_buffer_size = 2;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    // moves beyond the buffer size are dropped, and other actions are not buffered
+    assert!(game.queue_input(InputAction::Move(Direction::Right)));
+    assert!(game.queue_input(InputAction::Move(Direction::Right)));
+    assert!(game.queue_input(InputAction::Move(Direction::Right)));
+    assert!(!game.queue_input(InputAction::Inventory));
+    assert_eq!(2, game.input_queue.len());
+
+    let input_action = game.next_queued_input().unwrap();
+    game.step_game(input_action);
+    assert_eq!(Pos::new(1, 0), game.level.entities.pos[&player_id]);
+    assert_eq!(1, game.input_queue.len());
+
+    // an enemy noticing the player drops the remaining moves
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(4, 0), &mut game.msg_log);
+    game.msg_log.log(Msg::StateChange(gol, Behavior::Alert(Pos::new(1, 0))));
+    game.step_game(InputAction::None);
+    assert!(game.input_queue.is_empty());
+
+    // a buffer size of zero disables buffering
+    game.config.input_buffer_size = 0;
+    assert!(!game.queue_input(InputAction::Move(Direction::Right)));
+    assert!(game.input_queue.is_empty());
+}
+
+#[test]
+pub fn test_use_item_on_self() {
+    let mut game = setup_test_game("
+        ..........
+        ..........
+        ..@.......
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let smoke_bomb = make_smoke_bomb(&mut game.level.entities, &game.config, Pos::new(2, 2), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, smoke_bomb);
+
+    // sneaking while using a smoke bomb on the player's tile sets it off there
+    game.settings.move_mode = MoveMode::Sneak;
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    game.step_game(InputAction::DropItem);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(game.level.has_item_in_inventory(player_id, Item::SmokeBomb).is_none());
+    let smoke_at_player = game.level.get_entities_at_pos(Pos::new(2, 2))
+                                    .iter()
+                                    .any(|id| game.level.entities.name[id] == EntityName::Smoke);
+    assert!(smoke_at_player);
+
+    // without sneaking the '5' direction drops the item as usual
+    let smoke_bomb = make_smoke_bomb(&mut game.level.entities, &game.config, Pos::new(2, 2), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, smoke_bomb);
+    game.settings.move_mode = MoveMode::Walk;
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    game.step_game(InputAction::DropItem);
+    assert!(game.level.has_item_in_inventory(player_id, Item::SmokeBomb).is_none());
+    assert_ne!(Pos::new(-1, -1), game.level.entities.pos[&smoke_bomb]);
+    assert!(game.level.entities.status[&smoke_bomb].alive);
+}
+
+#[test]
+pub fn test_cycle_use_targets() {
+    let mut game = setup_test_game("
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        .....@....
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let near_gol = make_gol(&mut game.level.entities, &game.config, Pos::new(7, 5), &mut game.msg_log);
+    let far_gol = make_gol(&mut game.level.entities, &game.config, Pos::new(5, 2), &mut game.msg_log);
+    let lantern = make_lantern(&mut game.level.entities, &game.config, Pos::new(5, 5), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, lantern);
+
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    assert_eq!(GameState::Use, game.settings.state);
+
+    let targets = use_targets(&game.level, &game.settings);
+    assert_eq!(vec!((Direction::Right, near_gol), (Direction::Up, far_gol)), targets);
+
+    // next visits the nearest target first, then each target in turn before wrapping
+    game.step_game(InputAction::NextTarget);
+    assert_eq!(Some(Direction::Right), game.settings.use_dir);
+    game.step_game(InputAction::NextTarget);
+    assert_eq!(Some(Direction::Up), game.settings.use_dir);
+    game.step_game(InputAction::NextTarget);
+    assert_eq!(Some(Direction::Right), game.settings.use_dir);
+
+    // previous wraps around the other way
+    game.step_game(InputAction::PrevTarget);
+    assert_eq!(Some(Direction::Up), game.settings.use_dir);
+}
+
+#[test]
+pub fn test_blink_behind_enemy() {
+    let mut game = setup_test_game("
+        ..........
+        ..........
+        ..@.......
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.energy[&player_id] = 10;
+    game.level.entities.skills[&player_id].clear();
+    game.level.entities.add_skill(player_id, Skill::Blink);
+
+    make_gol(&mut game.level.entities, &game.config, Pos::new(4, 2), &mut game.msg_log);
+    let blocking_gol = make_gol(&mut game.level.entities, &game.config, Pos::new(5, 2), &mut game.msg_log);
+    game.settings.cursor = Some(Pos::new(4, 2));
+
+    // the tile behind the targeted enemy is blocked, so the blink fails without using energy
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Alternate));
+    assert_eq!(Pos::new(2, 2), game.level.entities.pos[&player_id]);
+    assert_eq!(10, game.level.entities.energy[&player_id]);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::FailedBlink(_))));
+
+    game.level.entities.pos[&blocking_gol] = Pos::new(8, 8);
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Alternate));
+    assert_eq!(Pos::new(5, 2), game.level.entities.pos[&player_id]);
+    assert_eq!(9, game.level.entities.energy[&player_id]);
+
+    // an enemy beyond the blink radius can not be blinked behind
+    let far_gol = make_gol(&mut game.level.entities, &game.config, Pos::new(5, 2 + BLINK_RADIUS as i32 + 1), &mut game.msg_log);
+    game.settings.cursor = Some(game.level.entities.pos[&far_gol]);
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Alternate));
+    assert_eq!(Pos::new(5, 2), game.level.entities.pos[&player_id]);
+    assert_eq!(9, game.level.entities.energy[&player_id]);
+}
+
+#[test]
+pub fn test_free_camera_takes_no_turns() {
+    let mut config = Config::from_file("../config.yaml");
+    config.free_camera = true;
+    let mut game = setup_test_game_config("
+        ..........
+        ..........
+        ..@.......
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(6, 6), &mut game.msg_log);
+    game.level.entities.behavior[&gol] = Behavior::Investigating(Pos::new(2, 2));
+
+    let turn_count = game.settings.turn_count;
+
+    game.step_game(InputAction::FreeCamera);
+    assert_eq!(GameState::FreeCamera, game.settings.state);
+    assert_eq!(Some(Pos::new(2, 2)), game.settings.free_camera);
+
+    // the camera moves, stopping at the edge of the map, without the player or enemies acting
+    for _ in 0..20 {
+        game.step_game(InputAction::Move(Direction::Right));
+    }
+    assert_eq!(Some(Pos::new(9, 2)), game.settings.free_camera);
+    assert_eq!(turn_count, game.settings.turn_count);
+    assert_eq!(Pos::new(2, 2), game.level.entities.pos[&player_id]);
+    assert_eq!(Pos::new(6, 6), game.level.entities.pos[&gol]);
+
+    game.step_game(InputAction::Esc);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(None, game.settings.free_camera);
+    assert_eq!(turn_count, game.settings.turn_count);
+
+    // the free camera is a debug option, and is ignored unless enabled
+    game.config.free_camera = false;
+    game.step_game(InputAction::FreeCamera);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(None, game.settings.free_camera);
+}
+
+#[test]
+pub fn test_swap_stone() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    let swap_stone = make_swap_stone(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, swap_stone);
+
+    game.msg_log.log(Msg::ItemThrow(player_id, swap_stone, Pos::new(0, 0), Pos::new(3, 0), false));
+    resolve_messages(&mut game);
+
+    assert_eq!(Pos::new(3, 0), game.level.entities.pos[&player_id]);
+    assert_eq!(Pos::new(0, 0), game.level.entities.pos[&gol]);
+    assert!(!game.level.entities.status[&swap_stone].alive);
+
+    // columns can not be swapped with
+    let column = make_column(&mut game.level.entities, &game.config, Pos::new(6, 0), &mut game.msg_log);
+    let swap_stone = make_swap_stone(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, swap_stone);
+
+    game.msg_log.log(Msg::ItemThrow(player_id, swap_stone, Pos::new(3, 0), Pos::new(6, 0), false));
+    resolve_messages(&mut game);
+
+    assert_eq!(Pos::new(3, 0), game.level.entities.pos[&player_id]);
+    assert_eq!(Pos::new(6, 0), game.level.entities.pos[&column]);
+}
+
+#[test]
+pub fn test_hourglass() {
+    let mut config = Config::from_file("../config.yaml");
+    config.hourglass_turns = 3;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol_pos = Pos::new(4, 0);
+    let gol = make_gol(&mut game.level.entities, &game.config, gol_pos, &mut game.msg_log);
+    game.level.entities.behavior[&gol] = Behavior::Attacking(player_id);
+
+    let hourglass = make_hourglass(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, hourglass);
+
+    // using the hourglass counts as the first turn of stopped time
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    assert_eq!(2, game.level.entities.status[&player_id].time_stop);
+    assert!(game.level.entities.inventory[&player_id].is_empty());
+    assert_eq!(gol_pos, game.level.entities.pos[&gol]);
+
+    // passing still uses up stopped time
+    game.step_game(InputAction::Pass);
+    game.step_game(InputAction::Pass);
+    assert_eq!(0, game.level.entities.status[&player_id].time_stop);
+    assert_eq!(gol_pos, game.level.entities.pos[&gol]);
+
+    game.step_game(InputAction::Pass);
+    assert_ne!(gol_pos, game.level.entities.pos[&gol]);
+}
+
+#[test]
+pub fn test_recall_stone() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let recall_stone = make_recall_stone(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, recall_stone);
+
+    // the first use marks the player's tile and keeps the stone
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    assert_eq!(Some(&Pos::new(0, 0)), game.level.entities.recall_pos.get(&player_id));
+    assert!(game.level.entities.inventory[&player_id].contains(&recall_stone));
+
+    // a recall onto a blocked tile fails, keeping the mark
+    game.level.entities.pos[&player_id] = Pos::new(4, 4);
+    let column = make_column(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    assert!(game.msg_log.turn_messages.contains(&Msg::FailedRecall(player_id)));
+    assert_eq!(Pos::new(4, 4), game.level.entities.pos[&player_id]);
+    assert_eq!(Some(&Pos::new(0, 0)), game.level.entities.recall_pos.get(&player_id));
+
+    // once the tile is clear the player blinks back, using up the stone
+    game.level.entities.remove_entity(column);
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    assert_eq!(Pos::new(0, 0), game.level.entities.pos[&player_id]);
+    assert!(game.level.entities.inventory[&player_id].is_empty());
+    assert!(game.level.entities.recall_pos.get(&player_id).is_none());
+
+    // the mark does not carry over to a new level
+    game.level.entities.recall_pos.insert(player_id, Pos::new(2, 2));
+    map_construct(&MapLoadConfig::Empty, &mut game);
+    assert!(game.level.entities.recall_pos.get(&player_id).is_none());
+}
+
+#[test]
+pub fn test_mirror_reflects_one_attack() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(1, 1), &mut game.msg_log);
+    game.level.entities.behavior[&gol] = Behavior::Attacking(player_id);
+
+    let mirror = make_mirror(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, mirror);
+
+    // the reflected attack does the golem's own damage, not the damage of the player's hammer
+    let hammer = make_hammer(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, hammer);
+
+    let player_hp = game.level.entities.hp[&player_id].hp;
+    let gol_hp = game.level.entities.hp[&gol].hp;
+
+    // the golem's attack is turned back on it, and the mirror is used up
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    assert!(game.msg_log.turn_messages.contains(&Msg::Reflected(player_id, gol)));
+    assert_eq!(player_hp, game.level.entities.hp[&player_id].hp);
+    assert_eq!(gol_hp - 1, game.level.entities.hp[&gol].hp);
+    assert!(!game.level.entities.status[&player_id].reflecting);
+    assert!(!game.level.entities.inventory[&player_id].contains(&mirror));
+
+    // only the one attack is reflected
+    game.step_game(InputAction::Pass);
+    assert_eq!(player_hp - 1, game.level.entities.hp[&player_id].hp);
+}
+
+#[test]
+pub fn test_mirror_reflects_ranged_attack() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol_pos = Pos::new(3, 3);
+    let gol = make_gol(&mut game.level.entities, &game.config, gol_pos, &mut game.msg_log);
+    game.level.entities.behavior[&gol] = Behavior::Attacking(player_id);
+
+    let mirror = make_mirror(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, mirror);
+
+    let player_hp = game.level.entities.hp[&player_id].hp;
+    let gol_hp = game.level.entities.hp[&gol].hp;
+
+    // the golem's beam is sent back to its own tile, with the attack sound there
+    game.step_game(InputAction::StartUseItem(ItemClass::Consumable));
+    assert!(game.msg_log.turn_messages.contains(&Msg::Reflected(player_id, gol)));
+    assert!(game.msg_log.turn_messages.contains(&Msg::Attack(player_id, gol, 1)));
+    assert!(game.msg_log.turn_messages.contains(&Msg::Sound(player_id, gol_pos, game.config.sound_radius_attack)));
+    assert_eq!(player_hp, game.level.entities.hp[&player_id].hp);
+    assert_eq!(gol_hp - 1, game.level.entities.hp[&gol].hp);
+}
+
+#[test]
+pub fn test_boomerang_returns() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(2, 0), &mut game.msg_log);
+    let boomerang = make_boomerang(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, boomerang);
+
+    // the boomerang flies past the golem, hitting it on the way out
+    game.msg_log.log(Msg::BoomerangThrow(player_id, boomerang, Pos::new(0, 0), Pos::new(4, 0)));
+    resolve_messages(&mut game);
+    assert!(game.msg_log.turn_messages.contains(&Msg::Hit(player_id, Pos::new(2, 0), WeaponType::Blunt, AttackStyle::Normal)));
+    assert_eq!(Pos::new(4, 0), game.level.entities.pos[&boomerang]);
+    assert!(game.level.entities.inventory[&player_id].is_empty());
+    game.level.entities.status[&gol].alive = false;
+
+    // the first turn ends the throw, and the boomerang comes back on the next
+    game.step_game(InputAction::Pass);
+    assert!(game.level.entities.inventory[&player_id].is_empty());
+    game.step_game(InputAction::Pass);
+    assert!(game.level.entities.inventory[&player_id].contains(&boomerang));
+    assert_eq!(None, game.level.entities.status[&boomerang].boomerang);
+
+    // a wall between the boomerang and the player leaves it on the ground
+    game.msg_log.log(Msg::BoomerangThrow(player_id, boomerang, Pos::new(0, 0), Pos::new(4, 0)));
+    resolve_messages(&mut game);
+    game.level.map[(1, 0)].block_move = true;
+    game.step_game(InputAction::Pass);
+    game.step_game(InputAction::Pass);
+    assert!(game.level.entities.inventory[&player_id].is_empty());
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&boomerang]);
+
+    // a second boomerang thrown while the first is in flight does not stop the first from returning
+    game.level.map[(1, 0)].block_move = false;
+    game.level.entities.pick_up_item(player_id, boomerang);
+    let other_boomerang = make_boomerang(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.msg_log.log(Msg::BoomerangThrow(player_id, boomerang, Pos::new(0, 0), Pos::new(4, 0)));
+    resolve_messages(&mut game);
+    game.level.entities.pick_up_item(player_id, other_boomerang);
+    game.msg_log.log(Msg::BoomerangThrow(player_id, other_boomerang, Pos::new(0, 0), Pos::new(0, 4)));
+    resolve_messages(&mut game);
+    game.msg_log.clear();
+    game.step_game(InputAction::Pass);
+    game.step_game(InputAction::Pass);
+    assert!(game.msg_log.turn_messages.contains(&Msg::PickedUp(player_id, boomerang)));
+    assert!(game.msg_log.turn_messages.contains(&Msg::PickedUp(player_id, other_boomerang)));
+}
+
+#[test]
+pub fn test_marking_stone() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+    let marking_stone = make_marking_stone(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, marking_stone);
+
+    game.msg_log.log(Msg::ItemThrow(player_id, marking_stone, Pos::new(0, 0), Pos::new(3, 0), false));
+    resolve_messages(&mut game);
+    let turns = game.config.marking_stone_turns;
+    assert_eq!(Some((turns, Pos::new(3, 0))), game.level.entities.status[&gol].marked);
+
+    // the impression follows the golem out of the player's FoV
+    game.level.entities.pos[&gol] = Pos::new(9, 9);
+    game.msg_log.clear();
+    resolve_marks(&mut game);
+    assert!(game.msg_log.info_messages.contains(&InfoMsg::ImpressionCleared(Pos::new(3, 0))));
+    assert!(game.msg_log.info_messages.contains(&InfoMsg::Impression(Pos::new(9, 9))));
+    assert_eq!(Some((turns - 1, Pos::new(9, 9))), game.level.entities.status[&gol].marked);
+
+    // the mark ends when the golem dies
+    game.level.entities.status[&gol].alive = false;
+    game.msg_log.clear();
+    resolve_marks(&mut game);
+    assert!(game.msg_log.info_messages.contains(&InfoMsg::ImpressionCleared(Pos::new(9, 9))));
+    assert_eq!(None, game.level.entities.status[&gol].marked);
+}
+
+#[test]
+pub fn test_marking_stone_once_per_run() {
+    let mut config = Config::from_file("../config.yaml");
+    config.starting_inventory = vec!("markingstone".to_string());
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    assert!(game.level.is_in_inventory(player_id, Item::MarkingStone).is_some());
+
+    // the stone is given at the start of the run, so switching classes does not give another
+    game.msg_log.log(Msg::AddClass(EntityClass::Wind));
+    resolve_messages(&mut game);
+    game.msg_log.log(Msg::AddClass(EntityClass::Body));
+    resolve_messages(&mut game);
+    let num_stones = game.level.entities.ids.iter().filter(|id| game.level.entities.name[id] == EntityName::MarkingStone).count();
+    assert_eq!(1, num_stones);
+}
+
+#[test]
+pub fn test_roll_passes_over_traps() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.status[&player_id].test_mode = true;
+
+    let passed_trap = make_spike_trap(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    let landing_trap = make_spike_trap(&mut game.level.entities, &game.config, Pos::new(2, 0), &mut game.msg_log);
+
+    game.msg_log.log(Msg::Roll(player_id, Direction::Right, SKILL_ROLL_AMOUNT));
+    step_logic(&mut game);
+
+    // the trap rolled over is dodged, but the one landed on still triggers
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&player_id]);
+    assert!(!game.level.entities.needs_removal[&passed_trap]);
+    assert!(game.level.entities.needs_removal[&landing_trap]);
+    assert!(!game.level.entities.status[&player_id].rolling);
+}
+
+#[test]
+pub fn test_dash_attack() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(3, 0), &mut game.msg_log);
+
+    // dashing stops next to the golem and attacks it
+    game.msg_log.log(Msg::TryDash(player_id, Direction::Right));
+    resolve_messages(&mut game);
+
+    assert_eq!(Pos::new(2, 0), game.level.entities.pos[&player_id]);
+    assert_eq!(SKILL_DASH_STUN_TURNS, game.level.entities.status[&gol].frozen);
+
+    // dashing does not pass through walls
+    game.level.entities.pos[&player_id] = Pos::new(0, 2);
+    game.level.map[(2, 2)] = Tile::wall();
+
+    game.msg_log.log(Msg::TryDash(player_id, Direction::Right));
+    resolve_messages(&mut game);
+
+    assert_eq!(Pos::new(1, 2), game.level.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_stone_volley() {
+    let mut game = setup_test_game("
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let stone1 = make_stone(&mut game.level.entities, &game.config, Pos::new(0, 5), &mut game.msg_log);
+    let stone2 = make_stone(&mut game.level.entities, &game.config, Pos::new(0, 5), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, stone1);
+    game.level.entities.pick_up_item(player_id, stone2);
+
+    // only the stones in the inventory are thrown, even if the volley could throw more
+    game.msg_log.log(Msg::StoneVolley(player_id, Pos::new(0, 5), Pos::new(4, 5)));
+    resolve_messages(&mut game);
+
+    assert!(game.level.has_item_in_inventory(player_id, Item::Stone).is_none());
+    assert_ne!(Pos::new(0, 5), game.level.entities.pos[&stone1]);
+    assert_ne!(Pos::new(0, 5), game.level.entities.pos[&stone2]);
+
+    // the volley is a skill, and costs energy like the other skills
+    assert_eq!(game.config.player_energy - 1, game.level.entities.energy[&player_id]);
+}
+
+#[test]
+pub fn test_disabled_entities_removed_from_fixed_maps() {
+    let mut config = Config::from_file("../config.yaml");
+    config.disabled_entities = vec!(EntityName::Column);
+    let mut game = Game::new(0, config);
+
+    map_construct(&MapLoadConfig::TestColumns, &mut game);
+
+    assert!(game.level.find_by_name(EntityName::Player).is_some());
+    assert!(game.level.find_by_name(EntityName::Column).is_none());
+}
+
+#[test]
+pub fn test_starting_inventory() {
+    let mut config = Config::from_file("../config.yaml");
+    config.starting_inventory = vec!("dagger".to_string(), "Sword".to_string(), "stone".to_string(), "not_an_item".to_string());
+    let mut game = Game::new(0, config);
+
+    map_construct(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    assert_eq!(2, game.level.entities.inventory[&player_id].len());
+    assert!(game.level.has_item_in_inventory(player_id, Item::Dagger).is_some());
+    assert!(game.level.has_item_in_inventory(player_id, Item::Stone).is_some());
+
+    // the sword does not fit with the dagger, so it is left at the player's feet
+    let sword_id = game.level.find_by_name(EntityName::Sword).unwrap();
+    assert_eq!(game.settings.level_start, game.level.entities.pos[&sword_id]);
+}
+
+#[test]
+pub fn test_wall_sight() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    game.level.map[(2, 0)] = Tile::wall();
+    game.level.map[(2, 2)] = Tile::wall();
+    game.level.map[(2, 3)] = Tile::wall();
+
+    assert!(game.level.pos_in_fov(player_id, Pos::new(2, 0)));
+    assert!(!game.level.pos_in_fov(player_id, Pos::new(3, 0)));
+
+    game.level.entities.passive[&player_id].wall_sight = true;
+
+    // the tile behind the wall is visible, but nothing further
+    assert!(game.level.pos_in_fov(player_id, Pos::new(3, 0)));
+    assert!(!game.level.pos_in_fov(player_id, Pos::new(4, 0)));
+
+    // sight does not continue past a second wall
+    game.level.entities.pos[&player_id] = Pos::new(2, 1);
+    assert!(game.level.pos_in_fov(player_id, Pos::new(2, 3)));
+    assert!(!game.level.pos_in_fov(player_id, Pos::new(2, 4)));
+
+    // carrying a glass eye also gives wall sight
+    game.level.entities.passive[&player_id].wall_sight = false;
+    game.level.entities.pos[&player_id] = Pos::new(0, 0);
+    assert!(!game.level.pos_in_fov(player_id, Pos::new(3, 0)));
+
+    let glass_eye = make_glass_eye(&mut game.level.entities, &game.config, Pos::new(0, 0), &mut game.msg_log);
+    game.level.entities.pick_up_item(player_id, glass_eye);
+    assert!(game.level.pos_in_fov(player_id, Pos::new(3, 0)));
+}
+
+#[test]
+pub fn test_player_fov_cache_invalidated_by_map_changes() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let visible = |fov: &Vec<(Pos, FovResult)>, pos: Pos| fov.iter().any(|(fov_pos, result)| *fov_pos == pos && *result == FovResult::Inside);
+
+    let fov = game.level.player_fov(player_id);
+    assert!(visible(&fov, Pos::new(3, 0)));
+    assert_eq!(fov, game.level.player_fov(player_id));
+
+    // placing a wall changes the map version, so the cached fov is not reused
+    let version = game.level.map.version;
+    game.level.map[(2, 0)] = Tile::wall();
+    assert_ne!(version, game.level.map.version);
+
+    let fov = game.level.player_fov(player_id);
+    assert!(!visible(&fov, Pos::new(3, 0)));
+
+    // moving also recomputes the fov
+    game.level.entities.pos[&player_id] = Pos::new(3, 1);
+    let fov = game.level.player_fov(player_id);
+    assert!(visible(&fov, Pos::new(3, 0)));
+}
+
+#[test]
+pub fn test_hint_trigger_fires_once() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let trigger = make_hint_trigger(&mut game.level.entities, &game.config, Pos::new(1, 0), 2, &mut game.msg_log);
+
+    game.msg_log.clear();
+    game.step_game(InputAction::Move(Direction::Right));
+    assert!(game.msg_log.turn_messages.iter().any(|msg| *msg == Msg::Hint(2)));
+    assert_eq!((2, true), game.level.entities.hint[&trigger]);
+
+    game.step_game(InputAction::Move(Direction::Left));
+    game.msg_log.clear();
+    game.step_game(InputAction::Move(Direction::Right));
+    assert!(!game.msg_log.turn_messages.iter().any(|msg| *msg == Msg::Hint(2)));
+}
+
+#[test]
+pub fn test_large_entity_footprint() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(4, 4), &mut game.msg_log);
+
+    // a 2x2 footprint can't overlap a wall
+    game.level.map[(5, 5)].block_move = true;
+    assert!(!game.level.set_entity_size(gol, (2, 2)));
+    assert!(game.level.entities.size.get(&gol).is_none());
+
+    game.level.map[(5, 5)].block_move = false;
+    assert!(game.level.set_entity_size(gol, (2, 2)));
+
+    // every tile of the footprint blocks and reports the entity
+    for pos in [Pos::new(4, 4), Pos::new(5, 4), Pos::new(4, 5), Pos::new(5, 5)].iter() {
+        assert_eq!(Some(gol), game.level.has_blocking_entity(*pos));
+        assert!(game.level.get_entities_at_pos(*pos).contains(&gol));
+    }
+    assert_eq!(None, game.level.has_blocking_entity(Pos::new(6, 4)));
+
+    // the player can't walk into the footprint, or push the entity
+    game.level.entities.pos[&player_id] = Pos::new(6, 5);
+    assert!(calculate_move(Direction::Left, Reach::single(1), player_id, &game.level).is_none());
+
+    // the large entity only moves if its entire footprint is clear
+    game.level.map[(6, 4)].block_move = true;
+    assert!(calculate_move(Direction::Right, Reach::single(1), gol, &game.level).is_none());
+    let movement = calculate_move(Direction::Left, Reach::single(1), gol, &game.level).unwrap();
+    assert_eq!(Pos::new(3, 4), movement.pos);
+}
+
+#[test]
+pub fn test_difficulty_scales_fixed_map_hp() {
+    let mut config = Config::from_file("../config.yaml");
+    config.difficulty = 1.5;
+    let mut game = Game::new(0, config);
+
+    map_construct(&MapLoadConfig::Tutorial, &mut game);
+
+    let gol = game.level.find_by_name(EntityName::Gol).unwrap();
+    assert_eq!(15, game.level.entities.hp[&gol].max_hp);
+    assert_eq!(15, game.level.entities.hp[&gol].hp);
+
+    // the player is not scaled
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    assert_eq!(game.config.player_health, game.level.entities.hp[&player_id].max_hp);
+}
+
+#[test]
+pub fn test_cycle_item_slot() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let dagger = make_dagger(&mut game.level.entities, &game.config, Pos::new(-1, -1), &mut game.msg_log);
+    game.level.entities.inventory[&player_id].push_back(dagger);
+
+    // a single item of a class can't be cycled
+    game.step_game(InputAction::CycleSlot(ItemClass::Primary));
+    assert_eq!(Some(0), game.level.entities.item_by_class(player_id, ItemClass::Primary));
+
+    let stone = make_stone(&mut game.level.entities, &game.config, Pos::new(-1, -1), &mut game.msg_log);
+    let sword = make_sword(&mut game.level.entities, &game.config, Pos::new(-1, -1), &mut game.msg_log);
+    game.level.entities.inventory[&player_id].push_back(stone);
+    game.level.entities.inventory[&player_id].push_back(sword);
+
+    game.step_game(InputAction::CycleSlot(ItemClass::Primary));
+    let index = game.level.entities.item_by_class(player_id, ItemClass::Primary).unwrap();
+    assert_eq!(sword, game.level.entities.inventory[&player_id][index]);
+
+    // the choice persists across turns
+    game.step_game(InputAction::Pass);
+    let index = game.level.entities.item_by_class(player_id, ItemClass::Primary).unwrap();
+    assert_eq!(sword, game.level.entities.inventory[&player_id][index]);
+
+    game.step_game(InputAction::CycleSlot(ItemClass::Primary));
+    let index = game.level.entities.item_by_class(player_id, ItemClass::Primary).unwrap();
+    assert_eq!(dagger, game.level.entities.inventory[&player_id][index]);
+}
+
+#[test]
+pub fn test_skill_preview_matches_result() {
+    let mut game = setup_test_game("
+        ..........
+        ..........
+        ..........
+        ..........
+        ....@.....
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.energy[&player_id] = 10;
+    game.level.entities.skills[&player_id].clear();
+    game.level.entities.add_skill(player_id, Skill::PassWall);
+    game.level.entities.add_skill(player_id, Skill::GrassWall);
+
+    // pass wall previews the tile past the wall
+    game.level.map[(5, 4)] = Tile::wall();
+    game.step_game(InputAction::StartUseSkill(0, ActionMode::Primary));
+    game.msg_log.info_messages.clear();
+    game.step_game(InputAction::UseDir(Direction::Right));
+    assert!(game.msg_log.info_messages.contains(&roguelike_core::messaging::InfoMsg::PassWallPreview(Pos::new(6, 4))));
+
+    game.step_game(InputAction::FinalizeUse);
+    assert_eq!(Pos::new(6, 4), game.level.entities.pos[&player_id]);
+
+    // grass wall previews exactly the walls that are placed
+    game.step_game(InputAction::StartUseSkill(1, ActionMode::Primary));
+    game.msg_log.info_messages.clear();
+    game.step_game(InputAction::UseDir(Direction::Up));
+    let previews = game.msg_log.info_messages.iter().filter_map(|msg| {
+        if let roguelike_core::messaging::InfoMsg::GrassWallPreview(pos, dir) = msg {
+            Some((*pos, *dir))
+        } else {
+            None
+        }
+    }).collect::<Vec<(Pos, Direction)>>();
+    assert_eq!(3, previews.len());
 
+    game.step_game(InputAction::FinalizeUse);
+    for (pos, dir) in previews.iter() {
+        assert!(!game.level.map.can_place_intertile_wall(*pos, *dir));
+    }
+    assert_eq!(Wall::ShortWall, game.level.map[(6, 3)].bottom_wall);
+}
 
-pub fn step_logic(game: &mut Game) -> bool {
-    // clean up removable entities
-    clean_entities(&mut game.level.entities, &mut game.msg_log);
+#[test]
+pub fn test_poison_kills_and_credits_poisoner() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
 
     let player_id = game.level.find_by_name(EntityName::Player).unwrap();
 
-    // Thaw entities at the start of the player's turn.
-    if game.level.entities.took_turn[&player_id] != 0 {
-        for id in game.level.entities.ids.iter() {
-            game.level.entities.took_turn[id] = 0;
-            game.level.entities.status[id].blinked = false;
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(8, 8), &mut game.msg_log);
+    game.level.entities.hp[&gol].hp = 2;
 
-            // Slowly thaw any frozen entities.
-            if let Some(status) = game.level.entities.status.get_mut(id) {
-                if status.frozen > 0 {
-                    game.msg_log.log(Msg::Thaw(*id, 1));
-                }
-            }
-        }
-    }
+    game.msg_log.log(Msg::Poisoned(gol, player_id, 3));
+    resolve_messages(&mut game);
+    assert_eq!(3, game.level.entities.status[&gol].poisoned);
 
-    resolve_messages(game);
+    // poison is applied at the start of each turn after the player acts
+    game.step_game(InputAction::Pass);
+    game.step_game(InputAction::Pass);
+    assert_eq!(1, game.level.entities.hp[&gol].hp);
+    assert_eq!(2, game.level.entities.status[&gol].poisoned);
 
-    let won_level = level_exit_condition_met(&game.level, game.settings.exit_condition);
+    game.msg_log.clear();
+    game.step_game(InputAction::Pass);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| *msg == Msg::Killed(player_id, gol, 1)));
+}
 
-    // resolve enemy action
-    let monster = timer!("MONSTER");
+#[test]
+pub fn test_spire_attack_poisons() {
+    let mut config = Config::from_file("../config.yaml");
+    config.spire_venom_turns = 2;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
 
-    if game.level.entities.status[&player_id].alive && !won_level {
-        let turn = game.level.entities.took_turn[&player_id];
-        if turn != 0 {
-            // Handle player stamina change if they took a turn
-            if turn & (Turn::Run.turn() | Turn::Jump.turn() | Turn::Attack.turn()) != 0 {
-                if game.level.entities.stamina[&player_id] > 0 {
-                    game.msg_log.log(Msg::UsedStamina(player_id, 1));
-                }
-            } else {
-                if game.level.entities.stamina[&player_id] < game.config.player_stamina_max {
-                    game.msg_log.log(Msg::GainStamina(player_id, 1));
-                }
-            }
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
 
-            step_ai(game);
-            run_thumpers(game);
-        }
-    }
-    drop(monster);
+    let spire = make_spire(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    attack(spire, player_id, &mut game.level, &mut game.msg_log);
+    resolve_messages(&mut game);
+    assert_eq!(2, game.level.entities.status[&player_id].poisoned);
+    assert_eq!(Some(spire), game.level.entities.status[&player_id].poisoner);
+}
 
+fn step_ai(game: &mut Game) {
+    // idle sounds are resolved before any AI acts so they are not mistaken for an AI's action.
+    for entity_id in game.level.entities.active_ais().iter() {
+        ai_idle_sound(*entity_id, &game.level, &mut game.rng, &mut game.msg_log, &game.config);
+    }
     resolve_messages(game);
 
-    // check status effects
-    for entity_id in game.level.entities.ids.iter() {
-        if let Some(mut status) = game.level.entities.status.get_mut(entity_id) {
-            // frozen status is handled separately.
+    if game.config.simultaneous_enemy_turns {
+        step_ai_simultaneous(game);
+        return;
+    }
 
-            if status.stone > 0 {
-                status.stone -= 1;
-            }
+    let ai_ids: Vec<EntityId> = game.level.entities.active_ais();
 
-            if status.soft_steps > 0 {
-                status.soft_steps -= 1;
-            }
-        }
+    for entity_id in ai_ids.iter() {
+        step_ai_turn(game, *entity_id);
     }
+}
 
-    if game.level.entities.took_turn[&player_id] != 0 {
-        game.settings.turn_count += 1;
-
-        // check on whether the player has their hammer raised
-        if let Some((item_id, dir, turns)) = game.level.entities.status[&player_id].hammer_raised {
-            if turns == 0 {
-                game.level.entities.status[&player_id].hammer_raised = None;
-            } else {
-                game.level.entities.status[&player_id].hammer_raised = Some((item_id, dir, turns - 1));
-            }
+/// Let an AI act until it has taken its turn.
+fn step_ai_turn(game: &mut Game, entity_id: EntityId) {
+    while game.level.entities.took_turn[&entity_id] == 0 {
+        let aggression = game.performance.aggression(&game.config);
+        ai_take_turn(entity_id, &mut game.level, &game.config, aggression, &mut game.rng, &mut game.msg_log);
+
+        // If the AI has nothing to do, end its turn
+        if game.msg_log.messages.len() == 0 {
+            game.level.entities.took_turn[&entity_id] |= Turn::Pass.turn();
+        } else {
+            resolve_messages(game);
         }
     }
 
-    if game.msg_log.messages.len() > 0 {
-        resolve_messages(game);
+    // If there are remaining messages for an entity, clear them
+    game.level.entities.messages[&entity_id].clear();
+}
+
+/// The position an alert AI will move to on its next turn. The turn is taken on a copy of
+/// the game, so the plan comes from the same decisions as the turn itself. There is no
+/// planned move for an idle AI, or one that would attack or wait instead of moving.
+pub fn ai_planned_move(game: &mut Game, entity_id: EntityId) -> Option<Pos> {
+    match game.level.entities.behavior.get(&entity_id) {
+        Some(Behavior::Attacking(_)) | Some(Behavior::Investigating(_)) => {}
+        _ => return None,
     }
 
-    // perform count down of entities waiting to be removed
-    game.level.entities.count_down();
+    // the vaults are only used when generating levels, so they are not copied
+    let vaults = std::mem::take(&mut game.vaults);
+    let mut plan_game = game.clone();
+    game.vaults = vaults;
 
-    for id in game.level.entities.ids.iter() {
-        if game.level.entities.needs_removal[id] {
-            game.msg_log.log(Msg::MarkedForRemoval(*id));
-        }
+    plan_game.msg_log.clear();
+    plan_game.level.entities.took_turn[&entity_id] = 0;
+    step_ai_turn(&mut plan_game, entity_id);
+
+    let start_pos = game.level.entities.pos[&entity_id];
+    let planned_pos = plan_game.level.entities.pos[&entity_id];
+    if planned_pos != start_pos {
+        return Some(planned_pos);
     }
 
-    return level_exit_condition_met(&game.level, game.settings.exit_condition);
+    return None;
 }
 
-/// Check whether the exit condition for the game is met.
-fn level_exit_condition_met(level: &Level, exit_condition: LevelExitCondition) -> bool {
-    // loop over objects in inventory, and check whether any
-    // are the key object.
-    let player_id = level.find_by_name(EntityName::Player).unwrap();
-    let player_pos = level.entities.pos[&player_id];
+/// Every AI chooses its first action against the level as it was before any AI acted,
+/// and then the actions are resolved in order of EntityId. An AI that chose nothing
+/// ends its turn, while one that still has not taken its turn after its action, such
+/// as one that just noticed the player, continues acting as it would in sequential mode.
+fn step_ai_simultaneous(game: &mut Game) {
+    let mut ai_ids: Vec<EntityId> = game.level.entities.active_ais();
+    ai_ids.sort();
 
-    let mut exit_condition_met = false;
+    let aggression = game.performance.aggression(&game.config);
+    let mut planned: Vec<(EntityId, Vec<Msg>)> = Vec::new();
+    for entity_id in ai_ids.iter() {
+        ai_take_turn(*entity_id, &mut game.level, &game.config, aggression, &mut game.rng, &mut game.msg_log);
 
-    match exit_condition {
-        LevelExitCondition::RightEdge => {
-            if (player_pos.x + 1) == level.map.width() {
-                exit_condition_met = true;
-            }
-        }
+        // these messages are already recorded for the turn, so they are resolved below
+        // without being logged again.
+        let msgs = game.msg_log.messages.drain(..).collect::<Vec<Msg>>();
+        planned.push((*entity_id, msgs));
+    }
 
-        LevelExitCondition::KeyAndGoal => {
-            // Have Key, On exit tile
-            if let Some(exit_id) = level.find_by_name(EntityName::Exit) {
-                let exit_pos = level.entities.pos[&exit_id];
+    let held = remove_move_conflicts(&mut planned, &game.level);
 
-                let has_key = level.is_in_inventory(player_id, Item::Key).is_some();
+    for (entity_id, msgs) in planned {
+        // an earlier action may have killed this entity
+        if !game.level.entities.status[&entity_id].alive {
+            continue;
+        }
 
-                let on_exit_tile = exit_pos == player_pos;
+        // an idle AI does not get a second look at the level after the others have acted
+        if msgs.is_empty() {
+            game.level.entities.took_turn[&entity_id] |= Turn::Pass.turn();
+        }
 
-                exit_condition_met = has_key && on_exit_tile;
-            }
+        game.msg_log.messages.extend(msgs);
+        resolve_messages(game);
+
+        if held.contains(&entity_id) {
+            game.level.entities.took_turn[&entity_id] |= Turn::Pass.turn();
         }
-    }
 
-    return exit_condition_met;
+        step_ai_turn(game, entity_id);
+    }
 }
 
-#[test]
-pub fn test_game_step() {
-    let mut config = Config::from_file("../config.yaml");
-    config.map_load = MapLoadConfig::Empty;
-    let mut game = Game::new(0, config.clone());
-    let mut input_action;
+/// Remove planned moves into a tile that an entity with a lower EntityId is also moving
+/// into, returning the entities whose move was removed.
+fn remove_move_conflicts(planned: &mut Vec<(EntityId, Vec<Msg>)>, level: &Level) -> Vec<EntityId> {
+    let mut claimed: HashSet<Pos> = HashSet::new();
+    let mut held = Vec::new();
 
-    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
-    map_construct(&MapLoadConfig::Empty, &mut game);
-    game.level.entities.pos[&player_id] = Pos::new(0, 0);
+    planned.sort_by_key(|(entity_id, _msgs)| *entity_id);
 
-    input_action = InputAction::Move(Direction::Right);
-    game.step_game(input_action);
-    let player_pos = game.level.entities.pos[&player_id];
-    assert_eq!(Pos::new(1, 0), player_pos);
+    for (entity_id, msgs) in planned.iter_mut() {
+        let entity_pos = level.entities.pos[entity_id];
 
-    input_action = InputAction::Move(Direction::Down);
-    game.step_game(input_action);
-    let player_pos = game.level.entities.pos[&player_id];
-    assert_eq!(Pos::new(1, 1), player_pos);
+        let len_before = msgs.len();
+        msgs.retain(|msg| {
+            if let Msg::TryMove(_, direction, amount, _) = msg {
+                if *amount > 0 {
+                    return claimed.insert(direction.offset_pos(entity_pos, *amount as i32));
+                }
+            }
+            return true;
+        });
 
-    input_action = InputAction::Move(Direction::Left);
-    game.step_game(input_action);
-    let player_pos = game.level.entities.pos[&player_id];
-    assert_eq!(Pos::new(0, 1), player_pos);
+        if msgs.len() != len_before {
+            held.push(*entity_id);
+        }
+    }
 
-    input_action = InputAction::Move(Direction::Up);
-    game.step_game(input_action);
-    let player_pos = game.level.entities.pos[&player_id];
-    assert_eq!(Pos::new(0, 0), player_pos);
+    return held;
 }
 
+#[test]
+fn test_simultaneous_move_conflicts() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let gol0 = make_gol(&mut game.level.entities, &game.config, Pos::new(1, 1), &mut game.msg_log);
+    let gol1 = make_gol(&mut game.level.entities, &game.config, Pos::new(3, 1), &mut game.msg_log);
+    let gol2 = make_gol(&mut game.level.entities, &game.config, Pos::new(5, 1), &mut game.msg_log);
+
+    // the first two gols move into the same tile, planned in reverse order
+    let mut planned = vec!((gol2, vec!(Msg::TryMove(gol2, Direction::Right, 1, MoveMode::Walk))),
+                           (gol1, vec!(Msg::TryMove(gol1, Direction::Left, 1, MoveMode::Walk))),
+                           (gol0, vec!(Msg::TryMove(gol0, Direction::Right, 1, MoveMode::Walk))));
+    let held = remove_move_conflicts(&mut planned, &game.level);
+
+    assert_eq!(vec!(gol1), held);
+    assert_eq!(gol0, planned[0].0);
+    assert_eq!(1, planned[0].1.len());
+    assert_eq!(0, planned[1].1.len());
+    assert_eq!(1, planned[2].1.len());
+}
 
-fn step_ai(game: &mut Game) {
-    let ai_ids: Vec<EntityId> = game.level.entities.active_ais();
+#[test]
+fn test_simultaneous_turn_idle_ai() {
+    let mut config = Config::from_file("../config.yaml");
+    config.simultaneous_enemy_turns = true;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    // both gols face away from the player, and the idle gol faces away from the other gol
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(4, 8), &mut game.msg_log);
+    game.level.entities.direction[&gol] = Direction::Right;
+    game.level.entities.behavior[&gol] = Behavior::Investigating(Pos::new(6, 8));
 
-    for entity_id in ai_ids.iter() {
-        while game.level.entities.took_turn[entity_id] == 0 {
-            ai_take_turn(*entity_id, &mut game.level, &game.config, &mut game.msg_log);
+    let idle_gol = make_gol(&mut game.level.entities, &game.config, Pos::new(7, 8), &mut game.msg_log);
+    game.level.entities.direction[&idle_gol] = Direction::Right;
 
-            // If the AI has nothing to do, end its turn
-            if game.msg_log.messages.len() == 0 {
-                game.level.entities.took_turn[entity_id] |= Turn::Pass.turn();
-            } else {
-                resolve_messages(game);
-            }
-        }
+    game.step_game(InputAction::Pass);
 
-        // If there are remaining messages for an entity, clear them
-        game.level.entities.messages[entity_id].clear();
-    }
+    // the investigating gol moves, and the idle gol does not react to its footsteps
+    // until the next turn, as it chose its action before the move.
+    assert_eq!(Pos::new(5, 8), game.level.entities.pos[&gol]);
+    assert_eq!(Pos::new(7, 8), game.level.entities.pos[&idle_gol]);
+    assert_eq!(Behavior::Idle, game.level.entities.behavior[&idle_gol]);
+}
+
+#[test]
+fn test_enemy_idle_sounds_do_not_alert() {
+    let mut config = Config::from_file("../config.yaml");
+    config.enemy_idle_sounds = true;
+    config.enemy_idle_sound_chance = 1.0;
+    config.enemy_idle_sound_entities = vec!(EntityName::Gol);
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    // the gols are too far away to see the player, but can hear each other
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(8, 8), &mut game.msg_log);
+    let other_gol = make_gol(&mut game.level.entities, &game.config, Pos::new(9, 8), &mut game.msg_log);
+
+    game.step_game(InputAction::Pass);
+
+    let idle_sounds = game.msg_log.turn_messages.iter().filter(|msg| matches!(msg, Msg::IdleSound(_, _, _))).count();
+    assert_eq!(2, idle_sounds);
+    assert_eq!(Behavior::Idle, game.level.entities.behavior[&gol]);
+    assert_eq!(Behavior::Idle, game.level.entities.behavior[&other_gol]);
 }
 
 #[test]
@@ -364,6 +2288,79 @@ fn test_ai_investigate_not_in_fov_heard_sound() {
     assert_eq!(game.msg_log.messages[0], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
 }
 
+#[test]
+fn test_ai_investigate_follows_scent() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let start_pos = Pos::new(0, 0);
+    let gol = make_gol(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.msg_log.clear();
+    game.level.entities.direction[&gol] = Direction::Right;
+    game.level.entities.behavior[&gol] = Behavior::Investigating(start_pos);
+
+    // the player is hidden behind a wall, having walked along the top row
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.entities.pos[&player_id] = Pos::new(5, 0);
+    game.level.map[(4, 0)] = Tile::wall();
+    game.level.leave_scent(Pos::new(1, 0), 2);
+    game.level.leave_scent(Pos::new(2, 0), 3);
+
+    // the gol follows the trail instead of becoming idle at its target
+    ai_investigate(start_pos, gol, &mut game.level, &mut game.msg_log, &game.config);
+    assert_eq!(2, game.msg_log.messages.len());
+    assert_eq!(game.msg_log.messages[0], Msg::TryMove(gol, Direction::Right, 1, MoveMode::Walk));
+    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Investigating(Pos::new(1, 0))));
+}
+
+#[test]
+fn test_scent_trail() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    game.level.map[(2, 0)] = Tile::water();
+
+    // walking leaves scent, which fades each turn
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(game.config.scent_turns - 1, game.level.scent_at(Pos::new(1, 0)));
+
+    // water leaves no scent
+    game.msg_log.log(Msg::Moved(player_id, MoveType::Move, MoveMode::Walk, Pos::new(2, 0)));
+    resolve_messages(&mut game);
+    assert_eq!(0, game.level.scent_at(Pos::new(2, 0)));
+
+    // neither does stepping softly
+    game.level.entities.status[&player_id].soft_steps = 2;
+    game.msg_log.log(Msg::Moved(player_id, MoveType::Move, MoveMode::Walk, Pos::new(3, 0)));
+    resolve_messages(&mut game);
+    assert_eq!(0, game.level.scent_at(Pos::new(3, 0)));
+
+    // rebuilding the level clears the trail
+    map_construct(&MapLoadConfig::Empty, &mut game);
+    assert_eq!(0, game.level.scent.len());
+}
+
 #[test]
 fn test_ai_investigate_moves() {
     let config = Config::from_file("../config.yaml");
@@ -478,6 +2475,153 @@ fn test_pick_up_misc() {
     assert!(inventory.iter().position(|id| *id == key).is_some());
 }
 
+#[test]
+fn test_objective_exit_condition() {
+    let mut config = Config::from_file("../config.yaml");
+    config.objective_exit = true;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    // without a key and exit, the level is left by its right edge
+    assert_eq!(LevelExitCondition::RightEdge, game.settings.exit_condition);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let key = make_key(&mut game.level.entities, &game.config, Pos::new(1, 0), &mut game.msg_log);
+    let exit_pos = Pos::new(3, 0);
+    make_exit(&mut game.level.entities, &game.config, exit_pos, &mut game.msg_log);
+
+    // the player needs to both carry the key and stand on the exit
+    game.level.entities.set_pos(player_id, exit_pos);
+    assert!(!level_exit_condition_met(&game.level, LevelExitCondition::KeyAndGoal));
+
+    game.level.entities.pick_up_item(player_id, key);
+    assert!(level_exit_condition_met(&game.level, LevelExitCondition::KeyAndGoal));
+
+    game.level.entities.set_pos(player_id, Pos::new(2, 0));
+    assert!(!level_exit_condition_met(&game.level, LevelExitCondition::KeyAndGoal));
+}
+
+#[test]
+fn test_objective_exit_from_map() {
+    let mut config = Config::from_file("../config.yaml");
+    config.objective_exit = true;
+    let mut game = Game::new(0, config);
+
+    let map_file = std::env::temp_dir().join("test_objective_exit_from_map.txt");
+    std::fs::write(&map_file, "@.k.e\n").unwrap();
+    let map_load_config = MapLoadConfig::TextFile(map_file.display().to_string());
+
+    // a map with both a key and an exit requires carrying the key to the exit
+    map_construct(&map_load_config, &mut game);
+    assert_eq!(LevelExitCondition::KeyAndGoal, game.settings.exit_condition);
+
+    // without the objective option, the right edge is used even with a key and exit
+    game.config.objective_exit = false;
+    map_construct(&map_load_config, &mut game);
+    assert_eq!(LevelExitCondition::RightEdge, game.settings.exit_condition);
+
+    std::fs::remove_file(&map_file).unwrap();
+}
+
+#[test]
+fn test_win_after_last_level() {
+    let mut config = Config::from_file("../config.yaml");
+    config.levels_to_win = 1;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    // walking off the right edge finishes the only level, winning the run
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let width = game.level.map.width();
+    game.level.entities.set_pos(player_id, Pos::new(width - 2, 0));
+    game.step_game(InputAction::Move(Direction::Right));
+    assert_eq!(GameState::Win, game.settings.state);
+    assert_eq!(1, game.settings.level_num);
+
+    game.step_game(InputAction::Restart);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(0, game.settings.level_num);
+}
+
+#[test]
+fn test_pick_up_inventory_full() {
+    let mut config = Config::from_file("../config.yaml");
+    config.inventory_slots = 2;
+    let mut game = setup_test_game_config("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ", config);
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let start_pos = game.level.entities.pos[&player_id];
+
+    let stone0 = make_stone(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.step_game(InputAction::Pickup);
+
+    // stones share a slot, so a second stone still fits
+    let _stone1 = make_stone(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.step_game(InputAction::Pickup);
+    assert_eq!(1, game.level.entities.inventory_slots(player_id).len());
+
+    // items that are not misc replace the item of their class, so they never use a slot
+    let _sword = make_sword(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.step_game(InputAction::Pickup);
+    let _herb = make_herb(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.step_game(InputAction::Pickup);
+    assert_eq!(4, game.level.entities.inventory[&player_id].len());
+    assert_eq!(1, game.level.entities.inventory_slots(player_id).len());
+
+    let _key0 = make_key(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.step_game(InputAction::Pickup);
+    assert_eq!(5, game.level.entities.inventory[&player_id].len());
+    assert_eq!(2, game.level.entities.inventory_slots(player_id).len());
+
+    // the second key does not fit, so the inventory is opened to choose what to drop
+    let key1 = make_key(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.step_game(InputAction::Pickup);
+    assert_eq!(5, game.level.entities.inventory[&player_id].len());
+    assert_eq!(GameState::Inventory, game.settings.state);
+    assert_eq!(Some(key1), game.settings.pending_pickup);
+
+    // the stones were placed first in the inventory
+    game.step_game(InputAction::SelectEntry(0));
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(None, game.settings.pending_pickup);
+    assert!(game.level.entities.inventory[&player_id].contains(&key1));
+    assert!(game.level.is_in_inventory(player_id, Item::Stone).is_none());
+    assert!(game.level.is_in_inventory(player_id, Item::Sword).is_some());
+    assert_ne!(Pos::new(-1, -1), game.level.entities.pos[&stone0]);
+    assert_eq!(2, game.level.entities.inventory_slots(player_id).len());
+}
+
 #[test]
 fn test_use_mode_drop() {
     let mut game = Game::new(0, Config::from_file("../config.yaml"));
@@ -571,6 +2715,127 @@ fn test_throw_stone() {
     assert_eq!(floor_pos, game.level.entities.pos[&stone]);
 }
 
+#[test]
+fn test_line_of_fire() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let start_pos = Pos::new(0, 0);
+    game.level.entities.pos[&player_id] = start_pos;
+
+    let target_pos = Pos::new(3, 0);
+    assert_eq!(LineOfFire::Clear, game.level.line_of_fire(start_pos, target_pos, false));
+
+    // a throw past its range lands short, while a sling's stone goes further
+    let far_pos = move_x(start_pos, PLAYER_THROW_DIST as i32 + 1);
+    assert_eq!(LineOfFire::OutOfRange, game.level.line_of_fire(start_pos, far_pos, false));
+    assert_eq!(LineOfFire::Clear, game.level.line_of_fire(start_pos, far_pos, true));
+
+    // an enemy on the target tile is hit, but one in front of it blocks the throw
+    let gol = make_gol(&mut game.level.entities, &game.config, target_pos, &mut game.msg_log);
+    assert_eq!(LineOfFire::Clear, game.level.line_of_fire(start_pos, target_pos, false));
+    game.level.entities.pos[&gol] = Pos::new(2, 0);
+    assert_eq!(LineOfFire::BlockedByEnemy, game.level.line_of_fire(start_pos, target_pos, false));
+    game.level.entities.remove_entity(gol);
+
+    let column = make_column(&mut game.level.entities, &game.config, Pos::new(2, 0), &mut game.msg_log);
+    assert_eq!(LineOfFire::BlockedByObject, game.level.line_of_fire(start_pos, target_pos, false));
+    game.level.entities.remove_entity(column);
+
+    // a short wall stops a throw just as it stops movement
+    game.level.map[Pos::new(2, 0)] = Tile::short_left_wall();
+    assert_ne!(target_pos, game.level.throw_towards(start_pos, target_pos));
+    assert_eq!(LineOfFire::BlockedByWall, game.level.line_of_fire(start_pos, target_pos, false));
+
+    // a throw past its range is still stopped by what is in the way
+    assert_eq!(LineOfFire::BlockedByWall, game.level.line_of_fire(start_pos, far_pos, false));
+}
+
+#[test]
+fn test_cursor_undo() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let start_pos = game.level.entities.pos[&player_id];
+
+    let stone = make_stone(&mut game.level.entities, &game.config, start_pos, &mut game.msg_log);
+    game.step_game(InputAction::Pickup);
+
+    game.step_game(InputAction::StartUseItem(ItemClass::Misc));
+    game.step_game(InputAction::CursorMove(Direction::Right, false, false));
+    game.step_game(InputAction::CursorMove(Direction::Right, false, false));
+    game.step_game(InputAction::CursorMove(Direction::Right, false, false));
+
+    // undoing an overshot move only moves the cursor back, keeping the item to throw
+    game.step_game(InputAction::CursorUndo);
+    assert_eq!(Some(move_x(start_pos, 2)), game.settings.cursor);
+    assert_eq!(Some(UseAction::Item(ItemClass::Misc)), game.settings.cursor_action);
+    assert_eq!(0, game.settings.turn_count);
+    assert!(game.level.entities.inventory[&player_id].contains(&stone));
+
+    game.step_game(InputAction::CursorUndo);
+    game.step_game(InputAction::CursorUndo);
+    assert_eq!(Some(start_pos), game.settings.cursor);
+
+    // there is nothing left to undo
+    game.step_game(InputAction::CursorUndo);
+    assert_eq!(Some(start_pos), game.settings.cursor);
+
+    // the history is cleared on leaving cursor mode
+    game.step_game(InputAction::CursorMove(Direction::Down, false, false));
+    game.step_game(InputAction::CursorToggle);
+    assert!(game.settings.cursor_history.is_empty());
+    assert_eq!(move_y(start_pos, 1), game.level.entities.pos[&stone]);
+}
+
+#[test]
+fn test_slow_motion_toggle() {
+    let mut game = setup_test_game("
+        @.........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+        ..........
+    ");
+
+    // slow motion is only a display setting, so toggling it does not take a turn
+    game.step_game(InputAction::SlowMotionToggle);
+    assert!(game.settings.slow_motion);
+    assert_eq!(0, game.settings.turn_count);
+    assert!(game.msg_log.turn_messages.contains(&Msg::TimeScale(game.config.slow_motion_scale)));
+
+    game.step_game(InputAction::SlowMotionToggle);
+    assert!(!game.settings.slow_motion);
+    assert!(game.msg_log.turn_messages.contains(&Msg::TimeScale(1.0)));
+}
+
 fn run_thumpers(game: &mut Game) {
     for id in game.level.entities.ids.iter() {
         if game.level.entities.name[id] == EntityName::Thumper {
@@ -8,10 +8,18 @@ use roguelike_map::*;
 use roguelike_core::types::*;
 use roguelike_core::messaging::*;
 use roguelike_core::movement::*;
+use roguelike_core::utils::reachable_positions;
+use roguelike_core::config::valid_time_scale;
+#[cfg(test)]
+use roguelike_core::config::Config;
 
 use roguelike_engine::input::*;
+use roguelike_engine::actions::InputAction;
 use roguelike_engine::game::*;
 use roguelike_engine::generation::*;
+use roguelike_engine::log::Log;
+#[cfg(test)]
+use roguelike_engine::map_construct::map_construct;
 
 
 // TODO
@@ -22,13 +30,12 @@ use roguelike_engine::generation::*;
 // possibly:
 // inject messages?
 // change settings
-// change config
 // line positions?
 // blocked pos, blocked path, etc
 // fov
 // convenience, like visible entity ids
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum GameCmd {
     PlayerId,
     Pos(u64),
@@ -38,6 +45,7 @@ pub enum GameCmd {
     Energy(u32),
     Facing(u64),
     SetFacing(u64, Direction),
+    SetSize(u64, i32, i32),
     MapSize,
     TileWalls(i32, i32),
     SetTileWalls(i32, i32, TileType, Wall, Wall), // type, left, bottom
@@ -61,8 +69,21 @@ pub enum GameCmd {
     LightTouch(bool),
     SureFooted(bool),
     QuickReflexes(bool),
+    WallSight(bool),
+    SoundDebug(bool),
+    SlowMotion(f32), // animation speed, 1.0 for normal speed
     Visible(EntityId, i32, i32),
+    Set(String, String), // config field name, value
     Blink,
+    Seed,
+    CheckConnectivity,
+    Entities,
+    // Each action is stepped in order, even if an earlier one kills the player or changes
+    // the level. Later actions then apply to the new state, such as the loss screen.
+    Actions(Vec<InputAction>),
+    SaveMap(String), // file path
+    LoadMap(String), // file path
+    ExportReplay,
     Exit,
 }
 
@@ -97,6 +118,11 @@ impl FromStr for GameCmd {
         } else if cmd == "energy" {
             let amount = args.next().ok_or("no arg")?.parse::<u32>().map_err(|err| format!("{}", err))?;
             return Ok(GameCmd::Energy(amount));
+        } else if cmd == "set_size" {
+            let id = args.next().ok_or("no arg")?.parse::<u64>().map_err(|err| format!("{}", err))?;
+            let width  = args.next().ok_or("no arg")?.parse::<i32>().map_err(|err| format!("{}", err))?;
+            let height  = args.next().ok_or("no arg")?.parse::<i32>().map_err(|err| format!("{}", err))?;
+            return Ok(GameCmd::SetSize(id, width, height));
         } else if cmd == "facing" {
             let id = args.next().ok_or("no arg")?.parse::<u64>().map_err(|err| format!("{}", err))?;
             if let Some(dir) = args.next() {
@@ -197,13 +223,60 @@ impl FromStr for GameCmd {
         } else if cmd == "quick_reflexes" {
             let onoff = args.next().ok_or("no arg")?.parse::<bool>().map_err(|err| format!("{}", err))?;
             return Ok(GameCmd::QuickReflexes(onoff));
+        } else if cmd == "wall_sight" {
+            let onoff = args.next().ok_or("no arg")?.parse::<bool>().map_err(|err| format!("{}", err))?;
+            return Ok(GameCmd::WallSight(onoff));
+        } else if cmd == "sound_debug" {
+            let onoff = args.next().ok_or("no arg")?.parse::<bool>().map_err(|err| format!("{}", err))?;
+            return Ok(GameCmd::SoundDebug(onoff));
+        } else if cmd == "slow_motion" {
+            let scale = args.next().ok_or("no arg")?.parse::<f32>().map_err(|err| format!("{}", err))?;
+            if !valid_time_scale(scale) {
+                return Err(format!("slow motion scale must be above 0, not {}", scale));
+            }
+            return Ok(GameCmd::SlowMotion(scale));
         } else if cmd == "visible" {
             let id = args.next().ok_or("no arg")?.parse::<u64>().map_err(|err| format!("{}", err))?;
             let x  = args.next().ok_or("no arg")?.parse::<i32>().map_err(|err| format!("{}", err))?;
             let y  = args.next().ok_or("no arg")?.parse::<i32>().map_err(|err| format!("{}", err))?;
             return Ok(GameCmd::Visible(id, x, y));
+        } else if cmd == "set" {
+            let field_name = args.next().ok_or("no arg")?.to_string();
+            let value = args.collect::<Vec<&str>>().join(" ");
+            if value.is_empty() {
+                return Err("no arg".to_string());
+            }
+            return Ok(GameCmd::Set(field_name, value));
         } else if cmd == "blink" {
             return Ok(GameCmd::Blink);
+        } else if cmd == "seed" {
+            return Ok(GameCmd::Seed);
+        } else if cmd == "check_connectivity" {
+            return Ok(GameCmd::CheckConnectivity);
+        } else if cmd == "entities" {
+            return Ok(GameCmd::Entities);
+        } else if cmd == "actions" {
+            let actions = args.filter(|arg| !arg.is_empty())
+                              .map(parse_action_token)
+                              .collect::<Result<Vec<InputAction>, String>>()?;
+            if actions.is_empty() {
+                return Err("no arg".to_string());
+            }
+            return Ok(GameCmd::Actions(actions));
+        } else if cmd == "save_map" || cmd == "load_map" {
+            // the path is taken from the original string, as file names can be case sensitive
+            let path = string.trim().splitn(2, ' ').nth(1).unwrap_or("").trim().to_string();
+            if path.is_empty() {
+                return Err("no arg".to_string());
+            }
+
+            if cmd == "save_map" {
+                return Ok(GameCmd::SaveMap(path));
+            } else {
+                return Ok(GameCmd::LoadMap(path));
+            }
+        } else if cmd == "export_replay" {
+            return Ok(GameCmd::ExportReplay);
         } else if cmd == "exit" {
             return Ok(GameCmd::Exit);
         }
@@ -212,6 +285,26 @@ impl FromStr for GameCmd {
     }
 }
 
+/// Parse a single action in the compact form used by the 'actions' command.
+/// An action is written as it is displayed, with underscores in place of spaces,
+/// such as 'pass', 'startuseitem_primary', or 'usedir_left'. Moves may also be
+/// written as 'move_up', and pressing an item key as 'use_z', 'use_x' or 'use_c'.
+fn parse_action_token(token: &str) -> Result<InputAction, String> {
+    if let Some(key) = token.strip_prefix("use_") {
+        let mut chars = key.chars();
+        let item_class = match (chars.next(), chars.next()) {
+            (Some(chr), None) => item_key_class(chr),
+            _ => None,
+        };
+        return item_class.map(InputAction::StartUseItem)
+                         .ok_or(format!("action '{}': '{}' is not an item key", token, key));
+    }
+
+    let token = token.strip_prefix("move_").unwrap_or(token);
+    let action_str = token.replace("_", " ");
+    return action_str.parse::<InputAction>().map_err(|err| format!("action '{}': {}", token, err));
+}
+
 impl GameCmd {
     pub fn name(&self) -> &str {
         if matches!(self, GameCmd::PlayerId) {
@@ -230,6 +323,8 @@ impl GameCmd {
             return "facing";
         } else if matches!(self, GameCmd::SetFacing(_, _)) {
             return "facing";
+        } else if matches!(self, GameCmd::SetSize(_, _, _)) {
+            return "set_size";
         } else if matches!(self, GameCmd::MapSize) {
             return "map_size";
         } else if matches!(self, GameCmd::TileWalls(_, _)) {
@@ -276,10 +371,32 @@ impl GameCmd {
             return "sure_footed";
         } else if matches!(self, GameCmd::QuickReflexes(_)) {
             return "quick_reflexes";
+        } else if matches!(self, GameCmd::WallSight(_)) {
+            return "wall_sight";
+        } else if matches!(self, GameCmd::SoundDebug(_)) {
+            return "sound_debug";
+        } else if matches!(self, GameCmd::SlowMotion(_)) {
+            return "slow_motion";
         } else if matches!(self, GameCmd::Visible(_, _, _)) {
             return "visible";
+        } else if matches!(self, GameCmd::Set(_, _)) {
+            return "set";
         } else if matches!(self, GameCmd::Blink) {
             return "blink";
+        } else if matches!(self, GameCmd::Seed) {
+            return "seed";
+        } else if matches!(self, GameCmd::CheckConnectivity) {
+            return "check_connectivity";
+        } else if matches!(self, GameCmd::Entities) {
+            return "entities";
+        } else if matches!(self, GameCmd::Actions(_)) {
+            return "actions";
+        } else if matches!(self, GameCmd::SaveMap(_)) {
+            return "save_map";
+        } else if matches!(self, GameCmd::LoadMap(_)) {
+            return "load_map";
+        } else if matches!(self, GameCmd::ExportReplay) {
+            return "export_replay";
         } else if matches!(self, GameCmd::Exit) {
             return "exit";
         } else {
@@ -352,6 +469,18 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
             return format!("{}", name);
         }
 
+        GameCmd::SetSize(id, width, height) => {
+            if !game.level.entities.pos.contains_key(id) {
+                return format!("{} false", name);
+            }
+
+            let placed = game.level.set_entity_size(*id, (*width, *height));
+            if placed {
+                game.msg_log.log(Msg::EntitySize(*id, *width, *height));
+            }
+            return format!("{} {}", name, placed);
+        }
+
         GameCmd::MapSize => {
             return format!("{} {} {}", name, game.level.map.width(), game.level.map.height());
         }
@@ -379,11 +508,181 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
             return format!("{}", name);
         }
 
+        GameCmd::Set(field_name, value) => {
+            match game.config.set_field(field_name, value) {
+                Ok(()) => return format!("{} {} {}", name, field_name, value),
+                Err(err) => return format!("{} error {}", name, err),
+            }
+        }
+
         GameCmd::Blink => {
             game.msg_log.log(Msg::Blink(player_id));
             return format!("{}", name);
         }
 
+        GameCmd::Actions(actions) => {
+            // each action is stepped the same way as a single key command's action
+            for input_action in actions.iter() {
+                game.step_game(*input_action);
+            }
+
+            let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+            let pos = game.level.entities.pos[&player_id];
+            let hp = game.level.entities.hp[&player_id].hp;
+            return format!("{} {} {} {} {} {}", name, actions.len(), pos.x, pos.y, hp, game.settings.state);
+        }
+
+        GameCmd::Seed => {
+            return format!("{} {}", name, game.seed);
+        }
+
+        GameCmd::CheckConnectivity => {
+            // flood from the level start, both walking and as if walls could be passed,
+            // to separate tiles that are only reachable with skills like PassWall.
+            let start_pos = game.settings.level_start;
+            let walkable = reachable_positions(&game.level.map, start_pos, false);
+            let with_skills = reachable_positions(&game.level.map, start_pos, true);
+
+            let mut unreachable = Vec::new();
+            let mut skills_only = Vec::new();
+            for pos in game.level.map.get_all_pos() {
+                if game.level.map[pos].block_move || walkable.contains(&pos) {
+                    continue;
+                }
+
+                if with_skills.contains(&pos) {
+                    skills_only.push(format!("{},{}", pos.x, pos.y));
+                } else {
+                    unreachable.push(format!("{},{}", pos.x, pos.y));
+                }
+            }
+
+            let mut unreachable_entities = Vec::new();
+            let mut skills_only_entities = Vec::new();
+            for entity_id in game.level.entities.ids.iter() {
+                let typ = game.level.entities.typ[entity_id];
+                if typ != EntityType::Enemy && typ != EntityType::Item {
+                    continue;
+                }
+
+                // items in an inventory are not on the map
+                let pos = game.level.entities.pos[entity_id];
+                if !game.level.map.is_within_bounds(pos) || walkable.contains(&pos) {
+                    continue;
+                }
+
+                let entity_str = format!("{}:{:?}:{},{}", entity_id, game.level.entities.name[entity_id], pos.x, pos.y);
+                if with_skills.contains(&pos) {
+                    skills_only_entities.push(entity_str);
+                } else {
+                    unreachable_entities.push(entity_str);
+                }
+            }
+
+            return format!("{} unreachable {} [{}] skills_only {} [{}] unreachable_entities [{}] skills_only_entities [{}]",
+                           name,
+                           unreachable.len(), unreachable.join(" "),
+                           skills_only.len(), skills_only.join(" "),
+                           unreachable_entities.join(" "),
+                           skills_only_entities.join(" "));
+        }
+
+        GameCmd::Entities => {
+            // every lookup here uses 'get' so that an entity with missing components is
+            // reported rather than causing the same panic being investigated.
+            let entities = &game.level.entities;
+            let mut entity_strs = Vec::new();
+            for entity_id in entities.ids.iter() {
+                let name_str = entities.name.get(entity_id).map_or("?".to_string(), |name| format!("{:?}", name));
+                let typ = entities.typ.get(entity_id).copied();
+                let typ_str = typ.map_or("?".to_string(), |typ| format!("{:?}", typ));
+                let pos_str = entities.pos.get(entity_id).map_or("?".to_string(), |pos| format!("{},{}", pos.x, pos.y));
+
+                let mut comps = Vec::new();
+                if entities.hp.contains_key(entity_id) {
+                    comps.push("hp");
+                }
+                if entities.behavior.contains_key(entity_id) {
+                    comps.push("behavior");
+                }
+                if entities.ai.contains_key(entity_id) {
+                    comps.push("ai");
+                }
+                if entities.trap.contains_key(entity_id) {
+                    comps.push("trap");
+                }
+                if entities.item.contains_key(entity_id) {
+                    comps.push("item");
+                }
+
+                let mut expected = vec!("name", "typ", "pos");
+                match typ {
+                    Some(EntityType::Player) => expected.push("hp"),
+                    Some(EntityType::Enemy) => expected.extend(&["hp", "behavior", "ai"]),
+                    Some(EntityType::Item) => expected.push("item"),
+                    _ => {},
+                }
+
+                let missing = expected.iter()
+                                      .filter(|comp| {
+                                          match **comp {
+                                              "name" => !entities.name.contains_key(entity_id),
+                                              "typ" => !entities.typ.contains_key(entity_id),
+                                              "pos" => !entities.pos.contains_key(entity_id),
+                                              comp => !comps.contains(&comp),
+                                          }
+                                      })
+                                      .map(|comp| comp.to_string())
+                                      .collect::<Vec<String>>();
+
+                let mut entity_str = format!("{} {} {} {} [{}]", entity_id, name_str, typ_str, pos_str, comps.join(" "));
+                if !missing.is_empty() {
+                    entity_str.push_str(&format!(" MISSING [{}]", missing.join(" ")));
+                }
+                entity_strs.push(entity_str);
+            }
+
+            return format!("{} {} {}", name, entities.ids.len(), entity_strs.join("; "));
+        }
+
+        GameCmd::SaveMap(path) => {
+            if let Err(err) = std::fs::write(path, game.level.map.to_bytes()) {
+                return format!("{} error {}", name, err);
+            }
+            return format!("{}", name);
+        }
+
+        GameCmd::LoadMap(path) => {
+            let map = std::fs::read(path).map_err(|err| err.to_string())
+                                         .and_then(|bytes| Map::from_bytes(&bytes));
+            match map {
+                // the level's entities are placed on the current map, so a map of another
+                // size could leave them out of bounds
+                Ok(map) if map.size() != game.level.map.size() => {
+                    let (width, height) = map.size();
+                    let (cur_width, cur_height) = game.level.map.size();
+                    return format!("{} error map is {}x{} but the level is {}x{}", name, width, height, cur_width, cur_height);
+                }
+
+                Ok(map) => {
+                    game.level.map = map;
+                    game.settings.map_changed = true;
+                    return format!("{}", name);
+                }
+
+                Err(err) => {
+                    return format!("{} error {}", name, err);
+                }
+            }
+        }
+
+        GameCmd::ExportReplay => {
+            match Log::export_replay(&game.settings.map_load_config) {
+                Ok(replay) => return format!("{} {}", name, replay),
+                Err(err) => return format!("{} error {}", name, err),
+            }
+        }
+
         GameCmd::Exit => {
             game.settings.running = false;
             return format!("{}", name);
@@ -501,6 +800,22 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
             return format!("{}", name);
         }
 
+        GameCmd::WallSight(onoff) => {
+            game.level.entities.passive[&player_id].wall_sight = *onoff;
+            return format!("{}", name);
+        }
+
+        GameCmd::SoundDebug(onoff) => {
+            game.msg_log.log(Msg::SoundDebug(*onoff));
+            return format!("{}", name);
+        }
+
+        GameCmd::SlowMotion(scale) => {
+            game.settings.slow_motion = *scale != 1.0;
+            game.msg_log.log(Msg::TimeScale(*scale));
+            return format!("{}", name);
+        }
+
         GameCmd::Visible(entity_id, x, y) => {
             let pos = Pos::new(*x, *y);
             let visible = game.level.pos_in_fov(*entity_id, pos);
@@ -511,3 +826,25 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
         // game.step_game(input_action);
     }
 }
+
+#[test]
+fn test_actions_command() {
+    let cmd = "actions move_right move_right use_z".parse::<GameCmd>();
+    let actions = vec!(InputAction::Move(Direction::Right),
+                       InputAction::Move(Direction::Right),
+                       InputAction::StartUseItem(ItemClass::Primary));
+    assert_eq!(Ok(GameCmd::Actions(actions)), cmd);
+
+    // only the item keys can be used this way
+    assert!("actions use_q".parse::<GameCmd>().is_err());
+    assert!("actions use_zx".parse::<GameCmd>().is_err());
+    assert!("actions".parse::<GameCmd>().is_err());
+
+    let mut game = Game::new(0, Config::from_file("../config.yaml"));
+    map_construct(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+    let cmd = "actions move_right move_right move_down".parse::<GameCmd>().unwrap();
+    execute_game_command(&cmd, &mut game);
+    assert_eq!(Pos::new(2, 1), game.level.entities.pos[&player_id]);
+}
@@ -26,6 +26,7 @@ pub fn main() {
     let io_recv = spawn_input_reader();
 
     let mut log = Log::new();
+    log.log_run_start(seed);
 
     game.emit_state_messages();
     for msg in game.msg_log.turn_messages.iter() {
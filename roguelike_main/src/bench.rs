@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use roguelike_utils::rng::*;
+
+use roguelike_map::*;
+
+use roguelike_core::types::*;
+use roguelike_core::config::Config;
+
+use roguelike_engine::game::*;
+use roguelike_engine::actions::*;
+use roguelike_engine::map_construct::map_construct;
+
+
+/// Run the game without a display for the given number of turns, choosing a random
+/// valid action each turn, and print timing statistics to stdout.
+/// When the player dies, or the run otherwise ends, the game is restarted so the full
+/// number of turns is always run.
+pub fn run_bench(seed: u64, turns: usize, map_config: &MapLoadConfig, config: Config) {
+    let mut game = Game::new(seed, config);
+    game.load_vaults("resources/vaults/");
+    map_construct(map_config, &mut game);
+
+    let mut rng = Rand32::new(seed);
+
+    let mut turns_taken = 0;
+    let mut steps = 0;
+    let mut restarts = 0;
+    let mut step_time = Duration::from_secs(0);
+    let mut max_step_time = Duration::from_secs(0);
+
+    let bench_start = Instant::now();
+    while turns_taken < turns {
+        let input_action;
+        match game.settings.state {
+            GameState::Playing => {
+                input_action = random_action(&game, &mut rng);
+            }
+
+            GameState::Lose | GameState::TimeUp => {
+                input_action = InputAction::Restart;
+            }
+
+            _ => {
+                // winning or ending up in a menu ends the run, so start a new one
+                // the same way as when time runs out.
+                game.settings.state = GameState::TimeUp;
+                input_action = InputAction::Restart;
+            }
+        }
+
+        let turn_count = game.settings.turn_count;
+
+        let step_start = Instant::now();
+        game.step_game(input_action);
+        let step_elapsed = step_start.elapsed();
+
+        step_time += step_elapsed;
+        max_step_time = max_step_time.max(step_elapsed);
+        steps += 1;
+
+        if input_action == InputAction::Restart {
+            restarts += 1;
+        } else if game.settings.turn_count != turn_count {
+            turns_taken += 1;
+        }
+    }
+    let bench_elapsed = bench_start.elapsed();
+
+    let secs = bench_elapsed.as_secs_f64();
+    println!("Benchmark: {} turns, {} steps, {} restarts", turns_taken, steps, restarts);
+    println!("  total time     {:.3} s", secs);
+    println!("  turns/sec      {:.1}", turns_taken as f64 / secs);
+    println!("  avg step_game  {:.3} ms", 1000.0 * step_time.as_secs_f64() / steps.max(1) as f64);
+    println!("  max step_game  {:.3} ms", 1000.0 * max_step_time.as_secs_f64());
+}
+
+/// Pick a random action the player could take: passing, or moving in a
+/// direction that is not blocked by a wall or the edge of the map.
+fn random_action(game: &Game, rng: &mut Rand32) -> InputAction {
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.level.entities.pos[&player_id];
+
+    let mut actions = vec!(InputAction::Pass);
+    for dir in Direction::directions().iter() {
+        let next_pos = dir.offset_pos(player_pos, 1);
+        if game.level.map.is_within_bounds(next_pos) &&
+           game.level.map.path_blocked_move(player_pos, next_pos).is_none() {
+            actions.push(InputAction::Move(*dir));
+        }
+    }
+
+    let index = rng_range_u32(rng, 0, actions.len() as u32) as usize;
+    return actions[index];
+}
@@ -0,0 +1,97 @@
+use std::fs;
+
+use roguelike_map::MapLoadConfig;
+
+use roguelike_core::config::Config;
+
+
+// deaths are kept across sessions so the hint can tell a player who keeps dying on a map.
+pub const DEATH_COUNTS_FILE: &str = "death_counts.txt";
+
+/// Count a death on the map given by the seed and map configuration, returning the number
+/// of deaths on that map so far. Each line of the file is the count, seed, and map
+/// configuration separated by tabs, so deaths on other maps are counted separately.
+pub fn record_death(seed: u64, map_config: &MapLoadConfig) -> usize {
+    return record_death_in(DEATH_COUNTS_FILE, seed, map_config);
+}
+
+fn record_death_in(file_name: &str, seed: u64, map_config: &MapLoadConfig) -> usize {
+    let map_config_str = map_config.to_string();
+
+    let mut counts: Vec<(usize, u64, String)> = Vec::new();
+    if let Ok(contents) = fs::read_to_string(file_name) {
+        for line in contents.lines() {
+            let mut parts = line.splitn(3, '\t');
+            let count = parts.next().and_then(|count| count.parse::<usize>().ok());
+            let line_seed = parts.next().and_then(|line_seed| line_seed.parse::<u64>().ok());
+            if let (Some(count), Some(line_seed), Some(line_map)) = (count, line_seed, parts.next()) {
+                counts.push((count, line_seed, line_map.to_string()));
+            }
+        }
+    }
+
+    let deaths;
+    if let Some(entry) = counts.iter_mut().find(|(_count, line_seed, line_map)| *line_seed == seed && *line_map == map_config_str) {
+        entry.0 += 1;
+        deaths = entry.0;
+    } else {
+        counts.push((1, seed, map_config_str));
+        deaths = 1;
+    }
+
+    let contents = counts.iter()
+                         .map(|(count, line_seed, line_map)| format!("{}\t{}\t{}\n", count, line_seed, line_map))
+                         .collect::<String>();
+    if let Err(err) = fs::write(file_name, contents) {
+        eprintln!("Could not write {}: {}", file_name, err);
+    }
+
+    return deaths;
+}
+
+/// The hint to show after the given number of deaths on a map, if any.
+pub fn death_hint(config: &Config, map_config: &MapLoadConfig, deaths: usize) -> Option<String> {
+    if !config.death_hints || deaths <= config.death_hint_threshold {
+        return None;
+    }
+
+    let map_config_str = map_config.to_string();
+    let hint = config.death_hint_maps.iter()
+                     .find(|(hint_map, _hint)| *hint_map == map_config_str)
+                     .map_or(config.death_hint_default.clone(), |(_hint_map, hint)| hint.clone());
+    return Some(hint);
+}
+
+#[test]
+fn test_record_death() {
+    let file_name = std::env::temp_dir().join("test_record_death_counts.txt");
+    let file_name = file_name.to_str().unwrap();
+    let _ = fs::remove_file(file_name);
+
+    assert_eq!(1, record_death_in(file_name, 1, &MapLoadConfig::Empty));
+    assert_eq!(2, record_death_in(file_name, 1, &MapLoadConfig::Empty));
+
+    // other seeds and map configurations are counted separately
+    assert_eq!(1, record_death_in(file_name, 2, &MapLoadConfig::Empty));
+    assert_eq!(1, record_death_in(file_name, 1, &MapLoadConfig::TestMap));
+    assert_eq!(3, record_death_in(file_name, 1, &MapLoadConfig::Empty));
+
+    let _ = fs::remove_file(file_name);
+}
+
+#[test]
+fn test_death_hint() {
+    let mut config = Config::from_file("../config.yaml");
+    config.death_hints = true;
+    config.death_hint_threshold = 3;
+    config.death_hint_default = "default hint".to_string();
+    config.death_hint_maps = vec!((MapLoadConfig::TestMap.to_string(), "test map hint".to_string()));
+
+    // the hint is only shown after more deaths than the threshold
+    assert_eq!(None, death_hint(&config, &MapLoadConfig::Empty, 3));
+    assert_eq!(Some("default hint".to_string()), death_hint(&config, &MapLoadConfig::Empty, 4));
+    assert_eq!(Some("test map hint".to_string()), death_hint(&config, &MapLoadConfig::TestMap, 4));
+
+    config.death_hints = false;
+    assert_eq!(None, death_hint(&config, &MapLoadConfig::Empty, 4));
+}
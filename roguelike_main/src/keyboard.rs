@@ -1,5 +1,5 @@
 use sdl2::event::Event;
-use sdl2::mouse::MouseButton;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
 use sdl2::keyboard::Keycode;
 
 use roguelike_utils::math::*;
@@ -72,6 +72,13 @@ pub fn translate_event(event: Event) -> Option<InputEvent> {
             return Some(InputEvent::MousePos(x, y));
         }
 
+        Event::MouseWheel {y, direction, ..} => {
+            if direction == MouseWheelDirection::Flipped {
+                return Some(InputEvent::MouseWheel(-y));
+            }
+            return Some(InputEvent::MouseWheel(y));
+        }
+
         Event::MouseButtonDown {mouse_btn, x, y, ..} => {
             let click;
             match mouse_btn {
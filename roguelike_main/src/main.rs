@@ -1,14 +1,19 @@
 mod throttler;
 mod keyboard;
 mod replay;
+mod bench;
+mod perf;
+mod death_hints;
 
 use std::fs;
 use std::io::{BufRead, Write, Cursor};
-use std::time::{Duration, Instant, SystemTime};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::path::Path;
 use std::str::FromStr;
 use std::thread;
-use std::sync::mpsc::{self, channel, Receiver};
+use std::sync::mpsc::{self, channel, Receiver, Sender};
+
+use sdl2::event::{Event, WindowEvent};
 
 use log::LevelFilter;
 use simple_logging;
@@ -19,10 +24,13 @@ use gumdrop::Options;
 use rmp_serde::{Deserializer, Serializer};
 use serde::{Deserialize, Serialize};
 
-use roguelike_map::MapLoadConfig;
+use roguelike_utils::math::Pos;
+
+use roguelike_map::{MapLoadConfig, FovResult};
 
 use roguelike_core::types::*;
-use roguelike_core::config::Config;
+use roguelike_core::config::{Config, FrameLimitMode};
+use roguelike_core::messaging::Msg;
 use roguelike_core::constants::{SCREEN_WIDTH, SCREEN_HEIGHT};
 
 use roguelike_engine::game::*;
@@ -39,16 +47,31 @@ use roguelike_display::render::*;
 
 use crate::throttler::*;
 use crate::replay::*;
+use crate::bench::*;
+use crate::perf::*;
+use crate::death_hints::*;
 
 
 pub const CONFIG_NAME: &str = "config.yaml";
+// autosaves are kept in rotating slots, named game.save.0, game.save.1, and so on.
 pub const GAME_SAVE_FILE: &str = "game.save";
 
+pub const REPLAY_STRING_FILE: &str = "replay_string.txt";
+
+/// Extra time to wait each frame while paused in the background.
+pub const UNFOCUSED_WAIT_MS: u64 = 100;
+
 #[derive(Debug, Clone, Options)]
 pub struct GameOptions {
     #[options(help = "replay from an input log file", short="r")]
     pub replay: Option<String>,
 
+    #[options(help = "replay a run from a string exported from the end screen or the export_replay command")]
+    pub replay_string: Option<String>,
+
+    #[options(help = "start from the given save file instead of a new map, such as when replaying a log recorded from that save")]
+    pub from_save: Option<String>,
+
     #[options(help = "record a session with the given name", short="d")]
     pub record: Option<String>,
 
@@ -76,13 +99,35 @@ pub struct GameOptions {
     #[options(help = "procgen map config", short="g")]
     pub procgen_map: Option<String>,
 
+    #[options(help = "run the given number of turns with random inputs and no display, reporting timing")]
+    pub bench: Option<usize>,
+
+    #[options(help = "append each frame's stage timings to the given CSV file")]
+    pub perf_csv: Option<String>,
+
     #[options(help = "display help text")]
     pub help: bool,
 }
 
 
 fn main() {
-    let opts = GameOptions::parse_args_default_or_exit();
+    let mut opts = GameOptions::parse_args_default_or_exit();
+
+    // a replay string carries its own seed and map configuration
+    if let Some(replay_string) = &opts.replay_string {
+        match ReplayString::decode(replay_string) {
+            Ok(replay) => {
+                opts.seed = Some(replay.seed);
+                opts.map_config = Some(replay.map_config.to_string());
+                opts.procgen_map = None;
+            }
+
+            Err(err) => {
+                eprintln!("Could not use replay string: {}", err);
+                return;
+            }
+        }
+    }
 
     let seed: u64 =
         if let Some(given_seed) = opts.seed {
@@ -98,6 +143,13 @@ fn main() {
                                       |level_str| LevelFilter::from_str(&level_str).expect("Log level unexpected!"));
     simple_logging::log_to_file("game.log", log_level).unwrap();
 
+    if let Some(turns) = opts.bench {
+        let config = Config::from_file(CONFIG_NAME);
+        let map_config = create_map_config(&opts, &config);
+        run_bench(seed, turns, &map_config, config);
+        return;
+    }
+
     run(seed, opts).unwrap();
 }
 
@@ -109,16 +161,26 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
     let mut timer;
     let canvas;
 
+    let mut config = Config::from_file(CONFIG_NAME);
+
     {
         let video = sdl_context.video()?;
         timer = sdl_context.timer()?;
         let window = video.window("Rust Roguelike", SCREEN_WIDTH, SCREEN_HEIGHT)
                           .position_centered().build().map_err(|e| e.to_string())?;
 
-        canvas = window.into_canvas()
-                       .accelerated()
-                       .build()
-                       .map_err(|e| e.to_string())?;
+        let mut canvas_builder = window.into_canvas().accelerated();
+        if config.frame_limit_mode == FrameLimitMode::Vsync {
+            canvas_builder = canvas_builder.present_vsync();
+        }
+        canvas = canvas_builder.build().map_err(|e| e.to_string())?;
+
+        // SDL creates the renderer without vsync if the driver does not support it.
+        let vsync_flag = sdl2::sys::SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC as u32;
+        if config.frame_limit_mode == FrameLimitMode::Vsync && canvas.info().flags & vsync_flag == 0 {
+            eprintln!("Vsync is not available, limiting the frame rate to {} instead", config.frame_rate);
+            config.frame_limit_mode = FrameLimitMode::Fixed;
+        }
 
         //sdl2::hint::set("SDL_HINT_RENDER_SCALE_QUALITY", "linear");
 
@@ -127,21 +189,9 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
     }
 
     /* Create Game Structure */
-    let config = Config::from_file(CONFIG_NAME);
-
     let mut game = Game::new(seed, config.clone());
     game.load_vaults("resources/vaults/");
 
-    let mut game_from_file = false;
-    if config.save_load {
-        if let Some((game_loaded, display_loaded)) = load_save(GAME_SAVE_FILE) {
-            game = game_loaded;
-            display.state = display_loaded;
-            display.load_atlas();
-            game_from_file = true;
-        }
-    }
-
     /* Create Map */
     let map_config: MapLoadConfig = create_map_config(&opts, &config);
 
@@ -171,7 +221,21 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
         }
     } else {
         /* Run Game */
-        if !game_from_file {
+        // a saved game is only loaded when chosen from the main menu, or given on the command line
+        if let Some(save_file) = &opts.from_save {
+            let (game_loaded, display_loaded) =
+                load_save(save_file).ok_or(format!("Could not load save file '{}'", save_file))?;
+            // the save contains the random number generator's state, so the game continues
+            // exactly as it would have when the save was made.
+            game = game_loaded;
+            game.apply_map_config();
+            display.state = display_loaded;
+            display.load_atlas();
+
+            if let Some(replay_file) = &opts.replay {
+                check_replay_rng_state(&game, replay_file)?;
+            }
+        } else {
             map_construct(&map_config, &mut game);
         }
         let event_pump = sdl_context.event_pump().unwrap();
@@ -179,21 +243,64 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
     }
 }
 
+/// A log recorded from a save only replays correctly if the save's random number generator
+/// is in the state the recording started with.
+fn check_replay_rng_state(game: &Game, replay_file: &str) -> Result<(), String> {
+    let save_state = game.rng.state();
+    match read_action_log_rng_state(replay_file) {
+        Some(log_state) if log_state != save_state => {
+            return Err(format!("Replay '{}' was recorded with RNG state {:?}, but the save has RNG state {:?}. The replay was not recorded from this save.",
+                               replay_file, log_state, save_state));
+        }
+
+        Some(_) => {}
+
+        None => {
+            eprintln!("Replay '{}' has no recorded RNG state, so it can not be checked against the save", replay_file);
+        }
+    }
+
+    return Ok(());
+}
+
 pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer: &mut sdl2::TimerSubsystem, mut event_pump: sdl2::EventPump) -> Result<(), String> {
     // read in the recorded action log, if one is provided
     let mut starting_actions = Vec::new();
     if let Some(replay_file) = &opts.replay {
         starting_actions = read_action_log(&replay_file);
+    } else if let Some(replay_string) = &opts.replay_string {
+        // the string was already checked when reading the options. Actions are popped
+        // off the end, so they are reversed to play in the order they were recorded.
+        let replay = ReplayString::decode(replay_string)?;
+        starting_actions = replay.actions.into_iter().rev().collect();
     }
 
+    // replays are often left running in the background, so they are never paused.
+    let replaying = !starting_actions.is_empty();
+
     let mut config_modified_time = fs::metadata(CONFIG_NAME).unwrap().modified().unwrap();
 
     let mut log = Log::new();
+    log.log_rng_state(game.rng.state());
+    if opts.from_save.is_some() {
+        log.log_save_loaded();
+    } else {
+        log.log_run_start(game.seed);
+    }
     let mut recording = Recording::new(&game, &display.state);
+    let mut turn_undo = TurnUndo::new(&game, &display.state);
 
     /* Setup FPS Throttling */
-    let frame_ms = 1000 / game.config.frame_rate as u64;
-    let fps_throttler = Throttler::new(Duration::from_millis(frame_ms));
+    // Only the fixed mode sleeps between frames. With vsync, presenting the canvas waits for
+    // the display instead. The mode is chosen when the canvas is created, so it is not reloaded.
+    let fps_throttler = match game.config.frame_limit_mode {
+        FrameLimitMode::Fixed => {
+            let frame_ms = 1000 / game.config.frame_rate as u64;
+            Some(Throttler::new(Duration::from_millis(frame_ms)))
+        }
+
+        FrameLimitMode::Vsync | FrameLimitMode::Uncapped => None,
+    };
 
     /* Set up Input Handling */
     let io_recv = spawn_input_reader();
@@ -201,8 +308,18 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
     /* Game Save Thread */
     // Serialization and storage take at least 6 ms, so this is done
     // in a separate thread to prevent taking time from the main loop.
-    let (game_sender, game_receiver) = channel::<(Game, DisplayState)>();
-    let _save_thread = thread::spawn(move || { save_game_thread(game_receiver); });
+    // Save files are only written and removed on this thread.
+    migrate_legacy_save();
+    let (game_sender, game_receiver) = channel::<SaveRequest>();
+    let (save_exists_sender, save_exists_receiver) = channel::<bool>();
+    let _save_thread = thread::spawn(move || { save_game_thread(game_receiver, save_exists_sender); });
+
+    // Start at the main menu, unless replaying a log or starting from a save, which start directly in the game.
+    game.settings.save_exists = any_save_exists(game.config.save_slots);
+    game.settings.run_id = new_run_id();
+    if starting_actions.is_empty() && opts.from_save.is_none() {
+        change_state(&mut game.settings, GameState::MainMenu, &mut game.msg_log);
+    }
 
     // Stepping logic here executes messages logged while setting up the level.
     step_logic(&mut game);
@@ -213,13 +330,23 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
     game.msg_log.clear();
     display.clear_console_messages();
 
+    let mut perf_csv = match &opts.perf_csv {
+        Some(file_name) => Some(PerfCsv::new(file_name)?),
+        None => None,
+    };
+
     /* Main Game Loop */
     let mut frame_time = Instant::now();
+    let mut mouse_pos = Pos::new(0, 0);
+    let mut game_over_screenshot_saved = false;
+    let mut death_recorded = false;
+    let mut run_saves_cleared = false;
     while game.settings.running {
         let mut any_updates = false;
 
         let _loop_timer = timer!("GAME_LOOP");
         let frame_start_time = Instant::now();
+        let mut stage_timer = StageTimer::new();
 
         /* Input */
         let mut input_actions: Vec<InputAction> = Vec::new();
@@ -233,7 +360,54 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
             // ticks is better then Instant for serialization.
             let ticks = timer.ticks();
             for sdl2_event in event_pump.poll_iter() {
-                if let Some(event) = keyboard::translate_event(sdl2_event) {
+                if let Event::Window { win_event, .. } = sdl2_event {
+                    match win_event {
+                        WindowEvent::FocusGained => {
+                            game.settings.focused = true;
+                        }
+
+                        WindowEvent::FocusLost => {
+                            game.settings.focused = false;
+
+                            // keys released while unfocused are never seen, so nothing is left held,
+                            // and moves buffered ahead are dropped.
+                            game.input = Input::new();
+                            game.input_queue.clear();
+                        }
+
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                let paused = game.config.pause_when_unfocused && !game.settings.focused && !replaying;
+                if paused && !matches!(sdl2_event, Event::Quit { .. }) {
+                    continue;
+                }
+
+                if let Some(mut event) = keyboard::translate_event(sdl2_event) {
+                    // Clicking an inventory button is the same as pressing its key.
+                    if let InputEvent::MouseButton(MouseClick::Left, mouse_pos, dir) = event {
+                        if let Some(chr) = display.button_from_pixel(mouse_pos) {
+                            event = InputEvent::Char(chr, dir);
+                        }
+                    }
+
+                    if let InputEvent::MousePos(x, y) = event {
+                        mouse_pos = Pos::new(x, y);
+
+                        // the map panel may be scrolled, so the camera offset is applied here
+                        if let Some(tile) = display.map_tile_from_pixel(mouse_pos) {
+                            display.state.show_debug("mouse  ", format!("{}", tile));
+                        }
+                    }
+
+                    // the wheel event does not carry the mouse position, so the last one is used
+                    if let InputEvent::MouseWheel(amount) = event {
+                        display.zoom_map(amount, mouse_pos);
+                        any_updates = true;
+                    }
+
                     // First check for [ and ], which are processed outside of the normal input
                     // system.
                     if game.config.recording && matches!(event, InputEvent::Char('[', KeyDir::Up)) {
@@ -256,24 +430,37 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
                 }
             }
         }
+        stage_timer.lap();
+
+        let paused = game.config.pause_when_unfocused && !game.settings.focused && !replaying;
 
         /* Misc */
         {
             let _misc_timer = timer!("MISC");
 
             // if there are starting actions to read, pop one off to play
-            if let Some(action) = starting_actions.pop() {
-                input_actions.push(action);
+            if !paused {
+                if let Some(action) = starting_actions.pop() {
+                    input_actions.push(action);
+                }
             }
 
-           /* Record Inputs to Log File */
-            for input_action in input_actions.iter() {
-                log.log_action(*input_action);
+            // moves pressed ahead are buffered, and played one at a time once the previous
+            // move has finished animating. Actions are logged as they are stepped, so queued
+            // actions are recorded when they are taken.
+            input_actions.retain(|input_action| !game.queue_input(*input_action));
+            if !display.animations_in_progress() {
+                if let Some(action) = game.next_queued_input() {
+                    input_actions.push(action);
+                }
             }
         }
+        stage_timer.lap();
 
         /* Logic */
-        {
+        // while paused the game is not stepped, but the display below still presents the last frame.
+        // Quitting is the only input let through while paused, so it is still handled.
+        if !paused || !input_actions.is_empty() {
             let _logic_timer = timer!("LOGIC");
 
             // if no actions, make sure to step the game anyway
@@ -282,6 +469,31 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
             }
 
             for input_action in input_actions {
+                if input_action == InputAction::UndoTurn {
+                    log.log_action(input_action);
+                    if let Some((prev_game, prev_display_state)) = turn_undo.undo() {
+                        game = prev_game;
+                        display.state = prev_display_state;
+                    }
+                    continue;
+                }
+
+                if input_action == InputAction::ExportReplay {
+                    export_replay(&game);
+                    continue;
+                }
+
+                if game.config.interruptible_animations {
+                    if let InputAction::Move(_) | InputAction::MoveTowardsCursor = input_action {
+                        display.interrupt_animations(&game.config);
+                    }
+                }
+
+                /* Record Inputs to Log File */
+                // this is done as each action is stepped, so a run started by an earlier
+                // action is marked in the log before the actions that belong to it.
+                log.log_action(input_action);
+
                 game.step_game(input_action);
                 
                 if game.config.recording && input_action != InputAction::None {
@@ -289,8 +501,13 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
                 }
 
                 output_messages(&mut game, &mut display, &mut recording, &mut log);
+
+                turn_undo.action(&game, &display.state);
+
+                process_menu_messages(&mut game, &mut display, &mut recording, &mut turn_undo, &game_sender);
             }
         }
+        stage_timer.lap();
 
         /* Display */
         {
@@ -301,13 +518,35 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
             let dt = Instant::now().duration_since(frame_time).as_secs_f32();
             frame_time = Instant::now();
 
-            display.state.show_debug("frame  ", format!("{:.6}", dt));
-
             update_display(&mut game, &mut display, dt)?;
 
             let disp_time = Instant::now().duration_since(frame_time).as_secs_f32();
             display.state.show_debug("display", format!("{:.6}", disp_time));
         }
+        stage_timer.lap();
+
+        /* Game Over Screenshot */
+        // Only saved once per game over, so the menu shown afterwards does not save every frame.
+        let game_over = game.settings.state == GameState::Win || game.settings.state == GameState::Lose;
+        if game_over && !game_over_screenshot_saved && game.config.game_over_screenshot {
+            game_over_screenshot_saved = true;
+            if let Err(err) = save_game_over_screenshot(game.seed, &mut game, &mut display) {
+                eprintln!("Could not save game over screenshot: {}", err);
+            }
+        } else if game.settings.state == GameState::Playing {
+            game_over_screenshot_saved = false;
+        }
+
+        /* Death Hints */
+        if game.settings.state == GameState::Lose && !death_recorded {
+            death_recorded = true;
+            if game.config.death_hints {
+                let deaths = record_death(game.settings.map_seed, &game.settings.map_load_config);
+                display.state.death_hint = death_hint(&game.config, &game.settings.map_load_config, deaths);
+            }
+        } else if game.settings.state == GameState::Playing {
+            death_recorded = false;
+        }
 
         game.msg_log.clear();
 
@@ -316,33 +555,63 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, timer:
             let _config_timer = timer!("CONFIG");
             reload_config(&mut config_modified_time, &mut game);
         }
+        stage_timer.lap();
 
         /* Save Game */
+        // without permadeath the player respawns, so the save is kept.
+        // A won run is over as well, so it can not be continued from the menu.
         let player_id = game.level.find_by_name(EntityName::Player).unwrap();
-        if game.level.entities.hp[&player_id].hp > 0 {
-            if game.settings.running && any_updates && game.config.save_load {
+        let player_alive = game.level.entities.hp[&player_id].hp > 0 || !game.config.permadeath;
+        if player_alive && game.settings.state != GameState::Win {
+            run_saves_cleared = false;
+
+            // the main menu decides whether the game is saved, so it is not saved automatically there
+            if game.settings.running && any_updates && game.config.save_load &&
+               game.settings.state != GameState::MainMenu {
                 let old_state = game.settings.state;
                 game.settings.state = GameState::Playing;
                 display.state.state = GameState::Playing;
-                game_sender.send((game.clone(), display.state.clone())).unwrap();
+                game_sender.send(SaveRequest::Save(game.clone(), display.state.clone())).unwrap();
                 game.settings.state = old_state;
                 display.state.state = old_state;
             }
-        } else {
-            let path = Path::new(GAME_SAVE_FILE);
-            if path.exists() {
-                std::fs::remove_file(path).unwrap();
-            }
+        } else if !run_saves_cleared {
+            // only this run's saves are removed, so an earlier run can still be continued
+            game_sender.send(SaveRequest::ClearRun(game.settings.run_id, game.config.save_slots)).unwrap();
+            run_saves_cleared = true;
         }
 
+        // the save thread reports whether any saves remain after each request
+        while let Ok(save_exists) = save_exists_receiver.try_recv() {
+            game.settings.save_exists = save_exists;
+        }
+        stage_timer.lap();
+
         /* Wait until the next tick to loop */
         {
+            // the frame time is measured before waiting for the next frame, so it shows the time
+            // spent on the frame rather than the frame interval, which is always near the budget.
             let frame_time = Instant::now().duration_since(frame_start_time).as_secs_f32();
-            display.state.show_debug("inframe", format!("{:.6}", frame_time));
+            display.state.show_debug("frame  ", format!("{:.6}", frame_time));
 
             let _wait_timer = timer!("WAIT");
-            fps_throttler.wait();
+            if let Some(fps_throttler) = &fps_throttler {
+                fps_throttler.wait();
+            }
+
+            if paused {
+                thread::sleep(Duration::from_millis(UNFOCUSED_WAIT_MS));
+            }
         }
+        stage_timer.lap();
+
+        if let Some(perf_csv) = &mut perf_csv {
+            perf_csv.record(game.settings.turn_count, &stage_timer)?;
+        }
+    }
+
+    if let Some(perf_csv) = &mut perf_csv {
+        perf_csv.flush()?;
     }
 
     // NOTE we could also just put these files in the right place to begin with...
@@ -370,6 +639,13 @@ fn output_messages(game: &mut Game, display: &mut Display, recording: &mut Recor
         if !game.config.minimal_output {
             log.log_msg(&format!("{}", msg));
         }
+
+        // the action log is split into runs, so a replay can be exported for just the current one
+        match msg {
+            Msg::Restart => log.log_run_start(game.seed),
+            Msg::ContinueGame => log.log_save_loaded(),
+            _ => {}
+        }
     }
 
     // Output informational messages (FoV, inventory, etc)
@@ -387,6 +663,55 @@ fn output_messages(game: &mut Game, display: &mut Display, recording: &mut Recor
     }
 }
 
+/// Handle the main menu's requests to load the saved game, or to save before leaving a game.
+fn process_menu_messages(game: &mut Game,
+                         display: &mut Display,
+                         recording: &mut Recording,
+                         turn_undo: &mut TurnUndo,
+                         game_sender: &Sender<SaveRequest>) {
+    for msg_index in 0..game.msg_log.turn_messages.len() {
+        match game.msg_log.turn_messages[msg_index] {
+            Msg::ContinueGame => {
+                if let Some((game_loaded, display_loaded)) = load_newest_save(game.config.save_slots) {
+                    *game = game_loaded;
+                    game.apply_map_config();
+                    display.state = display_loaded;
+                    display.load_atlas();
+
+                    // the loaded game is not related to the turns taken before it
+                    *recording = Recording::new(game, &display.state);
+                    *turn_undo = TurnUndo::new(game, &display.state);
+                } else {
+                    eprintln!("Could not load any save file {}.*", GAME_SAVE_FILE);
+                }
+                game.settings.save_exists = any_save_exists(game.config.save_slots);
+                return;
+            }
+
+            Msg::Restart => {
+                game.settings.run_id = new_run_id();
+            }
+
+            Msg::ReturnToMenu(save) => {
+                if save {
+                    let mut saved_game = game.clone();
+                    let mut saved_display_state = display.state.clone();
+                    saved_game.settings.state = GameState::Playing;
+                    saved_display_state.state = GameState::Playing;
+                    game_sender.send(SaveRequest::Save(saved_game, saved_display_state)).unwrap();
+                    game.settings.save_exists = true;
+                } else {
+                    // the run has been autosaved as it was played, so its saves are removed to
+                    // leave it unsaved. Saves from earlier runs are kept.
+                    game_sender.send(SaveRequest::ClearRun(game.settings.run_id, game.config.save_slots)).unwrap();
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
 fn save_record(record_name: &str) {
     // create log directory if it doesn't exist
     let path = format!("resources/test_logs/{}", record_name);
@@ -410,6 +735,7 @@ fn reload_config(config_modified_time: &mut SystemTime, game: &mut Game) {
         if current_config_modified_time != *config_modified_time {
             *config_modified_time = current_config_modified_time;
             game.config = Config::from_file(CONFIG_NAME);
+            game.apply_map_config();
         }
     }
 }
@@ -420,21 +746,62 @@ pub fn take_screenshot(game: &mut Game, display: &mut Display) -> Result<(), Str
     game.step_game(InputAction::None);
     render_all(&mut display.panels, &mut display.state, &display.sprites, &game.config, 0.1)?;
 
-    display.save_screenshot("screenshot");
+    display.save_screenshot("screenshot")?;
 
     return Ok(());
 }
 
+/// Reveal the whole map and save a screenshot of it, named with the seed and turn count.
+/// The player's view is restored afterwards.
+fn save_game_over_screenshot(seed: u64, game: &mut Game, display: &mut Display) -> Result<(), String> {
+    let dir = game.config.game_over_screenshot_dir.clone();
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+
+    let player_view = display.state.clone();
+
+    for x in 0..display.state.map.width() {
+        for y in 0..display.state.map.height() {
+            display.state.fov.insert(Pos::new(x, y), FovResult::Inside);
+        }
+    }
+    for entity_id in display.state.ids.iter() {
+        display.state.entities_in_fov.insert(*entity_id, FovResult::Inside);
+    }
+
+    let mut result = render_all(&mut display.panels, &mut display.state, &display.sprites, &game.config, 0.0);
+    if result.is_ok() {
+        display.draw_all(&mut game.rng);
+        let name = format!("{}/{}_{}", dir, seed, game.settings.turn_count);
+        result = display.save_screenshot(&name);
+    }
+
+    display.state = player_view;
+
+    return result;
+}
+
 fn update_display(game: &mut Game, display: &mut Display, dt: f32) -> Result<(), String> {
+    display.state.save_exists = game.settings.save_exists;
+    display.state.seed = game.seed;
+
     for msg in game.msg_log.turn_messages.iter() {
         display.process_message(*msg, &game.level.map, &game.config);
-        display.console_message(msg.msg_line(&game.level), &game.config);
+        if msg.verbosity() <= game.config.log_verbosity {
+            display.console_message(msg.msg_line(&game.level), &game.config);
+        }
     }
 
     for msg in game.msg_log.info_messages.iter() {
         display.process_info_message(*msg);
     }
 
+    // sprite overrides are names, which are not carried by messages, so they are copied here.
+    for (entity_id, sprite_name) in game.level.entities.sprite_override.iter() {
+        if display.state.sprite_override.get(&entity_id) != Some(sprite_name) {
+            display.set_sprite_override(entity_id, sprite_name, &game.config);
+        }
+    }
+
     let map_str = game.level.map.compact_chrs();
     display.map_message(&map_str);
 
@@ -450,7 +817,7 @@ fn update_display(game: &mut Game, display: &mut Display, dt: f32) -> Result<(),
     {
         let _draw_timer = timer!("DRAW");
         let update_time = Instant::now();
-        display.draw_all(&mut game.rng, &game.config);
+        display.draw_all(&mut game.rng);
 
         {
             let _present_timer = timer!("PRESENT");
@@ -463,6 +830,60 @@ fn update_display(game: &mut Game, display: &mut Display, dt: f32) -> Result<(),
     return Ok(());
 }
 
+/// The file for an autosave slot, where slot 0 is the newest save.
+fn save_slot_file(slot: usize) -> String {
+    return format!("{}.{}", GAME_SAVE_FILE, slot);
+}
+
+fn any_save_exists(save_slots: usize) -> bool {
+    return (0..save_slots.max(1)).any(|slot| Path::new(&save_slot_file(slot)).exists());
+}
+
+/// Load the newest save, falling back to older slots if a save can not be read.
+fn load_newest_save(save_slots: usize) -> Option<(Game, DisplayState)> {
+    for slot in 0..save_slots.max(1) {
+        let file_name = save_slot_file(slot);
+        if let Some(loaded) = load_save(&file_name) {
+            return Some(loaded);
+        } else if Path::new(&file_name).exists() {
+            eprintln!("Could not load save file {}, trying an older save", file_name);
+        }
+    }
+
+    return None;
+}
+
+/// Saves from before there were save slots are kept in GAME_SAVE_FILE, so such a save is
+/// moved into the newest slot where it can be continued.
+fn migrate_legacy_save() {
+    let slot_file = save_slot_file(0);
+    if Path::new(GAME_SAVE_FILE).exists() && !Path::new(&slot_file).exists() {
+        if let Err(err) = std::fs::rename(GAME_SAVE_FILE, &slot_file) {
+            eprintln!("Could not move save file {} to {}: {}", GAME_SAVE_FILE, slot_file, err);
+        }
+    }
+}
+
+/// Remove the saves made during the given run, leaving any saves from an earlier run.
+/// Saves that can not be read are left in place.
+fn clear_run_saves(run_id: u64, save_slots: usize) {
+    for slot in 0..save_slots.max(1) {
+        let file_name = save_slot_file(slot);
+        if let Some((saved_game, _)) = load_save(&file_name) {
+            if saved_game.settings.run_id == run_id {
+                if let Err(err) = std::fs::remove_file(&file_name) {
+                    eprintln!("Could not remove save file {}: {}", file_name, err);
+                }
+            }
+        }
+    }
+}
+
+/// Identify a new run, so its saves can be told apart from those of earlier runs.
+fn new_run_id() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as u64).unwrap_or(0);
+}
+
 fn load_save(filename: &str) -> Option<(Game, DisplayState)> {
     if let Ok(bytes) = std::fs::read(filename) {
         let cur = Cursor::new(&bytes[..]);
@@ -474,6 +895,22 @@ fn load_save(filename: &str) -> Option<(Game, DisplayState)> {
     return None
 }
 
+/// Write the run so far as a replay string, which can be played back with '--replay-string'.
+fn export_replay(game: &Game) {
+    match Log::export_replay(&game.settings.map_load_config) {
+        Ok(replay) => {
+            if let Err(err) = fs::write(REPLAY_STRING_FILE, &replay) {
+                eprintln!("Could not write '{}': {}", REPLAY_STRING_FILE, err);
+            }
+            println!("{}", replay);
+        }
+
+        Err(err) => {
+            eprintln!("Could not export replay: {}", err);
+        }
+    }
+}
+
 fn create_map_config(opts: &GameOptions, config: &Config) -> MapLoadConfig {
     let map_config: MapLoadConfig;
 
@@ -492,13 +929,52 @@ fn create_map_config(opts: &GameOptions, config: &Config) -> MapLoadConfig {
     return map_config;
 }
 
-fn save_game_thread(game_receiver: Receiver<(Game, DisplayState)>) {
+/// The requests handled by the save thread.
+enum SaveRequest {
+    Save(Game, DisplayState),
+    ClearRun(u64, usize), // run id, save slots
+}
+
+fn save_game_thread(save_receiver: Receiver<SaveRequest>, save_exists_sender: Sender<bool>) {
+    // The run and level in the newest slot. Autosaves of the same level overwrite it, so the
+    // older slots keep the starts of earlier levels and runs rather than the last few turns.
+    let mut newest_save: Option<(u64, usize)> = None;
+
     loop {
-        if let Ok(game) = game_receiver.recv() {
-            let mut buf = Vec::new();
-            game.serialize(&mut Serializer::new(&mut buf)).unwrap();
-            let mut save_game_file = std::fs::File::create(GAME_SAVE_FILE).unwrap();
-            save_game_file.write_all(&buf).unwrap();
+        if let Ok(request) = save_receiver.recv() {
+            let save_slots;
+            match request {
+                SaveRequest::Save(game, display_state) => {
+                    save_slots = game.config.save_slots.max(1);
+                    let save_key = (game.settings.run_id, game.settings.level_num);
+
+                    let mut buf = Vec::new();
+                    (game, display_state).serialize(&mut Serializer::new(&mut buf)).unwrap();
+
+                    // shift the older saves down a slot, dropping the oldest, so slot 0 is the newest.
+                    if newest_save != Some(save_key) {
+                        for slot in (0..save_slots - 1).rev() {
+                            let file_name = save_slot_file(slot);
+                            if Path::new(&file_name).exists() {
+                                std::fs::rename(&file_name, save_slot_file(slot + 1)).unwrap();
+                            }
+                        }
+                        newest_save = Some(save_key);
+                    }
+
+                    let mut save_game_file = std::fs::File::create(save_slot_file(0)).unwrap();
+                    save_game_file.write_all(&buf).unwrap();
+                }
+
+                SaveRequest::ClearRun(run_id, run_save_slots) => {
+                    save_slots = run_save_slots;
+                    clear_run_saves(run_id, save_slots);
+                    newest_save = None;
+                }
+            }
+
+            // the main loop may have finished, in which case nobody is waiting on this
+            let _ = save_exists_sender.send(any_save_exists(save_slots));
         } else {
             break;
         }
@@ -0,0 +1,88 @@
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::fs::File;
+use std::time::{Duration, Instant};
+
+
+/// Rows are buffered and only flushed every so many frames, so recording
+/// timings does not add file I/O to every frame.
+const PERF_FLUSH_ROWS: usize = 120;
+
+/// The stages of the game loop, in the order they are timed.
+pub const PERF_STAGES: [&str; 7] = ["input", "misc", "logic", "display", "config", "save", "wait"];
+
+/// Times each stage of a frame by the time since the previous stage ended.
+pub struct StageTimer {
+    last: Instant,
+    stages: Vec<Duration>,
+}
+
+impl StageTimer {
+    pub fn new() -> StageTimer {
+        return StageTimer { last: Instant::now(), stages: Vec::with_capacity(PERF_STAGES.len()) };
+    }
+
+    /// End the current stage, starting the next one.
+    pub fn lap(&mut self) {
+        let now = Instant::now();
+        self.stages.push(now.duration_since(self.last));
+        self.last = now;
+    }
+}
+
+/// Appends each frame's stage timings to a CSV file, for graphing performance over a session.
+pub struct PerfCsv {
+    writer: BufWriter<File>,
+    frame: u64,
+    rows_since_flush: usize,
+}
+
+impl PerfCsv {
+    pub fn new(file_name: &str) -> Result<PerfCsv, String> {
+        let file = OpenOptions::new().create(true)
+                                     .append(true)
+                                     .open(file_name)
+                                     .map_err(|err| format!("Could not open perf CSV '{}': {}", file_name, err))?;
+        let is_empty = file.metadata().map_or(true, |metadata| metadata.len() == 0);
+
+        let mut perf_csv = PerfCsv { writer: BufWriter::new(file), frame: 0, rows_since_flush: 0 };
+
+        // appending to an existing file keeps its header
+        if is_empty {
+            let stage_columns = PERF_STAGES.iter()
+                                           .map(|stage| format!("{}_ms", stage))
+                                           .collect::<Vec<String>>()
+                                           .join(",");
+            writeln!(perf_csv.writer, "frame,turn,{},total_ms", stage_columns).map_err(|err| err.to_string())?;
+        }
+
+        return Ok(perf_csv);
+    }
+
+    pub fn record(&mut self, turn: usize, stage_timer: &StageTimer) -> Result<(), String> {
+        let total: Duration = stage_timer.stages.iter().sum();
+        let stage_columns = stage_timer.stages.iter()
+                                       .map(|duration| format!("{:.3}", duration_ms(*duration)))
+                                       .collect::<Vec<String>>()
+                                       .join(",");
+        writeln!(self.writer, "{},{},{},{:.3}", self.frame, turn, stage_columns, duration_ms(total)).map_err(|err| err.to_string())?;
+        self.frame += 1;
+
+        self.rows_since_flush += 1;
+        if self.rows_since_flush >= PERF_FLUSH_ROWS {
+            self.flush()?;
+        }
+
+        return Ok(());
+    }
+
+    /// Write out any buffered rows. This is called when the game exits so no rows are lost.
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.rows_since_flush = 0;
+        return self.writer.flush().map_err(|err| err.to_string());
+    }
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    return duration.as_secs_f64() * 1000.0;
+}
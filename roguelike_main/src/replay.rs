@@ -11,7 +11,11 @@ use roguelike_core::config::*;
 #[cfg(test)]
 use roguelike_core::movement::*;
 #[cfg(test)]
+use roguelike_utils::math::Pos;
+#[cfg(test)]
+use roguelike_engine::generation::make_gol;
 use roguelike_core::types::*;
+use roguelike_core::messaging::Msg;
 
 use roguelike_engine::game::*;
 use roguelike_engine::actions::*;
@@ -86,6 +90,51 @@ impl Recording {
     }
 }
 
+/// Single turn undo available during normal play. Only the state from the start of the
+/// player's last turn is kept, so a turn can be undone once. The state is only copied when
+/// a turn is taken, rather than before every input.
+pub struct TurnUndo {
+    turn_start: (Game, DisplayState),
+    prev_state: Option<(Game, DisplayState)>,
+}
+
+impl TurnUndo {
+    pub fn new(game: &Game, display_state: &DisplayState) -> TurnUndo {
+        return TurnUndo { turn_start: (game.clone(), display_state.clone()), prev_state: None };
+    }
+
+    /// Check whether an action used up a turn. If so, the state from the start of that turn
+    /// can be undone to, and the current state becomes the start of the next turn.
+    /// Undo is not possible after the player dies or after changing levels.
+    pub fn action(self: &mut TurnUndo, game: &Game, display_state: &DisplayState) {
+        let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+
+        let new_level = game.settings.level_num != self.turn_start.0.settings.level_num ||
+                        game.msg_log.turn_messages.iter().any(|msg| *msg == Msg::NewLevel);
+        let took_turn = game.settings.turn_count != self.turn_start.0.settings.turn_count;
+        if !new_level && !took_turn {
+            return;
+        }
+
+        let turn_start = std::mem::replace(&mut self.turn_start, (game.clone(), display_state.clone()));
+
+        let player_dead = game.level.entities.hp[&player_id].hp <= 0;
+        if new_level || player_dead {
+            self.prev_state = None;
+        } else {
+            self.prev_state = Some(turn_start);
+        }
+    }
+
+    pub fn undo(self: &mut TurnUndo) -> Option<(Game, DisplayState)> {
+        let prev_state = self.prev_state.take();
+        if let Some(prev_state) = &prev_state {
+            self.turn_start = prev_state.clone();
+        }
+        return prev_state;
+    }
+}
+
 #[test]
 pub fn test_recording() {
     let mut config = Config::from_file("../config.yaml");
@@ -153,6 +202,112 @@ pub fn test_recording() {
     assert_eq!(starting_pos.y + 2, step3_2_pos.y);
 }
 
+#[test]
+pub fn test_sounds_kept_after_turn() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    let mut game = Game::new(0, config.clone());
+    map_construct(&config.map_load, &mut game);
+
+    let mut display_state = DisplayState::new();
+
+    // walking makes a sound, which is still there to draw once the turn's messages are processed
+    game.msg_log.clear();
+    game.step_game(InputAction::Move(Direction::Right));
+    for msg in game.msg_log.turn_messages.iter() {
+        display_state.process_turn_message(*msg, &game.level.map, &game.config);
+    }
+    assert!(!display_state.sound_events.is_empty());
+    assert!(!display_state.sound_tiles.is_empty());
+
+    // a silent turn clears the previous turn's sounds
+    game.msg_log.clear();
+    game.step_game(InputAction::Pass);
+    for msg in game.msg_log.turn_messages.iter() {
+        display_state.process_turn_message(*msg, &game.level.map, &game.config);
+    }
+    assert!(display_state.sound_events.is_empty());
+}
+
+#[test]
+pub fn test_idle_sounds_kept_after_turn() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.enemy_idle_sounds = true;
+    config.enemy_idle_sound_chance = 1.0;
+    config.enemy_idle_sound_entities = vec!(EntityName::Gol);
+    let mut game = Game::new(0, config.clone());
+    map_construct(&config.map_load, &mut game);
+
+    let mut display_state = DisplayState::new();
+
+    let gol = make_gol(&mut game.level.entities, &game.config, Pos::new(8, 8), &mut game.msg_log);
+
+    game.msg_log.clear();
+    game.step_game(InputAction::Pass);
+    for msg in game.msg_log.turn_messages.iter() {
+        display_state.process_turn_message(*msg, &game.level.map, &game.config);
+    }
+    assert!(display_state.sound_events.contains_key(&gol));
+}
+
+#[test]
+pub fn test_undo_turn_replay() {
+    fn take_actions(game: &mut Game, actions: &[InputAction], mut log: Option<&mut Log>) {
+        let mut display_state = DisplayState::new();
+        let mut turn_undo = TurnUndo::new(game, &display_state);
+        for input_action in actions.iter() {
+            if let Some(log) = log.as_mut() {
+                log.log_action(*input_action);
+            }
+
+            if *input_action == InputAction::UndoTurn {
+                if let Some((prev_game, prev_display_state)) = turn_undo.undo() {
+                    *game = prev_game;
+                    display_state = prev_display_state;
+                }
+                continue;
+            }
+
+            game.msg_log.clear();
+            game.step_game(*input_action);
+            turn_undo.action(game, &display_state);
+        }
+    }
+
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::TestPlayer;
+
+    let action_log_name = std::env::temp_dir().join("test_undo_turn_replay_actions.txt");
+    let message_log_name = std::env::temp_dir().join("test_undo_turn_replay_messages.txt");
+    let mut log = Log { action_log: std::fs::File::create(&action_log_name).unwrap(),
+                        message_log: std::fs::File::create(&message_log_name).unwrap(),
+                        console_output_only: true };
+
+    // walk down, undo it, and walk right instead
+    let mut game = Game::new(0, config.clone());
+    map_construct(&config.map_load, &mut game);
+    let player_id = game.level.find_by_name(EntityName::Player).unwrap();
+    let starting_pos = game.level.entities.pos[&player_id];
+    log.log_run_start(game.seed);
+    let actions = vec!(InputAction::Move(Direction::Right),
+                       InputAction::Move(Direction::Down),
+                       InputAction::UndoTurn,
+                       InputAction::Move(Direction::Right));
+    take_actions(&mut game, &actions, Some(&mut log));
+    let played_pos = game.level.entities.pos[&player_id];
+    assert_eq!(Pos::new(starting_pos.x + 2, starting_pos.y), played_pos);
+
+    // the undo is in the log, so replaying the log ends in the same place
+    let (seed, logged_actions) = Log::read_run(action_log_name.to_str().unwrap()).unwrap();
+    assert_eq!(actions, logged_actions);
+
+    let mut replayed_game = Game::new(seed, config.clone());
+    map_construct(&config.map_load, &mut replayed_game);
+    take_actions(&mut replayed_game, &logged_actions, None);
+    assert_eq!(played_pos, replayed_game.level.entities.pos[&player_id]);
+}
+
 
 pub const MAP_CONFIG_NAME: &str = "map_config.txt";
 
@@ -417,6 +572,24 @@ pub fn read_action_log(replay_file: &str) -> Vec<InputAction> {
     return starting_actions;
 }
 
+/// Read the random number generator state recorded at the start of an action log.
+/// Logs written before this state was recorded do not have one.
+pub fn read_action_log_rng_state(replay_file: &str) -> Option<(u64, u64)> {
+    let file =
+        std::fs::File::open(&replay_file).expect(&format!("Could not open replay file '{}'", &replay_file));
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line.ok()?;
+        let mut parts = line.split(" ");
+        if parts.next() == Some(Log::RNG_STATE_PREFIX) {
+            let state = parts.next()?.parse::<u64>().ok()?;
+            let inc = parts.next()?.parse::<u64>().ok()?;
+            return Some((state, inc));
+        }
+    }
+
+    return None;
+}
+
 pub fn read_message_log(message_file: &str) -> Vec<String> {
     let mut message_lines = Vec::new();
 
@@ -1,6 +1,8 @@
 use std::ops::{Index, IndexMut};
 use std::collections::{HashSet, HashMap};
 use std::cell::RefCell;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use symmetric_shadowcasting::Pos as SymPos;
 use symmetric_shadowcasting::compute_fov;
@@ -10,6 +12,9 @@ use smallvec::SmallVec;
 use itertools::Itertools;
 
 use serde_derive::*;
+use serde::{Serialize, Deserialize};
+
+use rmp_serde::{Deserializer, Serializer};
 
 use roguelike_utils::math::Pos;
 use roguelike_utils::line::*;
@@ -19,11 +24,39 @@ use crate::utils::*;
 use crate::types::*;
 
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Map {
 
     pub tiles: Vec<Vec<Tile>>,
     pub fov_cache: RefCell<HashMap<Pos, Vec<Pos>>>,
+
+    // changes whenever a tile may have been modified. Versions are unique across all maps,
+    // so results cached against one map are never mistaken for results on another.
+    #[serde(skip, default = "next_map_version")]
+    pub version: u64,
+
+    // whether diagonal movement can pass between perpendicular walls meeting at a corner
+    #[serde(default = "default_allow_corner_cutting")]
+    pub allow_corner_cutting: bool,
+}
+
+// the version only tracks changes, so maps with the same tiles are equal whatever their versions.
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        return self.tiles == other.tiles &&
+               self.fov_cache == other.fov_cache &&
+               self.allow_corner_cutting == other.allow_corner_cutting;
+    }
+}
+
+fn default_allow_corner_cutting() -> bool {
+    return true;
+}
+
+static NEXT_MAP_VERSION: AtomicU64 = AtomicU64::new(1);
+
+fn next_map_version() -> u64 {
+    return NEXT_MAP_VERSION.fetch_add(1, Ordering::Relaxed);
 }
 
 impl Map {
@@ -32,6 +65,8 @@ impl Map {
             Map {
                 tiles,
                 fov_cache: RefCell::new(HashMap::new()),
+                version: next_map_version(),
+                allow_corner_cutting: true,
             };
 
         return map;
@@ -43,6 +78,8 @@ impl Map {
             Map {
                 tiles,
                 fov_cache: RefCell::new(HashMap::new()),
+                version: next_map_version(),
+                allow_corner_cutting: true,
             };
 
         return map;
@@ -53,6 +90,8 @@ impl Map {
             Map {
                 tiles: Vec::new(),
                 fov_cache: RefCell::new(HashMap::new()),
+                version: next_map_version(),
+                allow_corner_cutting: true,
             };
 
         return map;
@@ -288,59 +327,83 @@ impl Map {
         return Pos::new(new_x, new_y);
     }
 
-    pub fn place_intertile_wall(&mut self, pos: Pos, material: Surface, direction: Direction) {
+    /// Can an intertile wall be placed on the given side of a tile? The tile on the
+    /// other side of the wall must not block movement, and must not be water.
+    pub fn can_place_intertile_wall(&self, pos: Pos, direction: Direction) -> bool {
         match direction {
             Direction::Left => {
                 let left_pos = move_x(pos, -1);
-                let tile_supports_wall = self.is_within_bounds(left_pos) && 
-                                         !self[left_pos].does_tile_block(BlockedType::Move) && 
-                                         self[pos].tile_type != TileType::Water;
-
-                if tile_supports_wall && self[pos].left_wall == Wall::Empty {
-                    self[pos].left_wall = Wall::ShortWall;
-                    self[pos].left_material = material;
-                }
+                return self.is_within_bounds(left_pos) && 
+                       self.is_within_bounds(pos) && 
+                       !self[left_pos].does_tile_block(BlockedType::Move) && 
+                       self[pos].tile_type != TileType::Water &&
+                       self[pos].left_wall == Wall::Empty;
             }
 
             Direction::Right => {
                 let pos = move_x(pos, 1);
-                let tile_supports_wall = self.is_within_bounds(pos) && 
-                                         !self[pos].does_tile_block(BlockedType::Move) && 
-                                         self[pos].tile_type != TileType::Water;
-
-                if tile_supports_wall && self[pos].left_wall == Wall::Empty {
-                    self[pos].left_wall = Wall::ShortWall;
-                    self[pos].left_material = material;
-                }
+                return self.is_within_bounds(pos) && 
+                       !self[pos].does_tile_block(BlockedType::Move) && 
+                       self[pos].tile_type != TileType::Water &&
+                       self[pos].left_wall == Wall::Empty;
             }
 
             Direction::Up => {
                 let pos = move_y(pos, -1);
-                let tile_supports_wall = self.is_within_bounds(pos) && 
-                                         !self[pos].does_tile_block(BlockedType::Move) && 
-                                         self[pos].tile_type != TileType::Water;
-
-                if tile_supports_wall && self[pos].bottom_wall == Wall::Empty {
-                    self[pos].bottom_wall = Wall::ShortWall;
-                    self[pos].bottom_material = material;
-                }
+                return self.is_within_bounds(pos) && 
+                       !self[pos].does_tile_block(BlockedType::Move) && 
+                       self[pos].tile_type != TileType::Water &&
+                       self[pos].bottom_wall == Wall::Empty;
             }
 
             Direction::Down => {
                 let down_pos = move_y(pos, 1);
-                let tile_supports_wall = self.is_within_bounds(down_pos) &&
-                                         !self[down_pos].does_tile_block(BlockedType::Move) &&
-                                         self[down_pos].tile_type != TileType::Water;
-
-                if tile_supports_wall && self[pos].bottom_wall == Wall::Empty {
-                    self[pos].bottom_wall = Wall::ShortWall;
-                    self[pos].bottom_material = material;
-                }
+                return self.is_within_bounds(down_pos) &&
+                       self.is_within_bounds(pos) &&
+                       !self[down_pos].does_tile_block(BlockedType::Move) &&
+                       self[down_pos].tile_type != TileType::Water &&
+                       self[pos].bottom_wall == Wall::Empty;
             }
 
             Direction::DownLeft | Direction::DownRight | Direction::UpLeft | Direction::UpRight => {
-                panic!("Placing an intertile wall on a diagonal makes no sense!");
+                return false;
+            }
+        }
+    }
+
+    pub fn place_intertile_wall(&mut self, pos: Pos, material: Surface, direction: Direction) {
+        if direction.diag() {
+            panic!("Placing an intertile wall on a diagonal makes no sense!");
+        }
+
+        if !self.can_place_intertile_wall(pos, direction) {
+            return;
+        }
+
+        match direction {
+            Direction::Left => {
+                self[pos].left_wall = Wall::ShortWall;
+                self[pos].left_material = material;
             }
+
+            Direction::Right => {
+                let pos = move_x(pos, 1);
+                self[pos].left_wall = Wall::ShortWall;
+                self[pos].left_material = material;
+            }
+
+            Direction::Up => {
+                let pos = move_y(pos, -1);
+                self[pos].bottom_wall = Wall::ShortWall;
+                self[pos].bottom_material = material;
+            }
+
+            Direction::Down => {
+                self[pos].bottom_wall = Wall::ShortWall;
+                self[pos].bottom_material = material;
+            }
+
+            _ => {}
         }
     }
 
@@ -458,6 +521,29 @@ impl Map {
 
         return chrs.iter().collect::<String>();
     }
+
+    /// Encode the map losslessly with MessagePack, for saving a map on its own rather than
+    /// as part of a full game.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.serialize(&mut Serializer::new(&mut bytes)).unwrap();
+        return bytes;
+    }
+
+    /// Decode a map written by to_bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Map, String> {
+        let mut de = Deserializer::new(Cursor::new(bytes));
+        let map: Map = Deserialize::deserialize(&mut de).map_err(|err| format!("Could not read map data: {}", err))?;
+
+        // the map is indexed by position, so it must have at least one tile and every
+        // column must be the same height.
+        let height = map.tiles.get(0).map(|column| column.len()).unwrap_or(0);
+        if height == 0 || map.tiles.iter().any(|column| column.len() != height) {
+            return Err("Map data does not have a valid size".to_string());
+        }
+
+        return Ok(map);
+    }
 }
 
 impl Index<(i32, i32)> for Map {
@@ -471,6 +557,7 @@ impl Index<(i32, i32)> for Map {
 impl IndexMut<(i32, i32)> for Map {
     fn index_mut(&mut self, index: (i32, i32)) -> &mut Tile {
         self.fov_cache.borrow_mut().clear();
+        self.version = next_map_version();
         &mut self.tiles[index.0 as usize][index.1 as usize]
     }
 }
@@ -486,6 +573,7 @@ impl Index<Pos> for Map {
 impl IndexMut<Pos> for Map {
     fn index_mut(&mut self, index: Pos) -> &mut Tile {
         self.fov_cache.borrow_mut().clear();
+        self.version = next_map_version();
         &mut self.tiles[index.x as usize][index.y as usize]
     }
 }
@@ -129,6 +129,47 @@ impl Map {
         return blocking_wall || blocking_tile;
     }
 
+    /// Check for blocking between two cardinally adjacent tiles, including
+    /// either tile being a full tile wall.
+    fn cardinal_blocked(&self, pos: Pos, other: Pos, blocked_type: BlockedType) -> bool {
+        if !self.is_within_bounds(pos) || !self.is_within_bounds(other) {
+            return true;
+        }
+
+        if self[pos].does_tile_block(blocked_type) || self[other].does_tile_block(blocked_type) {
+            return true;
+        }
+
+        let dxy = sub_pos(other, pos);
+        match Direction::from_dxy(dxy.x, dxy.y) {
+            Some(Direction::Left) => return self.blocked_left(pos, blocked_type),
+            Some(Direction::Right) => return self.blocked_right(pos, blocked_type),
+            Some(Direction::Up) => return self.blocked_up(pos, blocked_type),
+            Some(Direction::Down) => return self.blocked_down(pos, blocked_type),
+            _ => return false,
+        }
+    }
+
+    /// Check whether both sides of the corner crossed by a diagonal move are
+    /// blocked, so neither cardinal route around the corner is open. Full tile
+    /// walls count as walls along each of their sides, so short walls, tall walls,
+    /// and full tile walls are all treated the same.
+    pub fn corner_blocked(&self, start_pos: Pos, end_pos: Pos, blocked_type: BlockedType) -> bool {
+        let x_moved = Pos::new(end_pos.x, start_pos.y);
+        let y_moved = Pos::new(start_pos.x, end_pos.y);
+
+        // the four wall segments that meet at the corner
+        let start_x = self.cardinal_blocked(start_pos, x_moved, blocked_type);
+        let start_y = self.cardinal_blocked(start_pos, y_moved, blocked_type);
+        let x_end = self.cardinal_blocked(x_moved, end_pos, blocked_type);
+        let y_end = self.cardinal_blocked(y_moved, end_pos, blocked_type);
+
+        let x_side_blocked = start_x || x_end;
+        let y_side_blocked = start_y || y_end;
+
+        return x_side_blocked && y_side_blocked;
+    }
+
     pub fn path_blocked_fov(&self, start_pos: Pos, end_pos: Pos) -> Option<Blocked> {
         return self.path_blocked(start_pos, end_pos, BlockedType::Fov);
     }
@@ -337,6 +378,18 @@ impl Map {
             }
         }
 
+        // Unless corner cutting is allowed, a diagonal move can't squeeze between
+        // a corner whose sides are both blocked, even by full tile walls.
+        if !found_blocker &&
+           blocked_type == BlockedType::Move &&
+           !self.allow_corner_cutting &&
+           direction.diag() &&
+           self.corner_blocked(start_pos, end_pos, blocked_type) {
+            // a corner can't be jumped like a short wall, so treat it as a blocking tile
+            blocked.blocked_tile = true;
+            found_blocker = true;
+        }
+
         if found_blocker {
             return Some(blocked);
         } else {
@@ -711,3 +764,42 @@ fn test_path_blocked_all() {
     assert_eq!(false, blocked_positions[3].blocked_tile);
     assert_eq!(Wall::TallWall, blocked_positions[3].wall_type);
 }
+
+#[test]
+fn test_diagonal_corner_cutting() {
+    let mut map = Map::from_dims(10, 10);
+
+    // full tile walls on both sides of the corner between (5, 5) and (6, 4)
+    map[(6, 5)].block_move = true;
+    map[(5, 4)].block_move = true;
+
+    // by default a diagonal move may squeeze past the corner
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(6, 4), BlockedType::Move).is_none());
+    assert!(map.move_blocked(Pos::new(6, 4), Pos::new(5, 5), BlockedType::Move).is_none());
+
+    map.allow_corner_cutting = false;
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(6, 4), BlockedType::Move).is_some());
+    assert!(map.move_blocked(Pos::new(6, 4), Pos::new(5, 5), BlockedType::Move).is_some());
+
+    // corners are never jumped like short walls
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(6, 4), BlockedType::Move).unwrap().blocked_tile);
+
+    // diagonals which do not cross the corner are unaffected
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(4, 6), BlockedType::Move).is_none());
+
+    // sight is not affected by the corner rule
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(6, 4), BlockedType::Fov).is_none());
+
+    // a wall on only one side leaves the other route open, so the move is allowed
+    let mut map = Map::from_dims(10, 10);
+    map.allow_corner_cutting = false;
+    map[(6, 5)].block_move = true;
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(6, 4), BlockedType::Move).is_none());
+
+    map[(6, 4)].bottom_wall = Wall::ShortWall;
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(6, 4), BlockedType::Move).is_none());
+
+    // short walls and full tile walls are treated the same way
+    map[(5, 4)].bottom_wall = Wall::ShortWall;
+    assert!(map.move_blocked(Pos::new(5, 5), Pos::new(6, 4), BlockedType::Move).is_some());
+}
@@ -70,6 +70,16 @@ impl Direction {
         }
     }
 
+    /// The direction after reflecting the map across the given axis.
+    pub fn mirror(self, axis: MirrorAxis) -> Direction {
+        let dxy = self.into_move();
+        let dxy = match axis {
+            MirrorAxis::Horizontal => Pos::new(-dxy.x, dxy.y),
+            MirrorAxis::Vertical => Pos::new(dxy.x, -dxy.y),
+        };
+        return Direction::from_dxy(dxy.x, dxy.y).unwrap();
+    }
+
     pub fn horiz(self) -> bool {
         match self {
             Direction::Left | Direction::Right |
@@ -330,12 +340,45 @@ pub enum MapLoadConfig {
     TestArmil,
     TestVaults,
     TestTraps,
+    Tutorial,
     #[display("vault_file {0}")]
     VaultFile(String),
     #[display("procgen {0}")]
     ProcGen(String),
     #[display("test_gen {0}")]
     TestGen(String),
+    #[display("text_file {0}")]
+    TextFile(String),
+    #[display("symmetric {0} {1}")]
+    Symmetric(String, MirrorAxis),
+}
+
+/// The reflection used by symmetric maps. A horizontal mirror copies the left
+/// half of the map onto the right half, and a vertical mirror copies the top
+/// half onto the bottom half.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, FromStr, Serialize, Deserialize)]
+#[display(style = "snake_case")]
+pub enum MirrorAxis {
+    Horizontal,
+    Vertical,
+}
+
+impl MirrorAxis {
+    pub fn mirror(&self, pos: Pos, width: i32, height: i32) -> Pos {
+        match self {
+            MirrorAxis::Horizontal => mirror_in_x(pos, width),
+            MirrorAxis::Vertical => mirror_in_y(pos, height),
+        }
+    }
+
+    /// Whether the position is in the half of the map that is overwritten by the mirror.
+    /// Positions on the axis itself are in neither half.
+    pub fn in_mirrored_half(&self, pos: Pos, width: i32, height: i32) -> bool {
+        match self {
+            MirrorAxis::Horizontal => 2 * pos.x > width - 1,
+            MirrorAxis::Vertical => 2 * pos.y > height - 1,
+        }
+    }
 }
 
 impl Default for MapLoadConfig {
@@ -765,6 +808,94 @@ pub fn reorient_map(map: &Map, rotation: Rotation, mirror: bool) -> Map {
     return new_map;
 }
 
+/// Copy one half of the map onto the other half, reflected across the given axis.
+/// Walls are stored on the left and bottom of a tile, so a reflected wall moves
+/// to the neighboring tile on the other side.
+pub fn mirror_map_half(map: &mut Map, axis: MirrorAxis) {
+    let orig_map = map.clone();
+    let (width, height) = map.size();
+
+    for pos in map.get_all_pos() {
+        if !axis.in_mirrored_half(pos, width, height) {
+            continue;
+        }
+
+        let src_pos = axis.mirror(pos, width, height);
+        let mut tile = orig_map[src_pos];
+
+        match axis {
+            MirrorAxis::Horizontal => {
+                // the right wall of the source tile is the left wall of its right neighbor.
+                let wall_pos = move_x(src_pos, 1);
+                tile.left_wall = orig_map[wall_pos].left_wall;
+                tile.left_material = orig_map[wall_pos].left_material;
+            }
+
+            MirrorAxis::Vertical => {
+                // the top wall of the source tile is the bottom wall of the tile above it.
+                let wall_pos = move_y(src_pos, -1);
+                if orig_map.is_within_bounds(wall_pos) {
+                    tile.bottom_wall = orig_map[wall_pos].bottom_wall;
+                    tile.bottom_material = orig_map[wall_pos].bottom_material;
+                } else {
+                    tile.bottom_wall = Wall::Empty;
+                    tile.bottom_material = Surface::Floor;
+                }
+            }
+        }
+
+        map[pos] = tile;
+    }
+
+    // with an odd height, the wall below the axis row is the reflection of the wall above it.
+    if axis == MirrorAxis::Vertical && height % 2 == 1 && height > 1 {
+        let axis_y = height / 2;
+        for x in 0..width {
+            let above = orig_map[Pos::new(x, axis_y - 1)];
+            map[Pos::new(x, axis_y)].bottom_wall = above.bottom_wall;
+            map[Pos::new(x, axis_y)].bottom_material = above.bottom_material;
+        }
+    }
+}
+
+#[test]
+fn test_mirror_map_half() {
+    let mut map = Map::from_dims(5, 4);
+    map[Pos::new(1, 1)] = Tile::short_left_wall();
+    map[Pos::new(0, 2)] = Tile::water();
+    map[Pos::new(2, 3)] = Tile::short_left_wall();
+    mirror_map_half(&mut map, MirrorAxis::Horizontal);
+
+    // the wall between x=0 and x=1 is reflected to between x=3 and x=4.
+    assert_eq!(Wall::ShortWall, map[Pos::new(4, 1)].left_wall);
+    assert_eq!(Wall::Empty, map[Pos::new(3, 1)].left_wall);
+    assert_eq!(Wall::ShortWall, map[Pos::new(1, 1)].left_wall);
+    assert_eq!(map[Pos::new(0, 2)].tile_type, map[Pos::new(4, 2)].tile_type);
+
+    // the wall on the left of the axis column is reflected to its right.
+    assert_eq!(Wall::ShortWall, map[Pos::new(3, 3)].left_wall);
+
+    let mut map = Map::from_dims(4, 6);
+    map[Pos::new(1, 1)] = Tile::short_bottom_wall();
+    mirror_map_half(&mut map, MirrorAxis::Vertical);
+
+    // the wall between y=1 and y=2 is reflected to between y=3 and y=4.
+    assert_eq!(Wall::ShortWall, map[Pos::new(1, 3)].bottom_wall);
+    assert_eq!(Wall::Empty, map[Pos::new(1, 4)].bottom_wall);
+    assert_eq!(Wall::ShortWall, map[Pos::new(1, 1)].bottom_wall);
+
+    // with an odd height the axis row's lower wall mirrors its upper wall.
+    let mut map = Map::from_dims(4, 5);
+    map[Pos::new(1, 1)] = Tile::short_bottom_wall();
+    map[Pos::new(2, 2)] = Tile::short_bottom_wall();
+    mirror_map_half(&mut map, MirrorAxis::Vertical);
+
+    // the wall between y=1 and y=2 is reflected to between y=2 and y=3.
+    assert_eq!(Wall::ShortWall, map[Pos::new(1, 2)].bottom_wall);
+    assert_eq!(Wall::Empty, map[Pos::new(2, 2)].bottom_wall);
+    assert_eq!(Wall::Empty, map[Pos::new(2, 1)].bottom_wall);
+}
+
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Obstacle {